@@ -0,0 +1,148 @@
+//! Dominant color of an app icon, used to tint launcher rows.
+
+use base64::Engine;
+use std::collections::HashMap;
+
+/// Pixels more transparent than this are not part of the icon's shape.
+const MIN_ALPHA: u8 = 125;
+/// Channel values beyond these bounds count as white or black, which icons
+/// use for backgrounds and outlines rather than as their color.
+const NEAR_WHITE: u8 = 245;
+const NEAR_BLACK: u8 = 20;
+/// Low bits dropped from each channel when bucketing, so shades of one color
+/// are counted together.
+const BUCKET_SHIFT: u8 = 4;
+
+/// Decode a PNG into RGBA pixels.
+fn decode_rgba(png_bytes: &[u8]) -> Option<Vec<[u8; 4]>> {
+    let mut decoder = png::Decoder::new(png_bytes);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().ok()?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let frame = reader.next_frame(&mut buf).ok()?;
+    let data = &buf[..frame.buffer_size()];
+
+    let pixels = match frame.color_type {
+        png::ColorType::Rgba => data
+            .chunks_exact(4)
+            .map(|p| [p[0], p[1], p[2], p[3]])
+            .collect(),
+        png::ColorType::Rgb => data
+            .chunks_exact(3)
+            .map(|p| [p[0], p[1], p[2], 255])
+            .collect(),
+        png::ColorType::GrayscaleAlpha => data
+            .chunks_exact(2)
+            .map(|p| [p[0], p[0], p[0], p[1]])
+            .collect(),
+        png::ColorType::Grayscale => data.iter().map(|&g| [g, g, g, 255]).collect(),
+        // Palettes are expanded by the transformations above.
+        png::ColorType::Indexed => return None,
+    };
+    Some(pixels)
+}
+
+fn is_neutral(pixel: &[u8; 4]) -> bool {
+    pixel[..3].iter().all(|&c| c >= NEAR_WHITE) || pixel[..3].iter().all(|&c| c <= NEAR_BLACK)
+}
+
+/// Average color of the most common bucket among opaque pixels, skipping
+/// white and black unless the icon has nothing else.
+fn dominant_color(pixels: &[[u8; 4]]) -> Option<[u8; 3]> {
+    let opaque: Vec<&[u8; 4]> = pixels.iter().filter(|p| p[3] >= MIN_ALPHA).collect();
+    let colorful: Vec<&[u8; 4]> = opaque.iter().copied().filter(|p| !is_neutral(p)).collect();
+    let candidates = if colorful.is_empty() {
+        opaque
+    } else {
+        colorful
+    };
+
+    // Per bucket: pixel count and channel sums.
+    let mut buckets: HashMap<[u8; 3], (u64, [u64; 3])> = HashMap::new();
+    for pixel in candidates {
+        let key = [
+            pixel[0] >> BUCKET_SHIFT,
+            pixel[1] >> BUCKET_SHIFT,
+            pixel[2] >> BUCKET_SHIFT,
+        ];
+        let (count, sums) = buckets.entry(key).or_default();
+        *count += 1;
+        for (sum, &channel) in sums.iter_mut().zip(&pixel[..3]) {
+            *sum += u64::from(channel);
+        }
+    }
+
+    // Ties go to the lower bucket so the result does not depend on hash order.
+    let (_, (count, sums)) = buckets
+        .into_iter()
+        .max_by(|(a_key, a), (b_key, b)| a.0.cmp(&b.0).then(b_key.cmp(a_key)))?;
+    Some(sums.map(|sum| (sum / count) as u8))
+}
+
+/// Accent color as "#rrggbb" for a `data:image/png;base64,...` icon.
+pub(super) fn accent_from_data_url(data_url: &str) -> Option<String> {
+    let (_, encoded) = data_url.split_once("base64,")?;
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(encoded.trim())
+        .ok()?;
+    let [r, g, b] = dominant_color(&decode_rgba(&bytes)?)?;
+    Some(format!("#{r:02x}{g:02x}{b:02x}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode_png(width: u32, height: u32, rgba: &[[u8; 4]]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, width, height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&rgba.concat()).unwrap();
+        writer.finish().unwrap();
+        bytes
+    }
+
+    fn data_url(png: &[u8]) -> String {
+        format!(
+            "data:image/png;base64,{}",
+            base64::engine::general_purpose::STANDARD.encode(png)
+        )
+    }
+
+    #[test]
+    fn test_dominant_color_of_small_icon() {
+        // 4x4: mostly two close reds, some blue, a black outline pixel and a
+        // transparent white background that must not win.
+        let mut pixels = vec![[200, 30, 40, 255]; 5];
+        pixels.extend([[206, 28, 46, 255]; 3]);
+        pixels.extend([[20, 60, 220, 255]; 3]);
+        pixels.push([0, 0, 0, 255]);
+        pixels.extend([[255, 255, 255, 0]; 4]);
+        let accent = accent_from_data_url(&data_url(&encode_png(4, 4, &pixels))).unwrap();
+        assert_eq!(accent, "#ca1d2a");
+    }
+
+    #[test]
+    fn test_neutral_icons_fall_back_to_their_own_color() {
+        let pixels = [
+            [250, 250, 250, 255],
+            [250, 250, 250, 255],
+            [0, 0, 0, 255],
+            [0, 0, 0, 0],
+        ];
+        let accent = accent_from_data_url(&data_url(&encode_png(2, 2, &pixels))).unwrap();
+        assert_eq!(accent, "#fafafa");
+
+        assert_eq!(
+            accent_from_data_url("data:image/png;base64,not-a-png"),
+            None
+        );
+        let transparent = [[10, 200, 10, 0]; 4];
+        assert_eq!(
+            accent_from_data_url(&data_url(&encode_png(2, 2, &transparent))),
+            None
+        );
+    }
+}
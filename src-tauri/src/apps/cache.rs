@@ -1,16 +1,25 @@
-use crate::apps::{scanner, AppInfo};
-use crate::db::AppsRepository;
+use crate::apps::names::clean_app_name;
+use crate::apps::{accent, scanner, AppInfo, SearchResult};
+use crate::db::{AppsRepository, SettingsRepository};
+use crate::logging::log_nonfatal;
 use once_cell::sync::Lazy;
-use std::collections::HashMap;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
 use tokio::sync::RwLock;
 
 // In-memory caches for fast access
 static APP_CACHE: Lazy<Arc<RwLock<Vec<AppInfo>>>> = Lazy::new(|| Arc::new(RwLock::new(Vec::new())));
 static ICON_CACHE: Lazy<Arc<RwLock<HashMap<String, Option<String>>>>> =
     Lazy::new(|| Arc::new(RwLock::new(HashMap::new())));
+static SEARCH_CACHE: Lazy<RwLock<SearchCache>> =
+    Lazy::new(|| RwLock::new(SearchCache::new(SEARCH_CACHE_CAPACITY)));
 
-fn normalize_path_key(path: &str) -> String {
+/// Recent queries kept by the search cache.
+const SEARCH_CACHE_CAPACITY: usize = 32;
+
+pub(super) fn normalize_path_key(path: &str) -> String {
     path.trim()
         .trim_matches('"')
         .replace('/', "\\")
@@ -26,15 +35,121 @@ pub async fn get_cached_apps() -> Vec<AppInfo> {
     cache.clone()
 }
 
-pub async fn refresh_cache() {
-    // Scan both registry and start menu
-    let mut apps = scanner::scan_installed_apps();
-    apps.extend(scanner::scan_start_menu());
+/// Replace the cached app list. Every cached search result is stale after this.
+async fn set_cached_apps(apps: Vec<AppInfo>) {
+    *APP_CACHE.write().await = apps;
+    clear_search_cache().await;
+}
+
+/// Key for the search cache: one entry per normalized query and path-matching mode.
+pub(super) type SearchKey = (String, bool);
+
+/// Small LRU of recent search results, most recently used at the front.
+pub(super) struct SearchCache {
+    capacity: usize,
+    entries: VecDeque<(SearchKey, Vec<SearchResult>)>,
+}
+
+impl SearchCache {
+    pub(super) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub(super) fn get(&mut self, key: &SearchKey) -> Option<Vec<SearchResult>> {
+        let index = self.entries.iter().position(|(k, _)| k == key)?;
+        let entry = self.entries.remove(index)?;
+        let results = entry.1.clone();
+        self.entries.push_front(entry);
+        Some(results)
+    }
+
+    pub(super) fn insert(&mut self, key: SearchKey, results: Vec<SearchResult>) {
+        self.entries.retain(|(k, _)| *k != key);
+        self.entries.push_front((key, results));
+        self.entries.truncate(self.capacity);
+    }
+
+    pub(super) fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+pub(super) async fn get_cached_search(key: &SearchKey) -> Option<Vec<SearchResult>> {
+    SEARCH_CACHE.write().await.get(key)
+}
+
+pub(super) async fn cache_search(key: SearchKey, results: Vec<SearchResult>) {
+    SEARCH_CACHE.write().await.insert(key, results);
+}
+
+/// Drop every cached search, e.g. after the app list or launch counts change.
+pub(super) async fn clear_search_cache() {
+    SEARCH_CACHE.write().await.clear();
+}
+
+/// Which app sources `refresh_cache` scans and whether it cleans up their
+/// names; each defaults to on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScanSources {
+    pub registry: bool,
+    pub start_menu: bool,
+    pub name_cleanup: bool,
+}
+
+impl Default for ScanSources {
+    fn default() -> Self {
+        Self {
+            registry: true,
+            start_menu: true,
+            name_cleanup: true,
+        }
+    }
+}
+
+impl ScanSources {
+    /// Read the `scan_registry` / `scan_start_menu` / `name_cleanup` settings.
+    pub fn load() -> Self {
+        let flag = |key: &str| {
+            crate::parse_bool_setting(SettingsRepository::get(key).ok().flatten(), true)
+        };
+        Self {
+            registry: flag(crate::SETTING_SCAN_REGISTRY),
+            start_menu: flag(crate::SETTING_SCAN_START_MENU),
+            name_cleanup: flag(crate::SETTING_NAME_CLEANUP),
+        }
+    }
+}
+
+/// Merge per-source scan results, dropping disabled sources, deduplicating by
+/// normalized executable path and sorting by name. With `name_cleanup`, names
+/// lose trailing versions and architecture suffixes and the scanned name is
+/// kept in `raw_name`.
+fn merge_scan_results(
+    sources: ScanSources,
+    registry_apps: Vec<AppInfo>,
+    start_menu_apps: Vec<AppInfo>,
+) -> Vec<AppInfo> {
+    let mut apps = Vec::new();
+    if sources.registry {
+        apps.extend(registry_apps);
+    }
+    if sources.start_menu {
+        apps.extend(start_menu_apps);
+    }
 
     // Deduplicate by normalized executable path and keep richer publisher metadata.
     let mut deduped: HashMap<String, AppInfo> = HashMap::new();
     for mut app in apps {
-        app.name = normalize_display_name(&app.name);
+        let scanned_name = normalize_display_name(&app.name);
+        app.name = if sources.name_cleanup {
+            clean_app_name(&scanned_name)
+        } else {
+            scanned_name.clone()
+        };
+        app.raw_name = (app.name != scanned_name).then_some(scanned_name);
         app.path = app.path.trim().trim_matches('"').to_string();
 
         if app.name.is_empty() || app.path.is_empty() {
@@ -58,16 +173,85 @@ pub async fn refresh_cache() {
 
     let mut unique_apps: Vec<AppInfo> = deduped.into_values().collect();
     unique_apps.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+    unique_apps
+}
+
+/// Scan the enabled sources and return the merged app list, without persisting it.
+async fn scan_apps() -> Vec<AppInfo> {
+    let sources = tokio::task::spawn_blocking(ScanSources::load)
+        .await
+        .unwrap_or_default();
+
+    // Scan registry and start menu, skipping sources disabled in settings.
+    let registry_apps = if sources.registry {
+        scanner::scan_installed_apps()
+    } else {
+        Vec::new()
+    };
+    let start_menu_apps = if sources.start_menu {
+        scanner::scan_start_menu()
+    } else {
+        Vec::new()
+    };
+    merge_scan_results(sources, registry_apps, start_menu_apps)
+}
+
+pub async fn refresh_cache() {
+    let unique_apps = scan_apps().await;
 
     // Persist atomically to database.
     let apps_to_save = unique_apps.clone();
     match tokio::task::spawn_blocking(move || AppsRepository::sync_apps(&apps_to_save)).await {
         Ok(Ok(())) => {}
         Ok(Err(e)) => eprintln!("Failed to sync apps to database: {e}"),
-        Err(e) => eprintln!("Failed to join app sync task: {e}"),
+        Err(e) => log_nonfatal(format!("Failed to join app sync task: {e}")),
     }
 
-    *APP_CACHE.write().await = unique_apps;
+    set_cached_apps(unique_apps).await;
+}
+
+/// Difference between a fresh scan and the apps stored in the database.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MissingAppsReport {
+    /// Found by the scan but not stored, e.g. installed since the last refresh.
+    pub missing_from_db: Vec<AppInfo>,
+    /// Stored but no longer found by the scan, e.g. uninstalled or a disabled source.
+    pub missing_from_disk: Vec<AppInfo>,
+}
+
+/// Compare two app lists by normalized executable path.
+fn diff_app_sets(scanned: &[AppInfo], stored: &[AppInfo]) -> MissingAppsReport {
+    let path_keys = |apps: &[AppInfo]| {
+        apps.iter()
+            .map(|app| normalize_path_key(&app.path))
+            .collect::<HashSet<_>>()
+    };
+    let scanned_keys = path_keys(scanned);
+    let stored_keys = path_keys(stored);
+
+    let missing = |apps: &[AppInfo], other: &HashSet<String>| {
+        let mut values: Vec<AppInfo> = apps
+            .iter()
+            .filter(|app| !other.contains(&normalize_path_key(&app.path)))
+            .cloned()
+            .collect();
+        values.sort_by_key(|app| app.name.to_lowercase());
+        values
+    };
+    MissingAppsReport {
+        missing_from_db: missing(scanned, &stored_keys),
+        missing_from_disk: missing(stored, &scanned_keys),
+    }
+}
+
+/// Run a scan and diff it against the database without writing anything.
+pub async fn diagnose_missing_apps() -> Result<MissingAppsReport, String> {
+    let scanned = scan_apps().await;
+    let stored = tokio::task::spawn_blocking(AppsRepository::get_all_apps)
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?;
+    Ok(diff_app_sets(&scanned, &stored))
 }
 
 pub async fn initialize_cache() {
@@ -75,7 +259,7 @@ pub async fn initialize_cache() {
     let db_apps = tokio::task::spawn_blocking(AppsRepository::get_all_apps).await;
     let loaded_from_db = match db_apps {
         Ok(Ok(apps)) if !apps.is_empty() => {
-            *APP_CACHE.write().await = apps;
+            set_cached_apps(apps).await;
             true
         }
         Ok(Ok(_)) => false,
@@ -84,7 +268,7 @@ pub async fn initialize_cache() {
             false
         }
         Err(e) => {
-            eprintln!("Failed to join database read task: {e}");
+            log_nonfatal(format!("Failed to join database read task: {e}"));
             false
         }
     };
@@ -97,12 +281,52 @@ pub async fn initialize_cache() {
     // Always attempt one-time JSON usage migration after app list is available.
     match tokio::task::spawn_blocking(AppsRepository::migrate_from_json).await {
         Ok(Ok(())) => {}
-        Ok(Err(e)) => eprintln!("Failed to migrate usage stats: {e}"),
-        Err(e) => eprintln!("Failed to join usage migration task: {e}"),
+        Ok(Err(e)) => log_nonfatal(format!("Failed to migrate usage stats: {e}")),
+        Err(e) => log_nonfatal(format!("Failed to join usage migration task: {e}")),
     }
 }
 
 pub async fn get_or_extract_icon(path: String) -> Option<String> {
+    get_or_extract_icon_with(path, scanner::extract_icon_data_url).await
+}
+
+/// Accent color of the icon for `path`, from the database cache or computed
+/// from the (possibly freshly extracted) icon and then cached.
+pub async fn get_or_compute_icon_accent(path: String) -> Option<String> {
+    let trimmed = path.trim().to_string();
+    if trimmed.is_empty() {
+        return None;
+    }
+
+    let path_for_db = trimmed.clone();
+    if let Ok(Ok(Some(accent))) =
+        tokio::task::spawn_blocking(move || AppsRepository::get_icon_accent(&path_for_db)).await
+    {
+        return Some(accent);
+    }
+
+    let icon = get_or_extract_icon(trimmed.clone()).await?;
+    let accent = accent::accent_from_data_url(&icon)?;
+    let accent_for_save = accent.clone();
+    match tokio::task::spawn_blocking(move || {
+        AppsRepository::save_icon_accent(&trimmed, &accent_for_save)
+    })
+    .await
+    {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => log_nonfatal(format!("Failed to persist icon accent: {e}")),
+        Err(e) => log_nonfatal(format!("Failed to join icon accent save task: {e}")),
+    }
+    Some(accent)
+}
+
+/// Icon lookup through the memory and database caches, falling back to
+/// `extract`. Failures are cached in memory too, so an app without an icon is
+/// only attempted once per session, and flagged in the database for diagnostics.
+async fn get_or_extract_icon_with(
+    path: String,
+    extract: impl FnOnce(&str) -> Option<String>,
+) -> Option<String> {
     let trimmed = path.trim();
     if trimmed.is_empty() {
         return None;
@@ -129,7 +353,7 @@ pub async fn get_or_extract_icon(path: String) -> Option<String> {
     }
 
     // Extract from executable
-    let icon = scanner::extract_icon_data_url(trimmed);
+    let icon = extract(trimmed);
 
     // Save to caches
     if let Some(ref icon_data) = icon {
@@ -141,8 +365,19 @@ pub async fn get_or_extract_icon(path: String) -> Option<String> {
         .await
         {
             Ok(Ok(())) => {}
-            Ok(Err(e)) => eprintln!("Failed to persist app icon: {e}"),
-            Err(e) => eprintln!("Failed to join icon save task: {e}"),
+            Ok(Err(e)) => log_nonfatal(format!("Failed to persist app icon: {e}")),
+            Err(e) => log_nonfatal(format!("Failed to join icon save task: {e}")),
+        }
+    } else {
+        let path_for_save = trimmed.to_string();
+        match tokio::task::spawn_blocking(move || {
+            AppsRepository::mark_icon_extraction_failed(&path_for_save)
+        })
+        .await
+        {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => log_nonfatal(format!("Failed to record icon extraction failure: {e}")),
+            Err(e) => log_nonfatal(format!("Failed to join icon failure task: {e}")),
         }
     }
 
@@ -150,6 +385,31 @@ pub async fn get_or_extract_icon(path: String) -> Option<String> {
     icon
 }
 
+/// Drop persisted icons for stale apps and forget them in memory so they are
+/// re-extracted on demand. Returns the number of database rows cleared.
+pub async fn prune_cached_icons(
+    current_paths: Vec<String>,
+    older_than_days: i64,
+) -> Result<usize, String> {
+    let cutoff = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+        .saturating_sub(older_than_days.saturating_mul(86_400_000));
+
+    let pruned = tokio::task::spawn_blocking(move || {
+        AppsRepository::prune_icon_data(&current_paths, cutoff)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())?;
+
+    if pruned > 0 {
+        ICON_CACHE.write().await.clear();
+    }
+    Ok(pruned)
+}
+
 pub async fn record_app_launch(path: &str) {
     let key = normalize_path_key(path);
     if key.is_empty() {
@@ -159,17 +419,36 @@ pub async fn record_app_launch(path: &str) {
     let path_for_db = path.to_string();
     match tokio::task::spawn_blocking(move || AppsRepository::record_launch(&path_for_db)).await {
         Ok(Ok(())) => {}
-        Ok(Err(e)) => eprintln!("Failed to record app launch: {e}"),
-        Err(e) => eprintln!("Failed to join app launch task: {e}"),
+        Ok(Err(e)) => log_nonfatal(format!("Failed to record app launch: {e}")),
+        Err(e) => log_nonfatal(format!("Failed to join app launch task: {e}")),
     }
+    // Launch counts break ranking ties, so cached orderings may be stale.
+    clear_search_cache().await;
 }
 
-pub async fn get_suggested_apps(limit: usize) -> Vec<AppInfo> {
+/// Launch counts by normalized path, loaded once per search to break score
+/// ties. Empty when the database is unavailable.
+pub async fn get_launch_counts() -> HashMap<String, i64> {
+    match tokio::task::spawn_blocking(AppsRepository::launch_counts).await {
+        Ok(Ok(counts)) => counts,
+        Ok(Err(e)) => {
+            log_nonfatal(format!("Failed to load launch counts: {e}"));
+            HashMap::new()
+        }
+        Err(e) => {
+            log_nonfatal(format!("Failed to join launch counts task: {e}"));
+            HashMap::new()
+        }
+    }
+}
+
+pub async fn get_suggested_apps(limit: usize, within_days: Option<i64>) -> Vec<AppInfo> {
     let limit = limit.clamp(1, 20);
 
     // Try to get from database (has usage stats)
     let db_result =
-        tokio::task::spawn_blocking(move || AppsRepository::get_suggested_apps(limit)).await;
+        tokio::task::spawn_blocking(move || AppsRepository::get_suggested_apps(limit, within_days))
+            .await;
 
     if let Ok(Ok(apps)) = db_result {
         if !apps.is_empty() {
@@ -179,3 +458,165 @@ pub async fn get_suggested_apps(limit: usize) -> Vec<AppInfo> {
 
     Vec::new()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app(name: &str, path: &str) -> AppInfo {
+        AppInfo {
+            name: name.to_string(),
+            path: path.to_string(),
+            publisher: None,
+            raw_name: None,
+        }
+    }
+
+    #[test]
+    fn test_merge_scan_results_omits_disabled_sources() {
+        let registry = || {
+            vec![
+                app("Zed", "C:\\Apps\\zed.exe"),
+                app("Shared", "C:\\Apps\\shared.exe"),
+            ]
+        };
+        let start_menu = || {
+            vec![
+                app("Notes", "C:\\Apps\\notes.exe"),
+                app("Shared", "c:/apps/shared.exe"),
+            ]
+        };
+        let names = |apps: Vec<AppInfo>| apps.into_iter().map(|a| a.name).collect::<Vec<_>>();
+
+        assert_eq!(
+            names(merge_scan_results(
+                ScanSources::default(),
+                registry(),
+                start_menu()
+            )),
+            vec!["Notes", "Shared", "Zed"]
+        );
+
+        let no_start_menu = ScanSources {
+            start_menu: false,
+            ..ScanSources::default()
+        };
+        assert_eq!(
+            names(merge_scan_results(no_start_menu, registry(), start_menu())),
+            vec!["Shared", "Zed"]
+        );
+
+        let no_registry = ScanSources {
+            registry: false,
+            ..ScanSources::default()
+        };
+        assert_eq!(
+            names(merge_scan_results(no_registry, registry(), start_menu())),
+            vec!["Notes", "Shared"]
+        );
+    }
+
+    #[test]
+    fn test_merge_scan_results_cleans_names_when_enabled() {
+        let scanned = || vec![app("Node.js  (x64) v20.1", "C:\\Apps\\node.exe")];
+
+        let cleaned = merge_scan_results(ScanSources::default(), scanned(), Vec::new());
+        assert_eq!(cleaned[0].name, "Node.js");
+        assert_eq!(cleaned[0].raw_name.as_deref(), Some("Node.js (x64) v20.1"));
+
+        let raw = ScanSources {
+            name_cleanup: false,
+            ..ScanSources::default()
+        };
+        let kept = merge_scan_results(raw, scanned(), Vec::new());
+        assert_eq!(kept[0].name, "Node.js (x64) v20.1");
+        assert_eq!(kept[0].raw_name, None);
+    }
+
+    #[test]
+    fn test_diff_app_sets_reports_both_directions() {
+        let scanned = vec![
+            app("Zed", "C:\\Apps\\zed.exe"),
+            app("New Tool", "C:\\Apps\\new.exe"),
+            app("Shared", "c:/apps/shared.exe"),
+        ];
+        let stored = vec![
+            app("Shared", "C:\\Apps\\Shared.exe"),
+            app("Zed", "C:\\Apps\\zed.exe"),
+            app("Removed", "C:\\Old\\removed.exe"),
+        ];
+
+        let report = diff_app_sets(&scanned, &stored);
+        let names = |apps: &[AppInfo]| apps.iter().map(|a| a.name.clone()).collect::<Vec<_>>();
+        assert_eq!(names(&report.missing_from_db), vec!["New Tool"]);
+        assert_eq!(names(&report.missing_from_disk), vec!["Removed"]);
+
+        let in_sync = diff_app_sets(&stored, &stored);
+        assert!(in_sync.missing_from_db.is_empty());
+        assert!(in_sync.missing_from_disk.is_empty());
+    }
+
+    fn result(name: &str) -> SearchResult {
+        SearchResult {
+            app: app(name, &format!("C:\\Apps\\{name}.exe")),
+            score: 100,
+            match_indices: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_search_cache_is_bounded_lru() {
+        let mut cache = SearchCache::new(2);
+        let key = |q: &str| (q.to_string(), false);
+        cache.insert(key("a"), vec![result("Alpha")]);
+        cache.insert(key("b"), vec![result("Beta")]);
+        // Touch "a" so "b" becomes the least recently used.
+        assert!(cache.get(&key("a")).is_some());
+        cache.insert(key("c"), vec![result("Gamma")]);
+
+        assert!(cache.get(&key("b")).is_none());
+        assert!(cache.get(&key("a")).is_some());
+        assert!(cache.get(&key("c")).is_some());
+        assert!(cache.get(&("a".to_string(), true)).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_search_cache_hit_and_invalidation_on_app_list_change() {
+        let key = ("cache invalidation probe".to_string(), false);
+        let results = vec![result("Probe One"), result("Probe Two")];
+        cache_search(key.clone(), results.clone()).await;
+
+        let hit = get_cached_search(&key).await.expect("cache hit");
+        let names = |results: &[SearchResult]| {
+            results
+                .iter()
+                .map(|r| r.app.name.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(&hit), names(&results));
+
+        set_cached_apps(get_cached_apps().await).await;
+        assert!(get_cached_search(&key).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_known_icon_failure_is_not_retried() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let attempts = AtomicUsize::new(0);
+        let failing = |_: &str| {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            None
+        };
+        let path = "C:\\Tests\\no-icon-retry.exe".to_string();
+
+        assert_eq!(get_or_extract_icon_with(path.clone(), failing).await, None);
+        assert_eq!(get_or_extract_icon_with(path.clone(), failing).await, None);
+        // Different spelling of the same path hits the same cache entry.
+        assert_eq!(
+            get_or_extract_icon_with("c:/tests/no-icon-retry.exe".to_string(), failing).await,
+            None
+        );
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+}
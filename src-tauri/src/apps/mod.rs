@@ -1,27 +1,45 @@
+mod accent;
 mod cache;
+mod names;
 mod scanner;
 
 use fuzzy_matcher::FuzzyMatcher;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
 use std::path::Path;
 
+const CREATE_NO_WINDOW: u32 = 0x08000000;
+const IMAGE_SUBSYSTEM_WINDOWS_CUI: u16 = 3;
+/// Scores this close are treated as tied and ordered by launch count. Scores
+/// are bucketed (`score / SCORE_TIE_EPSILON`) so the order stays total.
+const SCORE_TIE_EPSILON: i64 = 10;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppInfo {
     pub name: String,
     pub path: String,
     pub publisher: Option<String>,
+    /// Scanned name before cleanup rules, kept only when they changed it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_name: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchResult {
     pub app: AppInfo,
     pub score: i64,
+    /// Byte ranges in `app.name` that matched the query, for highlighting.
+    /// Empty when only the basename or publisher matched.
+    #[serde(default)]
+    pub match_indices: Vec<(usize, usize)>,
 }
 
 pub use cache::{
-    get_cached_apps, get_or_extract_icon, get_suggested_apps, initialize_cache, record_app_launch,
-    refresh_cache,
+    get_cached_apps, get_launch_counts, get_or_compute_icon_accent, get_or_extract_icon,
+    get_suggested_apps, initialize_cache, prune_cached_icons, record_app_launch, refresh_cache,
+    MissingAppsReport,
 };
 
 fn path_basename(path: &str) -> String {
@@ -31,33 +49,252 @@ fn path_basename(path: &str) -> String {
         .unwrap_or_default()
 }
 
-#[tauri::command]
-pub async fn search_apps(query: String) -> Result<Vec<SearchResult>, String> {
-    let query = query.trim().to_string();
-    if query.is_empty() {
-        return Ok(Vec::new());
-    }
+/// How a search hit matched the query, strongest first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchKind {
+    Exact,
+    StartsWith,
+    Contains,
+    Publisher,
+    Fuzzy,
+}
 
-    let apps = get_cached_apps().await;
+/// Search results bucketed by [`MatchKind`], each group ordered by score.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GroupedResults {
+    pub exact: Vec<SearchResult>,
+    pub starts_with: Vec<SearchResult>,
+    pub contains: Vec<SearchResult>,
+    pub publisher: Vec<SearchResult>,
+    pub fuzzy: Vec<SearchResult>,
+}
 
-    if apps.is_empty() {
-        return Ok(Vec::new());
+/// Bucket for apps whose scan found no publisher.
+const UNKNOWN_PUBLISHER: &str = "Unknown";
+
+/// Cached apps from one publisher, for browsing by vendor.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublisherGroup {
+    pub publisher: String,
+    pub apps: Vec<AppInfo>,
+}
+
+/// Lowercased fields an app is matched on.
+struct SearchFields {
+    name: String,
+    basename: String,
+    publisher: String,
+    /// Directory segments of the path; empty unless path matching is on.
+    dirs: Vec<String>,
+}
+
+impl SearchFields {
+    /// Fields for a bare label with no path or publisher to fall back on.
+    fn for_label(label: &str) -> Self {
+        Self {
+            name: label.to_lowercase(),
+            basename: String::new(),
+            publisher: String::new(),
+            dirs: Vec::new(),
+        }
     }
 
-    let matcher = fuzzy_matcher::skim::SkimMatcherV2::default();
-    let query_lower = query.to_lowercase();
-    let query_len = query.chars().count();
-    let min_fuzzy_score = if query_len <= 2 {
+    fn new(app: &AppInfo, match_path: bool) -> Self {
+        let dirs = if match_path {
+            path_dir_segments(&app.path)
+        } else {
+            Vec::new()
+        };
+        Self {
+            name: app.name.to_lowercase(),
+            basename: path_basename(&app.path).to_lowercase(),
+            publisher: app.publisher.clone().unwrap_or_default().to_lowercase(),
+            dirs,
+        }
+    }
+}
+
+/// Lowercased folder names between the drive and the file, e.g.
+/// `["python310", "scripts"]` for `C:\Python310\Scripts\pip.exe`.
+fn path_dir_segments(path: &str) -> Vec<String> {
+    let path = path.trim().trim_matches('"').replace('/', "\\");
+    let mut segments = path
+        .split('\\')
+        .filter(|segment| !segment.is_empty() && !segment.ends_with(':'))
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>();
+    segments.pop();
+    segments
+}
+
+/// How one search term matched an app.
+struct TermMatch {
+    score: i64,
+    kind: MatchKind,
+    /// Char positions in the lowercased name covered by the match.
+    name_chars: Vec<usize>,
+}
+
+/// Score one lowercase search term against an app, or `None` if it doesn't match.
+fn score_term(
+    matcher: &fuzzy_matcher::skim::SkimMatcherV2,
+    fields: &SearchFields,
+    term: &str,
+) -> Option<TermMatch> {
+    let term_len = term.chars().count();
+    let min_fuzzy_score = if term_len <= 2 {
         35
-    } else if query_len <= 4 {
+    } else if term_len <= 4 {
         55
     } else {
         70
     };
-    let non_ascii_query = !query.is_ascii();
+
+    let name_contains = fields.name.contains(term);
+    let publisher_contains = fields.publisher.contains(term);
+    let basename_contains = fields.basename.contains(term);
+    let dir_contains = fields.dirs.iter().any(|dir| dir.contains(term));
+    let contains_match = name_contains || publisher_contains || basename_contains || dir_contains;
+
+    // For CJK/non-ASCII input, require direct contains to avoid unrelated fuzzy noise.
+    if !term.is_ascii() && !contains_match {
+        return None;
+    }
+
+    let fuzzy_name = matcher.fuzzy_match(&fields.name, term);
+    let fuzzy_basename = matcher.fuzzy_match(&fields.basename, term);
+    let fuzzy_score = fuzzy_name
+        .into_iter()
+        .chain(fuzzy_basename)
+        .max()
+        .unwrap_or(i64::MIN);
+
+    if !contains_match && fuzzy_score < min_fuzzy_score {
+        return None;
+    }
+
+    let mut score = fuzzy_score.max(0);
+    let kind = if fields.name.starts_with(term) {
+        score += 5000;
+        if fields.name == term {
+            MatchKind::Exact
+        } else {
+            MatchKind::StartsWith
+        }
+    } else if name_contains {
+        score += 3500;
+        MatchKind::Contains
+    } else if fields.basename.starts_with(term) {
+        score += 3200;
+        MatchKind::StartsWith
+    } else if basename_contains {
+        score += 2200;
+        MatchKind::Contains
+    } else if publisher_contains {
+        score += 1000;
+        MatchKind::Publisher
+    } else if dir_contains {
+        score += 800;
+        MatchKind::Contains
+    } else {
+        MatchKind::Fuzzy
+    };
+
+    let name_chars = if let Some(byte_start) = fields.name.find(term) {
+        let start = fields.name[..byte_start].chars().count();
+        (start..start + term_len).collect()
+    } else if kind == MatchKind::Fuzzy {
+        matcher
+            .fuzzy_indices(&fields.name, term)
+            .map(|(_, indices)| indices)
+            .unwrap_or_default()
+    } else {
+        Vec::new()
+    };
+
+    Some(TermMatch {
+        score,
+        kind,
+        name_chars,
+    })
+}
+
+/// Merge matched char positions of the lowercased name into byte ranges of `name`.
+fn name_match_ranges(name: &str, name_lower: &str, mut chars: Vec<usize>) -> Vec<(usize, usize)> {
+    // Lowercasing can change the char count (e.g. 'İ'), so positions wouldn't line up.
+    if name.chars().count() != name_lower.chars().count() {
+        return Vec::new();
+    }
+    chars.sort_unstable();
+    chars.dedup();
+
+    let bounds = name
+        .char_indices()
+        .map(|(start, c)| (start, start + c.len_utf8()))
+        .collect::<Vec<_>>();
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for (start, end) in chars.into_iter().filter_map(|i| bounds.get(i).copied()) {
+        match ranges.last_mut() {
+            Some(last) if last.1 == start => last.1 = end,
+            _ => ranges.push((start, end)),
+        }
+    }
+    ranges
+}
+
+/// Score one candidate against the whole lowercased query and its words.
+///
+/// Multi-word queries require every word to match and sum the per-word scores.
+/// The weakest word decides the match kind unless the whole phrase matches directly.
+fn score_fields(
+    matcher: &fuzzy_matcher::skim::SkimMatcherV2,
+    fields: &SearchFields,
+    query_lower: &str,
+    terms: &[&str],
+) -> Option<(i64, MatchKind, Vec<usize>)> {
+    if terms.len() <= 1 {
+        let matched = score_term(matcher, fields, query_lower)?;
+        return Some((matched.score, matched.kind, matched.name_chars));
+    }
+
+    let mut score = 0i64;
+    let mut weakest = MatchKind::Exact;
+    let mut name_chars = Vec::new();
+    for term in terms {
+        let matched = score_term(matcher, fields, term)?;
+        score += matched.score;
+        weakest = weakest.max(matched.kind);
+        name_chars.extend(matched.name_chars);
+    }
+
+    let kind = match score_term(matcher, fields, query_lower) {
+        Some(phrase) if phrase.kind != MatchKind::Fuzzy => {
+            score += phrase.score;
+            phrase.kind
+        }
+        _ => weakest,
+    };
+    Some((score, kind, name_chars))
+}
+
+/// Score and classify apps against `query`, returning at most 10 hits by score.
+///
+/// With `match_path`, folder names in the app's path count as weak contains matches.
+/// Near-equal scores are broken by `launch_counts` (keyed by normalized path),
+/// so the apps the user actually opens come first.
+fn rank_apps(
+    apps: Vec<AppInfo>,
+    query: &str,
+    match_path: bool,
+    launch_counts: &HashMap<String, i64>,
+) -> Vec<(SearchResult, MatchKind)> {
+    let matcher = fuzzy_matcher::skim::SkimMatcherV2::default();
+    let query_lower = query.to_lowercase();
+    let terms = query_lower.split_whitespace().collect::<Vec<_>>();
 
     let mut seen_paths = HashSet::new();
-    let mut results: Vec<SearchResult> = apps
+    let mut results: Vec<(SearchResult, MatchKind)> = apps
         .into_iter()
         .filter_map(|app| {
             let path_key = app.path.to_lowercase();
@@ -65,74 +302,299 @@ pub async fn search_apps(query: String) -> Result<Vec<SearchResult>, String> {
                 return None;
             }
 
-            let name_lower = app.name.to_lowercase();
-            let publisher_lower = app.publisher.clone().unwrap_or_default().to_lowercase();
-            let basename_lower = path_basename(&app.path).to_lowercase();
+            let fields = SearchFields::new(&app, match_path);
+            let (score, kind, name_chars) = score_fields(&matcher, &fields, &query_lower, &terms)?;
 
-            let name_contains = name_lower.contains(&query_lower);
-            let publisher_contains = publisher_lower.contains(&query_lower);
-            let basename_contains = basename_lower.contains(&query_lower);
-            let contains_match = name_contains || publisher_contains || basename_contains;
+            let match_indices = name_match_ranges(&app.name, &fields.name, name_chars);
+            Some((
+                SearchResult {
+                    app,
+                    score,
+                    match_indices,
+                },
+                kind,
+            ))
+        })
+        .collect();
 
-            // For CJK/non-ASCII input, require direct contains to avoid unrelated fuzzy noise.
-            if non_ascii_query && !contains_match {
-                return None;
-            }
+    let launches = |result: &SearchResult| {
+        launch_counts
+            .get(&cache::normalize_path_key(&result.app.path))
+            .copied()
+            .unwrap_or(0)
+    };
+    results.sort_by_cached_key(|(result, _)| {
+        (
+            Reverse(result.score.div_euclid(SCORE_TIE_EPSILON)),
+            Reverse(launches(result)),
+            Reverse(result.score),
+        )
+    });
+    results.truncate(10);
+    results
+}
 
-            let fuzzy_name = matcher.fuzzy_match(&name_lower, &query_lower);
-            let fuzzy_basename = matcher.fuzzy_match(&basename_lower, &query_lower);
-            let fuzzy_score = fuzzy_name
-                .into_iter()
-                .chain(fuzzy_basename)
-                .max()
-                .unwrap_or(i64::MIN);
+/// Rank plain strings with the same tiers and thresholds as app names, best
+/// first. Ties keep their input order.
+fn rank_candidates(
+    query: &str,
+    candidates: Vec<String>,
+    limit: Option<usize>,
+) -> Vec<(String, i64)> {
+    let matcher = fuzzy_matcher::skim::SkimMatcherV2::default();
+    let query_lower = query.trim().to_lowercase();
+    let terms = query_lower.split_whitespace().collect::<Vec<_>>();
+    if terms.is_empty() {
+        return Vec::new();
+    }
 
-            if !contains_match && fuzzy_score < min_fuzzy_score {
-                return None;
-            }
+    let mut ranked = candidates
+        .into_iter()
+        .filter_map(|candidate| {
+            let fields = SearchFields::for_label(&candidate);
+            let (score, _, _) = score_fields(&matcher, &fields, &query_lower, &terms)?;
+            Some((candidate, score))
+        })
+        .collect::<Vec<_>>();
 
-            let mut score = fuzzy_score.max(0);
-            if name_lower.starts_with(&query_lower) {
-                score += 5000;
-            } else if name_contains {
-                score += 3500;
-            } else if basename_lower.starts_with(&query_lower) {
-                score += 3200;
-            } else if basename_contains {
-                score += 2200;
-            } else if publisher_contains {
-                score += 1000;
-            }
+    ranked.sort_by_key(|(_, score)| Reverse(*score));
+    if let Some(limit) = limit {
+        ranked.truncate(limit);
+    }
+    ranked
+}
 
-            Some(SearchResult { app, score })
-        })
-        .collect();
+fn group_results(ranked: Vec<(SearchResult, MatchKind)>) -> GroupedResults {
+    let mut grouped = GroupedResults::default();
+    for (result, kind) in ranked {
+        let bucket = match kind {
+            MatchKind::Exact => &mut grouped.exact,
+            MatchKind::StartsWith => &mut grouped.starts_with,
+            MatchKind::Contains => &mut grouped.contains,
+            MatchKind::Publisher => &mut grouped.publisher,
+            MatchKind::Fuzzy => &mut grouped.fuzzy,
+        };
+        bucket.push(result);
+    }
+    grouped
+}
 
-    results.sort_by(|a, b| b.score.cmp(&a.score));
-    results.truncate(10);
+/// Group apps by publisher, matched case-insensitively and named as first
+/// seen. Groups and the apps in them are sorted by name; apps without a
+/// publisher go in a trailing "Unknown" group.
+fn group_by_publisher(apps: Vec<AppInfo>) -> Vec<PublisherGroup> {
+    let mut groups: HashMap<String, PublisherGroup> = HashMap::new();
+    let mut unknown = Vec::new();
+    for app in apps {
+        let publisher = app
+            .publisher
+            .as_deref()
+            .map(str::trim)
+            .filter(|publisher| !publisher.is_empty())
+            .map(str::to_string);
+        match publisher {
+            Some(publisher) => groups
+                .entry(publisher.to_lowercase())
+                .or_insert_with(|| PublisherGroup {
+                    publisher,
+                    apps: Vec::new(),
+                })
+                .apps
+                .push(app),
+            None => unknown.push(app),
+        }
+    }
 
+    let mut groups: Vec<PublisherGroup> = groups.into_values().collect();
+    groups.sort_by_cached_key(|group| group.publisher.to_lowercase());
+    if !unknown.is_empty() {
+        groups.push(PublisherGroup {
+            publisher: UNKNOWN_PUBLISHER.to_string(),
+            apps: unknown,
+        });
+    }
+    for group in &mut groups {
+        group.apps.sort_by_cached_key(|app| app.name.to_lowercase());
+    }
+    groups
+}
+
+/// Read the PE optional-header subsystem from the start of an executable.
+fn pe_subsystem(header: &[u8]) -> Option<u16> {
+    if header.get(0..2)? != b"MZ" {
+        return None;
+    }
+    let pe_offset = u32::from_le_bytes(header.get(0x3c..0x40)?.try_into().ok()?) as usize;
+    if header.get(pe_offset..pe_offset + 4)? != b"PE\0\0" {
+        return None;
+    }
+    // Subsystem sits 68 bytes into the optional header, after the 20-byte COFF header.
+    let offset = pe_offset + 4 + 20 + 68;
+    let bytes = header.get(offset..offset + 2)?;
+    Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn is_console_executable(path: &Path) -> bool {
+    let mut header = Vec::with_capacity(4096);
+    let read = std::fs::File::open(path).and_then(|file| file.take(4096).read_to_end(&mut header));
+    read.is_ok() && pe_subsystem(&header) == Some(IMAGE_SUBSYSTEM_WINDOWS_CUI)
+}
+
+/// Console tools keep their window when the user allows it; everything else launches hidden.
+fn launch_creation_flags(is_console: bool, show_console_window: bool) -> u32 {
+    if is_console && show_console_window {
+        0
+    } else {
+        CREATE_NO_WINDOW
+    }
+}
+
+/// With `match_path`, folder names in each app's path are matched as well;
+/// off by default because install folders add noise.
+#[tauri::command]
+pub async fn search_apps(
+    query: String,
+    match_path: Option<bool>,
+) -> Result<Vec<SearchResult>, String> {
+    let query = query.trim().to_string();
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let match_path = match_path.unwrap_or(false);
+    let key = (query.to_lowercase(), match_path);
+    if let Some(results) = cache::get_cached_search(&key).await {
+        return Ok(results);
+    }
+
+    let apps = get_cached_apps().await;
+
+    if apps.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let launch_counts = get_launch_counts().await;
+    let results: Vec<SearchResult> = rank_apps(apps, &query, match_path, &launch_counts)
+        .into_iter()
+        .map(|(result, _)| result)
+        .collect();
+    cache::cache_search(key, results.clone()).await;
     Ok(results)
 }
 
+/// Every cached app grouped by publisher, for a browse-by-vendor view.
 #[tauri::command]
-pub async fn launch_app(path: String) -> Result<(), String> {
-    use std::os::windows::process::CommandExt;
-    use std::process::Command;
+pub async fn list_apps_by_publisher() -> Result<Vec<PublisherGroup>, String> {
+    Ok(group_by_publisher(get_cached_apps().await))
+}
 
-    const CREATE_NO_WINDOW: u32 = 0x08000000;
+#[tauri::command]
+pub async fn search_apps_grouped(query: String) -> Result<GroupedResults, String> {
+    let query = query.trim().to_string();
+    if query.is_empty() {
+        return Ok(GroupedResults::default());
+    }
 
-    let requested_path = path.trim();
+    let apps = get_cached_apps().await;
+    let launch_counts = get_launch_counts().await;
+    Ok(group_results(rank_apps(
+        apps,
+        &query,
+        false,
+        &launch_counts,
+    )))
+}
+
+/// Rank an arbitrary list (e.g. command palette entries) exactly like app
+/// names are ranked by `search_apps`, so every surface orders matches the same way.
+#[tauri::command]
+pub async fn fuzzy_rank(
+    query: String,
+    candidates: Vec<String>,
+    limit: Option<usize>,
+) -> Result<Vec<(String, i64)>, String> {
+    Ok(rank_candidates(&query, candidates, limit))
+}
+
+/// Where an indexed app would be launched from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AppLaunchInfo {
+    pub exe_path: String,
+    /// The executable's folder; `None` when the path has no parent.
+    pub working_dir: Option<String>,
+    pub exists: bool,
+    /// Microsoft Store apps live under `WindowsApps` and are not launched by path.
+    pub is_store_app: bool,
+}
+
+/// The indexed app whose path equals `requested_path`, ignoring ASCII case.
+fn find_indexed_app<'a>(apps: &'a [AppInfo], requested_path: &str) -> Result<&'a AppInfo, String> {
+    let requested_path = requested_path.trim();
     if requested_path.is_empty() {
         return Err("Launch denied: empty app path".to_string());
     }
+    apps.iter()
+        .find(|app| app.path.eq_ignore_ascii_case(requested_path))
+        .ok_or_else(|| "Launch denied: app path is not in indexed search results".to_string())
+}
 
-    let apps = get_cached_apps().await;
-    let is_allowed = apps
-        .iter()
-        .any(|app| app.path.eq_ignore_ascii_case(requested_path));
-    if !is_allowed {
-        return Err("Launch denied: app path is not in indexed search results".to_string());
+/// Parent folder of a Windows path, keeping the trailing separator for drive roots.
+fn parent_dir(path: &str) -> Option<String> {
+    let path = path.trim().trim_matches('"');
+    let (parent, _) = path.rsplit_once(['\\', '/'])?;
+    if parent.is_empty() {
+        return None;
+    }
+    if parent.ends_with(':') {
+        return Some(format!("{parent}\\"));
+    }
+    Some(parent.to_string())
+}
+
+/// Folder a launched app starts in: the override when given, else the exe's folder.
+fn resolve_launch_dir(exe_path: &str, override_dir: Option<&str>) -> Option<String> {
+    override_dir
+        .map(|dir| dir.trim().trim_matches('"'))
+        .filter(|dir| !dir.is_empty())
+        .map(str::to_string)
+        .or_else(|| parent_dir(exe_path))
+}
+
+fn is_store_app_path(path: &str) -> bool {
+    let lower = path.trim().to_lowercase().replace('/', "\\");
+    lower.starts_with("shell:") || lower.contains("\\windowsapps\\")
+}
+
+fn launch_info(exe_path: &str, exists: bool) -> AppLaunchInfo {
+    let exe_path = exe_path.trim().trim_matches('"').to_string();
+    AppLaunchInfo {
+        working_dir: parent_dir(&exe_path),
+        is_store_app: is_store_app_path(&exe_path),
+        exists,
+        exe_path,
     }
+}
+
+/// Show where an indexed app will run from before launching it; `exists`
+/// is false when the executable has been removed since the last scan.
+#[tauri::command]
+pub async fn get_app_launch_info(path: String) -> Result<AppLaunchInfo, String> {
+    let apps = get_cached_apps().await;
+    let exe_path = find_indexed_app(&apps, &path)?.path.clone();
+    let exists = Path::new(exe_path.trim().trim_matches('"')).exists();
+    Ok(launch_info(&exe_path, exists))
+}
+
+/// Launch an indexed app. It starts in its own folder unless `working_dir`
+/// names another one.
+#[tauri::command]
+pub async fn launch_app(path: String, working_dir: Option<String>) -> Result<(), String> {
+    use std::os::windows::process::CommandExt;
+    use std::process::Command;
+
+    let requested_path = path.trim();
+    let apps = get_cached_apps().await;
+    find_indexed_app(&apps, requested_path)?;
 
     if !Path::new(requested_path).exists() {
         return Err(format!(
@@ -141,8 +603,29 @@ pub async fn launch_app(path: String) -> Result<(), String> {
         ));
     }
 
-    Command::new(requested_path)
-        .creation_flags(CREATE_NO_WINDOW)
+    let show_console_setting = tokio::task::spawn_blocking(|| {
+        crate::db::SettingsRepository::get(crate::SETTING_SHOW_CONSOLE_WINDOW)
+    })
+    .await
+    .ok()
+    .and_then(Result::ok)
+    .flatten();
+    let creation_flags = launch_creation_flags(
+        is_console_executable(Path::new(requested_path)),
+        crate::parse_bool_setting(show_console_setting, true),
+    );
+
+    let mut command = Command::new(requested_path);
+    command.creation_flags(creation_flags);
+    if let Some(dir) = resolve_launch_dir(requested_path, working_dir.as_deref()) {
+        if !Path::new(&dir).is_dir() {
+            return Err(format!(
+                "Launch denied: working directory not found at '{dir}'"
+            ));
+        }
+        command.current_dir(dir);
+    }
+    command
         .spawn()
         .map_err(|e| format!("Failed to launch {}: {}", requested_path, e))?;
 
@@ -157,20 +640,425 @@ pub async fn refresh_app_cache() -> Result<(), String> {
     Ok(())
 }
 
+#[tauri::command]
+pub async fn prune_icon_data(older_than_days: Option<i64>) -> Result<usize, String> {
+    let older_than_days = older_than_days.unwrap_or(90).max(1);
+    let current_paths = get_cached_apps()
+        .await
+        .into_iter()
+        .map(|app| app.path)
+        .collect::<Vec<_>>();
+    prune_cached_icons(current_paths, older_than_days).await
+}
+
+/// Paths of apps whose icon could not be extracted; the UI shows a generic icon for these.
+#[tauri::command]
+pub async fn list_icon_failures() -> Result<Vec<String>, String> {
+    tokio::task::spawn_blocking(crate::db::AppsRepository::list_icon_failures)
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+/// Most recent launches, newest first, for the activity view.
+#[tauri::command]
+pub async fn get_launch_timeline(
+    limit: Option<usize>,
+) -> Result<Vec<crate::db::LaunchEvent>, String> {
+    let limit = limit.unwrap_or(50).clamp(1, 500);
+    tokio::task::spawn_blocking(move || crate::db::AppsRepository::get_launch_timeline(limit))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+/// Diff a fresh scan against the stored app list, for "my app doesn't show up"
+/// troubleshooting. Nothing is written; use `refresh_app_cache` to apply.
+#[tauri::command]
+pub async fn diagnose_missing_apps() -> Result<MissingAppsReport, String> {
+    cache::diagnose_missing_apps().await
+}
+
+/// Merge a legacy `usage-stats.json` (by default the pre-SQLite location)
+/// into launch stats, returning how many apps were updated. The file is kept
+/// unless `delete_after` is set.
+#[tauri::command]
+pub async fn import_usage_stats(
+    path: Option<String>,
+    delete_after: Option<bool>,
+) -> Result<usize, String> {
+    let imported = tokio::task::spawn_blocking(move || {
+        let path = path
+            .map(|p| std::path::PathBuf::from(p.trim()))
+            .or_else(crate::db::AppsRepository::legacy_usage_json_path)
+            .ok_or_else(|| "Could not resolve the usage stats location".to_string())?;
+        if !path.exists() {
+            return Err(format!("No usage stats file at {}", path.display()));
+        }
+        crate::db::AppsRepository::import_usage_json(&path, delete_after.unwrap_or(false))
+            .map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+    cache::clear_search_cache().await;
+    Ok(imported)
+}
+
 #[tauri::command]
 pub async fn get_app_icon(path: String) -> Result<Option<String>, String> {
     Ok(get_or_extract_icon(path).await)
 }
 
+/// Dominant color of the app's icon as "#rrggbb", computed once and cached.
+/// `None` when the app has no icon.
 #[tauri::command]
-pub async fn get_suggestions(limit: Option<usize>) -> Result<Vec<SearchResult>, String> {
+pub async fn get_app_accent_color(path: String) -> Result<Option<String>, String> {
+    Ok(get_or_compute_icon_accent(path).await)
+}
+
+#[tauri::command]
+pub async fn get_suggestions(
+    limit: Option<usize>,
+    within_days: Option<i64>,
+) -> Result<Vec<SearchResult>, String> {
     let limit = limit.unwrap_or(8).clamp(1, 20);
-    let apps = get_suggested_apps(limit).await;
+    let apps = get_suggested_apps(limit, within_days).await;
 
     let suggestions = apps
         .into_iter()
-        .map(|app| SearchResult { app, score: 0 })
+        .map(|app| SearchResult {
+            app,
+            score: 0,
+            match_indices: Vec::new(),
+        })
         .collect();
 
     Ok(suggestions)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app(name: &str, path: &str, publisher: Option<&str>) -> AppInfo {
+        AppInfo {
+            name: name.to_string(),
+            path: path.to_string(),
+            publisher: publisher.map(str::to_string),
+            raw_name: None,
+        }
+    }
+
+    fn pe_header(subsystem: u16) -> Vec<u8> {
+        let pe_offset = 0x80usize;
+        let mut header = vec![0u8; 0x200];
+        header[0..2].copy_from_slice(b"MZ");
+        header[0x3c..0x40].copy_from_slice(&(pe_offset as u32).to_le_bytes());
+        header[pe_offset..pe_offset + 4].copy_from_slice(b"PE\0\0");
+        let offset = pe_offset + 4 + 20 + 68;
+        header[offset..offset + 2].copy_from_slice(&subsystem.to_le_bytes());
+        header
+    }
+
+    #[test]
+    fn test_pe_subsystem_detection() {
+        assert_eq!(
+            pe_subsystem(&pe_header(3)),
+            Some(IMAGE_SUBSYSTEM_WINDOWS_CUI)
+        );
+        assert_eq!(pe_subsystem(&pe_header(2)), Some(2));
+        assert_eq!(pe_subsystem(b"not an executable"), None);
+    }
+
+    #[test]
+    fn test_launch_creation_flags() {
+        assert_eq!(launch_creation_flags(true, true), 0);
+        assert_eq!(launch_creation_flags(true, false), CREATE_NO_WINDOW);
+        assert_eq!(launch_creation_flags(false, true), CREATE_NO_WINDOW);
+        assert_eq!(launch_creation_flags(false, false), CREATE_NO_WINDOW);
+    }
+
+    #[test]
+    fn test_grouped_results_classify_matches() {
+        let apps = vec![
+            app("Visual Studio Code", r"C:\Apps\Code.exe", Some("Microsoft")),
+            app("Notepad", r"C:\Windows\notepad.exe", Some("Microsoft")),
+            app(
+                "Paint",
+                r"C:\Windows\mspaint.exe",
+                Some("Contoso Visual Labs"),
+            ),
+        ];
+
+        let grouped = group_results(rank_apps(apps, "visual", false, &HashMap::new()));
+
+        let names = |results: &[SearchResult]| {
+            results
+                .iter()
+                .map(|r| r.app.name.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(&grouped.starts_with), vec!["Visual Studio Code"]);
+        assert_eq!(names(&grouped.publisher), vec!["Paint"]);
+        assert!(grouped.exact.is_empty());
+        assert!(grouped.contains.is_empty());
+    }
+
+    #[test]
+    fn test_apps_grouped_by_publisher() {
+        let apps = vec![
+            app("Word", r"C:\Office\word.exe", Some("Microsoft")),
+            app("Tool", r"C:\Tools\tool.exe", None),
+            app("Excel", r"C:\Office\excel.exe", Some("microsoft ")),
+            app(
+                "Blender",
+                r"C:\Blender\blender.exe",
+                Some("Blender Foundation"),
+            ),
+            app("Archive", r"C:\Tools\archive.exe", Some("  ")),
+        ];
+
+        let groups = group_by_publisher(apps);
+
+        let summary = groups
+            .iter()
+            .map(|group| {
+                let names = group
+                    .apps
+                    .iter()
+                    .map(|a| a.name.as_str())
+                    .collect::<Vec<_>>();
+                (group.publisher.as_str(), names)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                ("Blender Foundation", vec!["Blender"]),
+                ("Microsoft", vec!["Excel", "Word"]),
+                (UNKNOWN_PUBLISHER, vec!["Archive", "Tool"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_grouped_results_share_result_cap() {
+        let apps = (0..15)
+            .map(|i| {
+                app(
+                    &format!("Tool {i}"),
+                    &format!(r"C:\Tools\tool{i}.exe"),
+                    None,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let grouped = group_results(rank_apps(apps, "tool", false, &HashMap::new()));
+
+        let total = grouped.exact.len()
+            + grouped.starts_with.len()
+            + grouped.contains.len()
+            + grouped.publisher.len()
+            + grouped.fuzzy.len();
+        assert_eq!(total, 10);
+    }
+
+    #[test]
+    fn test_launch_count_breaks_score_ties() {
+        let apps = || {
+            vec![
+                app("Tool One", r"C:\Tools\one.exe", None),
+                app("Tool Two", r"C:\Tools\two.exe", None),
+                app("Power Tool", r"C:\Tools\power.exe", None),
+            ]
+        };
+        let names = |ranked: Vec<(SearchResult, MatchKind)>| {
+            ranked
+                .into_iter()
+                .map(|(result, _)| result.app.name)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            names(rank_apps(apps(), "tool", false, &HashMap::new())),
+            vec!["Tool One", "Tool Two", "Power Tool"]
+        );
+
+        let launch_counts = HashMap::from([
+            (r"c:\tools\two.exe".to_string(), 5),
+            (r"c:\tools\one.exe".to_string(), 1),
+            (r"c:\tools\power.exe".to_string(), 100),
+        ]);
+        // Usage only reorders equal scores; a weaker match stays behind.
+        assert_eq!(
+            names(rank_apps(apps(), "tool", false, &launch_counts)),
+            vec!["Tool Two", "Tool One", "Power Tool"]
+        );
+    }
+
+    #[test]
+    fn test_multi_word_query_requires_every_word() {
+        let apps = || {
+            vec![
+                app("Code Blocks", r"C:\Apps\codeblocks.exe", None),
+                app("Visual Paint", r"C:\Apps\vpaint.exe", None),
+                app("Visual Studio Code", r"C:\Apps\Code.exe", Some("Microsoft")),
+            ]
+        };
+        let names = |query: &str| {
+            rank_apps(apps(), query, false, &HashMap::new())
+                .into_iter()
+                .map(|(r, _)| r.app.name)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(names("visual code"), vec!["Visual Studio Code"]);
+        assert!(names("visual zzz").is_empty());
+
+        // A direct phrase match keeps its stronger classification.
+        let ranked = rank_apps(apps(), "visual studio code", false, &HashMap::new());
+        assert_eq!(ranked[0].0.app.name, "Visual Studio Code");
+        assert_eq!(ranked[0].1, MatchKind::Exact);
+    }
+
+    #[test]
+    fn test_match_indices_cover_matched_name_bytes() {
+        let apps = vec![
+            app("Visual Studio Code", r"C:\Apps\Code.exe", Some("Microsoft")),
+            app("Paint", r"C:\Windows\mspaint.exe", Some("Contoso Studio")),
+            app("微信 WeChat", r"C:\Apps\WeChat.exe", None),
+        ];
+        let indices = |query: &str, name: &str| {
+            rank_apps(apps.clone(), query, false, &HashMap::new())
+                .into_iter()
+                .find(|(r, _)| r.app.name == name)
+                .map(|(r, _)| r.match_indices)
+        };
+
+        assert_eq!(indices("studio", "Visual Studio Code"), Some(vec![(7, 13)]));
+        // Publisher-only match: nothing to highlight in the name.
+        assert_eq!(indices("studio", "Paint"), Some(Vec::new()));
+        assert_eq!(
+            indices("visual code", "Visual Studio Code"),
+            Some(vec![(0, 6), (14, 18)])
+        );
+        // Ranges are bytes, not chars.
+        assert_eq!(indices("微信", "微信 WeChat"), Some(vec![(0, 6)]));
+        assert_eq!(indices("zzz", "Visual Studio Code"), None);
+    }
+
+    #[test]
+    fn test_launch_info_derives_working_dir() {
+        let info = launch_info("\"C:\\Program Files\\Zed\\zed.exe\"", true);
+        assert_eq!(info.exe_path, "C:\\Program Files\\Zed\\zed.exe");
+        assert_eq!(info.working_dir.as_deref(), Some("C:\\Program Files\\Zed"));
+        assert!(info.exists);
+        assert!(!info.is_store_app);
+
+        assert_eq!(parent_dir("C:\\tool.exe").as_deref(), Some("C:\\"));
+        assert_eq!(parent_dir("D:/Apps/tool.exe").as_deref(), Some("D:/Apps"));
+        assert_eq!(parent_dir("tool.exe"), None);
+
+        let store = launch_info(
+            "C:\\Program Files\\WindowsApps\\Microsoft.WindowsCalculator_8wekyb3d8bbwe\\Calc.exe",
+            false,
+        );
+        assert!(store.is_store_app);
+        assert!(!store.exists);
+    }
+
+    #[test]
+    fn test_launch_dir_defaults_to_exe_parent() {
+        let exe = "C:\\Program Files\\Tool\\bin\\tool.exe";
+        assert_eq!(
+            resolve_launch_dir(exe, None).as_deref(),
+            Some("C:\\Program Files\\Tool\\bin")
+        );
+        assert_eq!(
+            resolve_launch_dir(exe, Some(" D:\\Work ")).as_deref(),
+            Some("D:\\Work")
+        );
+        // A blank override falls back to the default.
+        assert_eq!(
+            resolve_launch_dir(exe, Some("  ")),
+            launch_info(exe, true).working_dir
+        );
+    }
+
+    #[test]
+    fn test_launch_info_rejects_paths_outside_index() {
+        let apps = vec![app("Zed", "C:\\Apps\\Zed.exe", None)];
+        assert_eq!(
+            find_indexed_app(&apps, " c:\\apps\\zed.EXE ").unwrap().name,
+            "Zed"
+        );
+        assert!(find_indexed_app(&apps, "C:\\Windows\\System32\\cmd.exe")
+            .unwrap_err()
+            .contains("not in indexed"));
+        assert!(find_indexed_app(&apps, "   ").is_err());
+    }
+
+    #[test]
+    fn test_match_path_finds_app_by_folder_name() {
+        let apps = vec![
+            app("Python", "C:/Python310/python.exe", None),
+            app("pip", "C:/Python310/Scripts/pip.exe", None),
+            app("Notepad", "C:\\Windows\\notepad.exe", None),
+        ];
+        let names = |ranked: Vec<(SearchResult, MatchKind)>| {
+            ranked
+                .into_iter()
+                .map(|(result, _)| result.app.name)
+                .collect::<Vec<_>>()
+        };
+
+        assert!(names(rank_apps(apps.clone(), "scripts", false, &HashMap::new())).is_empty());
+        assert_eq!(
+            names(rank_apps(apps.clone(), "scripts", true, &HashMap::new())),
+            vec!["pip"]
+        );
+        // Name matches still outrank folder matches.
+        assert_eq!(
+            names(rank_apps(apps, "python310", true, &HashMap::new())),
+            vec!["Python", "pip"]
+        );
+        assert_eq!(
+            path_dir_segments("C:\\Python310\\Scripts\\pip.exe"),
+            vec!["python310", "scripts"]
+        );
+    }
+
+    #[test]
+    fn test_fuzzy_rank_matches_app_ordering() {
+        let names = [
+            "Notepad++",
+            "Visual Studio Code",
+            "Code Blocks",
+            "OneNote",
+            "Notepad",
+        ];
+        let apps = names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| app(name, &format!("C:/Apps/app{i}.exe"), None))
+            .collect::<Vec<_>>();
+
+        for query in ["note", "code", "notepad", "vs code", "ntpd"] {
+            let from_apps = rank_apps(apps.clone(), query, false, &HashMap::new())
+                .into_iter()
+                .map(|(result, _)| (result.app.name, result.score))
+                .collect::<Vec<_>>();
+            let from_candidates =
+                rank_candidates(query, names.iter().map(|n| n.to_string()).collect(), None);
+            assert_eq!(from_candidates, from_apps, "query {query:?}");
+        }
+
+        let limited = rank_candidates(
+            "note",
+            names.iter().map(|n| n.to_string()).collect(),
+            Some(1),
+        );
+        assert_eq!(limited.len(), 1);
+        assert!(rank_candidates("   ", vec!["Notepad".to_string()], None).is_empty());
+    }
+}
@@ -0,0 +1,107 @@
+//! Cleanup rules for scanned app names. Registry display names often carry
+//! version numbers and architecture suffixes ("Node.js (x64) v20.1") that
+//! get in the way of matching.
+
+/// Parenthesized suffixes naming a CPU architecture, compared after
+/// lowercasing and dropping spaces, '-' and '_'.
+const ARCH_SUFFIXES: &[&str] = &[
+    "64bit", "32bit", "x64", "x86", "x8664", "amd64", "arm64", "win64", "win32",
+];
+
+/// Words that may introduce a trailing version ("Git version 2.43").
+const VERSION_WORDS: &[&str] = &["version", "ver", "ver."];
+
+/// `text` without a trailing "(x64)"-style suffix, if it has one.
+fn strip_arch_suffix(text: &str) -> Option<&str> {
+    let open = text.strip_suffix(')')?.rfind('(')?;
+    let inner = text[open + 1..text.len() - 1]
+        .chars()
+        .filter(|c| !matches!(c, ' ' | '-' | '_'))
+        .collect::<String>()
+        .to_ascii_lowercase();
+    ARCH_SUFFIXES
+        .contains(&inner.as_str())
+        .then_some(&text[..open])
+}
+
+/// Dotted numbers like "20.1" or "v1.2.3"; a bare "2019" is kept since it is
+/// usually part of the product name.
+fn is_version(token: &str) -> bool {
+    let digits = token.strip_prefix(['v', 'V']).unwrap_or(token);
+    digits.contains('.')
+        && digits.starts_with(|c: char| c.is_ascii_digit())
+        && digits.ends_with(|c: char| c.is_ascii_digit())
+        && digits.chars().all(|c| c.is_ascii_digit() || c == '.')
+}
+
+/// `text` without a trailing version token (and a "version" word before it).
+fn strip_version_suffix(text: &str) -> Option<&str> {
+    let (rest, last) = text.rsplit_once(' ')?;
+    if !is_version(last) {
+        return None;
+    }
+    let rest = rest.trim_end();
+    match rest.rsplit_once(' ') {
+        Some((before, word)) if VERSION_WORDS.contains(&word.to_ascii_lowercase().as_str()) => {
+            Some(before)
+        }
+        _ => Some(rest),
+    }
+}
+
+/// Apply the cleanup rules to an already whitespace-normalized name,
+/// repeatedly stripping trailing versions and architecture suffixes. A name
+/// made only of such parts is returned unchanged.
+pub(super) fn clean_app_name(name: &str) -> String {
+    let mut cleaned = name;
+    loop {
+        let trimmed = cleaned.trim_end_matches([' ', '-', ',']);
+        match strip_arch_suffix(trimmed).or_else(|| strip_version_suffix(trimmed)) {
+            Some(rest) if !rest.trim().is_empty() => cleaned = rest,
+            _ => {
+                cleaned = trimmed;
+                break;
+            }
+        }
+    }
+    if cleaned.is_empty() {
+        name.to_string()
+    } else {
+        cleaned.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trailing_versions_stripped() {
+        assert_eq!(clean_app_name("Node.js v20.1"), "Node.js");
+        assert_eq!(clean_app_name("7-Zip 23.01"), "7-Zip");
+        assert_eq!(clean_app_name("Git version 2.43.0"), "Git");
+        assert_eq!(clean_app_name("CMake - 3.28.1"), "CMake");
+        // Years and single numbers are usually part of the name.
+        assert_eq!(clean_app_name("Office 2019"), "Office 2019");
+        assert_eq!(clean_app_name("Python 3"), "Python 3");
+        assert_eq!(clean_app_name("Paint.NET"), "Paint.NET");
+    }
+
+    #[test]
+    fn test_arch_suffixes_stripped() {
+        assert_eq!(clean_app_name("Node.js (x64) v20.1"), "Node.js");
+        assert_eq!(clean_app_name("Python 3.12.1 (64-bit)"), "Python");
+        assert_eq!(clean_app_name("Zoom (32 bit)"), "Zoom");
+        assert_eq!(clean_app_name("Tool (ARM64)"), "Tool");
+        // Other parentheticals are content.
+        assert_eq!(clean_app_name("Steam (Beta)"), "Steam (Beta)");
+        assert_eq!(clean_app_name("Notes (x64) Helper"), "Notes (x64) Helper");
+    }
+
+    #[test]
+    fn test_names_that_would_vanish_are_kept() {
+        assert_eq!(clean_app_name("1.2.3"), "1.2.3");
+        assert_eq!(clean_app_name("(x64)"), "(x64)");
+        assert_eq!(clean_app_name("v2.0 (x64)"), "v2.0");
+    }
+}
@@ -54,6 +54,7 @@ fn extract_apps_from_key(key: &windows_registry::Key) -> Vec<AppInfo> {
                                 name,
                                 path: clean_path,
                                 publisher: subkey.get_string("Publisher").ok(),
+                                raw_name: None,
                             });
                         }
                     }
@@ -174,6 +175,7 @@ fn scan_shortcuts_recursive(path: &PathBuf, apps: &mut Vec<AppInfo>) {
                                 name,
                                 path: target_path,
                                 publisher: None,
+                                raw_name: None,
                             });
                         }
                     }
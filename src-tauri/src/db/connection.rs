@@ -2,22 +2,133 @@ use crate::db::error::{DbError, DbResult};
 use crate::db::migrations;
 use once_cell::sync::Lazy;
 use rusqlite::Connection;
-use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, TryLockError};
+use std::time::{Duration, Instant};
 
 static DB_CONNECTION: Lazy<Arc<Mutex<Option<Connection>>>> =
     Lazy::new(|| Arc::new(Mutex::new(None)));
 
-/// Initialize the database connection and run migrations
-pub fn initialize(db_path: PathBuf) -> DbResult<()> {
+/// SQLite busy timeout used until the `db_busy_timeout_ms` setting is read.
+const DEFAULT_BUSY_TIMEOUT_MS: u64 = 5_000;
+
+/// `with_connection` logs a warning when a caller holds the lock longer than this.
+const LOCK_HELD_WARN_MS: u64 = 250;
+
+/// Who holds the connection lock now, and the longest hold seen so far.
+/// Kept behind its own mutex so it can be read while `DB_CONNECTION` is stuck.
+static LOCK_STATS: Lazy<Mutex<LockStats>> = Lazy::new(|| Mutex::new(LockStats::default()));
+
+#[derive(Debug, Default)]
+struct LockStats {
+    holder: Option<(String, Instant)>,
+    longest: Option<(String, u64)>,
+}
+
+impl LockStats {
+    fn enter(&mut self, caller: String, now: Instant) {
+        self.holder = Some((caller, now));
+    }
+
+    /// Clear the current holder and return how long it held the lock.
+    fn exit(&mut self, now: Instant) -> u64 {
+        let Some((caller, since)) = self.holder.take() else {
+            return 0;
+        };
+        let held_ms = now.duration_since(since).as_millis() as u64;
+        if !matches!(&self.longest, Some((_, ms)) if *ms >= held_ms) {
+            self.longest = Some((caller, held_ms));
+        }
+        held_ms
+    }
+
+    fn snapshot(&self, now: Instant) -> DbLockDiagnostics {
+        DbLockDiagnostics {
+            held_by: self.holder.as_ref().map(|(caller, _)| caller.clone()),
+            held_for_ms: self
+                .holder
+                .as_ref()
+                .map(|(_, since)| now.duration_since(*since).as_millis() as u64),
+            longest_hold_ms: self.longest.as_ref().map_or(0, |(_, ms)| *ms),
+            longest_hold_by: self.longest.as_ref().map(|(caller, _)| caller.clone()),
+        }
+    }
+}
+
+/// Connection lock state for diagnosing stalls and deadlocks.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DbLockDiagnostics {
+    /// Source location of the `with_connection` call holding the lock, if any.
+    pub held_by: Option<String>,
+    pub held_for_ms: Option<u64>,
+    pub longest_hold_ms: u64,
+    pub longest_hold_by: Option<String>,
+}
+
+/// Current lock holder and longest hold. Never waits on the connection lock.
+pub fn lock_diagnostics() -> DbResult<DbLockDiagnostics> {
+    LOCK_STATS
+        .lock()
+        .map(|stats| stats.snapshot(Instant::now()))
+        .map_err(|_| DbError::Connection("Failed to read lock diagnostics".to_string()))
+}
+
+/// Busy timeout in milliseconds, or `None` outside 100..=60000.
+pub(crate) fn parse_busy_timeout_ms(raw: Option<&str>) -> Option<u64> {
+    raw?.trim()
+        .parse::<u64>()
+        .ok()
+        .filter(|ms| (100..=60_000).contains(ms))
+}
+
+/// Background WAL checkpoint interval used until `wal_checkpoint_interval_minutes` is set.
+const DEFAULT_WAL_CHECKPOINT_INTERVAL_MINUTES: u64 = 10;
+const MAX_WAL_CHECKPOINT_INTERVAL_MINUTES: u64 = 24 * 60;
+
+/// Minutes between background checkpoints; non-positive values disable them.
+pub fn parse_wal_checkpoint_interval_minutes(raw: Option<&str>) -> u64 {
+    match raw.and_then(|value| value.trim().parse::<i64>().ok()) {
+        Some(minutes) if minutes <= 0 => 0,
+        Some(minutes) => (minutes as u64).min(MAX_WAL_CHECKPOINT_INTERVAL_MINUTES),
+        None => DEFAULT_WAL_CHECKPOINT_INTERVAL_MINUTES,
+    }
+}
+
+pub(crate) fn read_setting_conn(conn: &Connection, key: &str) -> DbResult<Option<String>> {
+    match conn.query_row("SELECT value FROM settings WHERE key = ?1", [key], |row| {
+        row.get::<_, String>(0)
+    }) {
+        Ok(value) => Ok(Some(value)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Apply the busy timeout from the settings table (or the default) and return it.
+fn apply_busy_timeout_conn(conn: &Connection) -> DbResult<Duration> {
+    let raw = read_setting_conn(conn, crate::SETTING_DB_BUSY_TIMEOUT_MS)?;
+    let timeout = Duration::from_millis(
+        parse_busy_timeout_ms(raw.as_deref()).unwrap_or(DEFAULT_BUSY_TIMEOUT_MS),
+    );
+    conn.busy_timeout(timeout)?;
+    Ok(timeout)
+}
+
+/// Re-read the busy timeout setting on the live connection.
+pub fn apply_busy_timeout() -> DbResult<Duration> {
+    with_connection(apply_busy_timeout_conn)
+}
+
+/// Open the database file at `db_path`, creating it if needed, and run migrations.
+fn open_on_disk(db_path: &Path) -> DbResult<Connection> {
     // Create parent directory if it doesn't exist
     if let Some(parent) = db_path.parent() {
         std::fs::create_dir_all(parent).map_err(DbError::Io)?;
     }
 
     // Open connection
-    let conn = Connection::open(&db_path)?;
+    let conn = Connection::open(db_path)?;
 
     // Configure SQLite for desktop app usage.
     conn.execute_batch(
@@ -27,23 +138,77 @@ pub fn initialize(db_path: PathBuf) -> DbResult<()> {
         PRAGMA synchronous = NORMAL;
         ",
     )?;
-    conn.busy_timeout(Duration::from_secs(5))?;
+    conn.busy_timeout(Duration::from_millis(DEFAULT_BUSY_TIMEOUT_MS))?;
 
     // Run migrations
     migrations::run_migrations(&conn)?;
+    apply_busy_timeout_conn(&conn)?;
+    Ok(conn)
+}
+
+/// A migrated database that lives only as long as the process.
+fn open_in_memory() -> DbResult<Connection> {
+    let conn = Connection::open_in_memory()?;
+    conn.execute_batch("PRAGMA foreign_keys = ON;")?;
+    migrations::run_migrations(&conn)?;
+    apply_busy_timeout_conn(&conn)?;
+    Ok(conn)
+}
 
-    // Store connection
-    {
-        let mut guard = DB_CONNECTION
-            .lock()
-            .map_err(|_| DbError::Connection("Failed to acquire lock".to_string()))?;
-        *guard = Some(conn);
+/// Whether opening failed because the file is out of reach, rather than
+/// because of what an existing file holds or who else is using it.
+fn is_unavailable(err: &DbError) -> bool {
+    match err {
+        DbError::Io(_) => true,
+        DbError::Sqlite(rusqlite::Error::SqliteFailure(e, _)) => matches!(
+            e.code,
+            rusqlite::ErrorCode::CannotOpen | rusqlite::ErrorCode::PermissionDenied
+        ),
+        _ => false,
     }
+}
+
+/// `open_on_disk`, or an in-memory database when the file cannot be located,
+/// created or opened, so the app keeps working without persistence. Errors
+/// from an existing file (a failed migration, a busy lock) are returned, since
+/// an empty database would hide the user's data and drop every write.
+fn open_with_fallback(db_path: DbResult<PathBuf>) -> DbResult<Connection> {
+    let err = match db_path {
+        Err(err) => err,
+        Ok(path) => match open_on_disk(&path) {
+            Ok(conn) => return Ok(conn),
+            Err(err) if is_unavailable(&err) => err,
+            Err(err) => return Err(err),
+        },
+    };
+    eprintln!(
+        "Database file unavailable ({err}); using an in-memory database. \
+         Settings, history and providers will not be saved this session."
+    );
+    open_in_memory()
+}
 
+fn store_connection(conn: Connection) -> DbResult<()> {
+    let mut guard = DB_CONNECTION
+        .lock()
+        .map_err(|_| DbError::Connection("Failed to acquire lock".to_string()))?;
+    *guard = Some(conn);
     Ok(())
 }
 
+/// Initialize the database connection and run migrations
+pub fn initialize(db_path: PathBuf) -> DbResult<()> {
+    store_connection(open_on_disk(&db_path)?)
+}
+
+/// Like `initialize`, but falls back to an in-memory database for this
+/// session instead of leaving the database layer unusable.
+pub fn initialize_with_fallback(db_path: DbResult<PathBuf>) -> DbResult<()> {
+    store_connection(open_with_fallback(db_path)?)
+}
+
 /// Execute a closure with the database connection
+#[track_caller]
 pub fn with_connection<F, T>(f: F) -> DbResult<T>
 where
     F: FnOnce(&Connection) -> DbResult<T>,
@@ -58,15 +223,200 @@ where
 
     // Important: do not call repository methods that re-enter with_connection
     // from inside this closure. std::sync::Mutex is not re-entrant.
-    f(conn)
+    let caller = std::panic::Location::caller();
+    if let Ok(mut stats) = LOCK_STATS.lock() {
+        stats.enter(caller.to_string(), Instant::now());
+    }
+    let result = f(conn);
+    let db_lock_held_ms = LOCK_STATS
+        .lock()
+        .map(|mut stats| stats.exit(Instant::now()))
+        .unwrap_or(0);
+    if db_lock_held_ms > LOCK_HELD_WARN_MS {
+        crate::logging::log_nonfatal(format!(
+            "Database lock held for {db_lock_held_ms}ms by {caller}"
+        ));
+    }
+    result
 }
 
-/// Shutdown and close the database connection
-#[allow(dead_code)]
+/// Fold the WAL back into the main database file and truncate it.
+fn checkpoint_conn(conn: &Connection) -> DbResult<()> {
+    let busy: i64 = conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |row| row.get(0))?;
+    if busy != 0 {
+        return Err(DbError::Connection(
+            "WAL checkpoint blocked by another reader or writer".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Checkpoint the WAL without closing the connection.
+pub fn checkpoint() -> DbResult<()> {
+    with_connection(checkpoint_conn)
+}
+
+/// Current `wal_checkpoint_interval_minutes`, parsed and clamped.
+pub fn wal_checkpoint_interval_minutes() -> DbResult<u64> {
+    with_connection(|conn| {
+        let raw = read_setting_conn(conn, crate::SETTING_WAL_CHECKPOINT_INTERVAL_MINUTES)?;
+        Ok(parse_wal_checkpoint_interval_minutes(raw.as_deref()))
+    })
+}
+
+/// Run a `PASSIVE` checkpoint, which never waits on readers or writers, and
+/// return the number of frames checkpointed. Returns `None` without touching
+/// the database when the connection is in use, so maintenance never queues
+/// behind (or ahead of) user queries.
+pub fn passive_checkpoint() -> DbResult<Option<i64>> {
+    let guard = match DB_CONNECTION.try_lock() {
+        Ok(guard) => guard,
+        Err(TryLockError::WouldBlock) => return Ok(None),
+        Err(TryLockError::Poisoned(_)) => {
+            return Err(DbError::Connection("Failed to acquire lock".to_string()))
+        }
+    };
+    let conn = guard
+        .as_ref()
+        .ok_or_else(|| DbError::Connection("Database not initialized".to_string()))?;
+
+    let checkpointed: i64 =
+        conn.query_row("PRAGMA wal_checkpoint(PASSIVE)", [], |row| row.get(2))?;
+    Ok(Some(checkpointed))
+}
+
+/// Checkpoint the WAL, then close the database connection.
 pub fn shutdown() {
-    if let Ok(mut guard) = DB_CONNECTION.lock() {
-        *guard = None;
+    let Ok(mut guard) = DB_CONNECTION.lock() else {
+        return;
+    };
+    let Some(conn) = guard.take() else {
+        return;
+    };
+
+    if let Err(e) = checkpoint_conn(&conn) {
+        eprintln!("Failed to checkpoint database on shutdown: {e}");
+    }
+    if let Err((_, e)) = conn.close() {
+        eprintln!("Failed to close database cleanly: {e}");
+    }
+}
+
+/// File next to the default database holding the `db_path_override` location.
+/// It cannot live in the settings table, which is inside the database it locates.
+const DB_PATH_OVERRIDE_FILE: &str = "db_path_override";
+
+/// Default database file name inside the app data directory.
+pub const DB_FILE_NAME: &str = "data.db";
+
+/// Database path to open: the override recorded in `data_dir`, or `default_path`.
+pub fn resolve_db_path(data_dir: &Path, default_path: PathBuf) -> PathBuf {
+    std::fs::read_to_string(data_dir.join(DB_PATH_OVERRIDE_FILE))
+        .ok()
+        .map(|raw| raw.trim().to_string())
+        .filter(|raw| !raw.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or(default_path)
+}
+
+/// Where the app keeps its files, for support and diagnostics. There is no
+/// log directory: diagnostics only go to stderr (see `crate::logging`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DataPaths {
+    pub data_dir: PathBuf,
+    /// The database actually opened, honouring any `db_path_override`.
+    pub db_path: PathBuf,
+}
+
+pub fn resolve_data_paths(data_dir: &Path) -> DataPaths {
+    DataPaths {
+        data_dir: data_dir.to_path_buf(),
+        db_path: resolve_db_path(data_dir, data_dir.join(DB_FILE_NAME)),
+    }
+}
+
+fn write_db_path_override(data_dir: &Path, path: &Path) -> DbResult<()> {
+    std::fs::create_dir_all(data_dir).map_err(DbError::Io)?;
+    std::fs::write(
+        data_dir.join(DB_PATH_OVERRIDE_FILE),
+        path.to_string_lossy().as_bytes(),
+    )
+    .map_err(DbError::Io)
+}
+
+fn sidecar_path(path: &Path, suffix: &str) -> PathBuf {
+    PathBuf::from(format!("{}{suffix}", path.display()))
+}
+
+/// Copy a closed database (and its WAL, if any) to `dest` after checking the
+/// destination is a new, writable location.
+fn copy_database(src: &Path, dest: &Path) -> DbResult<()> {
+    if dest.exists() {
+        let same = match (src.canonicalize(), dest.canonicalize()) {
+            (Ok(a), Ok(b)) => a == b,
+            _ => false,
+        };
+        return Err(DbError::Connection(if same {
+            "Destination is the current database file".to_string()
+        } else {
+            "Destination file already exists".to_string()
+        }));
     }
+
+    let parent = dest
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .ok_or_else(|| DbError::Connection("Destination has no parent directory".to_string()))?;
+    std::fs::create_dir_all(parent).map_err(DbError::Io)?;
+    let probe = parent.join(".ai-quick-search-write-test");
+    std::fs::write(&probe, b"")
+        .map_err(|e| DbError::Connection(format!("Destination directory is not writable: {e}")))?;
+    let _ = std::fs::remove_file(&probe);
+
+    std::fs::copy(src, dest).map_err(DbError::Io)?;
+    let src_wal = sidecar_path(src, "-wal");
+    if src_wal.exists() {
+        std::fs::copy(&src_wal, sidecar_path(dest, "-wal")).map_err(DbError::Io)?;
+    }
+    Ok(())
+}
+
+fn current_db_path() -> DbResult<PathBuf> {
+    with_connection(|conn| {
+        conn.path()
+            .filter(|p| !p.is_empty())
+            .map(PathBuf::from)
+            .ok_or_else(|| DbError::Connection("Database has no file path".to_string()))
+    })
+}
+
+/// Move the live database to `dest`: checkpoint and close it, copy it, record
+/// the override in `data_dir`, then reopen at the new location. The old file is
+/// left in place as a backup. If the copy fails the original is reopened.
+pub fn relocate(data_dir: &Path, dest: PathBuf) -> DbResult<PathBuf> {
+    let src = current_db_path()?;
+    shutdown();
+
+    if let Err(e) = copy_database(&src, &dest) {
+        initialize(src)?;
+        return Err(e);
+    }
+    if let Err(e) = initialize(dest.clone()) {
+        initialize(src)?;
+        return Err(e);
+    }
+    write_db_path_override(data_dir, &dest)?;
+    Ok(dest)
+}
+
+/// Open an in-memory database with all migrations applied, for repository tests.
+#[cfg(test)]
+pub fn open_test_connection() -> Connection {
+    let conn = Connection::open_in_memory().expect("open in-memory database");
+    conn.execute_batch("PRAGMA foreign_keys = ON;")
+        .expect("enable foreign keys");
+    migrations::run_migrations(&conn).expect("run migrations");
+    conn
 }
 
 #[cfg(test)]
@@ -74,6 +424,33 @@ mod tests {
     use super::*;
     use std::time::{SystemTime, UNIX_EPOCH};
 
+    #[test]
+    fn test_lock_stats_track_holder_and_longest_hold() {
+        let start = Instant::now();
+        let mut stats = LockStats::default();
+        assert_eq!(stats.snapshot(start).held_by, None);
+
+        stats.enter("src/a.rs:1:1".to_string(), start);
+        let during = stats.snapshot(start + Duration::from_millis(40));
+        assert_eq!(during.held_by.as_deref(), Some("src/a.rs:1:1"));
+        assert_eq!(during.held_for_ms, Some(40));
+        assert_eq!(stats.exit(start + Duration::from_millis(300)), 300);
+
+        stats.enter("src/b.rs:2:1".to_string(), start);
+        assert_eq!(stats.exit(start + Duration::from_millis(5)), 5);
+
+        let after = stats.snapshot(start + Duration::from_secs(1));
+        assert_eq!(
+            after,
+            DbLockDiagnostics {
+                held_by: None,
+                held_for_ms: None,
+                longest_hold_ms: 300,
+                longest_hold_by: Some("src/a.rs:1:1".to_string()),
+            }
+        );
+    }
+
     #[test]
     fn test_initialize_db_path() {
         let unique = SystemTime::now()
@@ -87,4 +464,200 @@ mod tests {
 
         let _ = std::fs::remove_file(path);
     }
+
+    #[test]
+    fn test_unwritable_path_falls_back_to_memory() {
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        // A regular file where the data directory should be cannot be created.
+        let blocker = std::env::temp_dir().join(format!("ai-quick-search-blocker-{unique}"));
+        std::fs::write(&blocker, b"").unwrap();
+
+        let conn = open_with_fallback(Ok(blocker.join("data").join(DB_FILE_NAME))).unwrap();
+        assert!(conn.path().is_none_or(str::is_empty));
+        assert_eq!(
+            migrations::get_current_version(&conn).unwrap(),
+            migrations::CURRENT_VERSION
+        );
+        conn.execute(
+            "INSERT INTO settings (key, value, updated_at) VALUES ('theme', 'dark', 0)",
+            [],
+        )
+        .unwrap();
+        assert_eq!(
+            read_setting_conn(&conn, "theme").unwrap(),
+            Some("dark".to_string())
+        );
+
+        let conn = open_with_fallback(Err(DbError::Connection("no data dir".to_string()))).unwrap();
+        assert!(conn.path().is_none_or(str::is_empty));
+
+        let _ = std::fs::remove_file(blocker);
+    }
+
+    #[test]
+    fn test_unreadable_existing_database_is_not_replaced() {
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let path = std::env::temp_dir().join(format!("ai-quick-search-garbage-{unique}.db"));
+        std::fs::write(&path, vec![0xAB; 4096]).unwrap();
+
+        assert!(open_with_fallback(Ok(path.clone())).is_err());
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_parse_wal_checkpoint_interval_minutes() {
+        assert_eq!(
+            parse_wal_checkpoint_interval_minutes(None),
+            DEFAULT_WAL_CHECKPOINT_INTERVAL_MINUTES
+        );
+        assert_eq!(
+            parse_wal_checkpoint_interval_minutes(Some("abc")),
+            DEFAULT_WAL_CHECKPOINT_INTERVAL_MINUTES
+        );
+        assert_eq!(parse_wal_checkpoint_interval_minutes(Some(" 30 ")), 30);
+        assert_eq!(parse_wal_checkpoint_interval_minutes(Some("0")), 0);
+        assert_eq!(parse_wal_checkpoint_interval_minutes(Some("-5")), 0);
+        assert_eq!(
+            parse_wal_checkpoint_interval_minutes(Some("100000")),
+            MAX_WAL_CHECKPOINT_INTERVAL_MINUTES
+        );
+    }
+
+    #[test]
+    fn test_checkpoint_truncates_wal() {
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let path = std::env::temp_dir().join(format!("ai-quick-search-wal-{unique}.db"));
+        let wal_path = PathBuf::from(format!("{}-wal", path.display()));
+
+        let conn = Connection::open(&path).unwrap();
+        conn.execute_batch(
+            "
+            PRAGMA journal_mode = WAL;
+            CREATE TABLE t (v TEXT);
+            INSERT INTO t (v) VALUES ('a'), ('b'), ('c');
+            ",
+        )
+        .unwrap();
+        assert!(std::fs::metadata(&wal_path).unwrap().len() > 0);
+
+        checkpoint_conn(&conn).unwrap();
+        assert_eq!(std::fs::metadata(&wal_path).unwrap().len(), 0);
+
+        drop(conn);
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&wal_path);
+        let _ = std::fs::remove_file(format!("{}-shm", path.display()));
+    }
+
+    #[test]
+    fn test_busy_timeout_setting_is_applied() {
+        let conn = open_test_connection();
+        let busy_timeout = |conn: &Connection| -> i64 {
+            conn.query_row("PRAGMA busy_timeout", [], |row| row.get(0))
+                .unwrap()
+        };
+
+        assert_eq!(
+            apply_busy_timeout_conn(&conn).unwrap(),
+            Duration::from_millis(DEFAULT_BUSY_TIMEOUT_MS)
+        );
+        assert_eq!(busy_timeout(&conn), 5_000);
+
+        conn.execute(
+            "INSERT INTO settings (key, value, updated_at) VALUES (?1, ' 1500 ', 0)",
+            [crate::SETTING_DB_BUSY_TIMEOUT_MS],
+        )
+        .unwrap();
+        assert_eq!(
+            apply_busy_timeout_conn(&conn).unwrap(),
+            Duration::from_millis(1_500)
+        );
+        assert_eq!(busy_timeout(&conn), 1_500);
+
+        // Out-of-range values fall back to the default.
+        conn.execute(
+            "UPDATE settings SET value = '5' WHERE key = ?1",
+            [crate::SETTING_DB_BUSY_TIMEOUT_MS],
+        )
+        .unwrap();
+        apply_busy_timeout_conn(&conn).unwrap();
+        assert_eq!(busy_timeout(&conn), 5_000);
+    }
+
+    #[test]
+    fn test_resolve_data_paths_follows_override() {
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let data_dir = std::env::temp_dir().join(format!("ai-quick-search-paths-{unique}"));
+
+        let paths = resolve_data_paths(&data_dir);
+        assert_eq!(paths.data_dir, data_dir);
+        assert_eq!(paths.db_path, data_dir.join(DB_FILE_NAME));
+
+        let moved = data_dir.join("elsewhere").join("data.db");
+        write_db_path_override(&data_dir, &moved).unwrap();
+        assert_eq!(resolve_data_paths(&data_dir).db_path, moved);
+
+        let _ = std::fs::remove_dir_all(data_dir);
+    }
+
+    #[test]
+    fn test_copy_database_and_reopen() {
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let root = std::env::temp_dir().join(format!("ai-quick-search-relocate-{unique}"));
+        let src = root.join("old").join("data.db");
+        let dest = root.join("new").join("nested").join("data.db");
+        std::fs::create_dir_all(src.parent().unwrap()).unwrap();
+
+        let conn = Connection::open(&src).unwrap();
+        conn.execute_batch(
+            "
+            PRAGMA journal_mode = WAL;
+            CREATE TABLE t (v TEXT);
+            INSERT INTO t (v) VALUES ('kept');
+            ",
+        )
+        .unwrap();
+        checkpoint_conn(&conn).unwrap();
+        conn.close().unwrap();
+
+        // Copying onto itself or over an existing file is refused.
+        assert!(copy_database(&src, &src).is_err());
+
+        copy_database(&src, &dest).unwrap();
+        assert!(copy_database(&src, &dest).is_err());
+
+        let reopened = Connection::open(&dest).unwrap();
+        let value: String = reopened
+            .query_row("SELECT v FROM t", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(value, "kept");
+        drop(reopened);
+
+        let data_dir = root.join("appdata");
+        let default_path = data_dir.join("data.db");
+        assert_eq!(
+            resolve_db_path(&data_dir, default_path.clone()),
+            default_path
+        );
+        write_db_path_override(&data_dir, &dest).unwrap();
+        assert_eq!(resolve_db_path(&data_dir, default_path), dest);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
 }
@@ -1,4 +1,21 @@
+mod v10_provider_responses_api;
+mod v11_prompt_templates;
+mod v12_provider_context_window;
+mod v13_provider_requests_per_minute;
+mod v14_message_provider_name;
+mod v15_app_icon_extraction_failed;
+mod v16_provider_role;
+mod v17_message_content_hash;
+mod v18_recent_queries;
+mod v19_launch_events;
 mod v1_initial;
+mod v20_provider_max_concurrency;
+mod v21_session_canonical_message;
+mod v22_session_tags;
+mod v23_column_presets;
+mod v24_app_raw_name;
+mod v25_message_requests;
+mod v26_app_icon_accent;
 mod v2_normalized_path;
 mod v3_providers;
 mod v4_provider_api_key_sqlite;
@@ -7,10 +24,28 @@ mod v6_chat_messages;
 mod v7_refactor_schema;
 mod v8_fix_shared_messages;
 mod v9_session_columns;
+mod verify;
 
 use crate::db::error::{DbError, DbResult};
 use std::time::{SystemTime, UNIX_EPOCH};
+use v10_provider_responses_api as V10;
+use v11_prompt_templates as V11;
+use v12_provider_context_window as V12;
+use v13_provider_requests_per_minute as V13;
+use v14_message_provider_name as V14;
+use v15_app_icon_extraction_failed as V15;
+use v16_provider_role as V16;
+use v17_message_content_hash as V17;
+use v18_recent_queries as V18;
+use v19_launch_events as V19;
 use v1_initial as V1;
+use v20_provider_max_concurrency as V20;
+use v21_session_canonical_message as V21;
+use v22_session_tags as V22;
+use v23_column_presets as V23;
+use v24_app_raw_name as V24;
+use v25_message_requests as V25;
+use v26_app_icon_accent as V26;
 use v2_normalized_path as V2;
 use v3_providers as V3;
 use v4_provider_api_key_sqlite as V4;
@@ -20,8 +55,11 @@ use v7_refactor_schema as V7;
 use v8_fix_shared_messages as V8;
 use v9_session_columns as V9;
 
+pub use v9_session_columns::{backfill_message_column_ids, ensure_session_columns};
+pub use verify::{verify_schema, SchemaVerification};
+
 #[allow(dead_code)]
-pub const CURRENT_VERSION: u32 = 9;
+pub const CURRENT_VERSION: u32 = 26;
 
 fn now_unix_ms() -> u64 {
     SystemTime::now()
@@ -30,7 +68,7 @@ fn now_unix_ms() -> u64 {
         .unwrap_or(0)
 }
 
-fn get_current_version(conn: &rusqlite::Connection) -> DbResult<u32> {
+pub(crate) fn get_current_version(conn: &rusqlite::Connection) -> DbResult<u32> {
     // First check if the schema_version table exists
     let table_exists: bool = conn
         .query_row(
@@ -122,6 +160,108 @@ pub fn run_migrations(conn: &rusqlite::Connection) -> DbResult<()> {
         set_version(conn, V9::VERSION)?;
     }
 
+    // V10: opt-in Responses API flag for OpenAI providers.
+    if current < V10::VERSION {
+        V10::apply(conn)?;
+        set_version(conn, V10::VERSION)?;
+    }
+
+    // V11: reusable prompt templates with optional shortcuts.
+    if current < V11::VERSION {
+        V11::apply(conn)?;
+        set_version(conn, V11::VERSION)?;
+    }
+
+    // V12: optional context window size per provider.
+    if current < V12::VERSION {
+        V12::apply(conn)?;
+        set_version(conn, V12::VERSION)?;
+    }
+
+    // V13: optional requests-per-minute cap per provider.
+    if current < V13::VERSION {
+        V13::apply(conn)?;
+        set_version(conn, V13::VERSION)?;
+    }
+
+    // V14: provider name snapshot on chat messages.
+    if current < V14::VERSION {
+        V14::apply(conn)?;
+        set_version(conn, V14::VERSION)?;
+    }
+
+    // V15: app icon extraction failure flag.
+    if current < V15::VERSION {
+        V15::apply(conn)?;
+        set_version(conn, V15::VERSION)?;
+    }
+
+    // V16: provider role (chat / utility / both).
+    if current < V16::VERSION {
+        V16::apply(conn)?;
+        set_version(conn, V16::VERSION)?;
+    }
+
+    // V17: content hash for finished assistant replies.
+    if current < V17::VERSION {
+        V17::apply(conn)?;
+        set_version(conn, V17::VERSION)?;
+    }
+
+    // V18: recent launcher queries.
+    if current < V18::VERSION {
+        V18::apply(conn)?;
+        set_version(conn, V18::VERSION)?;
+    }
+
+    // V19: per-launch timeline events.
+    if current < V19::VERSION {
+        V19::apply(conn)?;
+        set_version(conn, V19::VERSION)?;
+    }
+
+    // V20: per-provider concurrency limit.
+    if current < V20::VERSION {
+        V20::apply(conn)?;
+        set_version(conn, V20::VERSION)?;
+    }
+
+    // V21: canonical answer per session.
+    if current < V21::VERSION {
+        V21::apply(conn)?;
+        set_version(conn, V21::VERSION)?;
+    }
+
+    // V22: session tags.
+    if current < V22::VERSION {
+        V22::apply(conn)?;
+        set_version(conn, V22::VERSION)?;
+    }
+
+    // V23: column presets.
+    if current < V23::VERSION {
+        V23::apply(conn)?;
+        set_version(conn, V23::VERSION)?;
+    }
+
+    // V24: raw app names.
+    if current < V24::VERSION {
+        V24::apply(conn)?;
+        set_version(conn, V24::VERSION)?;
+    }
+
+    // V25: redacted request payloads per assistant message.
+    if current < V25::VERSION {
+        V25::apply(conn)?;
+        set_version(conn, V25::VERSION)?;
+    }
+
+    // V26: cached icon accent color.
+    if current < V26::VERSION {
+        V26::apply(conn)?;
+        set_version(conn, V26::VERSION)?;
+    }
+
     Ok(())
 }
 
@@ -131,6 +271,6 @@ mod tests {
 
     #[test]
     fn test_version_is_correct() {
-        assert_eq!(CURRENT_VERSION, 9);
+        assert_eq!(CURRENT_VERSION, 26);
     }
 }
@@ -0,0 +1,37 @@
+use crate::db::error::DbResult;
+
+pub const VERSION: u32 = 10;
+
+/// V10: opt-in flag for routing OpenAI providers through the Responses API.
+pub fn apply(conn: &rusqlite::Connection) -> DbResult<()> {
+    let has_column: bool = conn
+        .query_row(
+            "SELECT EXISTS(
+                SELECT 1
+                FROM pragma_table_info('providers')
+                WHERE name='use_responses_api'
+            )",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap_or(false);
+
+    if !has_column {
+        conn.execute(
+            "ALTER TABLE providers ADD COLUMN use_responses_api INTEGER NOT NULL DEFAULT 0",
+            [],
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_is_correct() {
+        assert_eq!(VERSION, 10);
+    }
+}
@@ -0,0 +1,30 @@
+use crate::db::error::DbResult;
+
+pub const VERSION: u32 = 11;
+
+/// V11: reusable prompt templates. `shortcut` is optional but unique when set.
+pub fn apply(conn: &rusqlite::Connection) -> DbResult<()> {
+    conn.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS prompt_templates (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            body TEXT NOT NULL DEFAULT '',
+            shortcut TEXT COLLATE NOCASE UNIQUE,
+            created_at INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL
+        );
+        ",
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_is_correct() {
+        assert_eq!(VERSION, 11);
+    }
+}
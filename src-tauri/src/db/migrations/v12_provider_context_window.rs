@@ -0,0 +1,37 @@
+use crate::db::error::DbResult;
+
+pub const VERSION: u32 = 12;
+
+/// V12: optional context window (in tokens) used for prompt-size routing hints.
+pub fn apply(conn: &rusqlite::Connection) -> DbResult<()> {
+    let has_column: bool = conn
+        .query_row(
+            "SELECT EXISTS(
+                SELECT 1
+                FROM pragma_table_info('providers')
+                WHERE name='context_window'
+            )",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap_or(false);
+
+    if !has_column {
+        conn.execute(
+            "ALTER TABLE providers ADD COLUMN context_window INTEGER",
+            [],
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_is_correct() {
+        assert_eq!(VERSION, 12);
+    }
+}
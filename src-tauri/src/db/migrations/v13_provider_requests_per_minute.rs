@@ -0,0 +1,37 @@
+use crate::db::error::DbResult;
+
+pub const VERSION: u32 = 13;
+
+/// V13: optional per-provider requests-per-minute cap enforced locally before sending.
+pub fn apply(conn: &rusqlite::Connection) -> DbResult<()> {
+    let has_column: bool = conn
+        .query_row(
+            "SELECT EXISTS(
+                SELECT 1
+                FROM pragma_table_info('providers')
+                WHERE name='requests_per_minute'
+            )",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap_or(false);
+
+    if !has_column {
+        conn.execute(
+            "ALTER TABLE providers ADD COLUMN requests_per_minute INTEGER",
+            [],
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_is_correct() {
+        assert_eq!(VERSION, 13);
+    }
+}
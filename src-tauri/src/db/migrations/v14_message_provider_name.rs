@@ -0,0 +1,45 @@
+use crate::db::error::DbResult;
+
+pub const VERSION: u32 = 14;
+
+/// V14: snapshot of the provider name on each message, backfilled from the
+/// providers that still exist.
+pub fn apply(conn: &rusqlite::Connection) -> DbResult<()> {
+    let has_column: bool = conn
+        .query_row(
+            "SELECT EXISTS(
+                SELECT 1
+                FROM pragma_table_info('chat_messages')
+                WHERE name='provider_name'
+            )",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap_or(false);
+
+    if !has_column {
+        conn.execute(
+            "ALTER TABLE chat_messages ADD COLUMN provider_name TEXT",
+            [],
+        )?;
+    }
+
+    conn.execute(
+        "UPDATE chat_messages
+         SET provider_name = (SELECT p.name FROM providers p WHERE p.id = chat_messages.provider_id)
+         WHERE provider_name IS NULL",
+        [],
+    )?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_is_correct() {
+        assert_eq!(VERSION, 14);
+    }
+}
@@ -0,0 +1,37 @@
+use crate::db::error::DbResult;
+
+pub const VERSION: u32 = 15;
+
+/// V15: remember apps whose icon could not be extracted, distinct from "not tried yet".
+pub fn apply(conn: &rusqlite::Connection) -> DbResult<()> {
+    let has_column: bool = conn
+        .query_row(
+            "SELECT EXISTS(
+                SELECT 1
+                FROM pragma_table_info('apps')
+                WHERE name='icon_extraction_failed'
+            )",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap_or(false);
+
+    if !has_column {
+        conn.execute(
+            "ALTER TABLE apps ADD COLUMN icon_extraction_failed INTEGER NOT NULL DEFAULT 0",
+            [],
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_is_correct() {
+        assert_eq!(VERSION, 15);
+    }
+}
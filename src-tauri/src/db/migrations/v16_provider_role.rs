@@ -0,0 +1,37 @@
+use crate::db::error::DbResult;
+
+pub const VERSION: u32 = 16;
+
+/// V16: provider role, so chat and utility tasks can use different providers.
+pub fn apply(conn: &rusqlite::Connection) -> DbResult<()> {
+    let has_column: bool = conn
+        .query_row(
+            "SELECT EXISTS(
+                SELECT 1
+                FROM pragma_table_info('providers')
+                WHERE name='role'
+            )",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap_or(false);
+
+    if !has_column {
+        conn.execute(
+            "ALTER TABLE providers ADD COLUMN role TEXT NOT NULL DEFAULT 'both'",
+            [],
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_is_correct() {
+        assert_eq!(VERSION, 16);
+    }
+}
@@ -0,0 +1,53 @@
+use crate::db::error::DbResult;
+use crate::db::repositories::content_hash;
+
+pub const VERSION: u32 = 17;
+
+/// V17: hash of each finished assistant reply, so identical answers across
+/// columns can be collapsed. Existing replies are backfilled.
+pub fn apply(conn: &rusqlite::Connection) -> DbResult<()> {
+    let has_column: bool = conn
+        .query_row(
+            "SELECT EXISTS(
+                SELECT 1
+                FROM pragma_table_info('chat_messages')
+                WHERE name='content_hash'
+            )",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap_or(false);
+
+    if !has_column {
+        conn.execute("ALTER TABLE chat_messages ADD COLUMN content_hash TEXT", [])?;
+    }
+
+    let pending: Vec<(String, String)> = {
+        let mut stmt = conn.prepare(
+            "SELECT id, content FROM chat_messages
+             WHERE role = 'assistant' AND status = 'done' AND content_hash IS NULL",
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        let mut values = Vec::new();
+        for row in rows {
+            values.push(row?);
+        }
+        values
+    };
+    let mut stmt = conn.prepare("UPDATE chat_messages SET content_hash = ?1 WHERE id = ?2")?;
+    for (id, content) in pending {
+        stmt.execute(rusqlite::params![content_hash(&content), id])?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_is_correct() {
+        assert_eq!(VERSION, 17);
+    }
+}
@@ -0,0 +1,27 @@
+use crate::db::error::DbResult;
+
+pub const VERSION: u32 = 18;
+
+/// V18: launcher query history, newest rows last.
+pub fn apply(conn: &rusqlite::Connection) -> DbResult<()> {
+    conn.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS recent_queries (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            text TEXT NOT NULL,
+            created_at INTEGER NOT NULL
+        );
+        ",
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_is_correct() {
+        assert_eq!(VERSION, 18);
+    }
+}
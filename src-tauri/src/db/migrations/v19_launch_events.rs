@@ -0,0 +1,33 @@
+use crate::db::error::DbResult;
+
+pub const VERSION: u32 = 19;
+
+/// V19: one row per app launch for the activity timeline. `app_usage` keeps
+/// the aggregate counters used for suggestions.
+pub fn apply(conn: &rusqlite::Connection) -> DbResult<()> {
+    conn.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS launch_events (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            app_id INTEGER NOT NULL,
+            launched_at INTEGER NOT NULL,
+            FOREIGN KEY (app_id) REFERENCES apps(id) ON DELETE CASCADE
+        );
+        CREATE INDEX IF NOT EXISTS idx_launch_events_app
+            ON launch_events(app_id, launched_at);
+        CREATE INDEX IF NOT EXISTS idx_launch_events_launched_at
+            ON launch_events(launched_at);
+        ",
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_is_correct() {
+        assert_eq!(VERSION, 19);
+    }
+}
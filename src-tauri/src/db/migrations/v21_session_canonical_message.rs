@@ -0,0 +1,39 @@
+use crate::db::error::DbResult;
+
+pub const VERSION: u32 = 21;
+
+/// V21: the answer a user picked as canonical among a session's columns.
+/// Cleared automatically when that message is deleted.
+pub fn apply(conn: &rusqlite::Connection) -> DbResult<()> {
+    let has_column: bool = conn
+        .query_row(
+            "SELECT EXISTS(
+                SELECT 1
+                FROM pragma_table_info('chat_sessions')
+                WHERE name='canonical_message_id'
+            )",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap_or(false);
+
+    if !has_column {
+        conn.execute(
+            "ALTER TABLE chat_sessions ADD COLUMN canonical_message_id TEXT
+             REFERENCES chat_messages(id) ON DELETE SET NULL",
+            [],
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_is_correct() {
+        assert_eq!(VERSION, 21);
+    }
+}
@@ -0,0 +1,30 @@
+use crate::db::error::DbResult;
+
+pub const VERSION: u32 = 22;
+
+/// V22: free-form tags on chat sessions, one row per (session, tag).
+pub fn apply(conn: &rusqlite::Connection) -> DbResult<()> {
+    conn.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS chat_session_tags (
+            session_id TEXT NOT NULL,
+            tag TEXT NOT NULL,
+            created_at INTEGER NOT NULL,
+            PRIMARY KEY (session_id, tag),
+            FOREIGN KEY (session_id) REFERENCES chat_sessions(id) ON DELETE CASCADE
+        );
+        CREATE INDEX IF NOT EXISTS idx_chat_session_tags_tag ON chat_session_tags(tag);
+        ",
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_is_correct() {
+        assert_eq!(VERSION, 22);
+    }
+}
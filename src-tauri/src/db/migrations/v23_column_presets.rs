@@ -0,0 +1,29 @@
+use crate::db::error::DbResult;
+
+pub const VERSION: u32 = 23;
+
+/// V23: named column layouts (ordered provider ids) for new sessions.
+pub fn apply(conn: &rusqlite::Connection) -> DbResult<()> {
+    conn.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS column_presets (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL COLLATE NOCASE UNIQUE,
+            provider_ids_json TEXT NOT NULL DEFAULT '[]',
+            created_at INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL
+        );
+        ",
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_is_correct() {
+        assert_eq!(VERSION, 23);
+    }
+}
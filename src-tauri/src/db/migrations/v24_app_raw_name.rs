@@ -0,0 +1,34 @@
+use crate::db::error::DbResult;
+
+pub const VERSION: u32 = 24;
+
+/// V24: the scanned app name before cleanup rules were applied.
+pub fn apply(conn: &rusqlite::Connection) -> DbResult<()> {
+    let has_column: bool = conn
+        .query_row(
+            "SELECT EXISTS(
+                SELECT 1
+                FROM pragma_table_info('apps')
+                WHERE name='raw_name'
+            )",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap_or(false);
+
+    if !has_column {
+        conn.execute("ALTER TABLE apps ADD COLUMN raw_name TEXT", [])?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_is_correct() {
+        assert_eq!(VERSION, 24);
+    }
+}
@@ -0,0 +1,30 @@
+use crate::db::error::DbResult;
+
+pub const VERSION: u32 = 25;
+
+/// V25: the redacted request that produced each assistant message, kept only
+/// while the `store_message_requests` setting is on.
+pub fn apply(conn: &rusqlite::Connection) -> DbResult<()> {
+    conn.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS message_requests (
+            message_id TEXT PRIMARY KEY,
+            provider_id TEXT NOT NULL,
+            payload_json TEXT NOT NULL,
+            created_at INTEGER NOT NULL,
+            FOREIGN KEY (message_id) REFERENCES chat_messages(id) ON DELETE CASCADE
+        );
+        ",
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_is_correct() {
+        assert_eq!(VERSION, 25);
+    }
+}
@@ -0,0 +1,34 @@
+use crate::db::error::DbResult;
+
+pub const VERSION: u32 = 26;
+
+/// V26: dominant icon color as "#rrggbb", computed on demand from `icon_data`.
+pub fn apply(conn: &rusqlite::Connection) -> DbResult<()> {
+    let has_column: bool = conn
+        .query_row(
+            "SELECT EXISTS(
+                SELECT 1
+                FROM pragma_table_info('apps')
+                WHERE name='icon_accent'
+            )",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap_or(false);
+
+    if !has_column {
+        conn.execute("ALTER TABLE apps ADD COLUMN icon_accent TEXT", [])?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_is_correct() {
+        assert_eq!(VERSION, 26);
+    }
+}
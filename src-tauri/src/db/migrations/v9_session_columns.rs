@@ -62,7 +62,18 @@ pub fn apply(conn: &rusqlite::Connection) -> DbResult<()> {
         [],
     )?;
 
+    ensure_session_columns(conn)?;
+    backfill_message_column_ids(conn)?;
+
+    Ok(())
+}
+
+/// Create columns for every session that has none, derived from
+/// `provider_ids_json` (or the providers seen in its messages).
+/// Returns the number of columns created.
+pub fn ensure_session_columns(conn: &rusqlite::Connection) -> DbResult<usize> {
     let now = now_unix_ms();
+    let mut created = 0usize;
 
     // Build columns for each session if missing.
     let sessions: Vec<(String, String)> = {
@@ -78,6 +89,16 @@ pub fn apply(conn: &rusqlite::Connection) -> DbResult<()> {
     };
 
     for (session_id, provider_ids_json) in sessions {
+        // Insert only if no columns exist for this session.
+        let existing_count: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM chat_session_columns WHERE session_id = ?1",
+            [&session_id],
+            |row| row.get(0),
+        )?;
+        if existing_count > 0 {
+            continue;
+        }
+
         let mut provider_ids = parse_provider_ids(&provider_ids_json);
         if provider_ids.is_empty() {
             // Fallback for legacy/inconsistent rows.
@@ -98,26 +119,26 @@ pub fn apply(conn: &rusqlite::Connection) -> DbResult<()> {
             provider_ids.push(String::new());
         }
 
-        // Insert only if no columns exist for this session.
-        let existing_count: i64 = conn.query_row(
-            "SELECT COUNT(*) FROM chat_session_columns WHERE session_id = ?1",
-            [&session_id],
-            |row| row.get(0),
-        )?;
-
-        if existing_count == 0 {
-            for (idx, provider_id) in provider_ids.iter().enumerate() {
-                let column_id = format!("{session_id}:c{idx}");
-                conn.execute(
-                    "INSERT OR IGNORE INTO chat_session_columns
-                     (id, session_id, position, provider_id, created_at, updated_at)
-                     VALUES (?1, ?2, ?3, ?4, ?5, ?5)",
-                    rusqlite::params![column_id, session_id, idx as i64, provider_id, now],
-                )?;
-            }
+        for (idx, provider_id) in provider_ids.iter().enumerate() {
+            let column_id = format!("{session_id}:c{idx}");
+            created += conn.execute(
+                "INSERT OR IGNORE INTO chat_session_columns
+                 (id, session_id, position, provider_id, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?5)",
+                rusqlite::params![column_id, session_id, idx as i64, provider_id, now],
+            )?;
         }
     }
 
+    Ok(created)
+}
+
+/// Assign a column to every message without one, preferring the first column
+/// that uses the message's provider and falling back to the session's first
+/// column. Returns the number of messages updated.
+pub fn backfill_message_column_ids(conn: &rusqlite::Connection) -> DbResult<usize> {
+    let mut updated = 0usize;
+
     // Backfill message.column_id for old rows.
     let message_rows: Vec<(String, String, String)> = {
         let mut stmt = conn.prepare(
@@ -165,14 +186,14 @@ pub fn apply(conn: &rusqlite::Connection) -> DbResult<()> {
             });
 
         if let Some(column_id) = candidate {
-            conn.execute(
+            updated += conn.execute(
                 "UPDATE chat_messages SET column_id = ?1 WHERE id = ?2",
                 rusqlite::params![column_id, message_id],
             )?;
         }
     }
 
-    Ok(())
+    Ok(updated)
 }
 
 #[cfg(test)]
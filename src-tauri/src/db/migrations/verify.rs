@@ -0,0 +1,114 @@
+use crate::db::error::DbResult;
+use rusqlite::Connection;
+use serde::Serialize;
+use std::collections::BTreeSet;
+
+/// Schema objects the migrations create but the live database lacks.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct SchemaVerification {
+    pub ok: bool,
+    pub schema_version: u32,
+    pub missing_tables: Vec<String>,
+    /// As `table.column`, for tables that exist.
+    pub missing_columns: Vec<String>,
+    pub missing_indexes: Vec<String>,
+    pub missing_triggers: Vec<String>,
+}
+
+fn object_names(conn: &Connection, kind: &str) -> DbResult<BTreeSet<String>> {
+    let mut stmt = conn.prepare(
+        "SELECT name FROM sqlite_master
+         WHERE type = ?1 AND name NOT LIKE 'sqlite_%'",
+    )?;
+    let names = stmt
+        .query_map([kind], |row| row.get(0))?
+        .collect::<Result<BTreeSet<String>, _>>()?;
+    Ok(names)
+}
+
+fn column_names(conn: &Connection, table: &str) -> DbResult<BTreeSet<String>> {
+    let mut stmt = conn.prepare("SELECT name FROM pragma_table_info(?1)")?;
+    let names = stmt
+        .query_map([table], |row| row.get(0))?
+        .collect::<Result<BTreeSet<String>, _>>()?;
+    Ok(names)
+}
+
+/// Compare `actual` against a fresh in-memory database with every migration
+/// applied, so the expected schema is always the one the migrations build.
+/// Read-only; `PRAGMA integrity_check` covers corruption, this covers drift
+/// such as hand edits or a partially applied migration.
+pub fn verify_schema(actual: &Connection) -> DbResult<SchemaVerification> {
+    let expected = Connection::open_in_memory()?;
+    super::run_migrations(&expected)?;
+    compare_schemas(&expected, actual)
+}
+
+fn compare_schemas(expected: &Connection, actual: &Connection) -> DbResult<SchemaVerification> {
+    let actual_tables = object_names(actual, "table")?;
+    let mut report = SchemaVerification {
+        schema_version: super::get_current_version(actual)?,
+        ..Default::default()
+    };
+
+    for table in object_names(expected, "table")? {
+        if !actual_tables.contains(&table) {
+            report.missing_tables.push(table);
+            continue;
+        }
+        let actual_columns = column_names(actual, &table)?;
+        for column in column_names(expected, &table)? {
+            if !actual_columns.contains(&column) {
+                report.missing_columns.push(format!("{table}.{column}"));
+            }
+        }
+    }
+
+    let missing = |kind: &str| -> DbResult<Vec<String>> {
+        let present = object_names(actual, kind)?;
+        Ok(object_names(expected, kind)?
+            .into_iter()
+            .filter(|name| !present.contains(name))
+            .collect())
+    };
+    report.missing_indexes = missing("index")?;
+    report.missing_triggers = missing("trigger")?;
+
+    report.ok = report.missing_tables.is_empty()
+        && report.missing_columns.is_empty()
+        && report.missing_indexes.is_empty()
+        && report.missing_triggers.is_empty();
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::connection;
+
+    #[test]
+    fn test_migrated_database_matches() {
+        let conn = connection::open_test_connection();
+        let report = verify_schema(&conn).unwrap();
+        assert!(report.ok, "{report:?}");
+        assert_eq!(report.schema_version, super::super::CURRENT_VERSION);
+    }
+
+    #[test]
+    fn test_drift_is_reported() {
+        let conn = connection::open_test_connection();
+        conn.execute_batch(
+            "DROP INDEX idx_chat_sessions_updated_at;
+             DROP TABLE recent_queries;
+             ALTER TABLE apps DROP COLUMN raw_name;",
+        )
+        .unwrap();
+
+        let report = verify_schema(&conn).unwrap();
+        assert!(!report.ok);
+        assert_eq!(report.missing_indexes, vec!["idx_chat_sessions_updated_at"]);
+        assert_eq!(report.missing_tables, vec!["recent_queries"]);
+        assert_eq!(report.missing_columns, vec!["apps.raw_name"]);
+        assert!(report.missing_triggers.is_empty());
+    }
+}
@@ -5,20 +5,115 @@ mod repositories;
 mod schema;
 
 use error::{DbError, DbResult};
+use std::path::PathBuf;
+use std::time::Duration;
 use tauri::{AppHandle, Manager};
 
-pub fn initialize(app: &AppHandle) -> DbResult<()> {
-    let db_path = app
-        .path()
+fn app_data_dir(app: &AppHandle) -> DbResult<PathBuf> {
+    app.path()
         .app_local_data_dir()
-        .map_err(|e| DbError::Connection(format!("Failed to get app data dir: {e}")))?
-        .join("data.db");
+        .map_err(|e| DbError::Connection(format!("Failed to get app data dir: {e}")))
+}
+
+/// Open the app database, or an in-memory one for this session when the
+/// data directory or file is unavailable.
+pub fn initialize(app: &AppHandle) -> DbResult<()> {
+    let db_path = app_data_dir(app).map(|data_dir| {
+        connection::resolve_db_path(&data_dir, data_dir.join(connection::DB_FILE_NAME))
+    });
+
+    connection::initialize_with_fallback(db_path)
+}
+
+/// Resolved data directory and database file.
+pub fn data_paths(app: &AppHandle) -> DbResult<DataPaths> {
+    Ok(connection::resolve_data_paths(&app_data_dir(app)?))
+}
+
+/// Move the database file to `new_path` and keep using it from there.
+pub fn relocate(app: &AppHandle, new_path: PathBuf) -> DbResult<PathBuf> {
+    let data_dir = app_data_dir(app)?;
+    connection::relocate(&data_dir, new_path)
+}
+
+/// Schema version recorded by the last applied migration.
+pub fn schema_version() -> DbResult<u32> {
+    connection::with_connection(migrations::get_current_version)
+}
+
+/// Tables, columns, indexes and triggers the migrations define but the
+/// database lacks. Read-only.
+pub fn verify_schema() -> DbResult<SchemaVerification> {
+    connection::with_connection(migrations::verify_schema)
+}
+
+pub use migrations::SchemaVerification;
+
+/// Who holds the database lock right now and the longest hold this session.
+pub fn lock_diagnostics() -> DbResult<DbLockDiagnostics> {
+    connection::lock_diagnostics()
+}
+
+/// Checkpoint the WAL so the database file is self-contained.
+pub fn flush() -> DbResult<()> {
+    connection::checkpoint()
+}
+
+pub(crate) use connection::parse_busy_timeout_ms;
+pub use connection::{parse_wal_checkpoint_interval_minutes, DataPaths, DbLockDiagnostics};
+
+/// Re-apply the `db_busy_timeout_ms` setting to the open connection.
+pub fn apply_busy_timeout() -> DbResult<()> {
+    connection::apply_busy_timeout().map(|_| ())
+}
+
+/// Periodically run a passive WAL checkpoint so long chat sessions don't grow
+/// the WAL without bound. The interval is re-read every round, so changes to
+/// `wal_checkpoint_interval_minutes` apply without a restart.
+pub fn spawn_wal_maintenance() {
+    // How often to look again while background checkpoints are disabled.
+    const DISABLED_POLL: Duration = Duration::from_secs(60);
+
+    tauri::async_runtime::spawn(async {
+        loop {
+            let minutes =
+                tauri::async_runtime::spawn_blocking(connection::wal_checkpoint_interval_minutes)
+                    .await
+                    .ok()
+                    .and_then(Result::ok)
+                    .unwrap_or(0);
+            if minutes == 0 {
+                tokio::time::sleep(DISABLED_POLL).await;
+                continue;
+            }
+
+            tokio::time::sleep(Duration::from_secs(minutes * 60)).await;
+            match tauri::async_runtime::spawn_blocking(connection::passive_checkpoint).await {
+                Ok(Ok(Some(frames))) => {
+                    if cfg!(debug_assertions) {
+                        eprintln!("Background WAL checkpoint moved {frames} frames");
+                    }
+                }
+                Ok(Ok(None)) => {}
+                Ok(Err(e)) => eprintln!("Background WAL checkpoint failed: {e}"),
+                Err(e) => eprintln!("Failed to join WAL checkpoint task: {e}"),
+            }
+        }
+    });
+}
 
-    connection::initialize(db_path)
+/// Flush and close the database; call once right before the process exits.
+pub fn shutdown() {
+    connection::shutdown();
 }
 
 pub use repositories::{
-    AppsRepository, ChatMessageRecord, ChatMessagesRepository, ChatSessionColumnRecord,
-    ChatSessionColumnsRepository, ChatSessionRecord, ChatSessionsRepository, MessageSearchResult,
-    ProvidersRepository, SettingsRepository,
+    content_hash, parse_max_messages_per_session, AppsRepository, ChatMessageRecord,
+    ChatMessageWithSession, ChatMessagesRepository, ChatSessionColumnRecord,
+    ChatSessionColumnsRepository, ChatSessionRecord, ChatSessionsRepository, ChatStats,
+    ColumnPresetRecord, ColumnPresetsRepository, ColumnWithLatest, CompareColumnSetup, DiffSegment,
+    FtsStats, LaunchEvent, MessageRequestRecord, MessageRequestsRepository, MessageSearchResult,
+    PromptTemplateRecord, PromptTemplatesRepository, ProvidersRepository, RecentQueriesRepository,
+    RecentQueryRecord, RepairReport, ResubmitSetup, SessionStorage, SettingsRepository,
+    MAX_RECENT_QUERIES,
 };
@@ -1,7 +1,10 @@
 use crate::apps::AppInfo;
 use crate::db::connection;
 use crate::db::error::DbResult;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 fn now_unix_ms() -> u64 {
@@ -18,6 +21,17 @@ fn normalize_path_key(path: &str) -> String {
         .to_lowercase()
 }
 
+/// Launch events kept per app; older ones are dropped as new launches arrive.
+const MAX_LAUNCH_EVENTS_PER_APP: i64 = 100;
+
+/// One entry of the launch timeline.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LaunchEvent {
+    pub name: String,
+    pub path: String,
+    pub launched_at: i64,
+}
+
 pub struct AppsRepository;
 
 impl AppsRepository {
@@ -30,9 +44,9 @@ impl AppsRepository {
 
             {
                 let mut stmt = tx.prepare_cached(
-                    "INSERT INTO apps (name, path, normalized_path, publisher, icon_data, created_at, updated_at)
+                    "INSERT INTO apps (name, path, normalized_path, publisher, raw_name, icon_data, created_at, updated_at)
                      VALUES (
-                         ?1, ?2, ?3, ?4,
+                         ?1, ?2, ?3, ?4, ?6,
                          COALESCE((SELECT icon_data FROM apps WHERE normalized_path = ?3), NULL),
                          COALESCE((SELECT created_at FROM apps WHERE normalized_path = ?3), ?5),
                          ?5
@@ -41,6 +55,7 @@ impl AppsRepository {
                         name = excluded.name,
                         path = excluded.path,
                         publisher = excluded.publisher,
+                        raw_name = excluded.raw_name,
                         updated_at = excluded.updated_at",
                 )?;
 
@@ -55,12 +70,14 @@ impl AppsRepository {
                         app.path,
                         normalized_path,
                         app.publisher,
-                        now
+                        now,
+                        app.raw_name
                     ])?;
                 }
             }
 
             if seen_paths.is_empty() {
+                tx.execute("DELETE FROM launch_events", [])?;
                 tx.execute("DELETE FROM app_usage", [])?;
                 tx.execute("DELETE FROM apps", [])?;
                 tx.commit()?;
@@ -97,7 +114,7 @@ impl AppsRepository {
     pub fn get_all_apps() -> DbResult<Vec<AppInfo>> {
         connection::with_connection(|conn| {
             let mut stmt = conn.prepare_cached(
-                "SELECT name, path, publisher FROM apps ORDER BY name COLLATE NOCASE",
+                "SELECT name, path, publisher, raw_name FROM apps ORDER BY name COLLATE NOCASE",
             )?;
 
             let apps = stmt
@@ -106,6 +123,7 @@ impl AppsRepository {
                         name: row.get(0)?,
                         path: row.get(1)?,
                         publisher: row.get(2)?,
+                        raw_name: row.get(3)?,
                     })
                 })?
                 .collect::<Result<Vec<_>, _>>()?;
@@ -123,79 +141,268 @@ impl AppsRepository {
         })
     }
 
-    /// Record an app launch (increment usage count)
+    /// Record an app launch (increment usage count and append a timeline event)
     pub fn record_launch(path: &str) -> DbResult<()> {
-        connection::with_connection(|conn| {
-            let normalized = normalize_path_key(path);
+        let now = now_unix_ms();
+        connection::with_connection(|conn| Self::record_launch_with_conn(conn, path, now))
+    }
 
-            // First get the app_id
-            let app_id: Option<i64> = conn
-                .query_row(
-                    "SELECT id FROM apps WHERE normalized_path = ?1",
-                    [&normalized],
-                    |row| row.get(0),
-                )
-                .ok();
+    fn record_launch_with_conn(conn: &Connection, path: &str, now: u64) -> DbResult<()> {
+        let normalized = normalize_path_key(path);
+
+        // First get the app_id
+        let app_id: Option<i64> = conn
+            .query_row(
+                "SELECT id FROM apps WHERE normalized_path = ?1",
+                [&normalized],
+                |row| row.get(0),
+            )
+            .ok();
+
+        let app_id = match app_id {
+            Some(id) => id,
+            None => return Ok(()), // App not in database, skip
+        };
 
-            let app_id = match app_id {
-                Some(id) => id,
-                None => return Ok(()), // App not in database, skip
-            };
+        // Insert or update usage record
+        conn.execute(
+            "INSERT INTO app_usage (app_id, launch_count, last_launched_at, first_launched_at)
+             VALUES (?1, 1, ?2, ?2)
+             ON CONFLICT(app_id) DO UPDATE SET
+                launch_count = launch_count + 1,
+                last_launched_at = ?2",
+            rusqlite::params![app_id, now],
+        )?;
+
+        conn.execute(
+            "INSERT INTO launch_events (app_id, launched_at) VALUES (?1, ?2)",
+            rusqlite::params![app_id, now],
+        )?;
+        conn.execute(
+            "DELETE FROM launch_events
+             WHERE app_id = ?1 AND id NOT IN (
+                SELECT id FROM launch_events WHERE app_id = ?1
+                ORDER BY launched_at DESC, id DESC
+                LIMIT ?2
+             )",
+            rusqlite::params![app_id, MAX_LAUNCH_EVENTS_PER_APP],
+        )?;
 
-            let now = now_unix_ms();
+        Ok(())
+    }
 
-            // Insert or update usage record
-            conn.execute(
-                "INSERT INTO app_usage (app_id, launch_count, last_launched_at, first_launched_at)
-                 VALUES (?1, 1, ?2, ?2)
-                 ON CONFLICT(app_id) DO UPDATE SET
-                    launch_count = launch_count + 1,
-                    last_launched_at = ?2",
-                rusqlite::params![app_id, now],
-            )?;
+    /// Launch count of every app launched at least once, keyed by normalized path.
+    pub fn launch_counts() -> DbResult<HashMap<String, i64>> {
+        connection::with_connection(Self::launch_counts_with_conn)
+    }
 
-            Ok(())
-        })
+    fn launch_counts_with_conn(conn: &Connection) -> DbResult<HashMap<String, i64>> {
+        let mut stmt = conn.prepare_cached(
+            "SELECT a.normalized_path, u.launch_count
+             FROM apps a
+             JOIN app_usage u ON a.id = u.app_id
+             WHERE u.launch_count > 0",
+        )?;
+        let counts = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<HashMap<_, _>, _>>()?;
+        Ok(counts)
     }
 
-    /// Get suggested apps based on usage statistics
-    pub fn get_suggested_apps(limit: usize) -> DbResult<Vec<AppInfo>> {
-        connection::with_connection(|conn| {
-            let mut stmt = conn.prepare_cached(
-                "SELECT a.name, a.path, a.publisher
-                 FROM apps a
-                 JOIN app_usage u ON a.id = u.app_id
-                 WHERE u.launch_count > 0
-                 ORDER BY u.launch_count DESC, u.last_launched_at DESC
-                 LIMIT ?1",
-            )?;
+    /// Most recent launches across all apps, newest first.
+    pub fn get_launch_timeline(limit: usize) -> DbResult<Vec<LaunchEvent>> {
+        connection::with_connection(|conn| Self::get_launch_timeline_with_conn(conn, limit))
+    }
 
-            let apps = stmt
-                .query_map([limit as i64], |row| {
-                    Ok(AppInfo {
-                        name: row.get(0)?,
-                        path: row.get(1)?,
-                        publisher: row.get(2)?,
-                    })
-                })?
-                .collect::<Result<Vec<_>, _>>()?;
+    fn get_launch_timeline_with_conn(
+        conn: &Connection,
+        limit: usize,
+    ) -> DbResult<Vec<LaunchEvent>> {
+        let mut stmt = conn.prepare_cached(
+            "SELECT a.name, a.path, e.launched_at
+             FROM launch_events e
+             JOIN apps a ON a.id = e.app_id
+             ORDER BY e.launched_at DESC, e.id DESC
+             LIMIT ?1",
+        )?;
+
+        let events = stmt
+            .query_map([limit as i64], |row| {
+                Ok(LaunchEvent {
+                    name: row.get(0)?,
+                    path: row.get(1)?,
+                    launched_at: row.get(2)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(events)
+    }
 
-            Ok(apps)
-        })
+    /// Get suggested apps based on usage statistics, optionally only those
+    /// launched within the last `within_days` days.
+    pub fn get_suggested_apps(limit: usize, within_days: Option<i64>) -> DbResult<Vec<AppInfo>> {
+        let cutoff = recency_cutoff(now_unix_ms(), within_days);
+        connection::with_connection(|conn| Self::get_suggested_apps_with_conn(conn, limit, cutoff))
+    }
+
+    fn get_suggested_apps_with_conn(
+        conn: &Connection,
+        limit: usize,
+        cutoff: Option<i64>,
+    ) -> DbResult<Vec<AppInfo>> {
+        let mut stmt = conn.prepare_cached(
+            "SELECT a.name, a.path, a.publisher, a.raw_name
+             FROM apps a
+             JOIN app_usage u ON a.id = u.app_id
+             WHERE u.launch_count > 0
+               AND (?2 IS NULL OR u.last_launched_at >= ?2)
+             ORDER BY u.launch_count DESC, u.last_launched_at DESC
+             LIMIT ?1",
+        )?;
+
+        let apps = stmt
+            .query_map(rusqlite::params![limit as i64, cutoff], |row| {
+                Ok(AppInfo {
+                    name: row.get(0)?,
+                    path: row.get(1)?,
+                    publisher: row.get(2)?,
+                    raw_name: row.get(3)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(apps)
     }
 
     /// Save icon data for an app
     pub fn save_icon(path: &str, icon_data: &str) -> DbResult<()> {
+        connection::with_connection(|conn| Self::save_icon_with_conn(conn, path, icon_data))
+    }
+
+    fn save_icon_with_conn(conn: &Connection, path: &str, icon_data: &str) -> DbResult<()> {
+        let normalized = normalize_path_key(path);
+        conn.execute(
+            "UPDATE apps SET icon_data = ?1, icon_accent = NULL, icon_extraction_failed = 0,
+                updated_at = ?3
+             WHERE normalized_path = ?2",
+            rusqlite::params![icon_data, normalized, now_unix_ms()],
+        )?;
+        Ok(())
+    }
+
+    /// Cached accent color of the icon for `path`, if one was computed.
+    pub fn get_icon_accent(path: &str) -> DbResult<Option<String>> {
+        connection::with_connection(|conn| Self::get_icon_accent_with_conn(conn, path))
+    }
+
+    fn get_icon_accent_with_conn(conn: &Connection, path: &str) -> DbResult<Option<String>> {
+        let result = conn.query_row(
+            "SELECT icon_accent FROM apps WHERE normalized_path = ?1",
+            [normalize_path_key(path)],
+            |row| row.get::<_, Option<String>>(0),
+        );
+        match result {
+            Ok(accent) => Ok(accent),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Cache `accent` for `path`; cleared again whenever a new icon is saved.
+    pub fn save_icon_accent(path: &str, accent: &str) -> DbResult<()> {
+        connection::with_connection(|conn| Self::save_icon_accent_with_conn(conn, path, accent))
+    }
+
+    fn save_icon_accent_with_conn(conn: &Connection, path: &str, accent: &str) -> DbResult<()> {
+        conn.execute(
+            "UPDATE apps SET icon_accent = ?1 WHERE normalized_path = ?2",
+            rusqlite::params![accent, normalize_path_key(path)],
+        )?;
+        Ok(())
+    }
+
+    /// Remember that no icon could be extracted for `path`.
+    pub fn mark_icon_extraction_failed(path: &str) -> DbResult<()> {
+        connection::with_connection(|conn| Self::mark_icon_extraction_failed_with_conn(conn, path))
+    }
+
+    fn mark_icon_extraction_failed_with_conn(conn: &Connection, path: &str) -> DbResult<()> {
+        conn.execute(
+            "UPDATE apps SET icon_extraction_failed = 1, updated_at = ?2 WHERE normalized_path = ?1",
+            rusqlite::params![normalize_path_key(path), now_unix_ms()],
+        )?;
+        Ok(())
+    }
+
+    /// Paths of apps whose icon extraction failed, ordered by app name.
+    pub fn list_icon_failures() -> DbResult<Vec<String>> {
+        connection::with_connection(Self::list_icon_failures_with_conn)
+    }
+
+    fn list_icon_failures_with_conn(conn: &Connection) -> DbResult<Vec<String>> {
+        let mut stmt = conn.prepare_cached(
+            "SELECT path FROM apps WHERE icon_extraction_failed = 1 ORDER BY name COLLATE NOCASE",
+        )?;
+        let paths = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(paths)
+    }
+
+    /// Null out `icon_data` for apps missing from `current_paths` or last
+    /// launched before `cutoff`. Installed apps that were never launched keep
+    /// their icons, since they still show up in search. Returns the number of
+    /// rows cleared; run `VACUUM` afterwards to actually shrink the file.
+    pub fn prune_icon_data(current_paths: &[String], cutoff: i64) -> DbResult<usize> {
         connection::with_connection(|conn| {
-            let normalized = normalize_path_key(path);
-            conn.execute(
-                "UPDATE apps SET icon_data = ?1, updated_at = ?3 WHERE normalized_path = ?2",
-                rusqlite::params![icon_data, normalized, now_unix_ms()],
-            )?;
-            Ok(())
+            Self::prune_icon_data_with_conn(conn, current_paths, cutoff)
         })
     }
 
+    fn prune_icon_data_with_conn(
+        conn: &Connection,
+        current_paths: &[String],
+        cutoff: i64,
+    ) -> DbResult<usize> {
+        let current: HashSet<String> = current_paths
+            .iter()
+            .map(|path| normalize_path_key(path))
+            .collect();
+
+        let tx = conn.unchecked_transaction()?;
+        let stale_ids = {
+            let mut stmt = tx.prepare(
+                "SELECT a.id, a.normalized_path, u.last_launched_at
+                 FROM apps a
+                 LEFT JOIN app_usage u ON a.id = u.app_id
+                 WHERE a.icon_data IS NOT NULL",
+            )?;
+            let mut rows = stmt.query([])?;
+            let mut ids = Vec::new();
+            while let Some(row) = rows.next()? {
+                let id: i64 = row.get(0)?;
+                let normalized_path: String = row.get(1)?;
+                let last_launched_at: Option<i64> = row.get(2)?;
+                let launched_long_ago = last_launched_at.is_some_and(|at| at < cutoff);
+                if !current.contains(&normalized_path) || launched_long_ago {
+                    ids.push(id);
+                }
+            }
+            ids
+        };
+
+        {
+            let mut stmt = tx.prepare("UPDATE apps SET icon_data = NULL WHERE id = ?1")?;
+            for id in &stale_ids {
+                stmt.execute([id])?;
+            }
+        }
+
+        tx.commit()?;
+        Ok(stale_ids.len())
+    }
+
     /// Get icon data for an app
     pub fn get_icon(path: &str) -> DbResult<Option<String>> {
         connection::with_connection(|conn| {
@@ -216,62 +423,70 @@ impl AppsRepository {
 
     /// Migrate usage stats from JSON file to database
     pub fn migrate_from_json() -> DbResult<()> {
-        use dirs::data_local_dir;
-        use std::fs;
-
-        let json_path =
-            data_local_dir().map(|dir| dir.join("ai-quick-search").join("usage-stats.json"));
-
-        let json_path = match json_path {
+        let json_path = match Self::legacy_usage_json_path() {
             Some(path) if path.exists() => path,
             _ => return Ok(()), // No JSON file to migrate
         };
+        Self::import_usage_json(&json_path, true)?;
+        Ok(())
+    }
 
-        let contents =
-            fs::read_to_string(&json_path).map_err(|e| crate::db::error::DbError::Io(e))?;
+    /// Merge a legacy `usage-stats.json` into `app_usage`, returning how many
+    /// apps' stats were merged. Entries for unknown apps are skipped, and
+    /// counts only ever grow, so re-importing the same file is harmless.
+    pub fn import_usage_json(path: &Path, delete_after: bool) -> DbResult<usize> {
+        let contents = std::fs::read_to_string(path)?;
+        let merged =
+            connection::with_connection(|conn| Self::merge_usage_json_with_conn(conn, &contents))?;
+
+        if delete_after {
+            let _ = std::fs::remove_file(path);
+        }
+        Ok(merged)
+    }
 
-        let usage: HashMap<String, UsageEntryJson> = serde_json::from_str(&contents)?;
+    fn merge_usage_json_with_conn(conn: &Connection, contents: &str) -> DbResult<usize> {
+        let usage: HashMap<String, UsageEntryJson> = serde_json::from_str(contents)?;
 
-        connection::with_connection(|conn| {
-            let tx = conn.unchecked_transaction()?;
+        let tx = conn.unchecked_transaction()?;
+        let mut merged = 0;
+        for (path_key, entry) in usage {
+            let normalized_path = normalize_path_key(&path_key);
+            if normalized_path.is_empty() {
+                continue;
+            }
 
-            for (path_key, entry) in usage {
-                let normalized_path = normalize_path_key(&path_key);
-                if normalized_path.is_empty() {
-                    continue;
-                }
+            // Find app by normalized path
+            let app_id: Option<i64> = tx
+                .query_row(
+                    "SELECT id FROM apps WHERE normalized_path = ?1",
+                    [&normalized_path],
+                    |row| row.get(0),
+                )
+                .ok();
 
-                // Find app by normalized path
-                let app_id: Option<i64> = tx
-                    .query_row(
-                        "SELECT id FROM apps WHERE normalized_path = ?1",
-                        [&normalized_path],
-                        |row| row.get(0),
-                    )
-                    .ok();
-
-                if let Some(app_id) = app_id {
-                    let timestamp = normalize_usage_timestamp(entry.last_launched_at);
-                    tx.execute(
-                        "INSERT INTO app_usage (app_id, launch_count, last_launched_at, first_launched_at)
-                         VALUES (?1, ?2, ?3, ?3)
-                         ON CONFLICT(app_id) DO UPDATE SET
-                            launch_count = MAX(app_usage.launch_count, excluded.launch_count),
-                            last_launched_at = MAX(app_usage.last_launched_at, excluded.last_launched_at),
-                            first_launched_at = MIN(app_usage.first_launched_at, excluded.first_launched_at)",
-                        rusqlite::params![app_id, entry.launch_count, timestamp],
-                    )?;
-                }
+            if let Some(app_id) = app_id {
+                let timestamp = normalize_usage_timestamp(entry.last_launched_at);
+                tx.execute(
+                    "INSERT INTO app_usage (app_id, launch_count, last_launched_at, first_launched_at)
+                     VALUES (?1, ?2, ?3, ?3)
+                     ON CONFLICT(app_id) DO UPDATE SET
+                        launch_count = MAX(app_usage.launch_count, excluded.launch_count),
+                        last_launched_at = MAX(app_usage.last_launched_at, excluded.last_launched_at),
+                        first_launched_at = MIN(app_usage.first_launched_at, excluded.first_launched_at)",
+                    rusqlite::params![app_id, entry.launch_count, timestamp],
+                )?;
+                merged += 1;
             }
+        }
 
-            tx.commit()?;
-            Ok(())
-        })?;
-
-        // Optionally remove the JSON file after successful migration
-        let _ = std::fs::remove_file(&json_path);
+        tx.commit()?;
+        Ok(merged)
+    }
 
-        Ok(())
+    /// Where versions before the SQLite store kept usage stats.
+    pub fn legacy_usage_json_path() -> Option<PathBuf> {
+        dirs::data_local_dir().map(|dir| dir.join("ai-quick-search").join("usage-stats.json"))
     }
 }
 
@@ -282,6 +497,13 @@ struct UsageEntryJson {
     last_launched_at: u64,
 }
 
+/// Millisecond cutoff for a "used in the last N days" filter; non-positive windows disable it.
+fn recency_cutoff(now_ms: u64, within_days: Option<i64>) -> Option<i64> {
+    within_days
+        .filter(|days| *days > 0)
+        .map(|days| (now_ms as i64).saturating_sub(days.saturating_mul(86_400_000)))
+}
+
 fn normalize_usage_timestamp(value: u64) -> u64 {
     // Legacy JSON stored seconds; SQLite stores milliseconds.
     if value < 1_000_000_000_000 {
@@ -300,4 +522,248 @@ mod tests {
         assert_eq!(normalize_path_key("C:\\Test\\App.exe"), "c:\\test\\app.exe");
         assert_eq!(normalize_path_key("C:/Test/App.exe"), "c:\\test\\app.exe");
     }
+
+    #[test]
+    fn test_import_usage_json_merges_known_apps() {
+        let conn = connection::open_test_connection();
+        for (id, path) in [(1, "C:\\Apps\\Editor.exe"), (2, "C:\\Apps\\Browser.exe")] {
+            conn.execute(
+                "INSERT INTO apps (id, name, path, normalized_path, created_at, updated_at)
+                 VALUES (?1, ?2, ?2, ?3, 0, 0)",
+                rusqlite::params![id, path, normalize_path_key(path)],
+            )
+            .unwrap();
+        }
+        conn.execute(
+            "INSERT INTO app_usage (app_id, launch_count, last_launched_at, first_launched_at)
+             VALUES (2, 40, 1700000000000, 1600000000000)",
+            [],
+        )
+        .unwrap();
+
+        // Seconds-based timestamps, mixed path separators and an uninstalled app.
+        let json = r#"{
+            "c:/apps/editor.exe": {"launch_count": 7, "last_launched_at": 1650000000},
+            "C:\\Apps\\Browser.exe": {"launch_count": 12, "last_launched_at": 1710000000},
+            "C:\\Gone\\Old.exe": {"launch_count": 3, "last_launched_at": 1500000000}
+        }"#;
+        let merged = AppsRepository::merge_usage_json_with_conn(&conn, json).unwrap();
+        assert_eq!(merged, 2);
+
+        let usage = |app_id: i64| -> (i64, i64, i64) {
+            conn.query_row(
+                "SELECT launch_count, last_launched_at, first_launched_at
+                 FROM app_usage WHERE app_id = ?1",
+                [app_id],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap()
+        };
+        assert_eq!(usage(1), (7, 1_650_000_000_000, 1_650_000_000_000));
+        // Existing stats keep the larger count and the wider date range.
+        assert_eq!(usage(2), (40, 1_710_000_000_000, 1_600_000_000_000));
+    }
+
+    #[test]
+    fn test_suggestions_respect_recency_window() {
+        let conn = connection::open_test_connection();
+        let now = now_unix_ms();
+        let day_ms = 86_400_000u64;
+        for (id, name, launches, last_launched_at) in [
+            (1, "Old Favorite", 50, now - 30 * day_ms),
+            (2, "Recent", 3, now - day_ms),
+            (3, "Recent Busy", 9, now - 2 * day_ms),
+        ] {
+            conn.execute(
+                "INSERT INTO apps (id, name, path, normalized_path, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, 0, 0)",
+                rusqlite::params![
+                    id,
+                    name,
+                    format!("C:\\{name}.exe"),
+                    normalize_path_key(&format!("C:\\{name}.exe"))
+                ],
+            )
+            .unwrap();
+            conn.execute(
+                "INSERT INTO app_usage (app_id, launch_count, last_launched_at, first_launched_at)
+                 VALUES (?1, ?2, ?3, ?3)",
+                rusqlite::params![id, launches, last_launched_at],
+            )
+            .unwrap();
+        }
+
+        let names = |cutoff| {
+            AppsRepository::get_suggested_apps_with_conn(&conn, 10, cutoff)
+                .unwrap()
+                .into_iter()
+                .map(|app| app.name)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            names(recency_cutoff(now, Some(7))),
+            vec!["Recent Busy", "Recent"]
+        );
+        assert_eq!(
+            names(recency_cutoff(now, None)),
+            vec!["Old Favorite", "Recent Busy", "Recent"]
+        );
+        assert_eq!(recency_cutoff(now, Some(0)), None);
+    }
+
+    #[test]
+    fn test_prune_icon_data_targets_stale_rows() {
+        let conn = connection::open_test_connection();
+        for (id, name, icon) in [
+            (1, "Recent", Some("icon-a")),
+            (2, "Old", Some("icon-b")),
+            (3, "Never", Some("icon-c")),
+            (4, "Uninstalled", Some("icon-d")),
+            (5, "NoIcon", None),
+        ] {
+            let path = format!("C:\\{name}.exe");
+            conn.execute(
+                "INSERT INTO apps (id, name, path, normalized_path, icon_data, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, 0, 0)",
+                rusqlite::params![id, name, path, normalize_path_key(&path), icon],
+            )
+            .unwrap();
+        }
+        for (id, last_launched_at) in [(1, 1_000), (2, 10), (4, 1_000)] {
+            conn.execute(
+                "INSERT INTO app_usage (app_id, launch_count, last_launched_at, first_launched_at)
+                 VALUES (?1, 1, ?2, ?2)",
+                rusqlite::params![id, last_launched_at],
+            )
+            .unwrap();
+        }
+
+        let current = ["Recent", "Old", "Never", "NoIcon"]
+            .iter()
+            .map(|name| format!("C:\\{name}.exe"))
+            .collect::<Vec<_>>();
+        let cleared = AppsRepository::prune_icon_data_with_conn(&conn, &current, 500).unwrap();
+        assert_eq!(cleared, 2);
+
+        let with_icons = conn
+            .prepare("SELECT name FROM apps WHERE icon_data IS NOT NULL ORDER BY id")
+            .unwrap()
+            .query_map([], |row| row.get::<_, String>(0))
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(with_icons, vec!["Recent", "Never"]);
+    }
+
+    #[test]
+    fn test_icon_failures_are_listed_until_an_icon_is_saved() {
+        let conn = connection::open_test_connection();
+        for (id, name) in [(1, "Zeta"), (2, "Alpha"), (3, "Fine")] {
+            let path = format!("C:\\{name}.exe");
+            conn.execute(
+                "INSERT INTO apps (id, name, path, normalized_path, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, 0, 0)",
+                rusqlite::params![id, name, path, normalize_path_key(&path)],
+            )
+            .unwrap();
+        }
+
+        AppsRepository::mark_icon_extraction_failed_with_conn(&conn, "C:\\Zeta.exe").unwrap();
+        AppsRepository::mark_icon_extraction_failed_with_conn(&conn, "c:/alpha.exe").unwrap();
+        assert_eq!(
+            AppsRepository::list_icon_failures_with_conn(&conn).unwrap(),
+            vec!["C:\\Alpha.exe", "C:\\Zeta.exe"]
+        );
+
+        AppsRepository::save_icon_with_conn(&conn, "C:\\Zeta.exe", "icon").unwrap();
+        assert_eq!(
+            AppsRepository::list_icon_failures_with_conn(&conn).unwrap(),
+            vec!["C:\\Alpha.exe"]
+        );
+    }
+
+    #[test]
+    fn test_icon_accent_is_cleared_by_a_new_icon() {
+        let conn = connection::open_test_connection();
+        conn.execute(
+            "INSERT INTO apps (id, name, path, normalized_path, created_at, updated_at)
+             VALUES (1, 'Paint', 'C:\\Paint.exe', ?1, 0, 0)",
+            [normalize_path_key("C:\\Paint.exe")],
+        )
+        .unwrap();
+
+        AppsRepository::save_icon_accent_with_conn(&conn, "c:/paint.exe", "#c03020").unwrap();
+        assert_eq!(
+            AppsRepository::get_icon_accent_with_conn(&conn, "C:\\Paint.exe").unwrap(),
+            Some("#c03020".to_string())
+        );
+
+        AppsRepository::save_icon_with_conn(&conn, "C:\\Paint.exe", "icon").unwrap();
+        assert_eq!(
+            AppsRepository::get_icon_accent_with_conn(&conn, "C:\\Paint.exe").unwrap(),
+            None
+        );
+        assert_eq!(
+            AppsRepository::get_icon_accent_with_conn(&conn, "C:\\Missing.exe").unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_launches_append_timeline_events() {
+        let conn = connection::open_test_connection();
+        for (id, name) in [(1, "Editor"), (2, "Browser")] {
+            conn.execute(
+                "INSERT INTO apps (id, name, path, normalized_path, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, 0, 0)",
+                rusqlite::params![
+                    id,
+                    name,
+                    format!("C:\\Apps\\{name}.exe"),
+                    normalize_path_key(&format!("C:\\Apps\\{name}.exe"))
+                ],
+            )
+            .unwrap();
+        }
+
+        for (path, at) in [
+            ("C:\\Apps\\Editor.exe", 100),
+            ("c:/apps/browser.exe", 200),
+            ("C:\\Apps\\Editor.exe", 300),
+            ("C:\\Gone\\Old.exe", 400),
+        ] {
+            AppsRepository::record_launch_with_conn(&conn, path, at).unwrap();
+        }
+
+        let timeline = AppsRepository::get_launch_timeline_with_conn(&conn, 10)
+            .unwrap()
+            .into_iter()
+            .map(|event| (event.name, event.launched_at))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            timeline,
+            vec![
+                ("Editor".to_string(), 300),
+                ("Browser".to_string(), 200),
+                ("Editor".to_string(), 100),
+            ]
+        );
+        assert_eq!(
+            AppsRepository::get_launch_timeline_with_conn(&conn, 1)
+                .unwrap()
+                .len(),
+            1
+        );
+
+        // Aggregate counters are still maintained for suggestions.
+        let launch_count: i64 = conn
+            .query_row(
+                "SELECT launch_count FROM app_usage WHERE app_id = 1",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(launch_count, 2);
+    }
 }
@@ -1,6 +1,10 @@
+use super::chat_session_columns::ChatSessionColumnsRepository;
+use super::chat_sessions::{ChatSessionsRepository, DEFAULT_SESSION_TITLE};
 use crate::db::connection;
 use crate::db::error::{DbError, DbResult};
+use rusqlite::{Connection, OptionalExtension};
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 fn now_unix_ms() -> i64 {
@@ -21,6 +25,73 @@ pub struct ChatMessageRecord {
     pub status: String,
     pub created_at: i64,
     pub updated_at: i64,
+    /// Provider name captured when the message was written, so the label
+    /// survives later renames or deletion of the provider.
+    #[serde(default)]
+    pub provider_name: Option<String>,
+    /// [`content_hash`] of a finished assistant reply; `None` otherwise.
+    #[serde(default)]
+    pub content_hash: Option<String>,
+}
+
+/// Column list shared by every message SELECT; keep in sync with `message_from_row`.
+pub(crate) const MESSAGE_COLUMNS: &str = "id, session_id, column_id, provider_id, role, content, status, created_at, updated_at, provider_name, content_hash";
+
+/// Stable hash of a reply (64-bit FNV-1a over the trimmed text, as hex), so
+/// columns that produced the same answer can be collapsed. Unlike std's
+/// hasher it never changes between builds, which matters for stored values.
+pub fn content_hash(content: &str) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in content.trim().bytes() {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    format!("{hash:016x}")
+}
+
+/// The hash stored with a message: only finished assistant replies get one.
+fn stored_content_hash(role: &str, status: &str, content: &str) -> Option<String> {
+    (role == "assistant" && status == "done").then(|| content_hash(content))
+}
+
+pub(crate) fn message_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<ChatMessageRecord> {
+    Ok(ChatMessageRecord {
+        id: row.get(0)?,
+        session_id: row.get(1)?,
+        column_id: row.get(2)?,
+        provider_id: row.get(3)?,
+        role: row.get(4)?,
+        content: row.get(5)?,
+        status: row.get(6)?,
+        created_at: row.get(7)?,
+        updated_at: row.get(8)?,
+        provider_name: row.get(9)?,
+        content_hash: row.get(10)?,
+    })
+}
+
+/// Current name of `provider_id`, for the message's `provider_name` snapshot.
+pub(crate) fn provider_name_snapshot(
+    conn: &Connection,
+    provider_id: &str,
+) -> DbResult<Option<String>> {
+    match conn.query_row(
+        "SELECT name FROM providers WHERE id = ?1",
+        [provider_id],
+        |row| row.get::<_, String>(0),
+    ) {
+        Ok(name) => Ok(Some(name)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// A message with the title of its session, for views spanning sessions.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatMessageWithSession {
+    #[serde(flatten)]
+    pub message: ChatMessageRecord,
+    pub session_title: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,6 +103,330 @@ pub struct MessageSearchResult {
     pub created_at: i64,
 }
 
+/// Messages kept per session; `0` (or an invalid value) means unlimited.
+pub fn parse_max_messages_per_session(raw: Option<&str>) -> usize {
+    raw.and_then(|value| value.trim().parse::<usize>().ok())
+        .unwrap_or(0)
+}
+
+/// Group a session's messages (oldest first) into turns: each user message
+/// starts a new turn in its column, and replies join the turn before them.
+/// Returns the message ids of each turn, ordered by when the turn started.
+fn group_turns(messages: &[(String, String, String)]) -> Vec<Vec<String>> {
+    let mut turns: Vec<Vec<String>> = Vec::new();
+    let mut open_turn: HashMap<&str, usize> = HashMap::new();
+    for (id, column_id, role) in messages {
+        match open_turn.get(column_id.as_str()) {
+            Some(&index) if role != "user" => turns[index].push(id.clone()),
+            _ => {
+                open_turn.insert(column_id.as_str(), turns.len());
+                turns.push(vec![id.clone()]);
+            }
+        }
+    }
+    turns
+}
+
+const USER_LABELS: &[&str] = &["user", "you", "me", "human"];
+const ASSISTANT_LABELS: &[&str] = &[
+    "assistant",
+    "ai",
+    "bot",
+    "model",
+    "chatgpt",
+    "gpt",
+    "claude",
+];
+/// Generic words that only name a role as a heading title (`## Answer`),
+/// since lines such as `Answer: 42` are common inside replies.
+const USER_HEADING_LABELS: &[&str] = &["question", "prompt"];
+const ASSISTANT_HEADING_LABELS: &[&str] = &["answer", "response"];
+
+fn label_role(label: &str, heading: bool) -> Option<&'static str> {
+    let label = label.trim().to_lowercase();
+    let label = label.as_str();
+    if USER_LABELS.contains(&label) || (heading && USER_HEADING_LABELS.contains(&label)) {
+        Some("user")
+    } else if ASSISTANT_LABELS.contains(&label)
+        || (heading && ASSISTANT_HEADING_LABELS.contains(&label))
+    {
+        Some("assistant")
+    } else {
+        None
+    }
+}
+
+/// A line that starts a new block, with the role it names (`None` for a
+/// heading whose title is not a known label) and any text after the label.
+fn transcript_marker(line: &str) -> Option<(Option<&'static str>, &str)> {
+    let trimmed = line.trim();
+    if let Some(heading) = trimmed.strip_prefix('#') {
+        let title = heading
+            .trim_start_matches('#')
+            .trim()
+            .trim_matches('*')
+            .trim();
+        let (label, rest) = title.split_once(':').unwrap_or((title, ""));
+        return Some(match label_role(label, true) {
+            Some(role) => (Some(role), rest.trim()),
+            None => (None, ""),
+        });
+    }
+    // `User: hi`, `**User:** hi`, `**User**: hi`, `> User: hi`.
+    let unquoted = trimmed.trim_start_matches('>').trim_start();
+    let (label, rest) = unquoted.split_once(':')?;
+    let role = label_role(label.trim_matches('*'), false)?;
+    Some((Some(role), rest.trim_start_matches('*').trim()))
+}
+
+/// Split a pasted plain-text or Markdown transcript into `(role, content)`
+/// messages. Blocks start at `User:`/`Assistant:` style labels or labeled
+/// headings. Only a transcript with no labels at all is split at its other
+/// `#` headings, each taking the role opposite the previous block (user
+/// first); otherwise they are kept as content. Text before the first label
+/// is the user's, and consecutive blocks with the same role are merged.
+pub(crate) fn parse_transcript(text: &str) -> Vec<(&'static str, String)> {
+    let labeled = text
+        .lines()
+        .any(|line| matches!(transcript_marker(line), Some((Some(_), _))));
+    let mut blocks: Vec<(&'static str, Vec<&str>)> = Vec::new();
+    let mut current: Option<(&'static str, Vec<&str>)> = None;
+    let next_role = |last: Option<&'static str>| match last {
+        Some("user") => "assistant",
+        _ => "user",
+    };
+
+    for line in text.lines() {
+        match transcript_marker(line).filter(|(role, _)| role.is_some() || !labeled) {
+            Some((role, rest)) => {
+                let last = current.as_ref().map(|(role, _)| *role);
+                if let Some(block) = current.take() {
+                    blocks.push(block);
+                }
+                let role = role.unwrap_or_else(|| next_role(last));
+                let lines = if rest.is_empty() {
+                    Vec::new()
+                } else {
+                    vec![rest]
+                };
+                current = Some((role, lines));
+            }
+            None => current
+                .get_or_insert_with(|| ("user", Vec::new()))
+                .1
+                .push(line),
+        }
+    }
+    blocks.extend(current);
+
+    let mut messages: Vec<(&'static str, String)> = Vec::new();
+    for (role, lines) in blocks {
+        let content = lines.join("\n").trim().to_string();
+        if content.is_empty() {
+            continue;
+        }
+        match messages.last_mut() {
+            Some((last_role, last)) if *last_role == role => {
+                last.push_str("\n\n");
+                last.push_str(&content);
+            }
+            _ => messages.push((role, content)),
+        }
+    }
+    messages
+}
+
+/// Build an OpenAI chat `{"messages": [...]}` body: the system prompt first, then
+/// the finished user/assistant turns in order. Identical consecutive user prompts
+/// (one copy per column) are collapsed, and each turn keeps a single reply so
+/// roles alternate: `canonical_id` in the turn holding it, otherwise the reply
+/// from `preferred_column`, otherwise the first one.
+fn openai_messages_json(
+    system_prompt: &str,
+    messages: &[ChatMessageRecord],
+    canonical_id: Option<&str>,
+    preferred_column: Option<&str>,
+) -> serde_json::Value {
+    let mut out: Vec<serde_json::Value> = Vec::new();
+    if !system_prompt.trim().is_empty() {
+        out.push(serde_json::json!({"role": "system", "content": system_prompt.trim()}));
+    }
+
+    let mut turns: Vec<(Option<&ChatMessageRecord>, Vec<&ChatMessageRecord>)> =
+        vec![(None, Vec::new())];
+    for message in messages {
+        if message.status == "error" || message.content.trim().is_empty() {
+            continue;
+        }
+        match message.role.as_str() {
+            "user" => {
+                let last_user = turns.last().and_then(|(user, _)| *user);
+                if last_user.is_some_and(|user| user.content == message.content) {
+                    continue;
+                }
+                turns.push((Some(message), Vec::new()));
+            }
+            "assistant" => turns.last_mut().unwrap().1.push(message),
+            _ => {}
+        }
+    }
+
+    for (user, replies) in turns {
+        if let Some(user) = user {
+            out.push(serde_json::json!({"role": "user", "content": user.content}));
+        }
+        let reply = replies
+            .iter()
+            .find(|reply| Some(reply.id.as_str()) == canonical_id)
+            .or_else(|| {
+                replies
+                    .iter()
+                    .find(|reply| Some(reply.column_id.as_str()) == preferred_column)
+            })
+            .or(replies.first());
+        if let Some(reply) = reply {
+            out.push(serde_json::json!({"role": "assistant", "content": reply.content}));
+        }
+    }
+
+    serde_json::json!({ "messages": out })
+}
+
+/// How well `chat_messages_fts` tracks `chat_messages`; any drift means the
+/// index needs a rebuild.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FtsStats {
+    /// Messages that have an index row.
+    pub indexed_message_count: i64,
+    pub total_message_count: i64,
+    /// Index rows whose message no longer exists.
+    pub orphaned_fts_rows: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffKind {
+    Equal,
+    Added,
+    Removed,
+}
+
+/// A run of text that both answers share, or that only one of them has.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiffSegment {
+    pub kind: DiffKind,
+    pub text: String,
+}
+
+/// Above this many LCS cells the changed middle is reported as one removal
+/// and one addition instead of being aligned word by word.
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+/// Split into alternating runs of whitespace and non-whitespace, so the
+/// segments concatenate back to the exact original text.
+fn diff_tokens(text: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    let mut in_space = None;
+    for (index, ch) in text.char_indices() {
+        let space = ch.is_whitespace();
+        if in_space.is_some_and(|prev| prev != space) {
+            tokens.push(&text[start..index]);
+            start = index;
+        }
+        in_space = Some(space);
+    }
+    if start < text.len() {
+        tokens.push(&text[start..]);
+    }
+    tokens
+}
+
+fn push_segment(segments: &mut Vec<DiffSegment>, kind: DiffKind, text: &str) {
+    match segments.last_mut() {
+        Some(last) if last.kind == kind => last.text.push_str(text),
+        _ => segments.push(DiffSegment {
+            kind,
+            text: text.to_string(),
+        }),
+    }
+}
+
+/// Word-level diff from `a` to `b`: equal segments are shared, removed ones
+/// only appear in `a`, added ones only in `b`. Adjacent segments of the same
+/// kind are merged.
+pub(crate) fn diff_words(a: &str, b: &str) -> Vec<DiffSegment> {
+    let a = diff_tokens(a);
+    let b = diff_tokens(b);
+    let prefix = a.iter().zip(&b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (a_mid, b_mid) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+
+    let mut segments = Vec::new();
+    for token in &a[..prefix] {
+        push_segment(&mut segments, DiffKind::Equal, token);
+    }
+    if (a_mid.len() + 1).saturating_mul(b_mid.len() + 1) > MAX_DIFF_CELLS {
+        for token in a_mid {
+            push_segment(&mut segments, DiffKind::Removed, token);
+        }
+        for token in b_mid {
+            push_segment(&mut segments, DiffKind::Added, token);
+        }
+    } else {
+        // lcs[i][j]: longest common subsequence of a_mid[i..] and b_mid[j..].
+        let width = b_mid.len() + 1;
+        let mut lcs = vec![0u32; (a_mid.len() + 1) * width];
+        for i in (0..a_mid.len()).rev() {
+            for j in (0..b_mid.len()).rev() {
+                lcs[i * width + j] = if a_mid[i] == b_mid[j] {
+                    lcs[(i + 1) * width + j + 1] + 1
+                } else {
+                    lcs[(i + 1) * width + j].max(lcs[i * width + j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < a_mid.len() && j < b_mid.len() {
+            if a_mid[i] == b_mid[j] {
+                push_segment(&mut segments, DiffKind::Equal, a_mid[i]);
+                i += 1;
+                j += 1;
+            } else if lcs[(i + 1) * width + j] >= lcs[i * width + j + 1] {
+                push_segment(&mut segments, DiffKind::Removed, a_mid[i]);
+                i += 1;
+            } else {
+                push_segment(&mut segments, DiffKind::Added, b_mid[j]);
+                j += 1;
+            }
+        }
+        for token in &a_mid[i..] {
+            push_segment(&mut segments, DiffKind::Removed, token);
+        }
+        for token in &b_mid[j..] {
+            push_segment(&mut segments, DiffKind::Added, token);
+        }
+    }
+    for token in &a[a.len() - suffix..] {
+        push_segment(&mut segments, DiffKind::Equal, token);
+    }
+    segments
+}
+
+/// A user message re-sent as a new turn, with what is needed to stream its reply.
+#[derive(Debug, Clone, Serialize)]
+pub struct ResubmitSetup {
+    /// The newly appended copy of the original user message.
+    pub user_message: ChatMessageRecord,
+    /// Finished turns of the column before the new message, oldest first.
+    pub history: Vec<ChatMessageRecord>,
+}
+
 pub struct ChatMessagesRepository;
 
 impl ChatMessagesRepository {
@@ -45,7 +440,7 @@ impl ChatMessagesRepository {
         connection::with_connection(|conn| {
             let sql = if limit > 0 {
                 format!(
-                    "SELECT id, session_id, column_id, provider_id, role, content, status, created_at, updated_at
+                    "SELECT {MESSAGE_COLUMNS}
                      FROM chat_messages
                      WHERE session_id = '{session_id}'
                      ORDER BY created_at ASC, id ASC
@@ -53,7 +448,7 @@ impl ChatMessagesRepository {
                 )
             } else {
                 format!(
-                    "SELECT id, session_id, column_id, provider_id, role, content, status, created_at, updated_at
+                    "SELECT {MESSAGE_COLUMNS}
                      FROM chat_messages
                      WHERE session_id = '{session_id}'
                      ORDER BY created_at ASC, id ASC"
@@ -61,19 +456,7 @@ impl ChatMessagesRepository {
             };
 
             let mut stmt = conn.prepare(&sql)?;
-            let rows = stmt.query_map([], |row| {
-                Ok(ChatMessageRecord {
-                    id: row.get(0)?,
-                    session_id: row.get(1)?,
-                    column_id: row.get(2)?,
-                    provider_id: row.get(3)?,
-                    role: row.get(4)?,
-                    content: row.get(5)?,
-                    status: row.get(6)?,
-                    created_at: row.get(7)?,
-                    updated_at: row.get(8)?,
-                })
-            })?;
+            let rows = stmt.query_map([], message_from_row)?;
 
             let mut result = Vec::new();
             for row in rows {
@@ -83,6 +466,57 @@ impl ChatMessagesRepository {
         })
     }
 
+    /// Every message of `provider_id` across sessions, newest first, as one
+    /// thread. `limit <= 0` returns all of them.
+    pub fn list_by_provider(
+        provider_id: &str,
+        limit: i64,
+        offset: i64,
+    ) -> DbResult<Vec<ChatMessageWithSession>> {
+        connection::with_connection(|conn| {
+            Self::list_by_provider_with_conn(conn, provider_id, limit, offset)
+        })
+    }
+
+    fn list_by_provider_with_conn(
+        conn: &Connection,
+        provider_id: &str,
+        limit: i64,
+        offset: i64,
+    ) -> DbResult<Vec<ChatMessageWithSession>> {
+        let message_columns = MESSAGE_COLUMNS
+            .split(", ")
+            .map(|column| format!("m.{column}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let offset_of_title = MESSAGE_COLUMNS.split(", ").count();
+        // SQLite treats a negative LIMIT as no limit.
+        let limit = if limit > 0 { limit } else { -1 };
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {message_columns}, s.title
+             FROM chat_messages m
+             JOIN chat_sessions s ON s.id = m.session_id
+             WHERE m.provider_id = ?1
+             ORDER BY m.created_at DESC, m.id DESC
+             LIMIT ?2 OFFSET ?3"
+        ))?;
+        let rows = stmt.query_map(
+            rusqlite::params![provider_id, limit, offset.max(0)],
+            |row| {
+                Ok(ChatMessageWithSession {
+                    message: message_from_row(row)?,
+                    session_title: row.get(offset_of_title)?,
+                })
+            },
+        )?;
+
+        let mut result = Vec::new();
+        for row in rows {
+            result.push(row?);
+        }
+        Ok(result)
+    }
+
     /// Count messages for a session (used for checking if there are more pages).
     pub fn count_by_session(session_id: &str) -> DbResult<i64> {
         connection::with_connection(|conn| {
@@ -114,13 +548,19 @@ impl ChatMessagesRepository {
                 return Err(DbError::Query("Invalid message status".to_string()));
             }
 
+            // A scratch session is written out by its first message.
+            ChatSessionsRepository::persist_scratch_with_conn(conn, session_id)?;
+
             let now = now_unix_ms();
             let created = created_at.unwrap_or(now);
             let updated = updated_at.unwrap_or(created);
+            let provider_name = provider_name_snapshot(conn, provider_id)?;
+            let content_hash = stored_content_hash(role, status, content);
             conn.execute(
                 "INSERT INTO chat_messages (
-                    id, session_id, column_id, provider_id, role, content, status, created_at, updated_at
-                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                    id, session_id, column_id, provider_id, role, content, status, created_at, updated_at,
+                    provider_name, content_hash
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
                 rusqlite::params![
                     id,
                     session_id,
@@ -131,6 +571,8 @@ impl ChatMessagesRepository {
                     status,
                     created,
                     updated,
+                    provider_name,
+                    content_hash,
                 ],
             )?;
 
@@ -139,6 +581,14 @@ impl ChatMessagesRepository {
                 rusqlite::params![now, session_id],
             )?;
 
+            let keep = parse_max_messages_per_session(
+                connection::read_setting_conn(conn, crate::SETTING_MAX_MESSAGES_PER_SESSION)?
+                    .as_deref(),
+            );
+            if keep > 0 {
+                Self::prune_session_with_conn(conn, session_id, keep)?;
+            }
+
             Ok(ChatMessageRecord {
                 id: id.to_string(),
                 session_id: session_id.to_string(),
@@ -149,65 +599,418 @@ impl ChatMessagesRepository {
                 status: status.to_string(),
                 created_at: created,
                 updated_at: updated,
+                provider_name,
+                content_hash,
             })
         })
     }
 
-    pub fn update_content(id: &str, content: &str, status: &str) -> DbResult<ChatMessageRecord> {
+    /// Parse a plain-text or Markdown transcript (see [`parse_transcript`])
+    /// and append it to `provider_id`'s column, returning how many messages
+    /// were inserted.
+    pub fn import_transcript(session_id: &str, provider_id: &str, text: &str) -> DbResult<usize> {
         connection::with_connection(|conn| {
-            if status != "streaming" && status != "done" && status != "error" {
-                return Err(DbError::Query("Invalid message status".to_string()));
-            }
+            Self::import_transcript_with_conn(conn, session_id, provider_id, text)
+        })
+    }
 
-            let now = now_unix_ms();
-            let rows = conn.execute(
-                "UPDATE chat_messages
-                 SET content = ?1, status = ?2, updated_at = ?3
-                 WHERE id = ?4",
-                rusqlite::params![content, status, now, id],
+    fn import_transcript_with_conn(
+        conn: &Connection,
+        session_id: &str,
+        provider_id: &str,
+        text: &str,
+    ) -> DbResult<usize> {
+        let messages = parse_transcript(text);
+        if messages.is_empty() {
+            return Err(DbError::Query(
+                "No messages found in transcript".to_string(),
+            ));
+        }
+
+        // A scratch session is written out by its first message.
+        ChatSessionsRepository::persist_scratch_with_conn(conn, session_id)?;
+        let tx = conn.unchecked_transaction()?;
+        let column_id: String = tx
+            .query_row(
+                "SELECT id FROM chat_session_columns
+                 WHERE session_id = ?1 AND provider_id = ?2
+                 ORDER BY position ASC LIMIT 1",
+                [session_id, provider_id],
+                |row| row.get(0),
+            )
+            .map_err(|e| match e {
+                rusqlite::Error::QueryReturnedNoRows => {
+                    DbError::Query("Provider has no column in this session".to_string())
+                }
+                _ => e.into(),
+            })?;
+        let provider_name = provider_name_snapshot(&tx, provider_id)?;
+
+        // Space the timestamps so the imported order survives sorting.
+        let now = now_unix_ms();
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO chat_messages (
+                    id, session_id, column_id, provider_id, role, content, status, created_at, updated_at,
+                    provider_name, content_hash
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, 'done', ?7, ?7, ?8, ?9)",
             )?;
+            for (index, (role, content)) in messages.iter().enumerate() {
+                stmt.execute(rusqlite::params![
+                    uuid::Uuid::new_v4().to_string(),
+                    session_id,
+                    column_id,
+                    provider_id,
+                    role,
+                    content,
+                    now + index as i64,
+                    provider_name,
+                    stored_content_hash(role, "done", content),
+                ])?;
+            }
+        }
+        tx.execute(
+            "UPDATE chat_sessions SET updated_at = ?1 WHERE id = ?2",
+            rusqlite::params![now, session_id],
+        )?;
 
-            if rows == 0 {
-                return Err(DbError::Query("Message not found".to_string()));
+        tx.commit()?;
+
+        // Pruning opens its own transaction, so it runs once the import is in.
+        let keep = parse_max_messages_per_session(
+            connection::read_setting_conn(conn, crate::SETTING_MAX_MESSAGES_PER_SESSION)?
+                .as_deref(),
+        );
+        if keep > 0 {
+            Self::prune_session_with_conn(conn, session_id, keep)?;
+        }
+        Ok(messages.len())
+    }
+
+    /// Create (or reclaim) the assistant row a backend stream writes into.
+    /// The frontend may already have inserted it under the same id.
+    pub fn begin_streaming(
+        id: &str,
+        session_id: &str,
+        column_id: &str,
+        provider_id: &str,
+    ) -> DbResult<()> {
+        connection::with_connection(|conn| {
+            Self::begin_streaming_with_conn(conn, id, session_id, column_id, provider_id)
+        })
+    }
+
+    fn begin_streaming_with_conn(
+        conn: &Connection,
+        id: &str,
+        session_id: &str,
+        column_id: &str,
+        provider_id: &str,
+    ) -> DbResult<()> {
+        let now = now_unix_ms();
+        conn.execute(
+            "INSERT INTO chat_messages (
+                id, session_id, column_id, provider_id, role, content, status, created_at, updated_at,
+                provider_name
+            ) VALUES (?1, ?2, ?3, ?4, 'assistant', '', 'streaming', ?5, ?5, ?6)
+            ON CONFLICT(id) DO UPDATE SET
+                status = 'streaming',
+                updated_at = excluded.updated_at,
+                provider_name = COALESCE(provider_name, excluded.provider_name)",
+            rusqlite::params![
+                id,
+                session_id,
+                column_id,
+                provider_id,
+                now,
+                provider_name_snapshot(conn, provider_id)?
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Messages of `column_id` in send order, ending at `until` (inclusive) when given.
+    fn column_context_with_conn(
+        conn: &Connection,
+        session_id: &str,
+        column_id: &str,
+        until: Option<&ChatMessageRecord>,
+    ) -> DbResult<Vec<ChatMessageRecord>> {
+        let (until_created_at, until_id) = match until {
+            Some(message) => (Some(message.created_at), message.id.as_str()),
+            None => (None, ""),
+        };
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {MESSAGE_COLUMNS}
+             FROM chat_messages
+             WHERE session_id = ?1 AND column_id = ?2
+               AND (?3 IS NULL OR created_at < ?3 OR (created_at = ?3 AND id <= ?4))
+             ORDER BY created_at ASC, id ASC"
+        ))?;
+        let rows = stmt.query_map(
+            rusqlite::params![session_id, column_id, until_created_at, until_id],
+            message_from_row,
+        )?;
+        let mut values = Vec::new();
+        for row in rows {
+            values.push(row?);
+        }
+        Ok(values)
+    }
+
+    /// Every message in `message_id`'s column up to and including it: the
+    /// conversation that led to (or would be sent for) that turn.
+    pub fn context(message_id: &str) -> DbResult<Vec<ChatMessageRecord>> {
+        connection::with_connection(|conn| Self::context_with_conn(conn, message_id))
+    }
+
+    fn context_with_conn(conn: &Connection, message_id: &str) -> DbResult<Vec<ChatMessageRecord>> {
+        let target = Self::get_with_conn(conn, message_id)?;
+        Self::column_context_with_conn(conn, &target.session_id, &target.column_id, Some(&target))
+    }
+
+    fn get_with_conn(conn: &Connection, message_id: &str) -> DbResult<ChatMessageRecord> {
+        match conn.query_row(
+            &format!("SELECT {MESSAGE_COLUMNS} FROM chat_messages WHERE id = ?1"),
+            [message_id],
+            message_from_row,
+        ) {
+            Ok(record) => Ok(record),
+            Err(rusqlite::Error::QueryReturnedNoRows) => {
+                Err(DbError::Query("Message not found".to_string()))
             }
+            Err(e) => Err(e.into()),
+        }
+    }
 
-            // Keep all DB operations on this connection handle to avoid
-            // re-entering with_connection and deadlocking the global mutex.
-            let record = conn
-                .query_row(
-                    "SELECT id, session_id, column_id, provider_id, role, content, status, created_at, updated_at
-                     FROM chat_messages WHERE id = ?1",
-                    [id],
-                    |row| {
-                        Ok(ChatMessageRecord {
-                            id: row.get(0)?,
-                            session_id: row.get(1)?,
-                            column_id: row.get(2)?,
-                            provider_id: row.get(3)?,
-                            role: row.get(4)?,
-                            content: row.get(5)?,
-                            status: row.get(6)?,
-                            created_at: row.get(7)?,
-                            updated_at: row.get(8)?,
-                        })
-                    },
-                )
-                .map_err(|e| match e {
-                    rusqlite::Error::QueryReturnedNoRows => {
-                        DbError::Query("Message not found".to_string())
-                    }
-                    _ => e.into(),
-                })?;
+    /// Word-level diff from message `message_id_a`'s content to `message_id_b`'s.
+    pub fn diff(message_id_a: &str, message_id_b: &str) -> DbResult<Vec<DiffSegment>> {
+        connection::with_connection(|conn| Self::diff_with_conn(conn, message_id_a, message_id_b))
+    }
 
-            conn.execute(
-                "UPDATE chat_sessions SET updated_at = ?1 WHERE id = ?2",
-                rusqlite::params![now, record.session_id],
-            )?;
+    fn diff_with_conn(
+        conn: &Connection,
+        message_id_a: &str,
+        message_id_b: &str,
+    ) -> DbResult<Vec<DiffSegment>> {
+        let a = Self::get_with_conn(conn, message_id_a)?;
+        let b = Self::get_with_conn(conn, message_id_b)?;
+        Ok(diff_words(&a.content, &b.content))
+    }
 
-            Ok(record)
+    /// Append a copy of user message `message_id` to the end of its column.
+    /// Earlier messages are left untouched.
+    pub fn resubmit(message_id: &str) -> DbResult<ResubmitSetup> {
+        connection::with_connection(|conn| Self::resubmit_with_conn(conn, message_id))
+    }
+
+    fn resubmit_with_conn(conn: &Connection, message_id: &str) -> DbResult<ResubmitSetup> {
+        let tx = conn.unchecked_transaction()?;
+        let source = match tx.query_row(
+            &format!("SELECT {MESSAGE_COLUMNS} FROM chat_messages WHERE id = ?1"),
+            [message_id],
+            message_from_row,
+        ) {
+            Ok(record) => record,
+            Err(rusqlite::Error::QueryReturnedNoRows) => {
+                return Err(DbError::Query("Message not found".to_string()))
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if source.role != "user" {
+            return Err(DbError::Query(
+                "Only user messages can be resubmitted".to_string(),
+            ));
+        }
+
+        let history =
+            Self::column_context_with_conn(&tx, &source.session_id, &source.column_id, None)?
+                .into_iter()
+                .filter(|m| m.status == "done" && !m.content.trim().is_empty())
+                .collect::<Vec<_>>();
+
+        // Answer with the column's current provider; fall back to the original one.
+        let provider_id = match tx.query_row(
+            "SELECT provider_id FROM chat_session_columns WHERE id = ?1",
+            [&source.column_id],
+            |row| row.get::<_, String>(0),
+        ) {
+            Ok(id) if !id.trim().is_empty() => id,
+            Ok(_) | Err(rusqlite::Error::QueryReturnedNoRows) => source.provider_id.clone(),
+            Err(e) => return Err(e.into()),
+        };
+
+        // Sort after every existing message even if the clock went backwards.
+        let last_created = history.last().map_or(0, |m| m.created_at);
+        let now = now_unix_ms().max(last_created + 1);
+        let user_message = ChatMessageRecord {
+            id: uuid::Uuid::new_v4().to_string(),
+            session_id: source.session_id.clone(),
+            column_id: source.column_id.clone(),
+            provider_name: provider_name_snapshot(&tx, &provider_id)?,
+            provider_id,
+            role: "user".to_string(),
+            content: source.content,
+            status: "done".to_string(),
+            created_at: now,
+            updated_at: now,
+            content_hash: None,
+        };
+        tx.execute(
+            "INSERT INTO chat_messages
+             (id, session_id, column_id, provider_id, role, content, status, created_at, updated_at,
+              provider_name)
+             VALUES (?1, ?2, ?3, ?4, 'user', ?5, 'done', ?6, ?6, ?7)",
+            rusqlite::params![
+                user_message.id,
+                user_message.session_id,
+                user_message.column_id,
+                user_message.provider_id,
+                user_message.content,
+                now,
+                user_message.provider_name
+            ],
+        )?;
+        tx.execute(
+            "UPDATE chat_sessions SET updated_at = ?1 WHERE id = ?2",
+            rusqlite::params![now, user_message.session_id],
+        )?;
+
+        tx.commit()?;
+        Ok(ResubmitSetup {
+            user_message,
+            history,
         })
     }
 
+    pub fn update_content(id: &str, content: &str, status: &str) -> DbResult<ChatMessageRecord> {
+        connection::with_connection(|conn| {
+            Self::update_content_with_conn(conn, id, content, status)
+        })
+    }
+
+    fn update_content_with_conn(
+        conn: &Connection,
+        id: &str,
+        content: &str,
+        status: &str,
+    ) -> DbResult<ChatMessageRecord> {
+        if status != "streaming" && status != "done" && status != "error" {
+            return Err(DbError::Query("Invalid message status".to_string()));
+        }
+
+        let now = now_unix_ms();
+        let rows = conn.execute(
+            "UPDATE chat_messages
+             SET content = ?1, status = ?2, updated_at = ?3,
+                 content_hash = CASE WHEN role = 'assistant' AND ?2 = 'done' THEN ?5 END
+             WHERE id = ?4",
+            rusqlite::params![content, status, now, id, content_hash(content)],
+        )?;
+
+        if rows == 0 {
+            return Err(DbError::Query("Message not found".to_string()));
+        }
+
+        // Keep all DB operations on this connection handle to avoid
+        // re-entering with_connection and deadlocking the global mutex.
+        let record = conn
+            .query_row(
+                &format!("SELECT {MESSAGE_COLUMNS} FROM chat_messages WHERE id = ?1"),
+                [id],
+                message_from_row,
+            )
+            .map_err(|e| match e {
+                rusqlite::Error::QueryReturnedNoRows => {
+                    DbError::Query("Message not found".to_string())
+                }
+                _ => e.into(),
+            })?;
+
+        conn.execute(
+            "UPDATE chat_sessions SET updated_at = ?1 WHERE id = ?2",
+            rusqlite::params![now, record.session_id],
+        )?;
+
+        Ok(record)
+    }
+
+    /// Delete the oldest whole turns of a session until at most `keep`
+    /// messages remain, returning how many were removed. Each column's newest
+    /// turn is always kept, so every column still starts with a user message,
+    /// and so is any turn with a reply still streaming.
+    fn prune_session_with_conn(
+        conn: &Connection,
+        session_id: &str,
+        keep: usize,
+    ) -> DbResult<usize> {
+        let mut streaming = HashSet::new();
+        let messages: Vec<(String, String, String)> = {
+            let mut stmt = conn.prepare(
+                "SELECT id, COALESCE(column_id, ''), role, status
+                 FROM chat_messages
+                 WHERE session_id = ?1
+                 ORDER BY created_at ASC, id ASC",
+            )?;
+            let rows = stmt.query_map([session_id], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get::<_, String>(3)?,
+                ))
+            })?;
+            let mut values = Vec::new();
+            for row in rows {
+                let (id, column_id, role, status) = row?;
+                if status == "streaming" {
+                    streaming.insert(id.clone());
+                }
+                values.push((id, column_id, role));
+            }
+            values
+        };
+        if messages.len() <= keep {
+            return Ok(0);
+        }
+
+        let turns = group_turns(&messages);
+        let column_of: HashMap<&str, &str> = messages
+            .iter()
+            .map(|(id, column_id, _)| (id.as_str(), column_id.as_str()))
+            .collect();
+        let mut newest_turn: HashMap<&str, usize> = HashMap::new();
+        for (index, turn) in turns.iter().enumerate() {
+            newest_turn.insert(column_of[turn[0].as_str()], index);
+        }
+
+        let mut remaining = messages.len();
+        let mut doomed: Vec<&String> = Vec::new();
+        for (index, turn) in turns.iter().enumerate() {
+            if remaining <= keep {
+                break;
+            }
+            if newest_turn[column_of[turn[0].as_str()]] == index
+                || turn.iter().any(|id| streaming.contains(id))
+            {
+                continue;
+            }
+            remaining -= turn.len();
+            doomed.extend(turn);
+        }
+
+        // The FTS delete trigger drops the matching index rows.
+        let tx = conn.unchecked_transaction()?;
+        {
+            let mut stmt = tx.prepare("DELETE FROM chat_messages WHERE id = ?1")?;
+            for id in &doomed {
+                stmt.execute([id])?;
+            }
+        }
+        tx.commit()?;
+        Ok(doomed.len())
+    }
+
     /// P11: Delete a single message by id.
     pub fn delete(id: &str) -> DbResult<()> {
         connection::with_connection(|conn| {
@@ -219,43 +1022,175 @@ impl ChatMessagesRepository {
         })
     }
 
-    /// P13: Full-text search across all messages using FTS5.
-    pub fn search(query: &str, limit: i64) -> DbResult<Vec<MessageSearchResult>> {
+    /// Delete the partial replies still streaming in `column_id`, returning
+    /// how many were removed. Finished messages are kept.
+    pub fn delete_streaming_in_column(column_id: &str) -> DbResult<usize> {
         connection::with_connection(|conn| {
-            let escaped = query.replace('"', "\"\"");
-            let fts_query = format!("\"{escaped}\"");
+            Self::delete_streaming_in_column_with_conn(conn, column_id)
+        })
+    }
 
-            let mut stmt = conn.prepare(
-                "SELECT
-                    f.id,
-                    f.session_id,
-                    COALESCE(s.title, 'Unknown') AS session_title,
-                    snippet(chat_messages_fts, 2, '<b>', '</b>', '…', 12) AS snippet,
-                    m.created_at
-                 FROM chat_messages_fts f
-                 JOIN chat_messages m ON m.id = f.id
-                 JOIN chat_sessions s ON s.id = f.session_id
-                 WHERE chat_messages_fts MATCH ?1
-                 ORDER BY rank
-                 LIMIT ?2",
-            )?;
+    fn delete_streaming_in_column_with_conn(conn: &Connection, column_id: &str) -> DbResult<usize> {
+        Ok(conn.execute(
+            "DELETE FROM chat_messages WHERE column_id = ?1 AND status = 'streaming'",
+            [column_id],
+        )?)
+    }
 
-            let rows = stmt.query_map(rusqlite::params![fts_query, limit], |row| {
-                Ok(MessageSearchResult {
-                    message_id: row.get(0)?,
-                    session_id: row.get(1)?,
-                    session_title: row.get(2)?,
-                    snippet: row.get(3)?,
-                    created_at: row.get(4)?,
-                })
-            })?;
+    /// Delete every message whose `provider_id` is `provider_id`, returning
+    /// how many were removed. That includes the user prompts stored in the
+    /// provider's columns, not just its replies. The provider itself is kept.
+    ///
+    /// A session still holding other providers' messages keeps its title and
+    /// prompt, since those messages answer the same prompts. A session left
+    /// without any messages is deleted with `remove_empty`, and otherwise has
+    /// its title and prompt cleared so nothing of the purged chat remains.
+    /// With `remove_empty`, the provider's emptied columns are dropped too
+    /// (never a session's last column).
+    pub fn purge_provider(provider_id: &str, remove_empty: bool) -> DbResult<usize> {
+        connection::with_connection(|conn| {
+            Self::purge_provider_with_conn(conn, provider_id, remove_empty)
+        })
+    }
 
-            let mut result: Vec<MessageSearchResult> = Vec::new();
+    fn purge_provider_with_conn(
+        conn: &Connection,
+        provider_id: &str,
+        remove_empty: bool,
+    ) -> DbResult<usize> {
+        let tx = conn.unchecked_transaction()?;
+        let session_ids: Vec<String> = {
+            let mut stmt =
+                tx.prepare("SELECT DISTINCT session_id FROM chat_messages WHERE provider_id = ?1")?;
+            let rows = stmt.query_map([provider_id], |row| row.get(0))?;
+            let mut values = Vec::new();
             for row in rows {
-                result.push(row?);
+                values.push(row?);
             }
-            Ok(result)
-        })
+            values
+        };
+
+        // The FTS delete trigger drops the matching index rows.
+        let deleted = tx.execute(
+            "DELETE FROM chat_messages WHERE provider_id = ?1",
+            [provider_id],
+        )?;
+
+        for session_id in &session_ids {
+            let remaining: i64 = tx.query_row(
+                "SELECT COUNT(*) FROM chat_messages WHERE session_id = ?1",
+                [session_id],
+                |row| row.get(0),
+            )?;
+            if remaining == 0 {
+                if remove_empty {
+                    tx.execute("DELETE FROM chat_sessions WHERE id = ?1", [session_id])?;
+                } else {
+                    tx.execute(
+                        "UPDATE chat_sessions SET title = ?1, prompt = '' WHERE id = ?2",
+                        [DEFAULT_SESSION_TITLE, session_id],
+                    )?;
+                }
+                continue;
+            }
+            if !remove_empty {
+                continue;
+            }
+            let columns_removed = tx.execute(
+                "DELETE FROM chat_session_columns
+                 WHERE session_id = ?1 AND provider_id = ?2
+                   AND NOT EXISTS (
+                       SELECT 1 FROM chat_messages m WHERE m.column_id = chat_session_columns.id
+                   )",
+                [session_id, provider_id],
+            )?;
+            if columns_removed > 0 {
+                ChatSessionColumnsRepository::sync_provider_ids_with_conn(&tx, session_id)?;
+            }
+        }
+
+        tx.commit()?;
+        Ok(deleted)
+    }
+
+    /// Compare the FTS index against the messages table.
+    pub fn search_index_stats() -> DbResult<FtsStats> {
+        connection::with_connection(Self::search_index_stats_with_conn)
+    }
+
+    fn search_index_stats_with_conn(conn: &Connection) -> DbResult<FtsStats> {
+        conn.query_row(
+            "SELECT
+                (SELECT COUNT(*) FROM chat_messages m
+                 WHERE EXISTS (SELECT 1 FROM chat_messages_fts f WHERE f.id = m.id)),
+                (SELECT COUNT(*) FROM chat_messages),
+                (SELECT COUNT(*) FROM chat_messages_fts f
+                 WHERE NOT EXISTS (SELECT 1 FROM chat_messages m WHERE m.id = f.id))",
+            [],
+            |row| {
+                Ok(FtsStats {
+                    indexed_message_count: row.get(0)?,
+                    total_message_count: row.get(1)?,
+                    orphaned_fts_rows: row.get(2)?,
+                })
+            },
+        )
+        .map_err(Into::into)
+    }
+
+    /// P13: Full-text search across all messages using FTS5.
+    /// With `dedup`, identical content within a session (e.g. the per-provider
+    /// copies of one user message) collapses to its earliest match.
+    pub fn search(query: &str, limit: i64, dedup: bool) -> DbResult<Vec<MessageSearchResult>> {
+        connection::with_connection(|conn| Self::search_with_conn(conn, query, limit, dedup))
+    }
+
+    fn search_with_conn(
+        conn: &Connection,
+        query: &str,
+        limit: i64,
+        dedup: bool,
+    ) -> DbResult<Vec<MessageSearchResult>> {
+        let escaped = query.replace('"', "\"\"");
+        let fts_query = format!("\"{escaped}\"");
+
+        let mut stmt = conn.prepare(
+            "SELECT
+                f.id,
+                f.session_id,
+                COALESCE(s.title, 'Unknown') AS session_title,
+                snippet(chat_messages_fts, 2, '<b>', '</b>', '…', 12) AS snippet,
+                m.created_at
+             FROM chat_messages_fts f
+             JOIN chat_messages m ON m.id = f.id
+             JOIN chat_sessions s ON s.id = f.session_id
+             WHERE chat_messages_fts MATCH ?1
+               AND (?3 = 0 OR NOT EXISTS (
+                    SELECT 1 FROM chat_messages d
+                    WHERE d.session_id = m.session_id
+                      AND d.content = m.content
+                      AND (d.created_at < m.created_at
+                           OR (d.created_at = m.created_at AND d.id < m.id))
+               ))
+             ORDER BY rank
+             LIMIT ?2",
+        )?;
+
+        let rows = stmt.query_map(rusqlite::params![fts_query, limit, dedup], |row| {
+            Ok(MessageSearchResult {
+                message_id: row.get(0)?,
+                session_id: row.get(1)?,
+                session_title: row.get(2)?,
+                snippet: row.get(3)?,
+                created_at: row.get(4)?,
+            })
+        })?;
+
+        let mut result: Vec<MessageSearchResult> = Vec::new();
+        for row in rows {
+            result.push(row?);
+        }
+        Ok(result)
     }
 
     /// P13: Export all messages for a session as an array of records (for JSON/Markdown export).
@@ -263,4 +1198,971 @@ impl ChatMessagesRepository {
         // Reuse list_by_session with no limit
         Self::list_by_session(session_id, 0, 0)
     }
+
+    /// Export a session (or one of its columns) as an OpenAI chat request body.
+    /// A whole-session export takes one reply per turn: the canonical answer's
+    /// column when one is set, otherwise the first column.
+    pub fn export_openai(session_id: &str, column_id: Option<&str>) -> DbResult<serde_json::Value> {
+        connection::with_connection(|conn| {
+            Self::export_openai_with_conn(conn, session_id, column_id)
+        })
+    }
+
+    fn export_openai_with_conn(
+        conn: &Connection,
+        session_id: &str,
+        column_id: Option<&str>,
+    ) -> DbResult<serde_json::Value> {
+        let (system_prompt, canonical_id): (String, Option<String>) = match conn.query_row(
+            "SELECT system_prompt, canonical_message_id FROM chat_sessions WHERE id = ?1",
+            [session_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ) {
+            Ok(value) => value,
+            Err(rusqlite::Error::QueryReturnedNoRows) => {
+                return Err(DbError::Query("Session not found".to_string()))
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {MESSAGE_COLUMNS}
+             FROM chat_messages
+             WHERE session_id = ?1 AND (?2 IS NULL OR column_id = ?2)
+             ORDER BY created_at ASC, id ASC"
+        ))?;
+        let messages = stmt
+            .query_map(rusqlite::params![session_id, column_id], message_from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Without a column, each turn's reply comes from the canonical
+        // answer's column, or else the first column.
+        let canonical_column = canonical_id.as_deref().and_then(|id| {
+            messages
+                .iter()
+                .find(|message| message.id == id)
+                .map(|message| message.column_id.as_str())
+        });
+        let first_column: Option<String> = conn
+            .query_row(
+                "SELECT id FROM chat_session_columns
+                 WHERE session_id = ?1 ORDER BY position ASC LIMIT 1",
+                [session_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        Ok(openai_messages_json(
+            &system_prompt,
+            &messages,
+            canonical_id.as_deref(),
+            column_id.or(canonical_column).or(first_column.as_deref()),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interrupted_stream_leaves_partial_message() {
+        let conn = connection::open_test_connection();
+        conn.execute(
+            "INSERT INTO chat_sessions (id, title, provider_ids_json, created_at, updated_at)
+             VALUES ('s1', 'Session', '[]', 0, 0)",
+            [],
+        )
+        .unwrap();
+
+        ChatMessagesRepository::begin_streaming_with_conn(&conn, "m1", "s1", "c1", "p1").unwrap();
+        ChatMessagesRepository::update_content_with_conn(&conn, "m1", "Partial ans", "streaming")
+            .unwrap();
+        // The app is killed here: no final `done`/`error` update ever lands.
+
+        let (content, status, role): (String, String, String) = conn
+            .query_row(
+                "SELECT content, status, role FROM chat_messages WHERE id = 'm1'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!(content, "Partial ans");
+        assert_eq!(status, "streaming");
+        assert_eq!(role, "assistant");
+
+        // A retried stream reclaims the same row instead of failing on the id.
+        ChatMessagesRepository::begin_streaming_with_conn(&conn, "m1", "s1", "c1", "p1").unwrap();
+        let record =
+            ChatMessagesRepository::update_content_with_conn(&conn, "m1", "Partial answer", "done")
+                .unwrap();
+        assert_eq!(record.content, "Partial answer");
+        assert_eq!(record.status, "done");
+    }
+
+    #[test]
+    fn test_diff_words_classifies_segments() {
+        let segment = |kind, text: &str| DiffSegment {
+            kind,
+            text: text.to_string(),
+        };
+        let a = "The quick brown fox jumps";
+        let b = "The slow brown fox leaps high";
+        let diff = diff_words(a, b);
+        assert_eq!(
+            diff,
+            vec![
+                segment(DiffKind::Equal, "The "),
+                segment(DiffKind::Removed, "quick"),
+                segment(DiffKind::Added, "slow"),
+                segment(DiffKind::Equal, " brown fox "),
+                segment(DiffKind::Removed, "jumps"),
+                segment(DiffKind::Added, "leaps high"),
+            ]
+        );
+
+        // Each side is recoverable from its own segments plus the shared ones.
+        let side = |skip| {
+            diff.iter()
+                .filter(|s| s.kind != skip)
+                .map(|s| s.text.as_str())
+                .collect::<String>()
+        };
+        assert_eq!(side(DiffKind::Added), a);
+        assert_eq!(side(DiffKind::Removed), b);
+
+        assert_eq!(
+            diff_words("same", "same"),
+            vec![segment(DiffKind::Equal, "same")]
+        );
+        assert!(diff_words("", "").is_empty());
+    }
+
+    #[test]
+    fn test_parse_transcript_well_formed() {
+        let text = "User: What is Rust?\nAssistant: A systems language.\n\nIt is memory safe.\nUser: Thanks!";
+        assert_eq!(
+            parse_transcript(text),
+            vec![
+                ("user", "What is Rust?".to_string()),
+                (
+                    "assistant",
+                    "A systems language.\n\nIt is memory safe.".to_string()
+                ),
+                ("user", "Thanks!".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_transcript_loose_formatting() {
+        let text = "\
+How do I list files?
+
+### Response
+Use `ls`.
+Note: add -la for details.
+
+**You:** and hidden ones?
+> **ChatGPT:** `ls -a` shows them.
+## Question
+Thanks
+## Answer
+Any time.";
+        assert_eq!(
+            parse_transcript(text),
+            vec![
+                ("user", "How do I list files?".to_string()),
+                (
+                    "assistant",
+                    "Use `ls`.\nNote: add -la for details.".to_string()
+                ),
+                ("user", "and hidden ones?".to_string()),
+                ("assistant", "`ls -a` shows them.".to_string()),
+                ("user", "Thanks".to_string()),
+                ("assistant", "Any time.".to_string()),
+            ]
+        );
+        assert!(parse_transcript("  \n\n").is_empty());
+
+        // With no labels anywhere, headings alone separate the turns.
+        assert_eq!(
+            parse_transcript("Intro question\n## Reply\nThe reply.\n## Follow-up\nThanks"),
+            vec![
+                ("user", "Intro question".to_string()),
+                ("assistant", "The reply.".to_string()),
+                ("user", "Thanks".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_transcript_keeps_headings_and_answer_lines_in_replies() {
+        let text = "\
+User: What is 6 * 7?
+Assistant: Let me work it out.
+## Step 1
+Multiply.
+Answer: 42
+Q: anything else?
+User: Thanks";
+        assert_eq!(
+            parse_transcript(text),
+            vec![
+                ("user", "What is 6 * 7?".to_string()),
+                (
+                    "assistant",
+                    "Let me work it out.\n## Step 1\nMultiply.\nAnswer: 42\nQ: anything else?"
+                        .to_string()
+                ),
+                ("user", "Thanks".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_import_transcript_appends_to_provider_column() {
+        let conn = connection::open_test_connection();
+        conn.execute_batch(
+            "INSERT INTO chat_sessions (id, title, provider_ids_json, created_at, updated_at)
+             VALUES ('s1', 'Session', '[\"p1\",\"p2\"]', 0, 0);
+             INSERT INTO chat_session_columns (id, session_id, position, provider_id, created_at, updated_at)
+             VALUES ('s1:c0', 's1', 0, 'p1', 0, 0), ('s1:c1', 's1', 1, 'p2', 0, 0);",
+        )
+        .unwrap();
+
+        let count = ChatMessagesRepository::import_transcript_with_conn(
+            &conn,
+            "s1",
+            "p2",
+            "User: hi\nAssistant: hello\nUser: bye",
+        )
+        .unwrap();
+        assert_eq!(count, 3);
+        let rows: Vec<(String, String, String)> = conn
+            .prepare("SELECT column_id, role, content FROM chat_messages ORDER BY created_at")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        let roles: Vec<&str> = rows.iter().map(|(_, role, _)| role.as_str()).collect();
+        assert_eq!(roles, vec!["user", "assistant", "user"]);
+        assert!(rows.iter().all(|(column_id, _, _)| column_id == "s1:c1"));
+
+        assert!(
+            ChatMessagesRepository::import_transcript_with_conn(&conn, "s1", "p3", "User: x")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_import_transcript_beyond_cap_prunes_session() {
+        let conn = connection::open_test_connection();
+        conn.execute_batch(
+            "INSERT INTO chat_sessions (id, title, provider_ids_json, created_at, updated_at)
+             VALUES ('s1', 'Session', '[\"p1\"]', 0, 0);
+             INSERT INTO chat_session_columns (id, session_id, position, provider_id, created_at, updated_at)
+             VALUES ('s1:c0', 's1', 0, 'p1', 0, 0);",
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO settings (key, value, updated_at) VALUES (?1, '4', 0)",
+            [crate::SETTING_MAX_MESSAGES_PER_SESSION],
+        )
+        .unwrap();
+
+        let count = ChatMessagesRepository::import_transcript_with_conn(
+            &conn,
+            "s1",
+            "p1",
+            "User: one\nAssistant: 1\nUser: two\nAssistant: 2\nUser: three\nAssistant: 3",
+        )
+        .unwrap();
+        assert_eq!(count, 6);
+        let contents: Vec<String> = conn
+            .prepare("SELECT content FROM chat_messages ORDER BY created_at")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(contents, ["two", "2", "three", "3"]);
+    }
+
+    #[test]
+    fn test_resubmit_appends_new_turn_and_keeps_history() {
+        let conn = connection::open_test_connection();
+        conn.execute(
+            "INSERT INTO chat_sessions (id, title, provider_ids_json, created_at, updated_at)
+             VALUES ('s1', 'Session', '[\"p1\"]', 0, 0)",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO chat_session_columns (id, session_id, position, provider_id, created_at, updated_at)
+             VALUES ('s1:c0', 's1', 0, 'p1', 0, 0)",
+            [],
+        )
+        .unwrap();
+        for (id, role, content, created_at) in [
+            ("m1", "user", "first question", 1),
+            ("m2", "assistant", "first answer", 2),
+            ("m3", "user", "second question", 3),
+            ("m4", "assistant", "second answer", 4),
+        ] {
+            conn.execute(
+                "INSERT INTO chat_messages
+                 (id, session_id, column_id, provider_id, role, content, status, created_at, updated_at)
+                 VALUES (?1, 's1', 's1:c0', 'p1', ?2, ?3, 'done', ?4, ?4)",
+                rusqlite::params![id, role, content, created_at],
+            )
+            .unwrap();
+        }
+
+        assert!(ChatMessagesRepository::resubmit_with_conn(&conn, "m2").is_err());
+
+        let setup = ChatMessagesRepository::resubmit_with_conn(&conn, "m1").unwrap();
+        assert_eq!(setup.user_message.content, "first question");
+        assert_eq!(setup.user_message.column_id, "s1:c0");
+        assert_ne!(setup.user_message.id, "m1");
+        let history_ids: Vec<&str> = setup.history.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(history_ids, ["m1", "m2", "m3", "m4"]);
+
+        ChatMessagesRepository::begin_streaming_with_conn(&conn, "a1", "s1", "s1:c0", "p1")
+            .unwrap();
+        ChatMessagesRepository::update_content_with_conn(&conn, "a1", "fresh answer", "done")
+            .unwrap();
+
+        let rows: Vec<(String, String, String)> = {
+            let mut stmt = conn
+                .prepare(
+                    "SELECT id, role, content FROM chat_messages
+                     WHERE column_id = 's1:c0' ORDER BY rowid ASC",
+                )
+                .unwrap();
+            stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+                .unwrap()
+                .map(Result::unwrap)
+                .collect()
+        };
+        assert_eq!(rows.len(), 6);
+        assert_eq!(
+            rows[..4]
+                .iter()
+                .map(|(id, _, content)| (id.as_str(), content.as_str()))
+                .collect::<Vec<_>>(),
+            [
+                ("m1", "first question"),
+                ("m2", "first answer"),
+                ("m3", "second question"),
+                ("m4", "second answer"),
+            ]
+        );
+        assert_eq!(rows[4].0, setup.user_message.id);
+        assert_eq!(
+            (rows[4].1.as_str(), rows[4].2.as_str()),
+            ("user", "first question")
+        );
+        assert_eq!(
+            rows[5],
+            (
+                "a1".to_string(),
+                "assistant".to_string(),
+                "fresh answer".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_create_beyond_cap_prunes_oldest_turns() {
+        let conn = connection::open_test_connection();
+        conn.execute(
+            "INSERT INTO chat_sessions (id, title, provider_ids_json, created_at, updated_at)
+             VALUES ('s1', 'Session', '[]', 0, 0)",
+            [],
+        )
+        .unwrap();
+        for (id, role, content, created_at) in [
+            ("m1", "user", "oldest question", 1),
+            ("m2", "assistant", "oldest answer", 2),
+            ("m3", "user", "middle question", 3),
+            ("m4", "assistant", "middle answer", 4),
+            ("m5", "user", "newest question", 5),
+            ("m6", "assistant", "newest answer", 6),
+        ] {
+            conn.execute(
+                "INSERT INTO chat_messages
+                 (id, session_id, column_id, provider_id, role, content, status, created_at, updated_at)
+                 VALUES (?1, 's1', 'c0', 'p1', ?2, ?3, 'done', ?4, ?4)",
+                rusqlite::params![id, role, content, created_at],
+            )
+            .unwrap();
+        }
+
+        assert_eq!(parse_max_messages_per_session(Some("abc")), 0);
+        // Five would split a turn, so the whole oldest turn goes and four remain.
+        let removed = ChatMessagesRepository::prune_session_with_conn(&conn, "s1", 5).unwrap();
+        assert_eq!(removed, 2);
+
+        let remaining: Vec<(String, String)> = {
+            let mut stmt = conn
+                .prepare("SELECT id, role FROM chat_messages ORDER BY created_at ASC")
+                .unwrap();
+            stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+                .unwrap()
+                .map(Result::unwrap)
+                .collect()
+        };
+        let ids: Vec<&str> = remaining.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(ids, ["m3", "m4", "m5", "m6"]);
+        assert_eq!(remaining[0].1, "user");
+
+        let fts_hits: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM chat_messages_fts WHERE chat_messages_fts MATCH 'oldest'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(fts_hits, 0);
+
+        // The newest turn survives even when it alone exceeds the cap.
+        ChatMessagesRepository::prune_session_with_conn(&conn, "s1", 1).unwrap();
+        let count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM chat_messages", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn test_prune_keeps_each_columns_newest_and_streaming_turns() {
+        let conn = connection::open_test_connection();
+        conn.execute(
+            "INSERT INTO chat_sessions (id, title, provider_ids_json, created_at, updated_at)
+             VALUES ('s1', 'Session', '[]', 0, 0)",
+            [],
+        )
+        .unwrap();
+        for (id, column_id, role, status, created_at) in [
+            ("m1", "c0", "user", "done", 1),
+            ("m2", "c0", "assistant", "done", 2),
+            ("m3", "c1", "user", "done", 3),
+            ("m4", "c1", "assistant", "streaming", 4),
+            ("m5", "c0", "user", "done", 5),
+            ("m6", "c0", "assistant", "done", 6),
+            ("m7", "c1", "user", "done", 7),
+        ] {
+            conn.execute(
+                "INSERT INTO chat_messages
+                 (id, session_id, column_id, provider_id, role, content, status, created_at, updated_at)
+                 VALUES (?1, 's1', ?2, 'p1', ?3, 'text', ?4, ?5, ?5)",
+                rusqlite::params![id, column_id, role, status, created_at],
+            )
+            .unwrap();
+        }
+
+        // Only c0's older turn may go: m3/m4 is still streaming, and the
+        // rest are each column's newest turn.
+        let removed = ChatMessagesRepository::prune_session_with_conn(&conn, "s1", 2).unwrap();
+        assert_eq!(removed, 2);
+        let ids: Vec<String> = conn
+            .prepare("SELECT id FROM chat_messages ORDER BY created_at ASC")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(ids, ["m3", "m4", "m5", "m6", "m7"]);
+    }
+
+    #[test]
+    fn test_search_index_stats_detect_drift() {
+        let conn = connection::open_test_connection();
+        conn.execute(
+            "INSERT INTO chat_sessions (id, title, provider_ids_json, created_at, updated_at)
+             VALUES ('s1', 'Session', '[]', 0, 0)",
+            [],
+        )
+        .unwrap();
+        for id in ["m1", "m2", "m3"] {
+            conn.execute(
+                "INSERT INTO chat_messages (id, session_id, column_id, provider_id, role, content, status, created_at, updated_at)
+                 VALUES (?1, 's1', 'c0', 'p1', 'user', 'indexed text', 'done', 1, 1)",
+                [id],
+            )
+            .unwrap();
+        }
+
+        let stats = ChatMessagesRepository::search_index_stats_with_conn(&conn).unwrap();
+        assert_eq!(
+            stats,
+            FtsStats {
+                indexed_message_count: 3,
+                total_message_count: 3,
+                orphaned_fts_rows: 0,
+            }
+        );
+
+        // Delete behind the trigger's back, as a crash mid-repair might.
+        conn.execute_batch(
+            "DROP TRIGGER trg_messages_fts_delete;
+             DELETE FROM chat_messages WHERE id = 'm2';",
+        )
+        .unwrap();
+
+        let stats = ChatMessagesRepository::search_index_stats_with_conn(&conn).unwrap();
+        assert_eq!(stats.indexed_message_count, 2);
+        assert_eq!(stats.total_message_count, 2);
+        assert_eq!(stats.orphaned_fts_rows, 1);
+    }
+
+    #[test]
+    fn test_list_by_provider_spans_sessions() {
+        let conn = connection::open_test_connection();
+        conn.execute_batch(
+            "INSERT INTO chat_sessions (id, title, provider_ids_json, created_at, updated_at)
+             VALUES ('s1', 'First', '[\"p1\",\"p2\"]', 0, 0),
+                    ('s2', 'Second', '[\"p1\"]', 0, 0);",
+        )
+        .unwrap();
+        for (id, session_id, provider_id, created_at) in [
+            ("a", "s1", "p1", 1),
+            ("b", "s1", "p2", 2),
+            ("c", "s2", "p1", 3),
+            ("d", "s1", "p1", 4),
+        ] {
+            conn.execute(
+                "INSERT INTO chat_messages
+                 (id, session_id, column_id, provider_id, role, content, status, created_at, updated_at)
+                 VALUES (?1, ?2, ?2 || ':c0', ?3, 'assistant', 'reply', 'done', ?4, ?4)",
+                rusqlite::params![id, session_id, provider_id, created_at],
+            )
+            .unwrap();
+        }
+
+        let thread = ChatMessagesRepository::list_by_provider_with_conn(&conn, "p1", 0, 0).unwrap();
+        let summary = thread
+            .iter()
+            .map(|m| (m.message.id.as_str(), m.session_title.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![("d", "First"), ("c", "Second"), ("a", "First")]
+        );
+
+        let page = ChatMessagesRepository::list_by_provider_with_conn(&conn, "p1", 1, 1).unwrap();
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].message.id, "c");
+    }
+
+    #[test]
+    fn test_delete_streaming_in_column_leaves_other_columns() {
+        let conn = connection::open_test_connection();
+        conn.execute_batch(
+            "INSERT INTO chat_sessions (id, title, provider_ids_json, created_at, updated_at)
+             VALUES ('s1', 'Two', '[\"p1\",\"p2\"]', 0, 0);
+             INSERT INTO chat_session_columns (id, session_id, position, provider_id, created_at, updated_at)
+             VALUES ('s1:c0', 's1', 0, 'p1', 0, 0), ('s1:c1', 's1', 1, 'p2', 0, 0);",
+        )
+        .unwrap();
+        for (id, column_id, provider_id, status) in [
+            ("done0", "s1:c0", "p1", "done"),
+            ("partial0", "s1:c0", "p1", "streaming"),
+            ("partial1", "s1:c1", "p2", "streaming"),
+        ] {
+            conn.execute(
+                "INSERT INTO chat_messages
+                 (id, session_id, column_id, provider_id, role, content, status, created_at, updated_at)
+                 VALUES (?1, 's1', ?2, ?3, 'assistant', 'half an answer', ?4, 1, 1)",
+                rusqlite::params![id, column_id, provider_id, status],
+            )
+            .unwrap();
+        }
+
+        let deleted =
+            ChatMessagesRepository::delete_streaming_in_column_with_conn(&conn, "s1:c0").unwrap();
+        assert_eq!(deleted, 1);
+
+        let remaining: Vec<String> = conn
+            .prepare("SELECT id FROM chat_messages ORDER BY id")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(remaining, vec!["done0", "partial1"]);
+        let stats = ChatMessagesRepository::search_index_stats_with_conn(&conn).unwrap();
+        assert_eq!(stats.indexed_message_count, 2);
+    }
+
+    #[test]
+    fn test_purge_provider_removes_only_its_messages() {
+        let conn = connection::open_test_connection();
+        conn.execute_batch(
+            "INSERT INTO chat_sessions (id, title, provider_ids_json, created_at, updated_at)
+             VALUES ('s1', 'Both', '[\"p1\",\"p2\"]', 0, 0),
+                    ('s2', 'Only p1', '[\"p1\"]', 0, 0);
+             INSERT INTO chat_session_columns (id, session_id, position, provider_id, created_at, updated_at)
+             VALUES ('s1:c0', 's1', 0, 'p1', 0, 0),
+                    ('s1:c1', 's1', 1, 'p2', 0, 0),
+                    ('s2:c0', 's2', 0, 'p1', 0, 0);",
+        )
+        .unwrap();
+        for (id, session_id, column_id, provider_id, role) in [
+            ("u1", "s1", "s1:c0", "p1", "user"),
+            ("a1", "s1", "s1:c0", "p1", "assistant"),
+            ("u2", "s1", "s1:c1", "p2", "user"),
+            ("a2", "s1", "s1:c1", "p2", "assistant"),
+            ("u3", "s2", "s2:c0", "p1", "user"),
+        ] {
+            conn.execute(
+                "INSERT INTO chat_messages
+                 (id, session_id, column_id, provider_id, role, content, status, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, 'purge me maybe', 'done', 1, 1)",
+                rusqlite::params![id, session_id, column_id, provider_id, role],
+            )
+            .unwrap();
+        }
+
+        let purged = ChatMessagesRepository::purge_provider_with_conn(&conn, "p1", true).unwrap();
+        assert_eq!(purged, 3);
+
+        let remaining: Vec<String> = conn
+            .prepare("SELECT id FROM chat_messages ORDER BY id")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(remaining, vec!["a2", "u2"]);
+        let stats = ChatMessagesRepository::search_index_stats_with_conn(&conn).unwrap();
+        assert_eq!(stats.indexed_message_count, 2);
+        assert_eq!(stats.orphaned_fts_rows, 0);
+
+        // The emptied session is gone; the other lost only p1's column.
+        let session_ids: Vec<String> = conn
+            .prepare("SELECT id FROM chat_sessions")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(session_ids, vec!["s1"]);
+        let provider_ids_json: String = conn
+            .query_row(
+                "SELECT provider_ids_json FROM chat_sessions WHERE id = 's1'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(provider_ids_json, r#"["p2"]"#);
+    }
+
+    #[test]
+    fn test_purge_provider_clears_titles_only_of_emptied_sessions() {
+        let conn = connection::open_test_connection();
+        conn.execute_batch(
+            "INSERT INTO chat_sessions (id, title, provider_ids_json, prompt, created_at, updated_at)
+             VALUES ('s1', 'Secret plans', '[\"p1\",\"p2\"]', 'secret plans?', 0, 0),
+                    ('s2', 'Private note', '[\"p1\"]', 'private note', 0, 0);
+             INSERT INTO chat_session_columns (id, session_id, position, provider_id, created_at, updated_at)
+             VALUES ('s1:c0', 's1', 0, 'p1', 0, 0),
+                    ('s1:c1', 's1', 1, 'p2', 0, 0),
+                    ('s2:c0', 's2', 0, 'p1', 0, 0);",
+        )
+        .unwrap();
+        for (id, session_id, column_id, provider_id, role) in [
+            ("u1", "s1", "s1:c0", "p1", "user"),
+            ("a1", "s1", "s1:c0", "p1", "assistant"),
+            ("u2", "s1", "s1:c1", "p2", "user"),
+            ("u3", "s2", "s2:c0", "p1", "user"),
+            ("a3", "s2", "s2:c0", "p1", "assistant"),
+        ] {
+            conn.execute(
+                "INSERT INTO chat_messages
+                 (id, session_id, column_id, provider_id, role, content, status, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, 'secret', 'done', 1, 1)",
+                rusqlite::params![id, session_id, column_id, provider_id, role],
+            )
+            .unwrap();
+        }
+
+        // The provider's user prompts go along with its replies.
+        let purged = ChatMessagesRepository::purge_provider_with_conn(&conn, "p1", false).unwrap();
+        assert_eq!(purged, 4);
+
+        let sessions: Vec<(String, String, String)> = conn
+            .prepare("SELECT id, title, prompt FROM chat_sessions ORDER BY id")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(
+            sessions,
+            vec![
+                (
+                    "s1".to_string(),
+                    "Secret plans".to_string(),
+                    "secret plans?".to_string()
+                ),
+                (
+                    "s2".to_string(),
+                    DEFAULT_SESSION_TITLE.to_string(),
+                    String::new()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_identical_replies_share_stored_hash() {
+        let conn = connection::open_test_connection();
+        conn.execute(
+            "INSERT INTO chat_sessions (id, title, provider_ids_json, created_at, updated_at)
+             VALUES ('s1', 'Session', '[]', 0, 0)",
+            [],
+        )
+        .unwrap();
+
+        assert_eq!(content_hash("Same answer"), content_hash("Same answer\n"));
+        assert_ne!(content_hash("Same answer"), content_hash("Other answer"));
+        assert_eq!(content_hash("").len(), 16);
+
+        for (id, column_id, content) in [
+            ("a1", "c0", "Same answer"),
+            ("a2", "c1", "Same answer"),
+            ("a3", "c2", "Other answer"),
+        ] {
+            ChatMessagesRepository::begin_streaming_with_conn(&conn, id, "s1", column_id, "p1")
+                .unwrap();
+            let streaming =
+                ChatMessagesRepository::update_content_with_conn(&conn, id, content, "streaming")
+                    .unwrap();
+            assert_eq!(streaming.content_hash, None);
+            ChatMessagesRepository::update_content_with_conn(&conn, id, content, "done").unwrap();
+        }
+
+        let stored = |id: &str| -> Option<String> {
+            conn.query_row(
+                "SELECT content_hash FROM chat_messages WHERE id = ?1",
+                [id],
+                |row| row.get(0),
+            )
+            .unwrap()
+        };
+        assert_eq!(stored("a1"), Some(content_hash("Same answer")));
+        assert_eq!(stored("a1"), stored("a2"));
+        assert_ne!(stored("a1"), stored("a3"));
+    }
+
+    #[test]
+    fn test_search_dedup_collapses_provider_copies() {
+        let conn = connection::open_test_connection();
+        conn.execute(
+            "INSERT INTO chat_sessions (id, title, provider_ids_json, created_at, updated_at)
+             VALUES ('s1', 'Session', '[]', 0, 0)",
+            [],
+        )
+        .unwrap();
+        for (id, provider_id, created_at) in [("u1", "p1", 10), ("u2", "p2", 11), ("u3", "p3", 12)]
+        {
+            conn.execute(
+                "INSERT INTO chat_messages (id, session_id, column_id, provider_id, role, content, status, created_at, updated_at)
+                 VALUES (?1, 's1', ?2, ?2, 'user', 'compare rust lifetimes', 'done', ?3, ?3)",
+                rusqlite::params![id, provider_id, created_at],
+            )
+            .unwrap();
+        }
+
+        let all = ChatMessagesRepository::search_with_conn(&conn, "lifetimes", 20, false).unwrap();
+        assert_eq!(all.len(), 3);
+
+        let deduped =
+            ChatMessagesRepository::search_with_conn(&conn, "lifetimes", 20, true).unwrap();
+        assert_eq!(deduped.len(), 1);
+        assert_eq!(deduped[0].message_id, "u1");
+    }
+
+    #[test]
+    fn test_export_openai_maps_roles_and_prepends_system() {
+        let conn = connection::open_test_connection();
+        conn.execute(
+            "INSERT INTO chat_sessions (id, title, provider_ids_json, system_prompt, created_at, updated_at)
+             VALUES ('s1', 'Session', '[]', 'Be brief.', 0, 0)",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO chat_session_columns (id, session_id, position, provider_id, created_at, updated_at)
+             VALUES ('c1', 's1', 0, 'p1', 0, 0), ('c0', 's1', 1, 'p1', 0, 0)",
+            [],
+        )
+        .unwrap();
+        for (id, column_id, role, content, status, created_at) in [
+            ("m1", "c0", "user", "hi", "done", 1),
+            ("m2", "c1", "user", "hi", "done", 1),
+            ("m3", "c0", "assistant", "hello from c0", "done", 2),
+            ("m4", "c1", "assistant", "hello from c1", "done", 2),
+            ("m5", "c0", "user", "thanks", "done", 3),
+            ("m6", "c1", "user", "thanks", "done", 3),
+            ("m7", "c0", "assistant", "welcome from c0", "done", 4),
+            ("m8", "c1", "assistant", "boom", "error", 4),
+        ] {
+            conn.execute(
+                "INSERT INTO chat_messages
+                 (id, session_id, column_id, provider_id, role, content, status, created_at, updated_at)
+                 VALUES (?1, 's1', ?2, 'p1', ?3, ?4, ?5, ?6, ?6)",
+                rusqlite::params![id, column_id, role, content, status, created_at],
+            )
+            .unwrap();
+        }
+
+        // One reply per turn, from the first column (c1) unless it failed.
+        let all = ChatMessagesRepository::export_openai_with_conn(&conn, "s1", None).unwrap();
+        assert_eq!(
+            all,
+            serde_json::json!({"messages": [
+                {"role": "system", "content": "Be brief."},
+                {"role": "user", "content": "hi"},
+                {"role": "assistant", "content": "hello from c1"},
+                {"role": "user", "content": "thanks"},
+                {"role": "assistant", "content": "welcome from c0"},
+            ]})
+        );
+
+        let column =
+            ChatMessagesRepository::export_openai_with_conn(&conn, "s1", Some("c1")).unwrap();
+        assert_eq!(
+            column,
+            serde_json::json!({"messages": [
+                {"role": "system", "content": "Be brief."},
+                {"role": "user", "content": "hi"},
+                {"role": "assistant", "content": "hello from c1"},
+                {"role": "user", "content": "thanks"},
+            ]})
+        );
+
+        assert!(ChatMessagesRepository::export_openai_with_conn(&conn, "missing", None).is_err());
+    }
+
+    #[test]
+    fn test_export_openai_prefers_canonical_answer() {
+        let conn = connection::open_test_connection();
+        conn.execute(
+            "INSERT INTO chat_sessions (id, title, provider_ids_json, created_at, updated_at)
+             VALUES ('s1', 'Session', '[]', 0, 0)",
+            [],
+        )
+        .unwrap();
+        for (id, column_id, role, content, created_at) in [
+            ("u1", "c0", "user", "pick one", 1),
+            ("u2", "c1", "user", "pick one", 1),
+            ("a1", "c0", "assistant", "answer from c0", 2),
+            ("a2", "c1", "assistant", "answer from c1", 2),
+            ("u3", "c0", "user", "and then?", 3),
+            ("u4", "c1", "user", "and then?", 3),
+            ("a3", "c0", "assistant", "next from c0", 4),
+            ("a4", "c1", "assistant", "next from c1", 4),
+        ] {
+            conn.execute(
+                "INSERT INTO chat_messages
+                 (id, session_id, column_id, provider_id, role, content, status, created_at, updated_at)
+                 VALUES (?1, 's1', ?2, 'p1', ?3, ?4, 'done', ?5, ?5)",
+                rusqlite::params![id, column_id, role, content, created_at],
+            )
+            .unwrap();
+        }
+        conn.execute(
+            "UPDATE chat_sessions SET canonical_message_id = 'a2' WHERE id = 's1'",
+            [],
+        )
+        .unwrap();
+
+        // Every turn answers once, from the canonical answer's column.
+        let all = ChatMessagesRepository::export_openai_with_conn(&conn, "s1", None).unwrap();
+        assert_eq!(
+            all,
+            serde_json::json!({"messages": [
+                {"role": "user", "content": "pick one"},
+                {"role": "assistant", "content": "answer from c1"},
+                {"role": "user", "content": "and then?"},
+                {"role": "assistant", "content": "next from c1"},
+            ]})
+        );
+
+        // A column export that does not hold the canonical answer is unaffected.
+        let column =
+            ChatMessagesRepository::export_openai_with_conn(&conn, "s1", Some("c0")).unwrap();
+        assert_eq!(column["messages"][1]["content"], "answer from c0");
+    }
+
+    #[test]
+    fn test_provider_name_snapshot_survives_rename_and_delete() {
+        let conn = connection::open_test_connection();
+        conn.execute_batch(
+            "INSERT INTO chat_sessions (id, title, provider_ids_json, created_at, updated_at)
+             VALUES ('s1', 'Session', '[]', 0, 0);
+             INSERT INTO providers (id, name, provider_type, model, created_at, updated_at)
+             VALUES ('p1', 'Work GPT', 'openai', 'm', 0, 0);",
+        )
+        .unwrap();
+
+        ChatMessagesRepository::begin_streaming_with_conn(&conn, "m1", "s1", "c1", "p1").unwrap();
+        conn.execute("UPDATE providers SET name = 'Renamed' WHERE id = 'p1'", [])
+            .unwrap();
+        let record =
+            ChatMessagesRepository::update_content_with_conn(&conn, "m1", "answer", "done")
+                .unwrap();
+        assert_eq!(record.provider_name.as_deref(), Some("Work GPT"));
+
+        // Re-claiming the row for another stream keeps the original label.
+        ChatMessagesRepository::begin_streaming_with_conn(&conn, "m1", "s1", "c1", "p1").unwrap();
+        conn.execute("DELETE FROM providers WHERE id = 'p1'", [])
+            .unwrap();
+        let record =
+            ChatMessagesRepository::update_content_with_conn(&conn, "m1", "answer", "done")
+                .unwrap();
+        assert_eq!(record.provider_name.as_deref(), Some("Work GPT"));
+        assert_eq!(provider_name_snapshot(&conn, "p1").unwrap(), None);
+    }
+
+    #[test]
+    fn test_message_context_stops_at_target() {
+        let conn = connection::open_test_connection();
+        conn.execute(
+            "INSERT INTO chat_sessions (id, title, provider_ids_json, created_at, updated_at)
+             VALUES ('s1', 'S', '[\"p1\"]', 0, 0)",
+            [],
+        )
+        .unwrap();
+        for (id, column_id, role, created_at) in [
+            ("m1", "s1:c0", "user", 1),
+            ("m2", "s1:c0", "assistant", 2),
+            ("other", "s1:c1", "assistant", 2),
+            ("m3", "s1:c0", "user", 3),
+            ("m4", "s1:c0", "assistant", 4),
+        ] {
+            conn.execute(
+                "INSERT INTO chat_messages
+                 (id, session_id, column_id, provider_id, role, content, status, created_at, updated_at)
+                 VALUES (?1, 's1', ?2, 'p1', ?3, ?1, 'done', ?4, ?4)",
+                rusqlite::params![id, column_id, role, created_at],
+            )
+            .unwrap();
+        }
+        let ids = |message_id: &str| {
+            ChatMessagesRepository::context_with_conn(&conn, message_id)
+                .unwrap()
+                .into_iter()
+                .map(|m| m.id)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(ids("m3"), vec!["m1", "m2", "m3"]);
+        assert_eq!(ids("m4"), vec!["m1", "m2", "m3", "m4"]);
+        assert_eq!(ids("other"), vec!["other"]);
+        assert!(ChatMessagesRepository::context_with_conn(&conn, "missing").is_err());
+    }
 }
@@ -1,5 +1,8 @@
+use super::chat_messages::{message_from_row, provider_name_snapshot, MESSAGE_COLUMNS};
 use crate::db::connection;
 use crate::db::error::{DbError, DbResult};
+use crate::db::migrations;
+use crate::db::ChatMessageRecord;
 use serde::{Deserialize, Serialize};
 use std::time::{SystemTime, UNIX_EPOCH};
 
@@ -20,6 +23,37 @@ pub struct ChatSessionColumnRecord {
     pub updated_at: i64,
 }
 
+/// Counts of each fix applied by `ChatSessionColumnsRepository::repair_integrity`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RepairReport {
+    /// Messages whose column_id pointed at a column that no longer exists.
+    pub dangling_column_refs: usize,
+    /// Columns created for sessions that had none.
+    pub columns_created: usize,
+    /// Messages assigned to a column by the v9 backfill.
+    pub messages_backfilled: usize,
+    /// Columns removed because they had no messages and no usable provider.
+    pub columns_removed: usize,
+}
+
+/// A column together with its most recent message, if it has any.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColumnWithLatest {
+    pub column: ChatSessionColumnRecord,
+    pub latest_message: Option<ChatMessageRecord>,
+}
+
+/// A new column forked from an existing one so another provider can answer
+/// the same last prompt (see `ChatSessionColumnsRepository::fork_for_compare`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompareColumnSetup {
+    pub column: ChatSessionColumnRecord,
+    /// Copy of the source column's last user message, now in the new column.
+    pub user_message: ChatMessageRecord,
+    /// Earlier finished turns of the source column, oldest first.
+    pub history: Vec<ChatMessageRecord>,
+}
+
 pub struct ChatSessionColumnsRepository;
 
 impl ChatSessionColumnsRepository {
@@ -49,6 +83,60 @@ impl ChatSessionColumnsRepository {
         })
     }
 
+    /// Every column of `session_id` in position order, each with its latest
+    /// message, in a single query.
+    pub fn list_with_latest(session_id: &str) -> DbResult<Vec<ColumnWithLatest>> {
+        connection::with_connection(|conn| Self::list_with_latest_with_conn(conn, session_id))
+    }
+
+    fn list_with_latest_with_conn(
+        conn: &rusqlite::Connection,
+        session_id: &str,
+    ) -> DbResult<Vec<ColumnWithLatest>> {
+        let message_columns = MESSAGE_COLUMNS
+            .split(", ")
+            .map(|column| format!("m.{column}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        // Message fields come first so `message_from_row` can read them as-is.
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {message_columns},
+                    c.id, c.session_id, c.position, c.provider_id, c.created_at, c.updated_at
+             FROM chat_session_columns c
+             LEFT JOIN chat_messages m ON m.id = (
+                 SELECT id FROM chat_messages
+                 WHERE column_id = c.id
+                 ORDER BY created_at DESC, id DESC
+                 LIMIT 1
+             )
+             WHERE c.session_id = ?1
+             ORDER BY c.position ASC"
+        ))?;
+        let offset = MESSAGE_COLUMNS.split(", ").count();
+        let rows = stmt.query_map([session_id], |row| {
+            let latest_message = match row.get::<_, Option<String>>(0)? {
+                Some(_) => Some(message_from_row(row)?),
+                None => None,
+            };
+            Ok(ColumnWithLatest {
+                column: ChatSessionColumnRecord {
+                    id: row.get(offset)?,
+                    session_id: row.get(offset + 1)?,
+                    position: row.get(offset + 2)?,
+                    provider_id: row.get(offset + 3)?,
+                    created_at: row.get(offset + 4)?,
+                    updated_at: row.get(offset + 5)?,
+                },
+                latest_message,
+            })
+        })?;
+        let mut result = Vec::new();
+        for row in rows {
+            result.push(row?);
+        }
+        Ok(result)
+    }
+
     pub fn set_provider(column_id: &str, provider_id: &str) -> DbResult<ChatSessionColumnRecord> {
         connection::with_connection(|conn| {
             let now = now_unix_ms();
@@ -107,4 +195,421 @@ impl ChatSessionColumnsRepository {
             .map_err(Into::into)
         })
     }
+
+    /// Append a column for `other_provider_id` and copy `column_id`'s last user
+    /// message into it, returning what the caller needs to stream the answer.
+    pub fn fork_for_compare(
+        session_id: &str,
+        column_id: &str,
+        other_provider_id: &str,
+    ) -> DbResult<CompareColumnSetup> {
+        connection::with_connection(|conn| {
+            Self::fork_for_compare_with_conn(conn, session_id, column_id, other_provider_id)
+        })
+    }
+
+    fn fork_for_compare_with_conn(
+        conn: &rusqlite::Connection,
+        session_id: &str,
+        column_id: &str,
+        other_provider_id: &str,
+    ) -> DbResult<CompareColumnSetup> {
+        let tx = conn.unchecked_transaction()?;
+
+        let messages: Vec<ChatMessageRecord> = {
+            let mut stmt = tx.prepare(&format!(
+                "SELECT {MESSAGE_COLUMNS}
+                 FROM chat_messages
+                 WHERE session_id = ?1 AND column_id = ?2
+                 ORDER BY created_at ASC, id ASC"
+            ))?;
+            let rows = stmt.query_map([session_id, column_id], message_from_row)?;
+            let mut values = Vec::new();
+            for row in rows {
+                values.push(row?);
+            }
+            values
+        };
+
+        let last_user_idx = messages
+            .iter()
+            .rposition(|m| m.role == "user")
+            .ok_or_else(|| DbError::Query("Column has no user message to compare".to_string()))?;
+        let source = &messages[last_user_idx];
+        let history = messages[..last_user_idx]
+            .iter()
+            .filter(|m| m.status == "done" && !m.content.trim().is_empty())
+            .cloned()
+            .collect::<Vec<_>>();
+
+        let now = now_unix_ms();
+        let position: i64 = tx.query_row(
+            "SELECT COALESCE(MAX(position), -1) + 1 FROM chat_session_columns WHERE session_id = ?1",
+            [session_id],
+            |row| row.get(0),
+        )?;
+        let new_column_id = format!("{session_id}:c{position}");
+        tx.execute(
+            "INSERT INTO chat_session_columns
+             (id, session_id, position, provider_id, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?5)",
+            rusqlite::params![new_column_id, session_id, position, other_provider_id, now],
+        )?;
+
+        let user_message = ChatMessageRecord {
+            id: uuid::Uuid::new_v4().to_string(),
+            session_id: session_id.to_string(),
+            column_id: new_column_id.clone(),
+            provider_id: other_provider_id.to_string(),
+            role: "user".to_string(),
+            content: source.content.clone(),
+            status: "done".to_string(),
+            created_at: now,
+            updated_at: now,
+            provider_name: provider_name_snapshot(&tx, other_provider_id)?,
+            content_hash: None,
+        };
+        tx.execute(
+            "INSERT INTO chat_messages
+             (id, session_id, column_id, provider_id, role, content, status, created_at, updated_at,
+              provider_name)
+             VALUES (?1, ?2, ?3, ?4, 'user', ?5, 'done', ?6, ?6, ?7)",
+            rusqlite::params![
+                user_message.id,
+                session_id,
+                new_column_id,
+                other_provider_id,
+                user_message.content,
+                now,
+                user_message.provider_name
+            ],
+        )?;
+
+        // Keep provider_ids_json in sync with current ordered columns.
+        let ordered: Vec<String> = {
+            let mut stmt = tx.prepare(
+                "SELECT provider_id FROM chat_session_columns
+                 WHERE session_id = ?1
+                 ORDER BY position ASC",
+            )?;
+            let rows = stmt.query_map([session_id], |row| row.get::<_, String>(0))?;
+            let mut values = Vec::new();
+            for row in rows {
+                values.push(row?);
+            }
+            values
+        };
+        tx.execute(
+            "UPDATE chat_sessions SET provider_ids_json = ?1, updated_at = ?2 WHERE id = ?3",
+            rusqlite::params![serde_json::to_string(&ordered)?, now, session_id],
+        )?;
+
+        tx.commit()?;
+        Ok(CompareColumnSetup {
+            column: ChatSessionColumnRecord {
+                id: new_column_id,
+                session_id: session_id.to_string(),
+                position,
+                provider_id: other_provider_id.to_string(),
+                created_at: now,
+                updated_at: now,
+            },
+            user_message,
+            history,
+        })
+    }
+
+    /// Keep a session's provider_ids_json in sync with its remaining ordered
+    /// columns, after columns were removed behind `save_state`'s back.
+    pub(super) fn sync_provider_ids_with_conn(
+        conn: &rusqlite::Connection,
+        session_id: &str,
+    ) -> DbResult<()> {
+        let ordered: Vec<String> = {
+            let mut stmt = conn.prepare(
+                "SELECT provider_id FROM chat_session_columns
+                 WHERE session_id = ?1 AND provider_id != ''
+                 ORDER BY position ASC",
+            )?;
+            let rows = stmt.query_map([session_id], |row| row.get::<_, String>(0))?;
+            let mut values = Vec::new();
+            for row in rows {
+                values.push(row?);
+            }
+            values
+        };
+        conn.execute(
+            "UPDATE chat_sessions SET provider_ids_json = ?1 WHERE id = ?2",
+            rusqlite::params![serde_json::to_string(&ordered)?, session_id],
+        )?;
+        Ok(())
+    }
+
+    /// Detect and repair orphaned columns and messages in one transaction.
+    pub fn repair_integrity() -> DbResult<RepairReport> {
+        connection::with_connection(Self::repair_integrity_with_conn)
+    }
+
+    fn repair_integrity_with_conn(conn: &rusqlite::Connection) -> DbResult<RepairReport> {
+        let tx = conn.unchecked_transaction()?;
+        // Clear references to missing columns so the backfill picks them up.
+        let dangling_column_refs = tx.execute(
+            "UPDATE chat_messages SET column_id = NULL
+             WHERE column_id IS NOT NULL AND column_id != ''
+               AND column_id NOT IN (SELECT id FROM chat_session_columns)",
+            [],
+        )?;
+
+        // Drop empty columns without a provider (blank or deleted), but never a
+        // session's last remaining column.
+        let removable: Vec<(String, String)> = {
+            let mut stmt = tx.prepare(
+                "SELECT c.id, c.session_id
+                 FROM chat_session_columns c
+                 WHERE (c.provider_id = '' OR c.provider_id NOT IN (SELECT id FROM providers))
+                   AND NOT EXISTS (
+                       SELECT 1 FROM chat_messages m
+                       WHERE m.column_id = c.id
+                          OR ((m.column_id IS NULL OR m.column_id = '')
+                              AND m.session_id = c.session_id
+                              AND m.provider_id = c.provider_id)
+                   )
+                   AND (
+                       SELECT COUNT(*) FROM chat_session_columns other
+                       WHERE other.session_id = c.session_id
+                   ) > 1",
+            )?;
+            let rows = stmt.query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })?;
+            let mut values = Vec::new();
+            for row in rows {
+                values.push(row?);
+            }
+            values
+        };
+
+        let mut report = RepairReport {
+            dangling_column_refs,
+            ..RepairReport::default()
+        };
+        let mut touched_sessions = Vec::new();
+        for (column_id, session_id) in &removable {
+            report.columns_removed += tx.execute(
+                "DELETE FROM chat_session_columns WHERE id = ?1",
+                [column_id],
+            )?;
+            if !touched_sessions.contains(session_id) {
+                touched_sessions.push(session_id.clone());
+            }
+        }
+
+        report.columns_created = migrations::ensure_session_columns(&tx)?;
+        report.messages_backfilled = migrations::backfill_message_column_ids(&tx)?;
+
+        for session_id in touched_sessions {
+            Self::sync_provider_ids_with_conn(&tx, &session_id)?;
+        }
+
+        tx.commit()?;
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seed_session(conn: &rusqlite::Connection, id: &str, provider_ids: &[&str]) {
+        conn.execute(
+            "INSERT INTO chat_sessions (id, title, provider_ids_json, created_at, updated_at)
+             VALUES (?1, 'Session', ?2, 0, 0)",
+            rusqlite::params![id, serde_json::to_string(provider_ids).unwrap()],
+        )
+        .unwrap();
+    }
+
+    fn seed_message(
+        conn: &rusqlite::Connection,
+        id: &str,
+        session_id: &str,
+        column_id: Option<&str>,
+    ) {
+        conn.execute(
+            "INSERT INTO chat_messages
+             (id, session_id, column_id, provider_id, role, content, status, created_at, updated_at)
+             VALUES (?1, ?2, ?3, 'p1', 'user', 'hello', 'done', 0, 0)",
+            rusqlite::params![id, session_id, column_id],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_list_with_latest_picks_newest_message_per_column() {
+        let conn = connection::open_test_connection();
+        seed_session(&conn, "s1", &["p1", "p2", "p3"]);
+        conn.execute(
+            "INSERT INTO chat_session_columns (id, session_id, position, provider_id, created_at, updated_at)
+             VALUES ('c1', 's1', 1, 'p2', 0, 0), ('c0', 's1', 0, 'p1', 0, 0), ('c2', 's1', 2, 'p3', 0, 0)",
+            [],
+        )
+        .unwrap();
+        // c0 has three messages, c1 one, c2 none.
+        for (id, column_id, created_at) in [
+            ("m1", "c0", 1),
+            ("m3", "c0", 3),
+            ("m2", "c0", 2),
+            ("m4", "c1", 1),
+        ] {
+            conn.execute(
+                "INSERT INTO chat_messages
+                 (id, session_id, column_id, provider_id, role, content, status, created_at, updated_at)
+                 VALUES (?1, 's1', ?2, 'p1', 'user', 'hello', 'done', ?3, ?3)",
+                rusqlite::params![id, column_id, created_at],
+            )
+            .unwrap();
+        }
+
+        let columns =
+            ChatSessionColumnsRepository::list_with_latest_with_conn(&conn, "s1").unwrap();
+        let summary: Vec<(&str, Option<&str>)> = columns
+            .iter()
+            .map(|entry| {
+                (
+                    entry.column.id.as_str(),
+                    entry.latest_message.as_ref().map(|m| m.id.as_str()),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![("c0", Some("m3")), ("c1", Some("m4")), ("c2", None)]
+        );
+        assert_eq!(columns[1].column.provider_id, "p2");
+        assert!(
+            ChatSessionColumnsRepository::list_with_latest_with_conn(&conn, "missing")
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_repair_integrity_fixes_orphans() {
+        let conn = connection::open_test_connection();
+        conn.execute(
+            "INSERT INTO providers (id, name, provider_type, model, created_at, updated_at)
+             VALUES ('p1', 'P1', 'openai', 'gpt-4o-mini', 0, 0)",
+            [],
+        )
+        .unwrap();
+
+        // s1: a live column, a column for a deleted provider, and messages
+        // pointing at a missing column or at none.
+        seed_session(&conn, "s1", &["p1", "gone"]);
+        conn.execute(
+            "INSERT INTO chat_session_columns (id, session_id, position, provider_id, created_at, updated_at)
+             VALUES ('s1:c0', 's1', 0, 'p1', 0, 0), ('s1:c1', 's1', 1, 'gone', 0, 0)",
+            [],
+        )
+        .unwrap();
+        seed_message(&conn, "m1", "s1", Some("s1:c9"));
+        seed_message(&conn, "m2", "s1", None);
+        // s2: pre-v9 session without any columns.
+        seed_session(&conn, "s2", &["p1"]);
+        seed_message(&conn, "m3", "s2", None);
+
+        let report = ChatSessionColumnsRepository::repair_integrity_with_conn(&conn).unwrap();
+        assert_eq!(report.dangling_column_refs, 1);
+        assert_eq!(report.columns_removed, 1);
+        assert_eq!(report.columns_created, 1);
+        assert_eq!(report.messages_backfilled, 3);
+
+        let column_of = |id: &str| -> String {
+            conn.query_row(
+                "SELECT column_id FROM chat_messages WHERE id = ?1",
+                [id],
+                |row| row.get(0),
+            )
+            .unwrap()
+        };
+        assert_eq!(column_of("m1"), "s1:c0");
+        assert_eq!(column_of("m2"), "s1:c0");
+        assert_eq!(column_of("m3"), "s2:c0");
+
+        let provider_ids_json: String = conn
+            .query_row(
+                "SELECT provider_ids_json FROM chat_sessions WHERE id = 's1'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(provider_ids_json, r#"["p1"]"#);
+
+        // A second pass finds nothing left to fix.
+        let again = ChatSessionColumnsRepository::repair_integrity_with_conn(&conn).unwrap();
+        assert_eq!(again.dangling_column_refs, 0);
+        assert_eq!(again.columns_removed, 0);
+        assert_eq!(again.columns_created, 0);
+        assert_eq!(again.messages_backfilled, 0);
+    }
+
+    #[test]
+    fn test_fork_for_compare_creates_column_and_user_message() {
+        let conn = connection::open_test_connection();
+        seed_session(&conn, "s1", &["p1"]);
+        conn.execute(
+            "INSERT INTO chat_session_columns (id, session_id, position, provider_id, created_at, updated_at)
+             VALUES ('s1:c0', 's1', 0, 'p1', 0, 0)",
+            [],
+        )
+        .unwrap();
+        for (id, role, content, created_at) in [
+            ("m1", "user", "first question", 1),
+            ("m2", "assistant", "first answer", 2),
+            ("m3", "user", "follow-up", 3),
+            ("m4", "assistant", "second answer", 4),
+        ] {
+            conn.execute(
+                "INSERT INTO chat_messages
+                 (id, session_id, column_id, provider_id, role, content, status, created_at, updated_at)
+                 VALUES (?1, 's1', 's1:c0', 'p1', ?2, ?3, 'done', ?4, ?4)",
+                rusqlite::params![id, role, content, created_at],
+            )
+            .unwrap();
+        }
+
+        let setup =
+            ChatSessionColumnsRepository::fork_for_compare_with_conn(&conn, "s1", "s1:c0", "p2")
+                .unwrap();
+
+        assert_eq!(setup.column.id, "s1:c1");
+        assert_eq!(setup.column.position, 1);
+        assert_eq!(setup.column.provider_id, "p2");
+        assert_eq!(setup.user_message.content, "follow-up");
+        assert_eq!(setup.user_message.column_id, "s1:c1");
+        let history_ids = setup
+            .history
+            .iter()
+            .map(|m| m.id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(history_ids, vec!["m1", "m2"]);
+
+        let copied: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM chat_messages
+                 WHERE column_id = 's1:c1' AND role = 'user' AND provider_id = 'p2'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(copied, 1);
+        let provider_ids_json: String = conn
+            .query_row(
+                "SELECT provider_ids_json FROM chat_sessions WHERE id = 's1'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(provider_ids_json, r#"["p1","p2"]"#);
+    }
 }
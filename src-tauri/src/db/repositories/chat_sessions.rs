@@ -1,6 +1,9 @@
 use crate::db::connection;
 use crate::db::error::{DbError, DbResult};
+use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 fn now_unix_ms() -> i64 {
@@ -21,10 +24,104 @@ pub struct ChatSessionRecord {
     pub system_prompt: String,
     /// Derived at read time: COUNT of user messages for this session.
     pub turns: i64,
+    /// Answer picked as the winner among the columns; exports prefer it.
+    pub canonical_message_id: Option<String>,
     pub created_at: i64,
     pub updated_at: i64,
 }
 
+/// Aggregate usage numbers for the stats dashboard.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChatStats {
+    pub session_count: i64,
+    pub message_count: i64,
+    pub total_user_messages: i64,
+    pub total_assistant_messages: i64,
+    pub first_session_at: Option<i64>,
+    pub last_activity_at: Option<i64>,
+    /// Provider id with the most assistant replies; ties go to the smaller id.
+    pub most_active_provider: Option<String>,
+}
+
+/// Space taken by one session, for the storage management view.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionStorage {
+    pub session_id: String,
+    pub title: String,
+    pub message_count: i64,
+    /// UTF-8 bytes of all message content.
+    pub total_content_bytes: i64,
+    /// Always 0: sessions store no icons yet; kept so the view can sum sources.
+    pub icon_bytes: i64,
+    /// Bytes of content held by the full-text index for this session, which
+    /// keeps its own copy of each message.
+    pub fts_estimate: i64,
+}
+
+/// Scratch sessions, kept in memory until their first message is saved so
+/// sessions opened and closed without chatting never reach the database.
+static SCRATCH_SESSIONS: Lazy<Mutex<HashMap<String, ChatSessionRecord>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn scratch_sessions() -> std::sync::MutexGuard<'static, HashMap<String, ChatSessionRecord>> {
+    SCRATCH_SESSIONS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Apply `change` to the scratch session `id`, if there is one, and return
+/// the updated record.
+fn update_scratch(
+    id: &str,
+    change: impl FnOnce(&mut ChatSessionRecord),
+) -> Option<ChatSessionRecord> {
+    let mut sessions = scratch_sessions();
+    let record = sessions.get_mut(id)?;
+    change(record);
+    record.updated_at = now_unix_ms();
+    Some(record.clone())
+}
+
+/// Title given to sessions created without one.
+pub(super) const DEFAULT_SESSION_TITLE: &str = "New Session";
+
+fn normalize_title(title: &str) -> String {
+    if title.trim().is_empty() {
+        DEFAULT_SESSION_TITLE.to_string()
+    } else {
+        title.trim().to_string()
+    }
+}
+
+/// Trim, drop blank and duplicate ids (keeping first occurrence order), and
+/// reject ids with no matching provider. An all-blank list becomes empty,
+/// which callers turn into the single placeholder column.
+fn sanitize_provider_ids(
+    conn: &rusqlite::Connection,
+    provider_ids: &[String],
+) -> DbResult<Vec<String>> {
+    let mut seen = HashSet::new();
+    let mut sanitized = Vec::new();
+    for provider_id in provider_ids {
+        let provider_id = provider_id.trim();
+        if provider_id.is_empty() || !seen.insert(provider_id) {
+            continue;
+        }
+        let exists: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM providers WHERE id = ?1)",
+            [provider_id],
+            |row| row.get(0),
+        )?;
+        if !exists {
+            return Err(DbError::Query(format!(
+                "Unknown provider id: {provider_id}"
+            )));
+        }
+        sanitized.push(provider_id.to_string());
+    }
+    Ok(sanitized)
+}
+
 pub struct ChatSessionsRepository;
 
 impl ChatSessionsRepository {
@@ -40,7 +137,8 @@ impl ChatSessionsRepository {
                     s.created_at,
                     s.updated_at,
                     (SELECT COUNT(*) FROM chat_messages m
-                     WHERE m.session_id = s.id AND m.role = 'user') AS turns
+                     WHERE m.session_id = s.id AND m.role = 'user') AS turns,
+                    s.canonical_message_id
                  FROM chat_sessions s
                  ORDER BY s.updated_at DESC",
             )?;
@@ -58,6 +156,7 @@ impl ChatSessionsRepository {
                     created_at: row.get(5)?,
                     updated_at: row.get(6)?,
                     turns: row.get(7)?,
+                    canonical_message_id: row.get(8)?,
                 })
             })?;
 
@@ -69,49 +168,285 @@ impl ChatSessionsRepository {
         })
     }
 
-    pub fn create(id: &str, title: &str, provider_ids: &[String]) -> DbResult<ChatSessionRecord> {
+    pub fn stats() -> DbResult<ChatStats> {
+        connection::with_connection(Self::stats_with_conn)
+    }
+
+    fn stats_with_conn(conn: &rusqlite::Connection) -> DbResult<ChatStats> {
+        let (session_count, first_session_at, last_session_update): (
+            i64,
+            Option<i64>,
+            Option<i64>,
+        ) = conn.query_row(
+            "SELECT COUNT(*), MIN(created_at), MAX(updated_at) FROM chat_sessions",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )?;
+
+        let (message_count, total_user_messages, total_assistant_messages, last_message_update): (
+            i64,
+            i64,
+            i64,
+            Option<i64>,
+        ) = conn.query_row(
+            "SELECT
+                COUNT(*),
+                COALESCE(SUM(CASE WHEN role = 'user' THEN 1 ELSE 0 END), 0),
+                COALESCE(SUM(CASE WHEN role = 'assistant' THEN 1 ELSE 0 END), 0),
+                MAX(updated_at)
+             FROM chat_messages",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )?;
+
+        let most_active_provider = match conn.query_row(
+            "SELECT provider_id FROM chat_messages
+             WHERE role = 'assistant' AND provider_id <> ''
+             GROUP BY provider_id
+             ORDER BY COUNT(*) DESC, provider_id ASC
+             LIMIT 1",
+            [],
+            |row| row.get::<_, String>(0),
+        ) {
+            Ok(provider_id) => Some(provider_id),
+            Err(rusqlite::Error::QueryReturnedNoRows) => None,
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(ChatStats {
+            session_count,
+            message_count,
+            total_user_messages,
+            total_assistant_messages,
+            first_session_at,
+            last_activity_at: last_session_update.max(last_message_update),
+            most_active_provider,
+        })
+    }
+
+    /// Storage used by `session_id`.
+    pub fn storage(session_id: &str) -> DbResult<SessionStorage> {
         connection::with_connection(|conn| {
-            let now = now_unix_ms();
-            let normalized_title = if title.trim().is_empty() {
-                "New Session".to_string()
-            } else {
-                title.trim().to_string()
-            };
+            Self::storage_with_conn(conn, Some(session_id), 1)?
+                .pop()
+                .ok_or_else(|| DbError::Query("Session not found".to_string()))
+        })
+    }
 
-            let provider_ids_json = serde_json::to_string(provider_ids)?;
+    /// The `limit` sessions with the most message content, heaviest first.
+    pub fn heaviest(limit: usize) -> DbResult<Vec<SessionStorage>> {
+        connection::with_connection(|conn| Self::storage_with_conn(conn, None, limit))
+    }
 
+    fn storage_with_conn(
+        conn: &rusqlite::Connection,
+        session_id: Option<&str>,
+        limit: usize,
+    ) -> DbResult<Vec<SessionStorage>> {
+        let mut stmt = conn.prepare(
+            "WITH m AS (
+                SELECT session_id, COUNT(*) AS n, SUM(LENGTH(CAST(content AS BLOB))) AS bytes
+                FROM chat_messages GROUP BY session_id
+             ), f AS (
+                SELECT session_id, SUM(LENGTH(CAST(content AS BLOB))) AS bytes
+                FROM chat_messages_fts GROUP BY session_id
+             )
+             SELECT s.id, s.title, COALESCE(m.n, 0), COALESCE(m.bytes, 0), COALESCE(f.bytes, 0)
+             FROM chat_sessions s
+             LEFT JOIN m ON m.session_id = s.id
+             LEFT JOIN f ON f.session_id = s.id
+             WHERE ?1 IS NULL OR s.id = ?1
+             ORDER BY COALESCE(m.bytes, 0) DESC, s.id ASC
+             LIMIT ?2",
+        )?;
+        let rows = stmt.query_map(rusqlite::params![session_id, limit as i64], |row| {
+            Ok(SessionStorage {
+                session_id: row.get(0)?,
+                title: row.get(1)?,
+                message_count: row.get(2)?,
+                total_content_bytes: row.get(3)?,
+                icon_bytes: 0,
+                fts_estimate: row.get(4)?,
+            })
+        })?;
+        let mut values = Vec::new();
+        for row in rows {
+            values.push(row?);
+        }
+        Ok(values)
+    }
+
+    pub fn create(id: &str, title: &str, provider_ids: &[String]) -> DbResult<ChatSessionRecord> {
+        connection::with_connection(|conn| Self::create_with_conn(conn, id, title, provider_ids))
+    }
+
+    pub(super) fn create_with_conn(
+        conn: &rusqlite::Connection,
+        id: &str,
+        title: &str,
+        provider_ids: &[String],
+    ) -> DbResult<ChatSessionRecord> {
+        let now = now_unix_ms();
+        let normalized_title = normalize_title(title);
+
+        let provider_ids = &sanitize_provider_ids(conn, provider_ids)?;
+        let provider_ids_json = serde_json::to_string(provider_ids)?;
+
+        conn.execute(
+            "INSERT INTO chat_sessions
+                (id, title, provider_ids_json, prompt, system_prompt, created_at, updated_at)
+             VALUES (?1, ?2, ?3, '', '', ?4, ?4)",
+            rusqlite::params![id, normalized_title, provider_ids_json, now],
+        )?;
+
+        let column_provider_ids = if provider_ids.is_empty() {
+            vec![String::new()]
+        } else {
+            provider_ids.to_vec()
+        };
+        for (idx, provider_id) in column_provider_ids.iter().enumerate() {
+            let column_id = format!("{id}:c{idx}");
             conn.execute(
-                "INSERT INTO chat_sessions
-                    (id, title, provider_ids_json, prompt, system_prompt, created_at, updated_at)
-                 VALUES (?1, ?2, ?3, '', '', ?4, ?4)",
-                rusqlite::params![id, normalized_title, provider_ids_json, now],
+                "INSERT OR REPLACE INTO chat_session_columns
+                 (id, session_id, position, provider_id, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?5)",
+                rusqlite::params![column_id, id, idx as i64, provider_id, now],
             )?;
+        }
 
-            let column_provider_ids = if provider_ids.is_empty() {
-                vec![String::new()]
-            } else {
-                provider_ids.to_vec()
-            };
-            for (idx, provider_id) in column_provider_ids.iter().enumerate() {
-                let column_id = format!("{id}:c{idx}");
-                conn.execute(
-                    "INSERT OR REPLACE INTO chat_session_columns
-                     (id, session_id, position, provider_id, created_at, updated_at)
-                     VALUES (?1, ?2, ?3, ?4, ?5, ?5)",
-                    rusqlite::params![column_id, id, idx as i64, provider_id, now],
+        Ok(ChatSessionRecord {
+            id: id.to_string(),
+            title: normalized_title,
+            provider_ids: provider_ids.to_vec(),
+            prompt: String::new(),
+            system_prompt: String::new(),
+            turns: 0,
+            canonical_message_id: None,
+            created_at: now,
+            updated_at: now,
+        })
+    }
+
+    /// Like `create`, but the session only lives in memory until
+    /// `persist_scratch`/the first message materializes it.
+    pub fn create_scratch(
+        id: &str,
+        title: &str,
+        provider_ids: &[String],
+    ) -> DbResult<ChatSessionRecord> {
+        connection::with_connection(|conn| {
+            Self::create_scratch_with_conn(conn, id, title, provider_ids)
+        })
+    }
+
+    fn create_scratch_with_conn(
+        conn: &rusqlite::Connection,
+        id: &str,
+        title: &str,
+        provider_ids: &[String],
+    ) -> DbResult<ChatSessionRecord> {
+        let exists: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM chat_sessions WHERE id = ?1)",
+            [id],
+            |row| row.get(0),
+        )?;
+        if exists {
+            return Err(DbError::Query("Session already exists".to_string()));
+        }
+
+        let now = now_unix_ms();
+        let record = ChatSessionRecord {
+            id: id.to_string(),
+            title: normalize_title(title),
+            provider_ids: sanitize_provider_ids(conn, provider_ids)?,
+            prompt: String::new(),
+            system_prompt: String::new(),
+            turns: 0,
+            canonical_message_id: None,
+            created_at: now,
+            updated_at: now,
+        };
+        scratch_sessions().insert(id.to_string(), record.clone());
+        Ok(record)
+    }
+
+    /// Write scratch session `id` to the database. Sessions that are already
+    /// persisted are returned as they are.
+    pub fn persist_scratch(id: &str) -> DbResult<ChatSessionRecord> {
+        connection::with_connection(|conn| {
+            if !Self::persist_scratch_with_conn(conn, id)? {
+                let exists: bool = conn.query_row(
+                    "SELECT EXISTS(SELECT 1 FROM chat_sessions WHERE id = ?1)",
+                    [id],
+                    |row| row.get(0),
                 )?;
+                if !exists {
+                    return Err(DbError::Query("Session not found".to_string()));
+                }
             }
+            Self::get_with_conn(conn, id)
+        })
+    }
 
-            Ok(ChatSessionRecord {
-                id: id.to_string(),
-                title: normalized_title,
-                provider_ids: provider_ids.to_vec(),
-                prompt: String::new(),
-                system_prompt: String::new(),
-                turns: 0,
-                created_at: now,
-                updated_at: now,
-            })
+    /// Materialize `id` if it is a scratch session; `Ok(false)` when it isn't one.
+    pub(super) fn persist_scratch_with_conn(
+        conn: &rusqlite::Connection,
+        id: &str,
+    ) -> DbResult<bool> {
+        let Some(record) = scratch_sessions().remove(id) else {
+            return Ok(false);
+        };
+        let persisted = Self::create_with_conn(conn, id, &record.title, &record.provider_ids)
+            .and_then(|_| {
+                conn.execute(
+                    "UPDATE chat_sessions SET prompt = ?1, system_prompt = ?2 WHERE id = ?3",
+                    rusqlite::params![record.prompt, record.system_prompt, id],
+                )?;
+                Ok(())
+            });
+        if let Err(e) = persisted {
+            scratch_sessions().insert(id.to_string(), record);
+            return Err(e);
+        }
+        Ok(true)
+    }
+
+    fn get_with_conn(conn: &rusqlite::Connection, id: &str) -> DbResult<ChatSessionRecord> {
+        conn.query_row(
+            "SELECT
+                s.id,
+                s.title,
+                s.provider_ids_json,
+                s.prompt,
+                s.system_prompt,
+                s.created_at,
+                s.updated_at,
+                (SELECT COUNT(*) FROM chat_messages m
+                 WHERE m.session_id = s.id AND m.role = 'user') AS turns,
+                s.canonical_message_id
+             FROM chat_sessions s
+             WHERE s.id = ?1",
+            [id],
+            |row| {
+                let provider_ids_json: String = row.get(2)?;
+                let provider_ids =
+                    serde_json::from_str::<Vec<String>>(&provider_ids_json).unwrap_or_default();
+                Ok(ChatSessionRecord {
+                    id: row.get(0)?,
+                    title: row.get(1)?,
+                    provider_ids,
+                    prompt: row.get(3)?,
+                    system_prompt: row.get(4)?,
+                    created_at: row.get(5)?,
+                    updated_at: row.get(6)?,
+                    turns: row.get(7)?,
+                    canonical_message_id: row.get(8)?,
+                })
+            },
+        )
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => DbError::Query("Session not found".to_string()),
+            _ => e.into(),
         })
     }
 
@@ -121,6 +456,11 @@ impl ChatSessionsRepository {
             if normalized_title.is_empty() {
                 return Err(DbError::Query("Session title cannot be empty".to_string()));
             }
+            if let Some(record) = update_scratch(id, |record| {
+                record.title = normalized_title.to_string();
+            }) {
+                return Ok(record);
+            }
 
             let now = now_unix_ms();
             let rows = conn.execute(
@@ -142,7 +482,8 @@ impl ChatSessionsRepository {
                     s.created_at,
                     s.updated_at,
                     (SELECT COUNT(*) FROM chat_messages m
-                     WHERE m.session_id = s.id AND m.role = 'user') AS turns
+                     WHERE m.session_id = s.id AND m.role = 'user') AS turns,
+                    s.canonical_message_id
                  FROM chat_sessions s
                  WHERE s.id = ?1",
                 [id],
@@ -159,6 +500,7 @@ impl ChatSessionsRepository {
                         created_at: row.get(5)?,
                         updated_at: row.get(6)?,
                         turns: row.get(7)?,
+                        canonical_message_id: row.get(8)?,
                     })
                 },
             )
@@ -179,85 +521,186 @@ impl ChatSessionsRepository {
         prompt: &str,
     ) -> DbResult<ChatSessionRecord> {
         connection::with_connection(|conn| {
-            let now = now_unix_ms();
-            let provider_ids_json = serde_json::to_string(provider_ids)?;
+            Self::save_state_with_conn(conn, id, provider_ids, prompt)
+        })
+    }
 
-            let rows = conn.execute(
-                "UPDATE chat_sessions
-                 SET provider_ids_json = ?1, prompt = ?2, updated_at = ?3
-                 WHERE id = ?4",
-                rusqlite::params![provider_ids_json, prompt, now, id],
-            )?;
+    fn save_state_with_conn(
+        conn: &rusqlite::Connection,
+        id: &str,
+        provider_ids: &[String],
+        prompt: &str,
+    ) -> DbResult<ChatSessionRecord> {
+        let now = now_unix_ms();
+        let provider_ids = &sanitize_provider_ids(conn, provider_ids)?;
+        if let Some(record) = update_scratch(id, |record| {
+            record.provider_ids = provider_ids.to_vec();
+            record.prompt = prompt.to_string();
+        }) {
+            return Ok(record);
+        }
+        let provider_ids_json = serde_json::to_string(provider_ids)?;
 
-            if rows == 0 {
-                return Err(DbError::Query("Session not found".to_string()));
-            }
+        let rows = conn.execute(
+            "UPDATE chat_sessions
+             SET provider_ids_json = ?1, prompt = ?2, updated_at = ?3
+             WHERE id = ?4",
+            rusqlite::params![provider_ids_json, prompt, now, id],
+        )?;
 
-            let column_provider_ids = if provider_ids.is_empty() {
-                vec![String::new()]
-            } else {
-                provider_ids.to_vec()
-            };
-            for (idx, provider_id) in column_provider_ids.iter().enumerate() {
-                let column_id = format!("{id}:c{idx}");
-                conn.execute(
-                    "INSERT OR REPLACE INTO chat_session_columns
-                     (id, session_id, position, provider_id, created_at, updated_at)
-                     VALUES (
-                        ?1, ?2, ?3, ?4,
-                        COALESCE((SELECT created_at FROM chat_session_columns WHERE id = ?1), ?5),
-                        ?5
-                     )",
-                    rusqlite::params![column_id, id, idx as i64, provider_id, now],
-                )?;
-            }
+        if rows == 0 {
+            return Err(DbError::Query("Session not found".to_string()));
+        }
+
+        let column_provider_ids = if provider_ids.is_empty() {
+            vec![String::new()]
+        } else {
+            provider_ids.to_vec()
+        };
+        for (idx, provider_id) in column_provider_ids.iter().enumerate() {
+            let column_id = format!("{id}:c{idx}");
             conn.execute(
-                "DELETE FROM chat_session_columns
-                 WHERE session_id = ?1 AND position >= ?2",
-                rusqlite::params![id, column_provider_ids.len() as i64],
+                "INSERT OR REPLACE INTO chat_session_columns
+                 (id, session_id, position, provider_id, created_at, updated_at)
+                 VALUES (
+                    ?1, ?2, ?3, ?4,
+                    COALESCE((SELECT created_at FROM chat_session_columns WHERE id = ?1), ?5),
+                    ?5
+                 )",
+                rusqlite::params![column_id, id, idx as i64, provider_id, now],
             )?;
+        }
+        conn.execute(
+            "DELETE FROM chat_session_columns
+             WHERE session_id = ?1 AND position >= ?2",
+            rusqlite::params![id, column_provider_ids.len() as i64],
+        )?;
 
-            conn.query_row(
-                "SELECT
-                    s.id,
-                    s.title,
-                    s.provider_ids_json,
-                    s.prompt,
-                    s.system_prompt,
-                    s.created_at,
-                    s.updated_at,
-                    (SELECT COUNT(*) FROM chat_messages m
-                     WHERE m.session_id = s.id AND m.role = 'user') AS turns
-                 FROM chat_sessions s
-                 WHERE s.id = ?1",
-                [id],
-                |row| {
-                    let provider_ids_json: String = row.get(2)?;
-                    let provider_ids =
-                        serde_json::from_str::<Vec<String>>(&provider_ids_json).unwrap_or_default();
-                    Ok(ChatSessionRecord {
-                        id: row.get(0)?,
-                        title: row.get(1)?,
-                        provider_ids,
-                        prompt: row.get(3)?,
-                        system_prompt: row.get(4)?,
-                        created_at: row.get(5)?,
-                        updated_at: row.get(6)?,
-                        turns: row.get(7)?,
-                    })
-                },
-            )
-            .map_err(|e| match e {
-                rusqlite::Error::QueryReturnedNoRows => {
-                    DbError::Query("Session not found".to_string())
-                }
-                _ => e.into(),
-            })
+        conn.query_row(
+            "SELECT
+                s.id,
+                s.title,
+                s.provider_ids_json,
+                s.prompt,
+                s.system_prompt,
+                s.created_at,
+                s.updated_at,
+                (SELECT COUNT(*) FROM chat_messages m
+                 WHERE m.session_id = s.id AND m.role = 'user') AS turns,
+                s.canonical_message_id
+             FROM chat_sessions s
+             WHERE s.id = ?1",
+            [id],
+            |row| {
+                let provider_ids_json: String = row.get(2)?;
+                let provider_ids =
+                    serde_json::from_str::<Vec<String>>(&provider_ids_json).unwrap_or_default();
+                Ok(ChatSessionRecord {
+                    id: row.get(0)?,
+                    title: row.get(1)?,
+                    provider_ids,
+                    prompt: row.get(3)?,
+                    system_prompt: row.get(4)?,
+                    created_at: row.get(5)?,
+                    updated_at: row.get(6)?,
+                    turns: row.get(7)?,
+                    canonical_message_id: row.get(8)?,
+                })
+            },
+        )
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => DbError::Query("Session not found".to_string()),
+            _ => e.into(),
         })
     }
 
+    /// Point every column of `session_id` that uses `old_provider_id`, and the
+    /// messages in those columns, at `new_provider_id`. Messages keep their
+    /// `provider_name` snapshot of who actually answered. Returns how many
+    /// columns were switched.
+    pub fn replace_provider(
+        session_id: &str,
+        old_provider_id: &str,
+        new_provider_id: &str,
+    ) -> DbResult<usize> {
+        connection::with_connection(|conn| {
+            Self::replace_provider_with_conn(conn, session_id, old_provider_id, new_provider_id)
+        })
+    }
+
+    fn replace_provider_with_conn(
+        conn: &rusqlite::Connection,
+        session_id: &str,
+        old_provider_id: &str,
+        new_provider_id: &str,
+    ) -> DbResult<usize> {
+        let new_provider_id = new_provider_id.trim();
+        if new_provider_id.is_empty() {
+            return Err(DbError::Query("New provider id is empty".to_string()));
+        }
+        let new_provider_id =
+            sanitize_provider_ids(conn, &[new_provider_id.to_string()])?.remove(0);
+
+        let session_exists: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM chat_sessions WHERE id = ?1)",
+            [session_id],
+            |row| row.get(0),
+        )?;
+        if !session_exists {
+            return Err(DbError::Query("Session not found".to_string()));
+        }
+
+        let now = now_unix_ms();
+        let tx = conn.unchecked_transaction()?;
+        tx.execute(
+            "UPDATE chat_messages
+             SET provider_id = ?1, updated_at = ?2
+             WHERE session_id = ?3
+               AND column_id IN (
+                 SELECT id FROM chat_session_columns
+                 WHERE session_id = ?3 AND provider_id = ?4
+               )",
+            rusqlite::params![new_provider_id, now, session_id, old_provider_id],
+        )?;
+        let columns = tx.execute(
+            "UPDATE chat_session_columns
+             SET provider_id = ?1, updated_at = ?2
+             WHERE session_id = ?3 AND provider_id = ?4",
+            rusqlite::params![new_provider_id, now, session_id, old_provider_id],
+        )?;
+
+        if columns > 0 {
+            // Keep provider_ids_json in sync with current ordered columns.
+            let ordered: Vec<String> = {
+                let mut stmt = tx.prepare(
+                    "SELECT provider_id FROM chat_session_columns
+                     WHERE session_id = ?1
+                     ORDER BY position ASC",
+                )?;
+                let rows = stmt.query_map([session_id], |row| row.get::<_, String>(0))?;
+                let mut values = Vec::new();
+                for row in rows {
+                    values.push(row?);
+                }
+                values
+            };
+            tx.execute(
+                "UPDATE chat_sessions SET provider_ids_json = ?1, updated_at = ?2 WHERE id = ?3",
+                rusqlite::params![serde_json::to_string(&ordered)?, now, session_id],
+            )?;
+        }
+
+        tx.commit()?;
+        Ok(columns)
+    }
+
     pub fn set_system_prompt(id: &str, system_prompt: &str) -> DbResult<ChatSessionRecord> {
         connection::with_connection(|conn| {
+            if let Some(record) = update_scratch(id, |record| {
+                record.system_prompt = system_prompt.to_string();
+            }) {
+                return Ok(record);
+            }
             let now = now_unix_ms();
             let rows = conn.execute(
                 "UPDATE chat_sessions SET system_prompt = ?1, updated_at = ?2 WHERE id = ?3",
@@ -278,7 +721,8 @@ impl ChatSessionsRepository {
                     s.created_at,
                     s.updated_at,
                     (SELECT COUNT(*) FROM chat_messages m
-                     WHERE m.session_id = s.id AND m.role = 'user') AS turns
+                     WHERE m.session_id = s.id AND m.role = 'user') AS turns,
+                    s.canonical_message_id
                  FROM chat_sessions s
                  WHERE s.id = ?1",
                 [id],
@@ -295,6 +739,7 @@ impl ChatSessionsRepository {
                         created_at: row.get(5)?,
                         updated_at: row.get(6)?,
                         turns: row.get(7)?,
+                        canonical_message_id: row.get(8)?,
                     })
                 },
             )
@@ -307,7 +752,129 @@ impl ChatSessionsRepository {
         })
     }
 
+    /// Mark assistant reply `message_id` as the session's canonical answer,
+    /// or clear the choice with `None`.
+    pub fn set_canonical_answer(
+        session_id: &str,
+        message_id: Option<&str>,
+    ) -> DbResult<ChatSessionRecord> {
+        connection::with_connection(|conn| {
+            Self::set_canonical_answer_with_conn(conn, session_id, message_id)
+        })
+    }
+
+    fn set_canonical_answer_with_conn(
+        conn: &rusqlite::Connection,
+        session_id: &str,
+        message_id: Option<&str>,
+    ) -> DbResult<ChatSessionRecord> {
+        if let Some(message_id) = message_id {
+            let role: String = match conn.query_row(
+                "SELECT role FROM chat_messages WHERE id = ?1 AND session_id = ?2",
+                [message_id, session_id],
+                |row| row.get(0),
+            ) {
+                Ok(role) => role,
+                Err(rusqlite::Error::QueryReturnedNoRows) => {
+                    return Err(DbError::Query("Message not found in session".to_string()))
+                }
+                Err(e) => return Err(e.into()),
+            };
+            if role != "assistant" {
+                return Err(DbError::Query(
+                    "Only assistant replies can be canonical".to_string(),
+                ));
+            }
+        }
+
+        let rows = conn.execute(
+            "UPDATE chat_sessions SET canonical_message_id = ?1, updated_at = ?2 WHERE id = ?3",
+            rusqlite::params![message_id, now_unix_ms(), session_id],
+        )?;
+        if rows == 0 {
+            return Err(DbError::Query("Session not found".to_string()));
+        }
+        Self::get_with_conn(conn, session_id)
+    }
+
+    /// Add (`add`) or remove `tag` on every session in `session_ids` in one
+    /// transaction, returning how many sessions changed. Fails without
+    /// changing anything if any id is unknown.
+    pub fn tag_sessions(session_ids: &[String], tag: &str, add: bool) -> DbResult<usize> {
+        connection::with_connection(|conn| {
+            Self::tag_sessions_with_conn(conn, session_ids, tag, add)
+        })
+    }
+
+    fn tag_sessions_with_conn(
+        conn: &rusqlite::Connection,
+        session_ids: &[String],
+        tag: &str,
+        add: bool,
+    ) -> DbResult<usize> {
+        let tag = tag.trim();
+        if tag.is_empty() {
+            return Err(DbError::Query("Tag must not be empty".to_string()));
+        }
+
+        let tx = conn.unchecked_transaction()?;
+        let now = now_unix_ms();
+        let mut affected = 0;
+        let mut seen = HashSet::new();
+        for session_id in session_ids {
+            if !seen.insert(session_id.as_str()) {
+                continue;
+            }
+            let exists: bool = tx.query_row(
+                "SELECT EXISTS(SELECT 1 FROM chat_sessions WHERE id = ?1)",
+                [session_id],
+                |row| row.get(0),
+            )?;
+            if !exists {
+                return Err(DbError::Query(format!("Session not found: {session_id}")));
+            }
+            affected += if add {
+                tx.execute(
+                    "INSERT OR IGNORE INTO chat_session_tags (session_id, tag, created_at)
+                     VALUES (?1, ?2, ?3)",
+                    rusqlite::params![session_id, tag, now],
+                )?
+            } else {
+                tx.execute(
+                    "DELETE FROM chat_session_tags WHERE session_id = ?1 AND tag = ?2",
+                    [session_id.as_str(), tag],
+                )?
+            };
+        }
+        tx.commit()?;
+        Ok(affected)
+    }
+
+    /// Tags of every tagged session, sorted by name.
+    pub fn tags_by_session() -> DbResult<HashMap<String, Vec<String>>> {
+        connection::with_connection(Self::tags_by_session_with_conn)
+    }
+
+    fn tags_by_session_with_conn(
+        conn: &rusqlite::Connection,
+    ) -> DbResult<HashMap<String, Vec<String>>> {
+        let mut stmt =
+            conn.prepare("SELECT session_id, tag FROM chat_session_tags ORDER BY session_id, tag")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+        let mut tags: HashMap<String, Vec<String>> = HashMap::new();
+        for row in rows {
+            let (session_id, tag) = row?;
+            tags.entry(session_id).or_default().push(tag);
+        }
+        Ok(tags)
+    }
+
     pub fn delete(id: &str) -> DbResult<()> {
+        if scratch_sessions().remove(id).is_some() {
+            return Ok(());
+        }
         connection::with_connection(|conn| {
             // Explicitly delete messages first as a safety net alongside FK cascade.
             conn.execute("DELETE FROM chat_messages WHERE session_id = ?1", [id])?;
@@ -316,3 +883,348 @@ impl ChatSessionsRepository {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seed_providers(conn: &rusqlite::Connection) {
+        conn.execute_batch(
+            "INSERT INTO providers (id, name, provider_type, model, created_at, updated_at)
+             VALUES ('p1', 'P1', 'openai', 'm', 0, 0), ('p2', 'P2', 'openai', 'm', 0, 0);",
+        )
+        .unwrap();
+    }
+
+    fn column_providers(conn: &rusqlite::Connection, session_id: &str) -> Vec<String> {
+        let mut stmt = conn
+            .prepare(
+                "SELECT provider_id FROM chat_session_columns
+                 WHERE session_id = ?1 ORDER BY position ASC",
+            )
+            .unwrap();
+        stmt.query_map([session_id], |row| row.get(0))
+            .unwrap()
+            .map(Result::unwrap)
+            .collect()
+    }
+
+    #[test]
+    fn test_provider_ids_are_deduped_in_order() {
+        let conn = connection::open_test_connection();
+        seed_providers(&conn);
+        let ids = ["p2", "p1", " p2 ", "p1"].map(String::from);
+
+        let session = ChatSessionsRepository::create_with_conn(&conn, "s1", "S", &ids).unwrap();
+        assert_eq!(session.provider_ids, vec!["p2", "p1"]);
+        assert_eq!(column_providers(&conn, "s1"), vec!["p2", "p1"]);
+
+        let saved =
+            ChatSessionsRepository::save_state_with_conn(&conn, "s1", &ids[2..], "").unwrap();
+        assert_eq!(saved.provider_ids, vec!["p2", "p1"]);
+
+        let unknown = ["p1", "ghost"].map(String::from);
+        assert!(ChatSessionsRepository::save_state_with_conn(&conn, "s1", &unknown, "").is_err());
+        assert_eq!(column_providers(&conn, "s1"), vec!["p2", "p1"]);
+    }
+
+    #[test]
+    fn test_session_storage_sums_content_bytes() {
+        let conn = connection::open_test_connection();
+        conn.execute_batch(
+            "INSERT INTO chat_sessions (id, title, provider_ids_json, created_at, updated_at)
+             VALUES ('s1', 'Small', '[]', 0, 0), ('s2', 'Large', '[]', 0, 0),
+                    ('s3', 'Empty', '[]', 0, 0);
+             INSERT INTO chat_messages
+             (id, session_id, column_id, provider_id, role, content, status, created_at, updated_at)
+             VALUES ('m1', 's1', 's1:c0', 'p1', 'user', 'héllo', 'done', 1, 1),
+                    ('m2', 's1', 's1:c0', 'p1', 'assistant', 'abc', 'done', 2, 2),
+                    ('m3', 's2', 's2:c0', 'p1', 'user', 'a much longer message', 'done', 1, 1);",
+        )
+        .unwrap();
+
+        let small = ChatSessionsRepository::storage_with_conn(&conn, Some("s1"), 1).unwrap();
+        assert_eq!(
+            small,
+            vec![SessionStorage {
+                session_id: "s1".to_string(),
+                title: "Small".to_string(),
+                message_count: 2,
+                // "é" is two bytes in UTF-8.
+                total_content_bytes: 9,
+                icon_bytes: 0,
+                fts_estimate: 9,
+            }]
+        );
+
+        let heaviest = ChatSessionsRepository::storage_with_conn(&conn, None, 2).unwrap();
+        let ids: Vec<&str> = heaviest.iter().map(|s| s.session_id.as_str()).collect();
+        assert_eq!(ids, vec!["s2", "s1"]);
+        assert!(
+            ChatSessionsRepository::storage_with_conn(&conn, Some("ghost"), 1)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_tag_sessions_bulk_add_and_remove() {
+        let conn = connection::open_test_connection();
+        for id in ["s1", "s2", "s3"] {
+            ChatSessionsRepository::create_with_conn(&conn, id, "S", &[]).unwrap();
+        }
+        let ids = ["s1", "s2", "s3"].map(String::from);
+
+        let added =
+            ChatSessionsRepository::tag_sessions_with_conn(&conn, &ids[..2], " work ", true)
+                .unwrap();
+        assert_eq!(added, 2);
+        // Already-tagged sessions don't count again.
+        assert_eq!(
+            ChatSessionsRepository::tag_sessions_with_conn(&conn, &ids, "work", true).unwrap(),
+            1
+        );
+        let tags = ChatSessionsRepository::tags_by_session_with_conn(&conn).unwrap();
+        assert_eq!(tags.len(), 3);
+        assert_eq!(tags["s1"], vec!["work"]);
+
+        let removed =
+            ChatSessionsRepository::tag_sessions_with_conn(&conn, &ids[1..], "work", false)
+                .unwrap();
+        assert_eq!(removed, 2);
+        let tags = ChatSessionsRepository::tags_by_session_with_conn(&conn).unwrap();
+        assert_eq!(tags.keys().collect::<Vec<_>>(), vec!["s1"]);
+
+        // An unknown id rolls back the whole batch.
+        let with_unknown = ["s2", "ghost"].map(String::from);
+        assert!(
+            ChatSessionsRepository::tag_sessions_with_conn(&conn, &with_unknown, "x", true)
+                .is_err()
+        );
+        assert!(!ChatSessionsRepository::tags_by_session_with_conn(&conn)
+            .unwrap()
+            .contains_key("s2"));
+    }
+
+    #[test]
+    fn test_set_canonical_answer_validates_message() {
+        let conn = connection::open_test_connection();
+        seed_providers(&conn);
+        let ids = ["p1", "p2"].map(String::from);
+        ChatSessionsRepository::create_with_conn(&conn, "s1", "S", &ids).unwrap();
+        ChatSessionsRepository::create_with_conn(&conn, "s2", "Other", &ids).unwrap();
+        conn.execute_batch(
+            "INSERT INTO chat_messages
+             (id, session_id, column_id, provider_id, role, content, status, created_at, updated_at)
+             VALUES ('u1', 's1', 's1:c0', 'p1', 'user', 'q', 'done', 1, 1),
+                    ('a1', 's1', 's1:c0', 'p1', 'assistant', 'x', 'done', 2, 2),
+                    ('a2', 's1', 's1:c1', 'p2', 'assistant', 'y', 'done', 2, 2),
+                    ('o1', 's2', 's2:c0', 'p1', 'assistant', 'z', 'done', 2, 2);",
+        )
+        .unwrap();
+
+        let session =
+            ChatSessionsRepository::set_canonical_answer_with_conn(&conn, "s1", Some("a2"))
+                .unwrap();
+        assert_eq!(session.canonical_message_id.as_deref(), Some("a2"));
+
+        // Messages from another session, user prompts and unknown ids are refused.
+        for bad in ["o1", "u1", "ghost"] {
+            assert!(
+                ChatSessionsRepository::set_canonical_answer_with_conn(&conn, "s1", Some(bad))
+                    .is_err()
+            );
+        }
+        assert_eq!(
+            ChatSessionsRepository::get_with_conn(&conn, "s1")
+                .unwrap()
+                .canonical_message_id
+                .as_deref(),
+            Some("a2")
+        );
+
+        // Deleting the canonical message clears the choice.
+        conn.execute("DELETE FROM chat_messages WHERE id = 'a2'", [])
+            .unwrap();
+        assert_eq!(
+            ChatSessionsRepository::get_with_conn(&conn, "s1")
+                .unwrap()
+                .canonical_message_id,
+            None
+        );
+
+        ChatSessionsRepository::set_canonical_answer_with_conn(&conn, "s1", Some("a1")).unwrap();
+        let cleared =
+            ChatSessionsRepository::set_canonical_answer_with_conn(&conn, "s1", None).unwrap();
+        assert_eq!(cleared.canonical_message_id, None);
+    }
+
+    #[test]
+    fn test_blank_provider_ids_are_dropped() {
+        let conn = connection::open_test_connection();
+        seed_providers(&conn);
+        let ids = ["", "p1", "   ", "p2"].map(String::from);
+
+        let session = ChatSessionsRepository::create_with_conn(&conn, "s1", "S", &ids).unwrap();
+        assert_eq!(session.provider_ids, vec!["p1", "p2"]);
+        assert_eq!(column_providers(&conn, "s1"), vec!["p1", "p2"]);
+    }
+
+    #[test]
+    fn test_empty_provider_ids_keep_placeholder_column() {
+        let conn = connection::open_test_connection();
+        seed_providers(&conn);
+
+        let session = ChatSessionsRepository::create_with_conn(&conn, "s1", "S", &[]).unwrap();
+        assert!(session.provider_ids.is_empty());
+        assert_eq!(column_providers(&conn, "s1"), vec![""]);
+
+        let blank = [" ", ""].map(String::from);
+        let saved = ChatSessionsRepository::save_state_with_conn(&conn, "s1", &blank, "").unwrap();
+        assert!(saved.provider_ids.is_empty());
+        assert_eq!(column_providers(&conn, "s1"), vec![""]);
+    }
+
+    #[test]
+    fn test_stats_aggregates_seeded_data() {
+        let conn = connection::open_test_connection();
+
+        let empty = ChatSessionsRepository::stats_with_conn(&conn).unwrap();
+        assert_eq!(empty.session_count, 0);
+        assert_eq!(empty.first_session_at, None);
+        assert_eq!(empty.most_active_provider, None);
+
+        for (id, created_at, updated_at) in [("s1", 100, 150), ("s2", 200, 900)] {
+            conn.execute(
+                "INSERT INTO chat_sessions (id, title, provider_ids_json, prompt, created_at, updated_at)
+                 VALUES (?1, ?1, '[]', '', ?2, ?3)",
+                rusqlite::params![id, created_at, updated_at],
+            )
+            .unwrap();
+        }
+        for (id, session_id, provider_id, role, updated_at) in [
+            ("m1", "s1", "p1", "user", 110),
+            ("m2", "s1", "p1", "assistant", 120),
+            ("m3", "s2", "p2", "user", 210),
+            ("m4", "s2", "p2", "assistant", 220),
+            ("m5", "s2", "p2", "assistant", 1_000),
+        ] {
+            conn.execute(
+                "INSERT INTO chat_messages
+                 (id, session_id, column_id, provider_id, role, content, status, created_at, updated_at)
+                 VALUES (?1, ?2, ?2 || ':c0', ?3, ?4, 'x', 'done', ?5, ?5)",
+                rusqlite::params![id, session_id, provider_id, role, updated_at],
+            )
+            .unwrap();
+        }
+
+        let stats = ChatSessionsRepository::stats_with_conn(&conn).unwrap();
+        assert_eq!(
+            stats,
+            ChatStats {
+                session_count: 2,
+                message_count: 5,
+                total_user_messages: 2,
+                total_assistant_messages: 3,
+                first_session_at: Some(100),
+                last_activity_at: Some(1_000),
+                most_active_provider: Some("p2".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn test_replace_provider_switches_only_matching_references() {
+        let conn = connection::open_test_connection();
+        seed_providers(&conn);
+        conn.execute(
+            "INSERT INTO providers (id, name, provider_type, model, created_at, updated_at)
+             VALUES ('p3', 'P3', 'openai', 'm', 0, 0)",
+            [],
+        )
+        .unwrap();
+        let ids = ["p1", "p2"].map(String::from);
+        ChatSessionsRepository::create_with_conn(&conn, "s1", "S", &ids).unwrap();
+        ChatSessionsRepository::create_with_conn(&conn, "s2", "Other", &ids).unwrap();
+        for (id, session_id, column_id, provider_id) in [
+            ("m1", "s1", "s1:c0", "p1"),
+            ("m2", "s1", "s1:c1", "p2"),
+            ("m3", "s2", "s2:c0", "p1"),
+        ] {
+            conn.execute(
+                "INSERT INTO chat_messages
+                 (id, session_id, column_id, provider_id, role, content, status, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, 'assistant', 'hi', 'done', 0, 0)",
+                rusqlite::params![id, session_id, column_id, provider_id],
+            )
+            .unwrap();
+        }
+
+        let switched =
+            ChatSessionsRepository::replace_provider_with_conn(&conn, "s1", "p1", "p3").unwrap();
+        assert_eq!(switched, 1);
+
+        let message_provider = |id: &str| -> String {
+            conn.query_row(
+                "SELECT provider_id FROM chat_messages WHERE id = ?1",
+                [id],
+                |row| row.get(0),
+            )
+            .unwrap()
+        };
+        let provider_ids_json = |id: &str| -> String {
+            conn.query_row(
+                "SELECT provider_ids_json FROM chat_sessions WHERE id = ?1",
+                [id],
+                |row| row.get(0),
+            )
+            .unwrap()
+        };
+        assert_eq!(column_providers(&conn, "s1"), vec!["p3", "p2"]);
+        assert_eq!(provider_ids_json("s1"), r#"["p3","p2"]"#);
+        assert_eq!(message_provider("m1"), "p3");
+        assert_eq!(message_provider("m2"), "p2");
+        // Other sessions using the old provider are untouched.
+        assert_eq!(column_providers(&conn, "s2"), vec!["p1", "p2"]);
+        assert_eq!(provider_ids_json("s2"), r#"["p1","p2"]"#);
+        assert_eq!(message_provider("m3"), "p1");
+
+        assert!(
+            ChatSessionsRepository::replace_provider_with_conn(&conn, "s1", "p2", "ghost").is_err()
+        );
+        assert!(
+            ChatSessionsRepository::replace_provider_with_conn(&conn, "nope", "p2", "p3").is_err()
+        );
+        assert_eq!(column_providers(&conn, "s1"), vec!["p3", "p2"]);
+    }
+
+    #[test]
+    fn test_abandoned_scratch_session_leaves_no_row() {
+        let conn = connection::open_test_connection();
+        seed_providers(&conn);
+        let session_count = || -> i64 {
+            conn.query_row("SELECT COUNT(*) FROM chat_sessions", [], |row| row.get(0))
+                .unwrap()
+        };
+        let ids = ["p1"].map(String::from);
+
+        let scratch =
+            ChatSessionsRepository::create_scratch_with_conn(&conn, "scratch-abandoned", "", &ids)
+                .unwrap();
+        assert_eq!(scratch.title, "New Session");
+        ChatSessionsRepository::delete("scratch-abandoned").unwrap();
+        assert_eq!(session_count(), 0);
+        assert!(
+            !ChatSessionsRepository::persist_scratch_with_conn(&conn, "scratch-abandoned").unwrap()
+        );
+
+        ChatSessionsRepository::create_scratch_with_conn(&conn, "scratch-kept", "Kept", &ids)
+            .unwrap();
+        assert_eq!(session_count(), 0);
+        assert!(ChatSessionsRepository::persist_scratch_with_conn(&conn, "scratch-kept").unwrap());
+        assert_eq!(session_count(), 1);
+        assert_eq!(column_providers(&conn, "scratch-kept"), vec!["p1"]);
+        // Already persisted: nothing left to materialize.
+        assert!(!ChatSessionsRepository::persist_scratch_with_conn(&conn, "scratch-kept").unwrap());
+    }
+}
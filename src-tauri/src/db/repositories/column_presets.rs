@@ -0,0 +1,285 @@
+use super::chat_sessions::{ChatSessionRecord, ChatSessionsRepository};
+use crate::db::connection;
+use crate::db::error::{DbError, DbResult};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn now_unix_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+/// A named, ordered set of providers to open a new session with.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColumnPresetRecord {
+    pub id: String,
+    pub name: String,
+    pub provider_ids: Vec<String>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+fn preset_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<ColumnPresetRecord> {
+    let provider_ids_json: String = row.get(2)?;
+    Ok(ColumnPresetRecord {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        provider_ids: serde_json::from_str(&provider_ids_json).unwrap_or_default(),
+        created_at: row.get(3)?,
+        updated_at: row.get(4)?,
+    })
+}
+
+/// Trimmed, de-duplicated ids in their original order. Existence is checked
+/// when a session is created, since providers can be deleted after saving.
+fn normalize_provider_ids(provider_ids: &[String]) -> Vec<String> {
+    let mut seen = HashSet::new();
+    provider_ids
+        .iter()
+        .map(|id| id.trim())
+        .filter(|id| !id.is_empty() && seen.insert(*id))
+        .map(str::to_string)
+        .collect()
+}
+
+fn normalize_name(name: &str) -> DbResult<String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(DbError::Query("Column preset name is empty".to_string()));
+    }
+    Ok(name.to_string())
+}
+
+fn map_name_conflict(err: rusqlite::Error) -> DbError {
+    match err {
+        rusqlite::Error::SqliteFailure(e, _)
+            if e.code == rusqlite::ErrorCode::ConstraintViolation =>
+        {
+            DbError::Query("Column preset name already exists".to_string())
+        }
+        other => other.into(),
+    }
+}
+
+pub struct ColumnPresetsRepository;
+
+impl ColumnPresetsRepository {
+    pub fn list() -> DbResult<Vec<ColumnPresetRecord>> {
+        connection::with_connection(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT id, name, provider_ids_json, created_at, updated_at
+                 FROM column_presets
+                 ORDER BY name COLLATE NOCASE ASC",
+            )?;
+            let rows = stmt.query_map([], preset_from_row)?;
+
+            let mut result = Vec::new();
+            for row in rows {
+                result.push(row?);
+            }
+            Ok(result)
+        })
+    }
+
+    pub fn create(name: &str, provider_ids: &[String]) -> DbResult<ColumnPresetRecord> {
+        connection::with_connection(|conn| Self::create_with_conn(conn, name, provider_ids))
+    }
+
+    fn create_with_conn(
+        conn: &Connection,
+        name: &str,
+        provider_ids: &[String],
+    ) -> DbResult<ColumnPresetRecord> {
+        let name = normalize_name(name)?;
+        let provider_ids = normalize_provider_ids(provider_ids);
+        let id = uuid::Uuid::new_v4().to_string();
+        let now = now_unix_ms();
+        conn.execute(
+            "INSERT INTO column_presets (id, name, provider_ids_json, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?4)",
+            rusqlite::params![id, name, serde_json::to_string(&provider_ids)?, now],
+        )
+        .map_err(map_name_conflict)?;
+
+        Ok(ColumnPresetRecord {
+            id,
+            name,
+            provider_ids,
+            created_at: now,
+            updated_at: now,
+        })
+    }
+
+    /// Rename a preset and/or replace its providers.
+    pub fn update(
+        id: &str,
+        name: Option<&str>,
+        provider_ids: Option<&[String]>,
+    ) -> DbResult<ColumnPresetRecord> {
+        connection::with_connection(|conn| {
+            let existing = Self::get_with_conn(conn, "id", id)?
+                .ok_or_else(|| DbError::Query("Column preset not found".to_string()))?;
+            let name = match name {
+                Some(name) => normalize_name(name)?,
+                None => existing.name,
+            };
+            let provider_ids = provider_ids
+                .map(normalize_provider_ids)
+                .unwrap_or(existing.provider_ids);
+            let now = now_unix_ms();
+
+            conn.execute(
+                "UPDATE column_presets
+                 SET name = ?1, provider_ids_json = ?2, updated_at = ?3
+                 WHERE id = ?4",
+                rusqlite::params![name, serde_json::to_string(&provider_ids)?, now, id],
+            )
+            .map_err(map_name_conflict)?;
+
+            Ok(ColumnPresetRecord {
+                id: id.to_string(),
+                name,
+                provider_ids,
+                created_at: existing.created_at,
+                updated_at: now,
+            })
+        })
+    }
+
+    pub fn delete(id: &str) -> DbResult<()> {
+        connection::with_connection(|conn| {
+            let rows = conn.execute("DELETE FROM column_presets WHERE id = ?1", [id])?;
+            if rows == 0 {
+                return Err(DbError::Query("Column preset not found".to_string()));
+            }
+            Ok(())
+        })
+    }
+
+    /// Create session `session_id` with one column per provider in the preset
+    /// named `preset_name` (case-insensitive), skipping providers that have
+    /// since been deleted.
+    pub fn create_session(
+        session_id: &str,
+        preset_name: &str,
+        title: &str,
+    ) -> DbResult<ChatSessionRecord> {
+        connection::with_connection(|conn| {
+            Self::create_session_with_conn(conn, session_id, preset_name, title)
+        })
+    }
+
+    fn create_session_with_conn(
+        conn: &Connection,
+        session_id: &str,
+        preset_name: &str,
+        title: &str,
+    ) -> DbResult<ChatSessionRecord> {
+        let preset = Self::get_with_conn(conn, "name", preset_name.trim())?
+            .ok_or_else(|| DbError::Query("Column preset not found".to_string()))?;
+
+        let mut provider_ids = Vec::new();
+        for provider_id in preset.provider_ids {
+            let exists: bool = conn.query_row(
+                "SELECT EXISTS(SELECT 1 FROM providers WHERE id = ?1)",
+                [&provider_id],
+                |row| row.get(0),
+            )?;
+            if exists {
+                provider_ids.push(provider_id);
+            }
+        }
+
+        let tx = conn.unchecked_transaction()?;
+        let session =
+            ChatSessionsRepository::create_with_conn(&tx, session_id, title, &provider_ids)?;
+        tx.commit()?;
+        Ok(session)
+    }
+
+    /// Look up a preset by `id` or `name`; `key` is one of those column names.
+    fn get_with_conn(
+        conn: &Connection,
+        key: &str,
+        value: &str,
+    ) -> DbResult<Option<ColumnPresetRecord>> {
+        let result = conn.query_row(
+            &format!(
+                "SELECT id, name, provider_ids_json, created_at, updated_at
+                 FROM column_presets WHERE {key} = ?1"
+            ),
+            [value],
+            preset_from_row,
+        );
+
+        match result {
+            Ok(record) => Ok(Some(record)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seed_providers(conn: &Connection) {
+        conn.execute_batch(
+            "INSERT INTO providers (id, name, provider_type, model, created_at, updated_at)
+             VALUES ('p1', 'P1', 'openai', 'm', 0, 0), ('p2', 'P2', 'openai', 'm', 0, 0),
+                    ('p3', 'P3', 'openai', 'm', 0, 0);",
+        )
+        .unwrap();
+    }
+
+    fn column_providers(conn: &Connection, session_id: &str) -> Vec<String> {
+        conn.prepare(
+            "SELECT provider_id FROM chat_session_columns
+             WHERE session_id = ?1 ORDER BY position ASC",
+        )
+        .unwrap()
+        .query_map([session_id], |row| row.get(0))
+        .unwrap()
+        .map(Result::unwrap)
+        .collect()
+    }
+
+    #[test]
+    fn test_session_from_preset_gets_its_columns() {
+        let conn = connection::open_test_connection();
+        seed_providers(&conn);
+        let ids = ["p3", " p1 ", "p2", "p3"].map(String::from);
+        let preset = ColumnPresetsRepository::create_with_conn(&conn, "Big three", &ids).unwrap();
+        assert_eq!(preset.provider_ids, vec!["p3", "p1", "p2"]);
+        assert!(ColumnPresetsRepository::create_with_conn(&conn, " big THREE ", &[]).is_err());
+
+        let session =
+            ColumnPresetsRepository::create_session_with_conn(&conn, "s1", "big three", "Compare")
+                .unwrap();
+        assert_eq!(session.provider_ids, vec!["p3", "p1", "p2"]);
+        assert_eq!(column_providers(&conn, "s1"), vec!["p3", "p1", "p2"]);
+        assert!(
+            ColumnPresetsRepository::create_session_with_conn(&conn, "s2", "unknown", "x").is_err()
+        );
+    }
+
+    #[test]
+    fn test_session_from_preset_skips_deleted_providers() {
+        let conn = connection::open_test_connection();
+        seed_providers(&conn);
+        let ids = ["p1", "p2", "p3"].map(String::from);
+        ColumnPresetsRepository::create_with_conn(&conn, "Trio", &ids).unwrap();
+        conn.execute("DELETE FROM providers WHERE id = 'p2'", [])
+            .unwrap();
+
+        let session =
+            ColumnPresetsRepository::create_session_with_conn(&conn, "s1", "Trio", "T").unwrap();
+        assert_eq!(session.provider_ids, vec!["p1", "p3"]);
+        assert_eq!(column_providers(&conn, "s1"), vec!["p1", "p3"]);
+    }
+}
@@ -0,0 +1,121 @@
+use crate::db::connection;
+use crate::db::error::DbResult;
+use rusqlite::{Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn now_unix_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+/// The request that produced an assistant message, with credentials removed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageRequestRecord {
+    pub message_id: String,
+    pub provider_id: String,
+    /// URL, non-secret headers, query and body as sent.
+    pub payload: serde_json::Value,
+    pub created_at: i64,
+}
+
+pub struct MessageRequestsRepository;
+
+impl MessageRequestsRepository {
+    /// Store the request for `message_id`, replacing the one from an earlier
+    /// attempt at the same message.
+    pub fn save(message_id: &str, provider_id: &str, payload: &serde_json::Value) -> DbResult<()> {
+        connection::with_connection(|conn| {
+            Self::save_with_conn(conn, message_id, provider_id, payload)
+        })
+    }
+
+    fn save_with_conn(
+        conn: &Connection,
+        message_id: &str,
+        provider_id: &str,
+        payload: &serde_json::Value,
+    ) -> DbResult<()> {
+        conn.execute(
+            "INSERT INTO message_requests (message_id, provider_id, payload_json, created_at)
+             VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(message_id) DO UPDATE SET
+                provider_id = excluded.provider_id,
+                payload_json = excluded.payload_json,
+                created_at = excluded.created_at",
+            rusqlite::params![message_id, provider_id, payload.to_string(), now_unix_ms()],
+        )?;
+        Ok(())
+    }
+
+    pub fn get(message_id: &str) -> DbResult<Option<MessageRequestRecord>> {
+        connection::with_connection(|conn| Self::get_with_conn(conn, message_id))
+    }
+
+    fn get_with_conn(
+        conn: &Connection,
+        message_id: &str,
+    ) -> DbResult<Option<MessageRequestRecord>> {
+        let record = conn
+            .query_row(
+                "SELECT message_id, provider_id, payload_json, created_at
+                 FROM message_requests WHERE message_id = ?1",
+                [message_id],
+                |row| {
+                    let payload_json: String = row.get(2)?;
+                    Ok(MessageRequestRecord {
+                        message_id: row.get(0)?,
+                        provider_id: row.get(1)?,
+                        payload: serde_json::from_str(&payload_json)
+                            .unwrap_or(serde_json::Value::Null),
+                        created_at: row.get(3)?,
+                    })
+                },
+            )
+            .optional()?;
+        Ok(record)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_round_trips_and_follows_its_message() {
+        let conn = connection::open_test_connection();
+        conn.execute_batch(
+            "INSERT INTO chat_sessions (id, title, provider_ids_json, created_at, updated_at)
+             VALUES ('s1', 'One', '[\"p1\"]', 0, 0);
+             INSERT INTO chat_messages
+             (id, session_id, column_id, provider_id, role, content, status, created_at, updated_at)
+             VALUES ('m1', 's1', 's1:c0', 'p1', 'assistant', '', 'streaming', 1, 1);",
+        )
+        .unwrap();
+
+        let first = serde_json::json!({ "body": { "model": "old" } });
+        let payload = serde_json::json!({
+            "url": "https://api.openai.com/v1/chat/completions",
+            "body": { "model": "gpt-4o", "messages": [{ "role": "user", "content": "hi" }] }
+        });
+        MessageRequestsRepository::save_with_conn(&conn, "m1", "p1", &first).unwrap();
+        MessageRequestsRepository::save_with_conn(&conn, "m1", "p1", &payload).unwrap();
+
+        let record = MessageRequestsRepository::get_with_conn(&conn, "m1")
+            .unwrap()
+            .unwrap();
+        assert_eq!(record.provider_id, "p1");
+        assert_eq!(record.payload, payload);
+        assert!(MessageRequestsRepository::get_with_conn(&conn, "m2")
+            .unwrap()
+            .is_none());
+
+        conn.execute("DELETE FROM chat_messages WHERE id = 'm1'", [])
+            .unwrap();
+        assert!(MessageRequestsRepository::get_with_conn(&conn, "m1")
+            .unwrap()
+            .is_none());
+    }
+}
@@ -2,12 +2,26 @@ mod apps;
 mod chat_messages;
 mod chat_session_columns;
 mod chat_sessions;
+mod column_presets;
+mod message_requests;
+mod prompt_templates;
 mod providers;
+mod recent_queries;
 mod settings;
 
-pub use apps::AppsRepository;
-pub use chat_messages::{ChatMessageRecord, ChatMessagesRepository, MessageSearchResult};
-pub use chat_session_columns::{ChatSessionColumnRecord, ChatSessionColumnsRepository};
-pub use chat_sessions::{ChatSessionRecord, ChatSessionsRepository};
+pub use apps::{AppsRepository, LaunchEvent};
+pub use chat_messages::{
+    content_hash, parse_max_messages_per_session, ChatMessageRecord, ChatMessageWithSession,
+    ChatMessagesRepository, DiffSegment, FtsStats, MessageSearchResult, ResubmitSetup,
+};
+pub use chat_session_columns::{
+    ChatSessionColumnRecord, ChatSessionColumnsRepository, ColumnWithLatest, CompareColumnSetup,
+    RepairReport,
+};
+pub use chat_sessions::{ChatSessionRecord, ChatSessionsRepository, ChatStats, SessionStorage};
+pub use column_presets::{ColumnPresetRecord, ColumnPresetsRepository};
+pub use message_requests::{MessageRequestRecord, MessageRequestsRepository};
+pub use prompt_templates::{PromptTemplateRecord, PromptTemplatesRepository};
 pub use providers::ProvidersRepository;
+pub use recent_queries::{RecentQueriesRepository, RecentQueryRecord, MAX_RECENT_QUERIES};
 pub use settings::SettingsRepository;
@@ -0,0 +1,262 @@
+use crate::db::connection;
+use crate::db::error::{DbError, DbResult};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn now_unix_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+/// Placeholder replaced by the user's input when a template is expanded.
+pub const TEMPLATE_INPUT_PLACEHOLDER: &str = "{{input}}";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptTemplateRecord {
+    pub id: String,
+    pub name: String,
+    pub body: String,
+    pub shortcut: Option<String>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+fn template_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<PromptTemplateRecord> {
+    Ok(PromptTemplateRecord {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        body: row.get(2)?,
+        shortcut: row.get(3)?,
+        created_at: row.get(4)?,
+        updated_at: row.get(5)?,
+    })
+}
+
+fn normalize_shortcut(shortcut: Option<&str>) -> Option<String> {
+    shortcut
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+}
+
+fn map_shortcut_conflict(err: rusqlite::Error) -> DbError {
+    match err {
+        rusqlite::Error::SqliteFailure(e, _)
+            if e.code == rusqlite::ErrorCode::ConstraintViolation =>
+        {
+            DbError::Query("Prompt template shortcut already exists".to_string())
+        }
+        other => other.into(),
+    }
+}
+
+/// Substitute `input` for every `{{input}}` in `body`; templates without the
+/// placeholder get the input appended after a blank line.
+pub fn expand_template_body(body: &str, input: &str) -> String {
+    if body.contains(TEMPLATE_INPUT_PLACEHOLDER) {
+        body.replace(TEMPLATE_INPUT_PLACEHOLDER, input)
+    } else if input.trim().is_empty() {
+        body.to_string()
+    } else if body.trim().is_empty() {
+        input.to_string()
+    } else {
+        format!("{}\n\n{input}", body.trim_end())
+    }
+}
+
+pub struct PromptTemplatesRepository;
+
+impl PromptTemplatesRepository {
+    pub fn list() -> DbResult<Vec<PromptTemplateRecord>> {
+        connection::with_connection(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT id, name, body, shortcut, created_at, updated_at
+                 FROM prompt_templates
+                 ORDER BY name COLLATE NOCASE ASC",
+            )?;
+            let rows = stmt.query_map([], template_from_row)?;
+
+            let mut result = Vec::new();
+            for row in rows {
+                result.push(row?);
+            }
+            Ok(result)
+        })
+    }
+
+    pub fn create(
+        name: &str,
+        body: &str,
+        shortcut: Option<&str>,
+    ) -> DbResult<PromptTemplateRecord> {
+        connection::with_connection(|conn| Self::create_with_conn(conn, name, body, shortcut))
+    }
+
+    fn create_with_conn(
+        conn: &Connection,
+        name: &str,
+        body: &str,
+        shortcut: Option<&str>,
+    ) -> DbResult<PromptTemplateRecord> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(DbError::Query("Prompt template name is empty".to_string()));
+        }
+
+        let id = uuid::Uuid::new_v4().to_string();
+        let shortcut = normalize_shortcut(shortcut);
+        let now = now_unix_ms();
+        conn.execute(
+            "INSERT INTO prompt_templates (id, name, body, shortcut, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?5)",
+            rusqlite::params![id, name, body, shortcut, now],
+        )
+        .map_err(map_shortcut_conflict)?;
+
+        Ok(PromptTemplateRecord {
+            id,
+            name: name.to_string(),
+            body: body.to_string(),
+            shortcut,
+            created_at: now,
+            updated_at: now,
+        })
+    }
+
+    /// Update any of name/body/shortcut; an empty shortcut clears it.
+    pub fn update(
+        id: &str,
+        name: Option<&str>,
+        body: Option<&str>,
+        shortcut: Option<&str>,
+    ) -> DbResult<PromptTemplateRecord> {
+        connection::with_connection(|conn| {
+            let existing = Self::get_with_conn(conn, id)?
+                .ok_or_else(|| DbError::Query("Prompt template not found".to_string()))?;
+
+            let name = match name.map(str::trim) {
+                Some("") => {
+                    return Err(DbError::Query("Prompt template name is empty".to_string()))
+                }
+                Some(name) => name.to_string(),
+                None => existing.name,
+            };
+            let body = body.map(str::to_string).unwrap_or(existing.body);
+            let shortcut = match shortcut {
+                Some(raw) => normalize_shortcut(Some(raw)),
+                None => existing.shortcut,
+            };
+            let now = now_unix_ms();
+
+            conn.execute(
+                "UPDATE prompt_templates
+                 SET name = ?1, body = ?2, shortcut = ?3, updated_at = ?4
+                 WHERE id = ?5",
+                rusqlite::params![name, body, shortcut, now, id],
+            )
+            .map_err(map_shortcut_conflict)?;
+
+            Ok(PromptTemplateRecord {
+                id: id.to_string(),
+                name,
+                body,
+                shortcut,
+                created_at: existing.created_at,
+                updated_at: now,
+            })
+        })
+    }
+
+    pub fn delete(id: &str) -> DbResult<()> {
+        connection::with_connection(|conn| {
+            let rows = conn.execute("DELETE FROM prompt_templates WHERE id = ?1", [id])?;
+            if rows == 0 {
+                return Err(DbError::Query("Prompt template not found".to_string()));
+            }
+            Ok(())
+        })
+    }
+
+    /// Look up a template by shortcut (case-insensitive) and expand it with `input`.
+    pub fn expand(shortcut: &str, input: &str) -> DbResult<String> {
+        connection::with_connection(|conn| Self::expand_with_conn(conn, shortcut, input))
+    }
+
+    fn expand_with_conn(conn: &Connection, shortcut: &str, input: &str) -> DbResult<String> {
+        let body = conn
+            .query_row(
+                "SELECT body FROM prompt_templates WHERE shortcut = ?1",
+                [shortcut.trim()],
+                |row| row.get::<_, String>(0),
+            )
+            .map_err(|e| match e {
+                rusqlite::Error::QueryReturnedNoRows => {
+                    DbError::Query("Prompt template not found".to_string())
+                }
+                _ => e.into(),
+            })?;
+        Ok(expand_template_body(&body, input))
+    }
+
+    fn get_with_conn(conn: &Connection, id: &str) -> DbResult<Option<PromptTemplateRecord>> {
+        let result = conn.query_row(
+            "SELECT id, name, body, shortcut, created_at, updated_at
+             FROM prompt_templates WHERE id = ?1",
+            [id],
+            template_from_row,
+        );
+
+        match result {
+            Ok(record) => Ok(Some(record)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_template_body() {
+        assert_eq!(
+            expand_template_body("Summarize the following:\n{{input}}", "text"),
+            "Summarize the following:\ntext"
+        );
+        assert_eq!(expand_template_body("{{input}} / {{input}}", "a"), "a / a");
+        assert_eq!(
+            expand_template_body("Explain like I'm 5:", "monads"),
+            "Explain like I'm 5:\n\nmonads"
+        );
+        assert_eq!(expand_template_body("Translate:", "  "), "Translate:");
+    }
+
+    #[test]
+    fn test_shortcuts_are_unique() {
+        let conn = connection::open_test_connection();
+        PromptTemplatesRepository::create_with_conn(
+            &conn,
+            "Summary",
+            "Sum: {{input}}",
+            Some("sum"),
+        )
+        .unwrap();
+
+        let duplicate =
+            PromptTemplatesRepository::create_with_conn(&conn, "Other", "x", Some(" SUM "));
+        assert!(matches!(duplicate, Err(DbError::Query(_))));
+
+        // Templates without a shortcut never conflict with each other.
+        PromptTemplatesRepository::create_with_conn(&conn, "A", "a", None).unwrap();
+        PromptTemplatesRepository::create_with_conn(&conn, "B", "b", Some("")).unwrap();
+
+        assert_eq!(
+            PromptTemplatesRepository::expand_with_conn(&conn, "Sum", "hello").unwrap(),
+            "Sum: hello"
+        );
+    }
+}
@@ -1,8 +1,10 @@
 use crate::db::connection;
 use crate::db::error::{DbError, DbResult};
 use crate::provider::{
-    CreateProviderRequest, Provider, ProviderType, ProviderView, UpdateProviderRequest,
+    resolve_base_url, CreateProviderRequest, CreateProviderResult, Provider, ProviderRole,
+    ProviderType, ProviderView, QueryReadiness, UpdateProviderRequest,
 };
+use std::collections::BTreeSet;
 use std::str::FromStr;
 use std::time::{SystemTime, UNIX_EPOCH};
 
@@ -13,32 +15,40 @@ fn now_unix_ms() -> i64 {
         .unwrap_or(0)
 }
 
+/// Column list shared by every provider SELECT; keep in sync with `provider_from_row`.
+const PROVIDER_COLUMNS: &str = "id, name, provider_type, base_url, model, is_active, display_order, use_responses_api, context_window, requests_per_minute, role, max_concurrency, created_at, updated_at";
+
+/// Number of columns in `PROVIDER_COLUMNS`; extra selected columns start at this index.
+const PROVIDER_COLUMN_COUNT: usize = 14;
+
+fn provider_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Provider> {
+    let provider_type_str: String = row.get(2)?;
+    let provider_type = ProviderType::from_str(&provider_type_str).unwrap_or(ProviderType::Custom);
+
+    Ok(Provider {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        provider_type,
+        base_url: row.get(3)?,
+        model: row.get(4)?,
+        is_active: row.get::<_, i32>(5)? == 1,
+        display_order: row.get(6)?,
+        use_responses_api: row.get::<_, i32>(7)? == 1,
+        context_window: row.get(8)?,
+        requests_per_minute: row.get(9)?,
+        role: ProviderRole::from_str(&row.get::<_, String>(10)?).unwrap_or_default(),
+        max_concurrency: row.get(11)?,
+        created_at: row.get(12)?,
+        updated_at: row.get(13)?,
+    })
+}
+
 pub struct ProvidersRepository;
 
 impl ProvidersRepository {
     fn get_with_conn(conn: &rusqlite::Connection, id: &str) -> DbResult<Option<Provider>> {
-        let result = conn.query_row(
-            "SELECT id, name, provider_type, base_url, model, is_active, display_order, created_at, updated_at
-             FROM providers WHERE id = ?1",
-            [id],
-            |row| {
-                let provider_type_str: String = row.get(2)?;
-                let provider_type =
-                    ProviderType::from_str(&provider_type_str).unwrap_or(ProviderType::Custom);
-
-                Ok(Provider {
-                    id: row.get(0)?,
-                    name: row.get(1)?,
-                    provider_type,
-                    base_url: row.get(3)?,
-                    model: row.get(4)?,
-                    is_active: row.get::<_, i32>(5)? == 1,
-                    display_order: row.get(6)?,
-                    created_at: row.get(7)?,
-                    updated_at: row.get(8)?,
-                })
-            },
-        );
+        let sql = format!("SELECT {PROVIDER_COLUMNS} FROM providers WHERE id = ?1");
+        let result = conn.query_row(&sql, [id], provider_from_row);
 
         match result {
             Ok(provider) => Ok(Some(provider)),
@@ -47,55 +57,91 @@ impl ProvidersRepository {
         }
     }
 
-    /// Create a new provider.
-    pub fn create(req: CreateProviderRequest) -> DbResult<Provider> {
-        connection::with_connection(|conn| {
-            let id = uuid::Uuid::new_v4().to_string();
-            let now = now_unix_ms();
-            let provider_type = req.provider_type;
-            let name = req.name;
-            let base_url = req
-                .base_url
-                .or_else(|| provider_type.default_base_url().map(|s| s.to_string()));
-            let model = req
-                .model
-                .unwrap_or_else(|| provider_type.default_model().to_string());
-            let api_key = req
-                .api_key
-                .map(|v| v.trim().to_string())
-                .filter(|v| !v.is_empty());
-
-            // Get the next display order.
-            let max_order: i32 = conn
-                .query_row(
-                    "SELECT COALESCE(MAX(display_order), -1) FROM providers",
-                    [],
-                    |row| row.get(0),
-                )
-                .unwrap_or(-1);
-            let display_order = max_order + 1;
-
-            // If this is the first provider, make it active.
-            let is_active = if max_order < 0 { 1 } else { 0 };
+    /// Create a new provider, or return the existing identical one unless
+    /// `allow_duplicate` is set. A non-empty API key in the request is saved
+    /// onto the existing provider; its other fields are left as they are.
+    pub fn create(req: CreateProviderRequest) -> DbResult<CreateProviderResult> {
+        connection::with_connection(|conn| Self::create_with_conn(conn, req))
+    }
 
-            conn.execute(
-                "INSERT INTO providers (
-                    id, name, provider_type, base_url, model, api_key, is_active, display_order, created_at, updated_at
-                 ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?9)",
-                rusqlite::params![
-                    id,
-                    name,
-                    provider_type.to_string(),
-                    base_url,
-                    model,
-                    api_key,
-                    is_active,
-                    display_order,
-                    now
-                ],
-            )?;
+    fn create_with_conn(
+        conn: &rusqlite::Connection,
+        req: CreateProviderRequest,
+    ) -> DbResult<CreateProviderResult> {
+        let id = uuid::Uuid::new_v4().to_string();
+        let now = now_unix_ms();
+        let provider_type = req.provider_type;
+        let name = req.name;
+        let base_url = req
+            .base_url
+            .or_else(|| provider_type.default_base_url().map(|s| s.to_string()));
+        let model = req
+            .model
+            .unwrap_or_else(|| provider_type.default_model().to_string());
+        let api_key = req
+            .api_key
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty());
+        let use_responses_api = req.use_responses_api.unwrap_or(false);
+        let context_window = req.context_window.filter(|v| *v > 0);
+        let requests_per_minute = req.requests_per_minute.filter(|v| *v > 0);
+        let role = req.role.unwrap_or_default();
+
+        if !req.allow_duplicate.unwrap_or(false) {
+            if let Some(mut provider) =
+                Self::find_matching_with_conn(conn, provider_type, base_url.as_deref(), &model)?
+            {
+                if let Some(api_key) = &api_key {
+                    conn.execute(
+                        "UPDATE providers SET api_key = ?1, updated_at = ?2 WHERE id = ?3",
+                        rusqlite::params![api_key, now, provider.id],
+                    )?;
+                    provider.updated_at = now;
+                }
+                return Ok(CreateProviderResult {
+                    provider,
+                    existing: true,
+                });
+            }
+        }
 
-            Ok(Provider {
+        // Get the next display order.
+        let max_order: i32 = conn
+            .query_row(
+                "SELECT COALESCE(MAX(display_order), -1) FROM providers",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap_or(-1);
+        let display_order = max_order + 1;
+
+        // If this is the first provider, make it active.
+        let is_active = if max_order < 0 { 1 } else { 0 };
+
+        conn.execute(
+            "INSERT INTO providers (
+                id, name, provider_type, base_url, model, api_key, is_active, display_order,
+                use_responses_api, context_window, requests_per_minute, role, created_at, updated_at
+             ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?13)",
+            rusqlite::params![
+                id,
+                name,
+                provider_type.to_string(),
+                base_url,
+                model,
+                api_key,
+                is_active,
+                display_order,
+                use_responses_api,
+                context_window,
+                requests_per_minute,
+                role.to_string(),
+                now
+            ],
+        )?;
+
+        Ok(CreateProviderResult {
+            provider: Provider {
                 id,
                 name,
                 provider_type,
@@ -103,47 +149,128 @@ impl ProvidersRepository {
                 model,
                 is_active: is_active == 1,
                 display_order,
+                use_responses_api,
+                context_window,
+                requests_per_minute,
+                max_concurrency: None,
+                role,
                 created_at: now,
                 updated_at: now,
-            })
+            },
+            existing: false,
         })
     }
 
+    /// First provider with the same type, effective base URL and model.
+    fn find_matching_with_conn(
+        conn: &rusqlite::Connection,
+        provider_type: ProviderType,
+        base_url: Option<&str>,
+        model: &str,
+    ) -> DbResult<Option<Provider>> {
+        let sql = format!("SELECT {PROVIDER_COLUMNS} FROM providers ORDER BY display_order ASC");
+        let mut stmt = conn.prepare(&sql)?;
+        let providers = stmt
+            .query_map([], provider_from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let base_url = resolve_base_url(provider_type, base_url);
+        Ok(providers.into_iter().find(|p| {
+            p.provider_type == provider_type
+                && p.resolved_base_url() == base_url
+                && p.model.trim() == model.trim()
+        }))
+    }
+
     /// List all providers with API key status.
     pub fn list() -> DbResult<Vec<ProviderView>> {
-        connection::with_connection(|conn| {
-            let mut stmt = conn.prepare(
-                "SELECT id, name, provider_type, base_url, model, is_active, display_order, created_at, updated_at,
-                        CASE WHEN api_key IS NULL OR TRIM(api_key) = '' THEN 0 ELSE 1 END AS has_api_key
-                 FROM providers
-                 ORDER BY display_order ASC",
-            )?;
+        connection::with_connection(|conn| Self::list_ordered_with_conn(conn, false))
+    }
 
-            let providers = stmt.query_map([], |row| {
-                let provider_type_str: String = row.get(2)?;
-                let provider_type =
-                    ProviderType::from_str(&provider_type_str).unwrap_or(ProviderType::Custom);
-
-                Ok(ProviderView {
-                    id: row.get(0)?,
-                    name: row.get(1)?,
-                    provider_type,
-                    base_url: row.get(3)?,
-                    model: row.get(4)?,
-                    is_active: row.get::<_, i32>(5)? == 1,
-                    display_order: row.get(6)?,
-                    has_api_key: row.get::<_, i32>(9)? == 1,
-                    created_at: row.get(7)?,
-                    updated_at: row.get(8)?,
-                })
-            })?;
+    /// Like `list`, but most-used first: by assistant replies stored in
+    /// `chat_messages`, then by the latest reply. Unused providers keep
+    /// their display order at the end.
+    pub fn list_by_usage() -> DbResult<Vec<ProviderView>> {
+        connection::with_connection(|conn| Self::list_ordered_with_conn(conn, true))
+    }
 
-            let mut result = Vec::new();
-            for provider in providers {
-                result.push(provider?);
-            }
+    fn list_ordered_with_conn(
+        conn: &rusqlite::Connection,
+        by_usage: bool,
+    ) -> DbResult<Vec<ProviderView>> {
+        let (usage_join, usage_order) = if by_usage {
+            (
+                "LEFT JOIN (
+                    SELECT provider_id, COUNT(*) AS message_count, MAX(created_at) AS last_used_at
+                    FROM chat_messages
+                    WHERE role = 'assistant'
+                    GROUP BY provider_id
+                 ) usage ON usage.provider_id = providers.id",
+                "COALESCE(usage.message_count, 0) DESC, COALESCE(usage.last_used_at, 0) DESC,",
+            )
+        } else {
+            ("", "")
+        };
+        let sql = format!(
+            "SELECT {PROVIDER_COLUMNS},
+                    CASE WHEN api_key IS NULL OR TRIM(api_key) = '' THEN 0 ELSE 1 END AS has_api_key
+             FROM providers
+             {usage_join}
+             ORDER BY {usage_order} display_order ASC"
+        );
+        let mut stmt = conn.prepare(&sql)?;
 
-            Ok(result)
+        let providers = stmt.query_map([], |row| {
+            let provider = provider_from_row(row)?;
+            let has_api_key = row.get::<_, i32>(PROVIDER_COLUMN_COUNT)? == 1;
+            Ok(ProviderView::from_provider(provider, has_api_key))
+        })?;
+
+        let mut result = Vec::new();
+        for provider in providers {
+            result.push(provider?);
+        }
+
+        Ok(result)
+    }
+
+    /// Distinct canonical provider types stored in the table, sorted by name.
+    /// Legacy aliases such as "ark" report as their canonical type, unknown ones as "custom".
+    pub fn list_types_in_use() -> DbResult<Vec<String>> {
+        connection::with_connection(Self::list_types_in_use_with_conn)
+    }
+
+    fn list_types_in_use_with_conn(conn: &rusqlite::Connection) -> DbResult<Vec<String>> {
+        let mut stmt = conn.prepare("SELECT DISTINCT provider_type FROM providers")?;
+        let raw_types = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let canonical = raw_types
+            .iter()
+            .map(|raw| {
+                ProviderType::from_str(raw.trim())
+                    .unwrap_or(ProviderType::Custom)
+                    .to_string()
+            })
+            .collect::<BTreeSet<_>>();
+
+        Ok(canonical.into_iter().collect())
+    }
+
+    /// List providers that are currently enabled, in display order.
+    pub fn list_active() -> DbResult<Vec<Provider>> {
+        connection::with_connection(|conn| {
+            let sql = format!(
+                "SELECT {PROVIDER_COLUMNS} FROM providers
+                 WHERE is_active = 1
+                 ORDER BY display_order ASC"
+            );
+            let mut stmt = conn.prepare(&sql)?;
+            let providers = stmt
+                .query_map([], provider_from_row)?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(providers)
         })
     }
 
@@ -152,48 +279,118 @@ impl ProvidersRepository {
         connection::with_connection(|conn| Self::get_with_conn(conn, id))
     }
 
-    /// Get the active provider with its API key.
-    pub fn get_active_with_key() -> DbResult<Option<(Provider, String)>> {
+    /// Provider for streaming chat when none is named: see `pick_for_role_with_conn`.
+    pub fn get_chat_provider_with_key() -> DbResult<Option<(Provider, String)>> {
+        connection::with_connection(|conn| Self::pick_for_role_with_conn(conn, ProviderRole::Chat))
+    }
+
+    /// Provider for auto-titling and inline tasks: see `pick_for_role_with_conn`.
+    pub fn get_utility_provider_with_key() -> DbResult<Option<(Provider, String)>> {
         connection::with_connection(|conn| {
-            let result = conn.query_row(
-                "SELECT id, name, provider_type, base_url, model, is_active, display_order, created_at, updated_at, api_key
-                 FROM providers WHERE is_active = 1
-                 ORDER BY display_order ASC LIMIT 1",
-                [],
-                |row| {
-                    let provider_type_str: String = row.get(2)?;
-                    let provider_type = ProviderType::from_str(&provider_type_str).unwrap_or(ProviderType::Custom);
-
-                    Ok((
-                        Provider {
-                            id: row.get(0)?,
-                            name: row.get(1)?,
-                            provider_type,
-                            base_url: row.get(3)?,
-                            model: row.get(4)?,
-                            is_active: row.get::<_, i32>(5)? == 1,
-                            display_order: row.get(6)?,
-                            created_at: row.get(7)?,
-                            updated_at: row.get(8)?,
-                        },
-                        row.get::<_, Option<String>>(9)?,
-                    ))
-                },
-            );
+            Self::pick_for_role_with_conn(conn, ProviderRole::Utility)
+        })
+    }
 
-            match result {
-                Ok((provider, api_key)) => {
-                    let api_key = api_key.unwrap_or_default();
-                    if api_key.trim().is_empty() {
-                        Ok(None)
-                    } else {
-                        Ok(Some((provider, api_key)))
-                    }
+    /// Active, keyed provider for `role`: the first of `ranked_for_role_with_conn`.
+    fn pick_for_role_with_conn(
+        conn: &rusqlite::Connection,
+        role: ProviderRole,
+    ) -> DbResult<Option<(Provider, String)>> {
+        Ok(Self::ranked_for_role_with_conn(conn, role)?
+            .into_iter()
+            .next())
+    }
+
+    /// Every active, keyed provider in the order they are tried for chat,
+    /// so a failed query can fail over to the next one.
+    pub fn get_chat_failover_chain() -> DbResult<Vec<(Provider, String)>> {
+        connection::with_connection(|conn| {
+            Self::ranked_for_role_with_conn(conn, ProviderRole::Chat)
+        })
+    }
+
+    /// Active, keyed providers for `role`: dedicated providers first, then
+    /// `both`, then any other active provider, each in display order.
+    fn ranked_for_role_with_conn(
+        conn: &rusqlite::Connection,
+        role: ProviderRole,
+    ) -> DbResult<Vec<(Provider, String)>> {
+        let sql = format!(
+            "SELECT {PROVIDER_COLUMNS}, api_key
+             FROM providers
+             WHERE is_active = 1 AND api_key IS NOT NULL AND TRIM(api_key) != ''
+             ORDER BY display_order ASC"
+        );
+        let mut stmt = conn.prepare(&sql)?;
+        let mut candidates = stmt
+            .query_map([], |row| {
+                Ok((
+                    provider_from_row(row)?,
+                    row.get::<_, String>(PROVIDER_COLUMN_COUNT)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Stable, so display order is kept within each rank.
+        candidates.sort_by_key(|(provider, _)| {
+            if provider.role == role {
+                0
+            } else if provider.role.handles(role) {
+                1
+            } else {
+                2
+            }
+        });
+        Ok(candidates)
+    }
+
+    /// Report whether a query can be sent, diagnosing a missing provider or key.
+    pub fn query_readiness() -> DbResult<QueryReadiness> {
+        connection::with_connection(Self::query_readiness_with_conn)
+    }
+
+    fn query_readiness_with_conn(conn: &rusqlite::Connection) -> DbResult<QueryReadiness> {
+        let readiness = match Self::first_active_with_conn(conn)? {
+            None => QueryReadiness {
+                has_active_provider: false,
+                has_api_key: false,
+                provider_name: None,
+                apps_only: false,
+                missing_reason: Some("no_active_provider".to_string()),
+            },
+            Some((provider, api_key)) => {
+                let has_api_key = !api_key.trim().is_empty();
+                QueryReadiness {
+                    has_active_provider: true,
+                    has_api_key,
+                    provider_name: Some(provider.name),
+                    apps_only: false,
+                    missing_reason: (!has_api_key).then(|| "missing_api_key".to_string()),
                 }
-                Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-                Err(e) => Err(e.into()),
             }
-        })
+        };
+        Ok(readiness)
+    }
+
+    /// First active provider in display order with its raw (possibly empty) API key.
+    fn first_active_with_conn(conn: &rusqlite::Connection) -> DbResult<Option<(Provider, String)>> {
+        let sql = format!(
+            "SELECT {PROVIDER_COLUMNS}, api_key
+             FROM providers WHERE is_active = 1
+             ORDER BY display_order ASC LIMIT 1"
+        );
+        let result = conn.query_row(&sql, [], |row| {
+            Ok((
+                provider_from_row(row)?,
+                row.get::<_, Option<String>>(PROVIDER_COLUMN_COUNT)?,
+            ))
+        });
+
+        match result {
+            Ok((provider, api_key)) => Ok(Some((provider, api_key.unwrap_or_default()))),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
     }
 
     /// Update a provider.
@@ -217,6 +414,22 @@ impl ProvidersRepository {
                 updates.push("model = ?");
                 params.push(Box::new(model.clone()));
             }
+            if let Some(use_responses_api) = req.use_responses_api {
+                updates.push("use_responses_api = ?");
+                params.push(Box::new(use_responses_api));
+            }
+            if let Some(context_window) = req.context_window {
+                updates.push("context_window = ?");
+                params.push(Box::new(Some(context_window).filter(|v| *v > 0)));
+            }
+            if let Some(requests_per_minute) = req.requests_per_minute {
+                updates.push("requests_per_minute = ?");
+                params.push(Box::new(Some(requests_per_minute).filter(|v| *v > 0)));
+            }
+            if let Some(role) = req.role {
+                updates.push("role = ?");
+                params.push(Box::new(role.to_string()));
+            }
 
             if updates.is_empty() {
                 return Self::get_with_conn(conn, id)?
@@ -286,6 +499,71 @@ impl ProvidersRepository {
         })
     }
 
+    /// Enable or disable every provider of `provider_type` (legacy aliases
+    /// included) in one transaction, returning how many actually changed.
+    /// Like `set_active`, this may leave no provider enabled.
+    pub fn set_active_by_type(provider_type: ProviderType, is_active: bool) -> DbResult<usize> {
+        connection::with_connection(|conn| {
+            Self::set_active_by_type_with_conn(conn, provider_type, is_active)
+        })
+    }
+
+    fn set_active_by_type_with_conn(
+        conn: &rusqlite::Connection,
+        provider_type: ProviderType,
+        is_active: bool,
+    ) -> DbResult<usize> {
+        let tx = conn.unchecked_transaction()?;
+        let ids = {
+            let mut stmt = tx.prepare("SELECT id, provider_type FROM providers")?;
+            let rows = stmt
+                .query_map([], |row| {
+                    Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            rows.into_iter()
+                .filter(|(_, raw)| {
+                    ProviderType::from_str(raw.trim()).unwrap_or(ProviderType::Custom)
+                        == provider_type
+                })
+                .map(|(id, _)| id)
+                .collect::<Vec<_>>()
+        };
+
+        let now = now_unix_ms();
+        let value = if is_active { 1 } else { 0 };
+        let mut changed = 0;
+        for id in ids {
+            changed += tx.execute(
+                "UPDATE providers SET is_active = ?1, updated_at = ?2
+                 WHERE id = ?3 AND is_active != ?1",
+                rusqlite::params![value, now, id],
+            )?;
+        }
+
+        tx.commit()?;
+        Ok(changed)
+    }
+
+    /// Cap how many requests to a provider may be in flight at once; `None` or
+    /// a non-positive value removes the cap.
+    pub fn set_max_concurrency(id: &str, max_concurrency: Option<i64>) -> DbResult<Provider> {
+        connection::with_connection(|conn| {
+            let now = now_unix_ms();
+            let rows_affected = conn.execute(
+                "UPDATE providers SET max_concurrency = ?1, updated_at = ?2 WHERE id = ?3",
+                rusqlite::params![max_concurrency.filter(|v| *v > 0), now, id],
+            )?;
+
+            if rows_affected == 0 {
+                return Err(DbError::Query("Provider not found".to_string()));
+            }
+
+            Self::get_with_conn(conn, id)?
+                .ok_or_else(|| DbError::Query("Provider not found".to_string()))
+        })
+    }
+
     /// Set the API key for a provider.
     pub fn set_api_key(provider_id: &str, api_key: &str) -> DbResult<()> {
         connection::with_connection(|conn| {
@@ -328,3 +606,322 @@ impl ProvidersRepository {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_list_types_in_use_normalizes_legacy_types() {
+        let conn = connection::open_test_connection();
+        for (id, provider_type) in [
+            ("p1", "openai"),
+            ("p2", "ark"),
+            ("p3", "volcengine"),
+            ("p4", "mystery-llm"),
+            ("p5", "OpenAI"),
+        ] {
+            conn.execute(
+                "INSERT INTO providers (id, name, provider_type, model, created_at, updated_at)
+                 VALUES (?1, ?1, ?2, 'm', 0, 0)",
+                rusqlite::params![id, provider_type],
+            )
+            .unwrap();
+        }
+
+        let types = ProvidersRepository::list_types_in_use_with_conn(&conn).unwrap();
+        assert_eq!(types, vec!["custom", "openai", "volcengine"]);
+    }
+
+    #[test]
+    fn test_list_by_usage_orders_by_reply_count_then_recency() {
+        let conn = connection::open_test_connection();
+        for (id, order) in [
+            ("idle", 0),
+            ("busy", 1),
+            ("recent", 2),
+            ("old", 3),
+            ("unused", 4),
+        ] {
+            conn.execute(
+                "INSERT INTO providers (id, name, provider_type, model, display_order, created_at, updated_at)
+                 VALUES (?1, ?1, 'openai', 'm', ?2, 0, 0)",
+                rusqlite::params![id, order],
+            )
+            .unwrap();
+        }
+        conn.execute(
+            "INSERT INTO chat_sessions (id, title, provider_ids_json, created_at, updated_at)
+             VALUES ('s1', 'Usage', '[]', 0, 0)",
+            [],
+        )
+        .unwrap();
+        // busy: 3 replies; recent and old: 1 each, recent later; idle: only a
+        // user message, which does not count.
+        for (n, (provider_id, role, created_at)) in [
+            ("busy", "assistant", 1),
+            ("busy", "assistant", 2),
+            ("busy", "assistant", 3),
+            ("old", "assistant", 4),
+            ("recent", "assistant", 9),
+            ("idle", "user", 10),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            conn.execute(
+                "INSERT INTO chat_messages
+                 (id, session_id, provider_id, role, content, status, created_at, updated_at)
+                 VALUES (?1, 's1', ?2, ?3, 'x', 'done', ?4, ?4)",
+                rusqlite::params![format!("m{n}"), provider_id, role, created_at],
+            )
+            .unwrap();
+        }
+
+        let ids = |views: Vec<ProviderView>| views.into_iter().map(|v| v.id).collect::<Vec<_>>();
+        assert_eq!(
+            ids(ProvidersRepository::list_ordered_with_conn(&conn, true).unwrap()),
+            vec!["busy", "recent", "old", "idle", "unused"]
+        );
+        assert_eq!(
+            ids(ProvidersRepository::list_ordered_with_conn(&conn, false).unwrap()),
+            vec!["idle", "busy", "recent", "old", "unused"]
+        );
+    }
+
+    #[test]
+    fn test_query_readiness_states() {
+        let conn = connection::open_test_connection();
+
+        let readiness = ProvidersRepository::query_readiness_with_conn(&conn).unwrap();
+        assert!(!readiness.has_active_provider);
+        assert_eq!(
+            readiness.missing_reason.as_deref(),
+            Some("no_active_provider")
+        );
+
+        conn.execute(
+            "INSERT INTO providers (id, name, provider_type, model, is_active, api_key, created_at, updated_at)
+             VALUES ('p1', 'Work OpenAI', 'openai', 'm', 1, '  ', 0, 0)",
+            [],
+        )
+        .unwrap();
+        let readiness = ProvidersRepository::query_readiness_with_conn(&conn).unwrap();
+        assert!(readiness.has_active_provider);
+        assert!(!readiness.has_api_key);
+        assert_eq!(readiness.provider_name.as_deref(), Some("Work OpenAI"));
+        assert_eq!(readiness.missing_reason.as_deref(), Some("missing_api_key"));
+
+        conn.execute(
+            "UPDATE providers SET api_key = 'sk-test' WHERE id = 'p1'",
+            [],
+        )
+        .unwrap();
+        let readiness = ProvidersRepository::query_readiness_with_conn(&conn).unwrap();
+        assert_eq!(
+            readiness,
+            QueryReadiness {
+                has_active_provider: true,
+                has_api_key: true,
+                provider_name: Some("Work OpenAI".to_string()),
+                apps_only: false,
+                missing_reason: None,
+            }
+        );
+    }
+
+    fn create_request(base_url: Option<&str>, model: &str) -> CreateProviderRequest {
+        CreateProviderRequest {
+            name: "OpenAI".to_string(),
+            provider_type: ProviderType::OpenAI,
+            base_url: base_url.map(str::to_string),
+            model: Some(model.to_string()),
+            api_key: None,
+            use_responses_api: None,
+            context_window: None,
+            requests_per_minute: None,
+            role: None,
+            allow_duplicate: None,
+        }
+    }
+
+    #[test]
+    fn test_create_returns_existing_duplicate_provider() {
+        let conn = connection::open_test_connection();
+        let first = ProvidersRepository::create_with_conn(&conn, create_request(None, "gpt-4o"))
+            .unwrap()
+            .provider;
+
+        // Same type and model; the explicit default URL differs only by a trailing slash.
+        let again = ProvidersRepository::create_with_conn(
+            &conn,
+            create_request(Some("https://api.openai.com/v1/"), "gpt-4o"),
+        )
+        .unwrap();
+        assert!(again.existing);
+        assert_eq!(again.provider.id, first.id);
+
+        let other_model =
+            ProvidersRepository::create_with_conn(&conn, create_request(None, "gpt-4o-mini"))
+                .unwrap();
+        assert!(!other_model.existing);
+        assert_ne!(other_model.provider.id, first.id);
+
+        let other_url = ProvidersRepository::create_with_conn(
+            &conn,
+            create_request(Some("https://gateway.example.com/v1"), "gpt-4o"),
+        )
+        .unwrap();
+        assert_ne!(other_url.provider.id, first.id);
+
+        let forced = ProvidersRepository::create_with_conn(
+            &conn,
+            CreateProviderRequest {
+                allow_duplicate: Some(true),
+                ..create_request(None, "gpt-4o")
+            },
+        )
+        .unwrap();
+        assert_ne!(forced.provider.id, first.id);
+
+        let count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM providers", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 4);
+    }
+
+    #[test]
+    fn test_duplicate_create_keeps_supplied_api_key() {
+        let conn = connection::open_test_connection();
+        let first = ProvidersRepository::create_with_conn(&conn, create_request(None, "gpt-4o"))
+            .unwrap()
+            .provider;
+        let api_key = |id: &str| -> Option<String> {
+            conn.query_row("SELECT api_key FROM providers WHERE id = ?1", [id], |row| {
+                row.get(0)
+            })
+            .unwrap()
+        };
+        assert_eq!(api_key(&first.id), None);
+
+        let again = ProvidersRepository::create_with_conn(
+            &conn,
+            CreateProviderRequest {
+                api_key: Some(" sk-new ".to_string()),
+                ..create_request(None, "gpt-4o")
+            },
+        )
+        .unwrap();
+        assert!(again.existing);
+        assert_eq!(again.provider.id, first.id);
+        assert_eq!(api_key(&first.id).as_deref(), Some("sk-new"));
+
+        // A blank key does not clear the one already saved.
+        ProvidersRepository::create_with_conn(
+            &conn,
+            CreateProviderRequest {
+                api_key: Some("  ".to_string()),
+                ..create_request(None, "gpt-4o")
+            },
+        )
+        .unwrap();
+        assert_eq!(api_key(&first.id).as_deref(), Some("sk-new"));
+    }
+
+    #[test]
+    fn test_pick_for_role_prefers_matching_providers() {
+        let conn = connection::open_test_connection();
+        let insert = |id: &str, order: i32, role: &str, is_active: i32, api_key: &str| {
+            conn.execute(
+                "INSERT INTO providers (id, name, provider_type, model, is_active, display_order, api_key, role, created_at, updated_at)
+                 VALUES (?1, ?1, 'openai', 'm', ?2, ?3, ?4, ?5, 0, 0)",
+                rusqlite::params![id, is_active, order, api_key, role],
+            )
+            .unwrap();
+        };
+        let pick = |role| {
+            ProvidersRepository::pick_for_role_with_conn(&conn, role)
+                .unwrap()
+                .map(|(provider, _)| provider.id)
+        };
+
+        assert_eq!(pick(ProviderRole::Chat), None);
+
+        insert("main", 0, "both", 1, "sk-main");
+        insert("cheap", 1, "utility", 1, "sk-cheap");
+        insert("keyless", 2, "chat", 1, " ");
+        insert("disabled", 3, "chat", 0, "sk-disabled");
+        assert_eq!(pick(ProviderRole::Chat).as_deref(), Some("main"));
+        assert_eq!(pick(ProviderRole::Utility).as_deref(), Some("cheap"));
+
+        insert("chatty", 4, "chat", 1, "sk-chat");
+        assert_eq!(pick(ProviderRole::Chat).as_deref(), Some("chatty"));
+
+        // With only a chat provider left, utility work still has somewhere to go.
+        conn.execute("DELETE FROM providers WHERE id IN ('main', 'cheap')", [])
+            .unwrap();
+        assert_eq!(pick(ProviderRole::Utility).as_deref(), Some("chatty"));
+
+        let stored = ProvidersRepository::get_with_conn(&conn, "chatty")
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.role, ProviderRole::Chat);
+    }
+
+    #[test]
+    fn test_set_active_by_type_counts_changed_providers() {
+        let conn = connection::open_test_connection();
+        for (id, provider_type, is_active) in [
+            ("p1", "volcengine", 1),
+            ("p2", "ark", 1),
+            ("p3", "volcengine", 0),
+            ("p4", "openai", 1),
+        ] {
+            conn.execute(
+                "INSERT INTO providers (id, name, provider_type, model, is_active, created_at, updated_at)
+                 VALUES (?1, ?1, ?2, 'm', ?3, 0, 0)",
+                rusqlite::params![id, provider_type, is_active],
+            )
+            .unwrap();
+        }
+        let active = || -> Vec<String> {
+            let mut stmt = conn
+                .prepare("SELECT id FROM providers WHERE is_active = 1 ORDER BY id")
+                .unwrap();
+            stmt.query_map([], |row| row.get(0))
+                .unwrap()
+                .map(Result::unwrap)
+                .collect()
+        };
+
+        let changed = ProvidersRepository::set_active_by_type_with_conn(
+            &conn,
+            ProviderType::Volcengine,
+            false,
+        )
+        .unwrap();
+        assert_eq!(changed, 2);
+        assert_eq!(active(), vec!["p4"]);
+
+        // Already-disabled providers are not counted again.
+        assert_eq!(
+            ProvidersRepository::set_active_by_type_with_conn(
+                &conn,
+                ProviderType::Volcengine,
+                false
+            )
+            .unwrap(),
+            0
+        );
+        assert_eq!(
+            ProvidersRepository::set_active_by_type_with_conn(
+                &conn,
+                ProviderType::Volcengine,
+                true
+            )
+            .unwrap(),
+            3
+        );
+        assert_eq!(active(), vec!["p1", "p2", "p3", "p4"]);
+    }
+}
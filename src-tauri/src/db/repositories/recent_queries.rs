@@ -0,0 +1,130 @@
+use crate::db::connection;
+use crate::db::error::DbResult;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn now_unix_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+/// Oldest queries beyond this many are evicted on every insert.
+pub const MAX_RECENT_QUERIES: usize = 50;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecentQueryRecord {
+    pub id: i64,
+    pub text: String,
+    pub created_at: i64,
+}
+
+pub struct RecentQueriesRepository;
+
+impl RecentQueriesRepository {
+    /// Remember a launcher query. Blank queries and repeats of the latest
+    /// entry are ignored.
+    pub fn record(text: &str) -> DbResult<()> {
+        connection::with_connection(|conn| Self::record_with_conn(conn, text, MAX_RECENT_QUERIES))
+    }
+
+    fn record_with_conn(conn: &Connection, text: &str, cap: usize) -> DbResult<()> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Ok(());
+        }
+
+        let latest = conn.query_row(
+            "SELECT text FROM recent_queries ORDER BY id DESC LIMIT 1",
+            [],
+            |row| row.get::<_, String>(0),
+        );
+        match latest {
+            Ok(latest) if latest == text => return Ok(()),
+            Ok(_) | Err(rusqlite::Error::QueryReturnedNoRows) => {}
+            Err(e) => return Err(e.into()),
+        }
+
+        conn.execute(
+            "INSERT INTO recent_queries (text, created_at) VALUES (?1, ?2)",
+            rusqlite::params![text, now_unix_ms()],
+        )?;
+        conn.execute(
+            "DELETE FROM recent_queries WHERE id NOT IN (
+                SELECT id FROM recent_queries ORDER BY id DESC LIMIT ?1
+             )",
+            [cap as i64],
+        )?;
+        Ok(())
+    }
+
+    /// Most recent queries first.
+    pub fn list(limit: usize) -> DbResult<Vec<RecentQueryRecord>> {
+        connection::with_connection(|conn| Self::list_with_conn(conn, limit))
+    }
+
+    fn list_with_conn(conn: &Connection, limit: usize) -> DbResult<Vec<RecentQueryRecord>> {
+        let mut stmt = conn
+            .prepare("SELECT id, text, created_at FROM recent_queries ORDER BY id DESC LIMIT ?1")?;
+        let rows = stmt.query_map([limit as i64], |row| {
+            Ok(RecentQueryRecord {
+                id: row.get(0)?,
+                text: row.get(1)?,
+                created_at: row.get(2)?,
+            })
+        })?;
+
+        let mut result = Vec::new();
+        for row in rows {
+            result.push(row?);
+        }
+        Ok(result)
+    }
+
+    pub fn clear() -> DbResult<()> {
+        connection::with_connection(|conn| {
+            conn.execute("DELETE FROM recent_queries", [])?;
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(conn: &Connection) -> Vec<String> {
+        RecentQueriesRepository::list_with_conn(conn, 100)
+            .unwrap()
+            .into_iter()
+            .map(|q| q.text)
+            .collect()
+    }
+
+    #[test]
+    fn test_record_evicts_oldest_beyond_cap() {
+        let conn = connection::open_test_connection();
+        for i in 0..5 {
+            RecentQueriesRepository::record_with_conn(&conn, &format!("q{i}"), 3).unwrap();
+        }
+
+        assert_eq!(texts(&conn), vec!["q4", "q3", "q2"]);
+        assert_eq!(
+            RecentQueriesRepository::list_with_conn(&conn, 1).unwrap()[0].text,
+            "q4"
+        );
+    }
+
+    #[test]
+    fn test_record_skips_consecutive_duplicates() {
+        let conn = connection::open_test_connection();
+        for text in ["rust", " rust ", "rust", "", "tauri", "rust"] {
+            RecentQueriesRepository::record_with_conn(&conn, text, MAX_RECENT_QUERIES).unwrap();
+        }
+
+        // Only a repeat of the latest entry is dropped; older repeats are kept.
+        assert_eq!(texts(&conn), vec!["rust", "tauri", "rust"]);
+    }
+}
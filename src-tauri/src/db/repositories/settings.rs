@@ -31,6 +31,19 @@ impl SettingsRepository {
         })
     }
 
+    /// Every stored setting as `(key, value)`, ordered by key.
+    pub fn list_all() -> DbResult<Vec<(String, String)>> {
+        connection::with_connection(|conn| {
+            let mut stmt = conn.prepare("SELECT key, value FROM settings ORDER BY key ASC")?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            let mut result = Vec::new();
+            for row in rows {
+                result.push(row?);
+            }
+            Ok(result)
+        })
+    }
+
     /// Set a setting value.
     pub fn set(key: &str, value: &str) -> DbResult<()> {
         connection::with_connection(|conn| {
@@ -1,4 +1,5 @@
 use serde::Serialize;
+use std::collections::HashMap;
 use std::sync::Mutex;
 use tauri::{
     menu::{Menu, MenuItem},
@@ -9,20 +10,33 @@ use tauri_plugin_global_shortcut::{GlobalShortcutExt, ShortcutState};
 
 mod apps;
 mod db;
+mod logging;
 mod provider;
+mod settings_schema;
+mod support;
 use apps::{
-    get_app_icon, get_suggestions, initialize_cache, launch_app, refresh_app_cache, search_apps,
+    diagnose_missing_apps, fuzzy_rank, get_app_accent_color, get_app_icon, get_app_launch_info,
+    get_launch_timeline, get_suggestions, import_usage_stats, initialize_cache, launch_app,
+    list_apps_by_publisher, list_icon_failures, prune_icon_data, refresh_app_cache, search_apps,
+    search_apps_grouped,
 };
 use db::{
-    ChatMessageRecord, ChatMessagesRepository, ChatSessionColumnRecord,
-    ChatSessionColumnsRepository, ChatSessionRecord, ChatSessionsRepository, MessageSearchResult,
-    ProvidersRepository, SettingsRepository,
+    ChatMessageRecord, ChatMessageWithSession, ChatMessagesRepository, ChatSessionColumnRecord,
+    ChatSessionColumnsRepository, ChatSessionRecord, ChatSessionsRepository, ChatStats,
+    ColumnPresetRecord, ColumnPresetsRepository, ColumnWithLatest, CompareColumnSetup, DiffSegment,
+    FtsStats, MessageRequestRecord, MessageRequestsRepository, MessageSearchResult,
+    PromptTemplateRecord, PromptTemplatesRepository, ProvidersRepository, RecentQueriesRepository,
+    RecentQueryRecord, RepairReport, ResubmitSetup, SessionStorage, SettingsRepository,
 };
 use provider::{
-    query_provider_once, query_stream, query_stream_provider,
-    test_provider_connection as run_provider_connection_test, ConnectionTestResult,
-    CreateProviderRequest, Provider, ProviderView, UpdateProviderRequest,
+    benchmark_provider as run_provider_benchmark, query_provider_once, query_stream,
+    query_stream_provider, query_utility_once, replay_last_failed_request,
+    test_api_key as run_api_key_test, test_provider_connection as run_provider_connection_test,
+    test_provider_streaming as run_provider_streaming_test, ConnectionTestResult,
+    CreateProviderRequest, CreateProviderResult, EffectiveConfig, Provider, ProviderChatMessage,
+    ProviderView, QueryReadiness, UpdateProviderRequest,
 };
+use support::generate_support_bundle;
 
 const SETTING_LAUNCH_ON_STARTUP: &str = "launch_on_startup";
 const SETTING_HIDE_ON_BLUR: &str = "hide_on_blur";
@@ -30,11 +44,32 @@ const SETTING_HOTKEY_TOGGLE_SEARCH: &str = "hotkey_toggle_search";
 const SETTING_HOTKEY_OPEN_SETTINGS: &str = "hotkey_open_settings";
 const SETTING_THEME: &str = "theme";
 const SETTING_DEFAULT_SYSTEM_PROMPT: &str = "default_system_prompt";
+const SETTING_SHOW_CONSOLE_WINDOW: &str = "show_console_window";
+const SETTING_REQUEST_TIMEOUT_SECS: &str = "request_timeout_secs";
+const SETTING_LAUNCHER_MODE: &str = "launcher_mode";
+const SETTING_DB_BUSY_TIMEOUT_MS: &str = "db_busy_timeout_ms";
+const SETTING_WAL_CHECKPOINT_INTERVAL_MINUTES: &str = "wal_checkpoint_interval_minutes";
+const SETTING_SCAN_REGISTRY: &str = "scan_registry";
+const SETTING_SCAN_START_MENU: &str = "scan_start_menu";
+const SETTING_ALWAYS_ON_TOP: &str = "always_on_top";
+const SETTING_PROVIDER_DEBUG_RECORD: &str = "provider_debug_record";
+const SETTING_MAX_MESSAGES_PER_SESSION: &str = "max_messages_per_session";
+const SETTING_MAX_PROMPT_CHARS: &str = "max_prompt_chars";
+const SETTING_PROMPT_OVERFLOW: &str = "prompt_overflow";
+const SETTING_PREWARM_PROVIDERS: &str = "prewarm_providers";
+const SETTING_VERBOSE_ERRORS: &str = "verbose_errors";
+const SETTING_PROVIDER_FAILOVER: &str = "provider_failover";
+const SETTING_NAME_CLEANUP: &str = "name_cleanup";
+const SETTING_STREAM_BATCH_MS: &str = "stream_batch_ms";
+const SETTING_STORE_MESSAGE_REQUESTS: &str = "store_message_requests";
+const SETTING_MAX_STREAM_DURATION_SECS: &str = "max_stream_duration_secs";
 const AUTOSTART_RUN_KEY: &str = "SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Run";
 const AUTOSTART_VALUE_NAME: &str = "AIQuickSearch";
 const DEFAULT_HOTKEY_TOGGLE_SEARCH: &str = "Alt + Space";
 const DEFAULT_HOTKEY_OPEN_SETTINGS: &str = "Ctrl + ,";
 const DEFAULT_THEME: &str = "system";
+const LAUNCHER_MODE_APPS_ONLY: &str = "apps_only";
+const LAUNCHER_MODE_HYBRID: &str = "hybrid";
 
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -45,6 +80,11 @@ struct AppSettingsPayload {
     hotkey_open_settings: String,
     theme: String,
     default_system_prompt: String,
+    show_console_window: bool,
+    launcher_mode: String,
+    scan_registry: bool,
+    scan_start_menu: bool,
+    always_on_top: bool,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -53,37 +93,43 @@ struct SettingUpdatedPayload {
     value: String,
 }
 
-#[derive(Debug)]
+/// Shortcuts that are actually registered; empty until registration succeeds.
+#[derive(Debug, Default)]
 struct HotkeyState {
-    toggle_search: Mutex<String>,
-    open_settings: Mutex<String>,
+    toggle_search: Mutex<Option<String>>,
+    open_settings: Mutex<Option<String>>,
 }
 
 impl HotkeyState {
-    fn new(toggle_search: String, open_settings: String) -> Self {
-        Self {
-            toggle_search: Mutex::new(toggle_search),
-            open_settings: Mutex::new(open_settings),
-        }
-    }
-
     fn current_toggle_search(&self) -> Option<String> {
-        self.toggle_search.lock().ok().map(|v| v.clone())
+        self.toggle_search.lock().ok().and_then(|v| v.clone())
     }
 
     fn current_open_settings(&self) -> Option<String> {
-        self.open_settings.lock().ok().map(|v| v.clone())
+        self.open_settings.lock().ok().and_then(|v| v.clone())
+    }
+
+    /// Shortcuts currently applied, keyed by their setting name.
+    fn registered(&self) -> HashMap<String, String> {
+        let mut hotkeys = HashMap::new();
+        if let Some(shortcut) = self.current_toggle_search() {
+            hotkeys.insert(SETTING_HOTKEY_TOGGLE_SEARCH.to_string(), shortcut);
+        }
+        if let Some(shortcut) = self.current_open_settings() {
+            hotkeys.insert(SETTING_HOTKEY_OPEN_SETTINGS.to_string(), shortcut);
+        }
+        hotkeys
     }
 
     fn set_toggle_search(&self, shortcut: String) {
         if let Ok(mut guard) = self.toggle_search.lock() {
-            *guard = shortcut;
+            *guard = Some(shortcut);
         }
     }
 
     fn set_open_settings(&self, shortcut: String) {
         if let Ok(mut guard) = self.open_settings.lock() {
-            *guard = shortcut;
+            *guard = Some(shortcut);
         }
     }
 }
@@ -96,6 +142,25 @@ fn parse_bool_setting(raw: Option<String>, default: bool) -> bool {
     }
 }
 
+/// Canonical launcher mode; anything unrecognised falls back to hybrid (apps + chat).
+fn parse_launcher_mode(raw: Option<&str>) -> &'static str {
+    match raw.map(|v| v.trim().to_ascii_lowercase()).as_deref() {
+        Some("apps_only" | "apps-only" | "apps") => LAUNCHER_MODE_APPS_ONLY,
+        _ => LAUNCHER_MODE_HYBRID,
+    }
+}
+
+/// Whether the launcher is configured without a chat surface.
+fn is_apps_only_mode() -> Result<bool, String> {
+    let raw = SettingsRepository::get(SETTING_LAUNCHER_MODE).map_err(|e| e.to_string())?;
+    Ok(parse_launcher_mode(raw.as_deref()) == LAUNCHER_MODE_APPS_ONLY)
+}
+
+/// The launcher stays below other windows unless `always_on_top` is explicitly enabled.
+fn parse_always_on_top(raw: Option<String>) -> bool {
+    parse_bool_setting(raw, false)
+}
+
 fn bool_to_setting(value: bool) -> &'static str {
     if value {
         "1"
@@ -138,8 +203,25 @@ fn set_launch_on_startup_enabled(enabled: bool) -> Result<(), String> {
     Ok(())
 }
 
+/// Map the full-width forms CJK input methods produce (`＋`, `，`, `；`,
+/// full-width letters and digits, ideographic space) to the ASCII the
+/// shortcut parser expects.
+fn normalize_hotkey_chars(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            '\u{3000}' | '\u{00A0}' => ' ',
+            '、' => ',',
+            '\u{FF01}'..='\u{FF5E}' => char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+            _ => c,
+        })
+        .collect()
+}
+
 fn normalize_hotkey_setting(raw: Option<String>, fallback: &str) -> String {
-    let value = raw.unwrap_or_default().trim().replace('，', ",");
+    let value = normalize_hotkey_chars(&raw.unwrap_or_default())
+        .trim()
+        .to_string();
     if value.is_empty() {
         fallback.to_string()
     } else {
@@ -147,6 +229,23 @@ fn normalize_hotkey_setting(raw: Option<String>, fallback: &str) -> String {
     }
 }
 
+fn parse_hotkey(raw: &str) -> Result<String, String> {
+    let normalized = normalize_hotkey_chars(raw).trim().to_string();
+    if normalized.is_empty() {
+        return Err("Hotkey is empty".to_string());
+    }
+    normalized
+        .parse::<tauri_plugin_global_shortcut::Shortcut>()
+        .map_err(|e| format!("Invalid hotkey '{normalized}': {e}"))?;
+    Ok(normalized)
+}
+
+/// Check a hotkey before saving it, returning the normalized form that would be registered.
+#[tauri::command]
+fn validate_hotkey(hotkey: String) -> Result<String, String> {
+    parse_hotkey(&hotkey)
+}
+
 fn position_main_window(window: &tauri::WebviewWindow) {
     if let Ok(Some(monitor)) = window.current_monitor() {
         let scale = monitor.scale_factor().max(1.0);
@@ -161,9 +260,7 @@ fn position_main_window(window: &tauri::WebviewWindow) {
         let x = ((monitor_width - window_width) / 2.0).floor();
         let y = (monitor_height * 0.2).floor();
 
-        let _ = window.set_position(tauri::Position::Logical(tauri::LogicalPosition::new(
-            x, y,
-        )));
+        let _ = window.set_position(tauri::Position::Logical(tauri::LogicalPosition::new(x, y)));
     }
 }
 
@@ -213,24 +310,19 @@ fn register_open_settings_shortcut(app: &tauri::AppHandle, shortcut: &str) -> Re
         .map_err(|e| e.to_string())
 }
 
+/// Register `shortcut`, logging why it failed. The error is still returned,
+/// including when another app holds the shortcut, so startup can fall back.
 fn register_hotkey_or_log(
-    app: &tauri::AppHandle,
     shortcut: &str,
-    register: fn(&tauri::AppHandle, &str) -> Result<(), String>,
+    register: impl FnOnce(&str) -> Result<(), String>,
 ) -> Result<(), String> {
-    if let Err(err) = register(app, shortcut) {
+    register(shortcut).inspect_err(|err| {
         if err.contains("HotKey already registered") {
-            eprintln!(
-                "Global shortcut '{}' is already in use. Continuing without it.",
-                shortcut
-            );
-            Ok(())
+            logging::log_nonfatal(format!("Global shortcut '{shortcut}' is already in use."));
         } else {
-            Err(err)
+            logging::log_nonfatal(format!("Failed to register '{shortcut}': {err}"));
         }
-    } else {
-        Ok(())
-    }
+    })
 }
 
 fn load_hotkeys_from_settings() -> Result<(String, String), String> {
@@ -245,6 +337,28 @@ fn load_hotkeys_from_settings() -> Result<(String, String), String> {
     Ok((toggle, open_settings))
 }
 
+/// Register `preferred` at startup, falling back to `fallback` when the OS
+/// refuses it. Only a shortcut that registered is recorded in `state`;
+/// returns the fallback when it was used. The fallback lasts for this
+/// session only, so the saved choice is tried again on the next launch.
+fn register_startup_hotkey(
+    state: &HotkeyState,
+    set_state: fn(&HotkeyState, String),
+    preferred: &str,
+    fallback: &str,
+    register: impl Fn(&str) -> Result<(), String>,
+) -> Option<String> {
+    if register(preferred).is_ok() {
+        set_state(state, preferred.to_string());
+        return None;
+    }
+    if preferred == fallback || register(fallback).is_err() {
+        return None;
+    }
+    set_state(state, fallback.to_string());
+    Some(fallback.to_string())
+}
+
 fn apply_hotkey_change(
     app: &tauri::AppHandle,
     state: &HotkeyState,
@@ -273,18 +387,23 @@ fn apply_hotkey_change(
     };
 
     let normalized = normalize_hotkey_setting(Some(raw_value.to_string()), fallback);
-    let old = current.unwrap_or_else(|| fallback.to_string());
-    if old == normalized {
+    parse_hotkey(&normalized)?;
+    // With nothing registered yet there is nothing to swap out.
+    if current.as_deref() == Some(normalized.as_str()) {
         return Ok(normalized);
     }
 
-    if app.global_shortcut().is_registered(old.as_str()) {
-        let _ = app.global_shortcut().unregister(old.as_str());
+    if let Some(old) = &current {
+        if app.global_shortcut().is_registered(old.as_str()) {
+            let _ = app.global_shortcut().unregister(old.as_str());
+        }
     }
 
     if let Err(err) = register(app, normalized.as_str()) {
-        if !app.global_shortcut().is_registered(old.as_str()) {
-            let _ = register(app, old.as_str());
+        if let Some(old) = &current {
+            if !app.global_shortcut().is_registered(old.as_str()) {
+                let _ = register(app, old.as_str());
+            }
         }
         return Err(err);
     }
@@ -294,33 +413,46 @@ fn apply_hotkey_change(
 }
 
 fn ensure_default_app_settings() -> Result<(), String> {
-    SettingsRepository::set_if_absent(SETTING_HIDE_ON_BLUR, bool_to_setting(true))
-        .map_err(|e| e.to_string())?;
-    SettingsRepository::set_if_absent(SETTING_HOTKEY_TOGGLE_SEARCH, DEFAULT_HOTKEY_TOGGLE_SEARCH)
-        .map_err(|e| e.to_string())?;
-    SettingsRepository::set_if_absent(SETTING_HOTKEY_OPEN_SETTINGS, DEFAULT_HOTKEY_OPEN_SETTINGS)
-        .map_err(|e| e.to_string())?;
-    SettingsRepository::set_if_absent(SETTING_THEME, DEFAULT_THEME).map_err(|e| e.to_string())?;
-    SettingsRepository::set_if_absent(SETTING_DEFAULT_SYSTEM_PROMPT, "")
-        .map_err(|e| e.to_string())?;
+    seed_app_settings(
+        |key, value| SettingsRepository::set_if_absent(key, value).map_err(|e| e.to_string()),
+        |key| SettingsRepository::get(key).map_err(|e| e.to_string()),
+        |key, value| SettingsRepository::set(key, value).map_err(|e| e.to_string()),
+        sync_launch_on_startup,
+    )
+}
 
-    let launch_setting =
-        SettingsRepository::get(SETTING_LAUNCH_ON_STARTUP).map_err(|e| e.to_string())?;
-    match launch_setting {
+/// Apply a saved launch-on-startup choice to the OS autostart entry, or read
+/// the entry when nothing is saved yet, returning the effective value.
+fn sync_launch_on_startup(saved: Option<String>) -> Result<bool, String> {
+    match saved {
         Some(raw) => {
             let enabled = parse_bool_setting(Some(raw), false);
             set_launch_on_startup_enabled(enabled)?;
-            SettingsRepository::set(SETTING_LAUNCH_ON_STARTUP, bool_to_setting(enabled))
-                .map_err(|e| e.to_string())?;
-        }
-        None => {
-            let enabled = is_launch_on_startup_enabled()?;
-            SettingsRepository::set(SETTING_LAUNCH_ON_STARTUP, bool_to_setting(enabled))
-                .map_err(|e| e.to_string())?;
+            Ok(enabled)
         }
+        None => is_launch_on_startup_enabled(),
     }
+}
 
-    Ok(())
+/// Seed the schema defaults, then store `launch_on_startup` as synced with the OS.
+fn seed_app_settings(
+    set_if_absent: impl FnMut(&str, &str) -> Result<(), String>,
+    get: impl FnOnce(&str) -> Result<Option<String>, String>,
+    set: impl FnOnce(&str, &str) -> Result<(), String>,
+    sync_launch_on_startup: impl FnOnce(Option<String>) -> Result<bool, String>,
+) -> Result<(), String> {
+    settings_schema::seed_default_settings(set_if_absent)?;
+    let enabled = sync_launch_on_startup(get(SETTING_LAUNCH_ON_STARTUP)?)?;
+    set(SETTING_LAUNCH_ON_STARTUP, bool_to_setting(enabled))
+}
+
+/// Shortcuts actually registered with the OS, which differ from the saved
+/// settings when startup had to fall back to a default.
+#[tauri::command]
+fn get_registered_hotkeys(app: tauri::AppHandle) -> Result<HashMap<String, String>, String> {
+    app.try_state::<HotkeyState>()
+        .map(|state| state.registered())
+        .ok_or_else(|| "Hotkeys are not initialized yet".to_string())
 }
 
 #[tauri::command]
@@ -355,6 +487,27 @@ async fn get_app_settings(_app: tauri::AppHandle) -> Result<AppSettingsPayload,
         let default_system_prompt = SettingsRepository::get(SETTING_DEFAULT_SYSTEM_PROMPT)
             .map_err(|e| e.to_string())?
             .unwrap_or_default();
+        let show_console_window = parse_bool_setting(
+            SettingsRepository::get(SETTING_SHOW_CONSOLE_WINDOW).map_err(|e| e.to_string())?,
+            true,
+        );
+        let launcher_mode = parse_launcher_mode(
+            SettingsRepository::get(SETTING_LAUNCHER_MODE)
+                .map_err(|e| e.to_string())?
+                .as_deref(),
+        )
+        .to_string();
+        let scan_registry = parse_bool_setting(
+            SettingsRepository::get(SETTING_SCAN_REGISTRY).map_err(|e| e.to_string())?,
+            true,
+        );
+        let scan_start_menu = parse_bool_setting(
+            SettingsRepository::get(SETTING_SCAN_START_MENU).map_err(|e| e.to_string())?,
+            true,
+        );
+        let always_on_top = parse_always_on_top(
+            SettingsRepository::get(SETTING_ALWAYS_ON_TOP).map_err(|e| e.to_string())?,
+        );
 
         Ok(AppSettingsPayload {
             launch_on_startup,
@@ -363,6 +516,11 @@ async fn get_app_settings(_app: tauri::AppHandle) -> Result<AppSettingsPayload,
             hotkey_open_settings,
             theme,
             default_system_prompt,
+            show_console_window,
+            launcher_mode,
+            scan_registry,
+            scan_start_menu,
+            always_on_top,
         })
     })
     .await
@@ -386,6 +544,70 @@ async fn set_app_setting(
         let normalized = apply_hotkey_change(&app, &state, &key, &value)?;
         SettingsRepository::set(&key, &normalized).map_err(|e| e.to_string())?;
         normalized
+    } else if key == SETTING_LAUNCHER_MODE {
+        let normalized = parse_launcher_mode(Some(&value)).to_string();
+        SettingsRepository::set(&key, &normalized).map_err(|e| e.to_string())?;
+        normalized
+    } else if key == SETTING_ALWAYS_ON_TOP {
+        let enabled = parse_always_on_top(Some(value));
+        apply_always_on_top(&app, enabled)?;
+        let normalized = bool_to_setting(enabled).to_string();
+        SettingsRepository::set(&key, &normalized).map_err(|e| e.to_string())?;
+        normalized
+    } else if key == SETTING_SCAN_REGISTRY
+        || key == SETTING_SCAN_START_MENU
+        || key == SETTING_NAME_CLEANUP
+    {
+        let normalized = bool_to_setting(parse_bool_setting(Some(value), true)).to_string();
+        SettingsRepository::set(&key, &normalized).map_err(|e| e.to_string())?;
+        // Rescan in the background so the app list reflects the new settings.
+        tauri::async_runtime::spawn(async {
+            let _ = refresh_app_cache().await;
+        });
+        normalized
+    } else if key == SETTING_DB_BUSY_TIMEOUT_MS {
+        let normalized = db::parse_busy_timeout_ms(Some(&value))
+            .ok_or_else(|| "Busy timeout must be between 100 and 60000 ms".to_string())?
+            .to_string();
+        SettingsRepository::set(&key, &normalized).map_err(|e| e.to_string())?;
+        db::apply_busy_timeout().map_err(|e| e.to_string())?;
+        normalized
+    } else if key == SETTING_WAL_CHECKPOINT_INTERVAL_MINUTES {
+        let normalized = db::parse_wal_checkpoint_interval_minutes(Some(&value)).to_string();
+        SettingsRepository::set(&key, &normalized).map_err(|e| e.to_string())?;
+        normalized
+    } else if key == SETTING_MAX_MESSAGES_PER_SESSION {
+        let normalized = db::parse_max_messages_per_session(Some(&value)).to_string();
+        SettingsRepository::set(&key, &normalized).map_err(|e| e.to_string())?;
+        normalized
+    } else if key == SETTING_MAX_PROMPT_CHARS {
+        let normalized = provider::parse_max_prompt_chars(Some(&value)).to_string();
+        SettingsRepository::set(&key, &normalized).map_err(|e| e.to_string())?;
+        normalized
+    } else if key == SETTING_REQUEST_TIMEOUT_SECS {
+        let normalized = provider::parse_request_timeout_secs(Some(&value)).to_string();
+        SettingsRepository::set(&key, &normalized).map_err(|e| e.to_string())?;
+        normalized
+    } else if key == SETTING_STREAM_BATCH_MS {
+        let normalized = provider::parse_stream_batch_ms(Some(&value)).to_string();
+        SettingsRepository::set(&key, &normalized).map_err(|e| e.to_string())?;
+        normalized
+    } else if key == SETTING_MAX_STREAM_DURATION_SECS {
+        let normalized = provider::parse_max_stream_duration_secs(Some(&value)).to_string();
+        SettingsRepository::set(&key, &normalized).map_err(|e| e.to_string())?;
+        normalized
+    } else if key == SETTING_PROMPT_OVERFLOW {
+        let normalized = provider::PromptOverflow::parse(Some(&value))
+            .as_str()
+            .to_string();
+        SettingsRepository::set(&key, &normalized).map_err(|e| e.to_string())?;
+        normalized
+    } else if key == SETTING_VERBOSE_ERRORS {
+        let enabled = parse_bool_setting(Some(value), cfg!(debug_assertions));
+        logging::set_verbose_errors(enabled);
+        let normalized = bool_to_setting(enabled).to_string();
+        SettingsRepository::set(&key, &normalized).map_err(|e| e.to_string())?;
+        normalized
     } else if key == SETTING_DEFAULT_SYSTEM_PROMPT {
         let normalized = value.trim().to_string();
         SettingsRepository::set(&key, &normalized).map_err(|e| e.to_string())?;
@@ -407,6 +629,32 @@ async fn set_app_setting(
     Ok(normalized_value)
 }
 
+fn apply_always_on_top(app: &tauri::AppHandle, enabled: bool) -> Result<(), String> {
+    if let Some(window) = app.get_webview_window("main") {
+        window
+            .set_always_on_top(enabled)
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Pin the launcher above other windows and remember the choice.
+#[tauri::command]
+async fn set_always_on_top(enabled: bool, app: tauri::AppHandle) -> Result<bool, String> {
+    apply_always_on_top(&app, enabled)?;
+    SettingsRepository::set(SETTING_ALWAYS_ON_TOP, bool_to_setting(enabled))
+        .map_err(|e| e.to_string())?;
+    app.emit(
+        "app-settings-updated",
+        SettingUpdatedPayload {
+            key: SETTING_ALWAYS_ON_TOP.to_string(),
+            value: bool_to_setting(enabled).to_string(),
+        },
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(enabled)
+}
+
 // Legacy commands (kept for backwards compatibility)
 #[tauri::command]
 async fn set_config(
@@ -436,11 +684,128 @@ async fn list_providers(_app: tauri::AppHandle) -> Result<Vec<ProviderView>, Str
         .map_err(|e| e.to_string())
 }
 
+/// Providers with the most replies first; unused ones follow in display order.
+#[tauri::command]
+async fn list_providers_by_usage(_app: tauri::AppHandle) -> Result<Vec<ProviderView>, String> {
+    tauri::async_runtime::spawn_blocking(ProvidersRepository::list_by_usage)
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+/// Resolved base URL, endpoint and auth scheme for a provider, with the key redacted.
+#[tauri::command]
+async fn get_effective_provider_config(
+    id: String,
+    _app: tauri::AppHandle,
+) -> Result<EffectiveConfig, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let provider = ProvidersRepository::get(&id)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| "Provider not found".to_string())?;
+        let api_key = ProvidersRepository::get_api_key(&id).map_err(|e| e.to_string())?;
+        Ok(provider::effective_config(
+            &provider,
+            !api_key.trim().is_empty(),
+        ))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+async fn list_provider_types_in_use(_app: tauri::AppHandle) -> Result<Vec<String>, String> {
+    tauri::async_runtime::spawn_blocking(ProvidersRepository::list_types_in_use)
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+/// Suggest the enabled provider best suited to a prompt of `prompt_len` characters.
+#[tauri::command]
+async fn recommend_provider(
+    prompt_len: usize,
+    _app: tauri::AppHandle,
+) -> Result<Option<String>, String> {
+    let providers = tauri::async_runtime::spawn_blocking(ProvidersRepository::list_active)
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?;
+    Ok(provider::recommend_provider_for_prompt(
+        &providers, prompt_len,
+    ))
+}
+
+/// Advisory token count for a request, including the default system prompt
+/// when the history does not carry its own.
+#[tauri::command]
+async fn estimate_tokens(
+    provider_id: String,
+    prompt: String,
+    history: Option<Vec<ProviderChatMessage>>,
+    _app: tauri::AppHandle,
+) -> Result<usize, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let provider = ProvidersRepository::get(&provider_id)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| "Provider not found".to_string())?;
+
+        let mut messages = history.unwrap_or_default();
+        if !messages.iter().any(|m| m.role == "system") {
+            let system_prompt = SettingsRepository::get(SETTING_DEFAULT_SYSTEM_PROMPT)
+                .map_err(|e| e.to_string())?
+                .unwrap_or_default();
+            if !system_prompt.trim().is_empty() {
+                messages.insert(
+                    0,
+                    ProviderChatMessage {
+                        role: "system".to_string(),
+                        content: system_prompt,
+                    },
+                );
+            }
+        }
+        let prompt_already_sent = messages
+            .iter()
+            .rev()
+            .find(|m| m.role == "user")
+            .is_some_and(|m| m.content.trim() == prompt.trim());
+        if !prompt_already_sent && !prompt.trim().is_empty() {
+            messages.push(ProviderChatMessage {
+                role: "user".to_string(),
+                content: prompt,
+            });
+        }
+
+        Ok(provider::estimate_message_tokens(
+            provider.provider_type,
+            &messages,
+        ))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Let the launcher disable or explain the chat input before anything is sent.
+#[tauri::command]
+async fn get_query_readiness(_app: tauri::AppHandle) -> Result<QueryReadiness, String> {
+    tauri::async_runtime::spawn_blocking(|| {
+        let mut readiness = ProvidersRepository::query_readiness().map_err(|e| e.to_string())?;
+        if is_apps_only_mode()? {
+            readiness.apps_only = true;
+            readiness.missing_reason = Some(LAUNCHER_MODE_APPS_ONLY.to_string());
+        }
+        Ok(readiness)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
 #[tauri::command]
 async fn create_provider(
     req: CreateProviderRequest,
     _app: tauri::AppHandle,
-) -> Result<Provider, String> {
+) -> Result<CreateProviderResult, String> {
     tauri::async_runtime::spawn_blocking(move || ProvidersRepository::create(req))
         .await
         .map_err(|e| e.to_string())?
@@ -479,6 +844,41 @@ async fn set_active_provider(
         .map_err(|e| e.to_string())
 }
 
+/// Enable or disable all providers of one type; returns how many changed.
+#[tauri::command]
+async fn set_active_by_type(
+    provider_type: String,
+    is_active: bool,
+    _app: tauri::AppHandle,
+) -> Result<usize, String> {
+    let parsed = provider_type.trim().parse::<provider::ProviderType>()?;
+    // Unknown names parse as Custom; only accept that when asked for by name.
+    if parsed == provider::ProviderType::Custom
+        && !provider_type.trim().eq_ignore_ascii_case("custom")
+    {
+        return Err(format!("Unknown provider type: {provider_type}"));
+    }
+    tauri::async_runtime::spawn_blocking(move || {
+        ProvidersRepository::set_active_by_type(parsed, is_active)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+/// Cap concurrent requests to one provider; `None` or `0` makes it unbounded.
+#[tauri::command]
+async fn set_provider_concurrency(
+    id: String,
+    n: Option<i64>,
+    _app: tauri::AppHandle,
+) -> Result<Provider, String> {
+    tauri::async_runtime::spawn_blocking(move || ProvidersRepository::set_max_concurrency(&id, n))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 async fn get_provider_api_key(id: String, _app: tauri::AppHandle) -> Result<String, String> {
     tauri::async_runtime::spawn_blocking(move || ProvidersRepository::get_api_key(&id))
@@ -507,6 +907,104 @@ async fn test_provider_connection(
     run_provider_connection_test(id).await
 }
 
+/// Check that the provider actually streams, not just that it answers.
+#[tauri::command]
+async fn test_provider_streaming(
+    id: String,
+    _app: tauri::AppHandle,
+) -> Result<provider::StreamTestResult, String> {
+    run_provider_streaming_test(id).await
+}
+
+/// Report every configuration problem across the active providers at once.
+#[tauri::command]
+async fn validate_all_providers(
+    test_connection: Option<bool>,
+    _app: tauri::AppHandle,
+) -> Result<Vec<provider::ProviderValidation>, String> {
+    provider::validate_all_providers(test_connection.unwrap_or(false)).await
+}
+
+/// Time a streamed reply (first token, total, throughput) from one provider.
+#[tauri::command]
+async fn benchmark_provider(
+    id: String,
+    prompt: String,
+    _app: tauri::AppHandle,
+) -> Result<provider::BenchmarkResult, String> {
+    run_provider_benchmark(id, prompt).await
+}
+
+/// Test a key from the provider form before it is saved.
+#[tauri::command]
+async fn test_api_key(
+    provider_type: provider::ProviderType,
+    base_url: Option<String>,
+    model: Option<String>,
+    api_key: String,
+    _app: tauri::AppHandle,
+) -> Result<ConnectionTestResult, String> {
+    run_api_key_test(provider_type, base_url, model, api_key).await
+}
+
+/// Validate a base URL typed into the provider form and return it the way it
+/// will be stored, with a warning when it already names an endpoint.
+#[tauri::command]
+fn normalize_base_url(
+    provider_type: provider::ProviderType,
+    input: String,
+) -> Result<provider::NormalizedBaseUrl, String> {
+    provider::normalize_base_url(provider_type, &input)
+}
+
+/// Guess the provider type from a pasted base URL so the form can preselect it.
+#[tauri::command]
+fn infer_provider_type(base_url: String) -> Result<provider::ProviderType, String> {
+    provider::infer_provider_type(&base_url)
+}
+
+/// Stop every query stream in flight. Returns how many streams were signalled.
+#[tauri::command]
+fn cancel_all_queries(app: tauri::AppHandle) -> usize {
+    provider::cancel_all_queries(&app)
+}
+
+/// Stop the streams in one column and drop its partial reply, e.g. when the
+/// column is being removed. Other columns keep streaming.
+#[tauri::command]
+async fn abort_column(column_id: String, app: tauri::AppHandle) -> Result<(), String> {
+    // Delete before cancelling: a cancelled stream only updates its row, so
+    // it cannot bring the partial message back.
+    let id = column_id.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        ChatMessagesRepository::delete_streaming_in_column(&id)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())?;
+    provider::cancel_column_streams(&app, &column_id);
+    Ok(())
+}
+
+/// The redacted request that produced an assistant message, if it was
+/// stored while `store_message_requests` was on.
+#[tauri::command]
+async fn get_message_request(
+    message_id: String,
+    _app: tauri::AppHandle,
+) -> Result<Option<MessageRequestRecord>, String> {
+    tauri::async_runtime::spawn_blocking(move || MessageRequestsRepository::get(&message_id))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+/// Connection check against the active provider, for the status indicator.
+#[tauri::command]
+async fn ping_active_provider(_app: tauri::AppHandle) -> Result<ConnectionTestResult, String> {
+    provider::ping_active_provider().await
+}
+
 // Chat session persistence commands
 #[tauri::command]
 async fn list_chat_sessions(_app: tauri::AppHandle) -> Result<Vec<ChatSessionRecord>, String> {
@@ -521,16 +1019,30 @@ async fn create_chat_session(
     id: String,
     title: String,
     provider_ids: Vec<String>,
+    scratch: Option<bool>,
     _app: tauri::AppHandle,
 ) -> Result<ChatSessionRecord, String> {
     tauri::async_runtime::spawn_blocking(move || {
-        ChatSessionsRepository::create(&id, &title, &provider_ids)
+        if scratch.unwrap_or(false) {
+            ChatSessionsRepository::create_scratch(&id, &title, &provider_ids)
+        } else {
+            ChatSessionsRepository::create(&id, &title, &provider_ids)
+        }
     })
     .await
     .map_err(|e| e.to_string())?
     .map_err(|e| e.to_string())
 }
 
+/// Write a scratch session to the database before its first message.
+#[tauri::command]
+async fn persist_session(id: String, _app: tauri::AppHandle) -> Result<ChatSessionRecord, String> {
+    tauri::async_runtime::spawn_blocking(move || ChatSessionsRepository::persist_scratch(&id))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 async fn list_chat_session_columns(
     session_id: String,
@@ -544,6 +1056,20 @@ async fn list_chat_session_columns(
     .map_err(|e| e.to_string())
 }
 
+/// Each column of a session with its most recent message, in one call.
+#[tauri::command]
+async fn list_columns_with_latest(
+    session_id: String,
+    _app: tauri::AppHandle,
+) -> Result<Vec<ColumnWithLatest>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        ChatSessionColumnsRepository::list_with_latest(&session_id)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 async fn set_chat_session_column_provider(
     column_id: String,
@@ -585,6 +1111,23 @@ async fn save_chat_session_state(
     .map_err(|e| e.to_string())
 }
 
+/// Swap `old_provider_id` for `new_provider_id` across one session's columns
+/// and their messages; returns the number of columns switched.
+#[tauri::command]
+async fn replace_provider_in_session(
+    session_id: String,
+    old_provider_id: String,
+    new_provider_id: String,
+    _app: tauri::AppHandle,
+) -> Result<usize, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        ChatSessionsRepository::replace_provider(&session_id, &old_provider_id, &new_provider_id)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 async fn set_session_system_prompt(
     id: String,
@@ -599,12 +1142,48 @@ async fn set_session_system_prompt(
     .map_err(|e| e.to_string())
 }
 
+/// Mark an assistant reply as the session's canonical answer (`None` clears it).
 #[tauri::command]
-async fn delete_chat_session(id: String, _app: tauri::AppHandle) -> Result<(), String> {
-    tauri::async_runtime::spawn_blocking(move || ChatSessionsRepository::delete(&id))
-        .await
-        .map_err(|e| e.to_string())?
-        .map_err(|e| e.to_string())
+async fn set_canonical_answer(
+    session_id: String,
+    message_id: Option<String>,
+    _app: tauri::AppHandle,
+) -> Result<ChatSessionRecord, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        ChatSessionsRepository::set_canonical_answer(&session_id, message_id.as_deref())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn delete_chat_session(id: String, _app: tauri::AppHandle) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || ChatSessionsRepository::delete(&id))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+/// Add or remove one tag across several sessions (multi-select in the
+/// session list), returning how many sessions changed.
+#[tauri::command]
+async fn tag_sessions(session_ids: Vec<String>, tag: String, add: bool) -> Result<usize, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        ChatSessionsRepository::tag_sessions(&session_ids, &tag, add)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+/// Tags of every tagged session, keyed by session id.
+#[tauri::command]
+async fn list_session_tags() -> Result<HashMap<String, Vec<String>>, String> {
+    tauri::async_runtime::spawn_blocking(ChatSessionsRepository::tags_by_session)
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
 }
 
 /// P10: Pagination support. limit=0 returns all messages.
@@ -627,6 +1206,26 @@ async fn list_chat_messages(
     .map_err(|e| e.to_string())
 }
 
+/// One provider's messages from every session, newest first. limit=0 returns all.
+#[tauri::command]
+async fn list_provider_messages(
+    provider_id: String,
+    limit: Option<i64>,
+    offset: Option<i64>,
+    _app: tauri::AppHandle,
+) -> Result<Vec<ChatMessageWithSession>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        ChatMessagesRepository::list_by_provider(
+            &provider_id,
+            limit.unwrap_or(0),
+            offset.unwrap_or(0),
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 async fn count_chat_messages(session_id: String, _app: tauri::AppHandle) -> Result<i64, String> {
     tauri::async_runtime::spawn_blocking(move || {
@@ -668,6 +1267,33 @@ async fn create_chat_message(
     .map_err(|e| e.to_string())
 }
 
+/// The column history leading up to and including `message_id`, oldest first.
+#[tauri::command]
+async fn get_message_context(
+    message_id: String,
+    _app: tauri::AppHandle,
+) -> Result<Vec<ChatMessageRecord>, String> {
+    tauri::async_runtime::spawn_blocking(move || ChatMessagesRepository::context(&message_id))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+/// Word-level diff between two answers, for side-by-side comparison.
+#[tauri::command]
+async fn diff_messages(
+    message_id_a: String,
+    message_id_b: String,
+    _app: tauri::AppHandle,
+) -> Result<Vec<DiffSegment>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        ChatMessagesRepository::diff(&message_id_a, &message_id_b)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 async fn update_chat_message(
     id: String,
@@ -692,15 +1318,47 @@ async fn delete_chat_message(id: String, _app: tauri::AppHandle) -> Result<(), S
         .map_err(|e| e.to_string())
 }
 
+/// Import a pasted plain-text or Markdown transcript into `provider_id`'s
+/// column of the session, returning how many messages were added.
+#[tauri::command]
+async fn import_text_conversation(
+    session_id: String,
+    text: String,
+    provider_id: String,
+) -> Result<usize, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        ChatMessagesRepository::import_transcript(&session_id, provider_id.trim(), &text)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+/// Delete every message tied to a provider for privacy, keeping the provider.
+/// With `remove_empty`, columns and sessions left empty are removed as well.
+#[tauri::command]
+async fn purge_provider_history(
+    provider_id: String,
+    remove_empty: Option<bool>,
+) -> Result<usize, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        ChatMessagesRepository::purge_provider(provider_id.trim(), remove_empty.unwrap_or(false))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
 /// P13: Full-text search across all messages.
 #[tauri::command]
 async fn search_chat_messages(
     query: String,
     limit: Option<i64>,
+    dedup: Option<bool>,
     _app: tauri::AppHandle,
 ) -> Result<Vec<MessageSearchResult>, String> {
     tauri::async_runtime::spawn_blocking(move || {
-        ChatMessagesRepository::search(&query, limit.unwrap_or(20))
+        ChatMessagesRepository::search(&query, limit.unwrap_or(20), dedup.unwrap_or(false))
     })
     .await
     .map_err(|e| e.to_string())?
@@ -721,6 +1379,412 @@ async fn export_session_messages(
     .map_err(|e| e.to_string())
 }
 
+/// Export a session (optionally a single column) as an OpenAI chat request body.
+#[tauri::command]
+async fn export_session_as_openai(
+    session_id: String,
+    column_id: Option<String>,
+    _app: tauri::AppHandle,
+) -> Result<serde_json::Value, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        ChatMessagesRepository::export_openai(&session_id, column_id.as_deref())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+/// Aggregate session and message counts for the usage dashboard.
+#[tauri::command]
+async fn get_chat_stats(_app: tauri::AppHandle) -> Result<ChatStats, String> {
+    tauri::async_runtime::spawn_blocking(ChatSessionsRepository::stats)
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+/// Message count and content size of one session, for the storage view.
+#[tauri::command]
+async fn get_session_storage(session_id: String) -> Result<SessionStorage, String> {
+    tauri::async_runtime::spawn_blocking(move || ChatSessionsRepository::storage(&session_id))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+/// Sessions taking the most space, heaviest first.
+#[tauri::command]
+async fn list_heaviest_sessions(limit: Option<usize>) -> Result<Vec<SessionStorage>, String> {
+    let limit = limit.unwrap_or(10).clamp(1, 100);
+    tauri::async_runtime::spawn_blocking(move || ChatSessionsRepository::heaviest(limit))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+/// Repair messages and columns left inconsistent by failed imports or pre-v9 data.
+#[tauri::command]
+async fn repair_session_integrity(_app: tauri::AppHandle) -> Result<RepairReport, String> {
+    tauri::async_runtime::spawn_blocking(ChatSessionColumnsRepository::repair_integrity)
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+/// Compare the message search index against the messages table, for
+/// diagnosing missing or stale search results.
+#[tauri::command]
+async fn get_search_index_stats(_app: tauri::AppHandle) -> Result<FtsStats, String> {
+    tauri::async_runtime::spawn_blocking(ChatMessagesRepository::search_index_stats)
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+/// Provider history for asking `prompt` again after `turns`.
+/// `normalize_messages` only appends the prompt when history has no user
+/// turn, so it has to be the last entry here.
+fn history_ending_with(
+    turns: &[ChatMessageRecord],
+    prompt: &ChatMessageRecord,
+) -> Vec<ProviderChatMessage> {
+    turns
+        .iter()
+        .chain(std::iter::once(prompt))
+        .map(|m| ProviderChatMessage {
+            role: m.role.clone(),
+            content: m.content.clone(),
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct CompareWithProviderResult {
+    #[serde(flatten)]
+    setup: CompareColumnSetup,
+    assistant_message_id: String,
+}
+
+/// Re-ask a column's last prompt to another provider in a new column.
+///
+/// Emits `chat:compare-column` with the new column before streaming so the UI
+/// can subscribe to `query:chunk:{column id}`; the reply is persisted as it arrives.
+#[tauri::command]
+async fn compare_with_provider(
+    session_id: String,
+    column_id: String,
+    other_provider_id: String,
+    app: tauri::AppHandle,
+) -> Result<CompareWithProviderResult, String> {
+    provider::ensure_chat_enabled().await?;
+    let setup = {
+        let session_id = session_id.clone();
+        let other_provider_id = other_provider_id.clone();
+        tauri::async_runtime::spawn_blocking(move || {
+            ChatSessionColumnsRepository::fork_for_compare(
+                &session_id,
+                &column_id,
+                &other_provider_id,
+            )
+        })
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?
+    };
+
+    let assistant_message_id = uuid::Uuid::new_v4().to_string();
+    let result = CompareWithProviderResult {
+        setup,
+        assistant_message_id: assistant_message_id.clone(),
+    };
+    app.emit("chat:compare-column", &result)
+        .map_err(|e| e.to_string())?;
+
+    let history = history_ending_with(&result.setup.history, &result.setup.user_message);
+    let new_column_id = result.setup.column.id.clone();
+    query_stream_provider(
+        other_provider_id,
+        result.setup.user_message.content.clone(),
+        Some(history),
+        Some(new_column_id.clone()),
+        Some(session_id),
+        Some(new_column_id),
+        Some(assistant_message_id),
+        app,
+    )
+    .await?;
+
+    Ok(result)
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ResubmitMessageResult {
+    #[serde(flatten)]
+    setup: ResubmitSetup,
+    assistant_message_id: String,
+}
+
+/// Send an earlier user message again as a new turn at the end of its column.
+///
+/// The reply streams on `query:chunk:{column id}` and is persisted as it arrives.
+#[tauri::command]
+async fn resubmit_message(
+    message_id: String,
+    app: tauri::AppHandle,
+) -> Result<ResubmitMessageResult, String> {
+    provider::ensure_chat_enabled().await?;
+    let setup =
+        tauri::async_runtime::spawn_blocking(move || ChatMessagesRepository::resubmit(&message_id))
+            .await
+            .map_err(|e| e.to_string())?
+            .map_err(|e| e.to_string())?;
+
+    let assistant_message_id = uuid::Uuid::new_v4().to_string();
+    let history = history_ending_with(&setup.history, &setup.user_message);
+    let column_id = setup.user_message.column_id.clone();
+    query_stream_provider(
+        setup.user_message.provider_id.clone(),
+        setup.user_message.content.clone(),
+        Some(history),
+        Some(column_id.clone()),
+        Some(setup.user_message.session_id.clone()),
+        Some(column_id),
+        Some(assistant_message_id.clone()),
+        app,
+    )
+    .await?;
+
+    Ok(ResubmitMessageResult {
+        setup,
+        assistant_message_id,
+    })
+}
+
+// Prompt template commands
+#[tauri::command]
+async fn list_prompt_templates(
+    _app: tauri::AppHandle,
+) -> Result<Vec<PromptTemplateRecord>, String> {
+    tauri::async_runtime::spawn_blocking(PromptTemplatesRepository::list)
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn create_prompt_template(
+    name: String,
+    body: String,
+    shortcut: Option<String>,
+    _app: tauri::AppHandle,
+) -> Result<PromptTemplateRecord, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        PromptTemplatesRepository::create(&name, &body, shortcut.as_deref())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn update_prompt_template(
+    id: String,
+    name: Option<String>,
+    body: Option<String>,
+    shortcut: Option<String>,
+    _app: tauri::AppHandle,
+) -> Result<PromptTemplateRecord, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        PromptTemplatesRepository::update(
+            &id,
+            name.as_deref(),
+            body.as_deref(),
+            shortcut.as_deref(),
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn delete_prompt_template(id: String, _app: tauri::AppHandle) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || PromptTemplatesRepository::delete(&id))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn expand_template(
+    shortcut: String,
+    input: String,
+    _app: tauri::AppHandle,
+) -> Result<String, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        PromptTemplatesRepository::expand(&shortcut, &input)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+// Column preset commands
+#[tauri::command]
+async fn list_column_presets(_app: tauri::AppHandle) -> Result<Vec<ColumnPresetRecord>, String> {
+    tauri::async_runtime::spawn_blocking(ColumnPresetsRepository::list)
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn create_column_preset(
+    name: String,
+    provider_ids: Vec<String>,
+    _app: tauri::AppHandle,
+) -> Result<ColumnPresetRecord, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        ColumnPresetsRepository::create(&name, &provider_ids)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn update_column_preset(
+    id: String,
+    name: Option<String>,
+    provider_ids: Option<Vec<String>>,
+    _app: tauri::AppHandle,
+) -> Result<ColumnPresetRecord, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        ColumnPresetsRepository::update(&id, name.as_deref(), provider_ids.as_deref())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn delete_column_preset(id: String, _app: tauri::AppHandle) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || ColumnPresetsRepository::delete(&id))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+/// Create a session whose columns come from a saved preset. Providers deleted
+/// since the preset was saved are skipped.
+#[tauri::command]
+async fn create_session_from_preset(
+    preset_name: String,
+    title: String,
+    _app: tauri::AppHandle,
+) -> Result<ChatSessionRecord, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let id = uuid::Uuid::new_v4().to_string();
+        ColumnPresetsRepository::create_session(&id, &preset_name, &title)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+// Launcher query history commands
+#[tauri::command]
+async fn record_query(text: String, _app: tauri::AppHandle) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || RecentQueriesRepository::record(&text))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+/// Recent launcher queries, newest first; all retained entries when `limit` is omitted.
+#[tauri::command]
+async fn list_recent_queries(
+    limit: Option<usize>,
+    _app: tauri::AppHandle,
+) -> Result<Vec<RecentQueryRecord>, String> {
+    let limit = limit.unwrap_or(db::MAX_RECENT_QUERIES);
+    tauri::async_runtime::spawn_blocking(move || RecentQueriesRepository::list(limit))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn clear_recent_queries(_app: tauri::AppHandle) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(RecentQueriesRepository::clear)
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+/// Report schema objects missing from the database compared to what the
+/// migrations create, e.g. after hand edits or an interrupted upgrade.
+#[tauri::command]
+async fn verify_schema(_app: tauri::AppHandle) -> Result<db::SchemaVerification, String> {
+    tauri::async_runtime::spawn_blocking(db::verify_schema)
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+/// Report which call holds the database lock and the longest hold so far, to
+/// diagnose stalls. Safe to call while the lock is stuck.
+#[tauri::command]
+fn get_db_lock_diagnostics() -> Result<db::DbLockDiagnostics, String> {
+    db::lock_diagnostics().map_err(|e| e.to_string())
+}
+
+/// Checkpoint the database WAL on demand (e.g. before backing up the data file).
+#[tauri::command]
+async fn flush_database(_app: tauri::AppHandle) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(db::flush)
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+/// Where the database and other app data live, for support requests.
+#[tauri::command]
+fn get_data_paths(app: tauri::AppHandle) -> Result<db::DataPaths, String> {
+    db::data_paths(&app).map_err(|e| e.to_string())
+}
+
+/// Show the app data directory (database, recordings) in Explorer.
+#[tauri::command]
+fn open_data_directory(app: tauri::AppHandle) -> Result<(), String> {
+    let data_dir = db::data_paths(&app).map_err(|e| e.to_string())?.data_dir;
+    if !data_dir.is_dir() {
+        return Err(format!(
+            "Data directory not found at '{}'",
+            data_dir.display()
+        ));
+    }
+    std::process::Command::new("explorer")
+        .arg(&data_dir)
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| format!("Failed to open data directory: {e}"))
+}
+
+/// Move data.db to another location (e.g. a larger disk) and reopen it there.
+#[tauri::command]
+async fn relocate_database(new_path: String, app: tauri::AppHandle) -> Result<String, String> {
+    let new_path = std::path::PathBuf::from(new_path.trim());
+    if !new_path.is_absolute() {
+        return Err("Database path must be absolute".to_string());
+    }
+    tauri::async_runtime::spawn_blocking(move || db::relocate(&app, new_path))
+        .await
+        .map_err(|e| e.to_string())?
+        .map(|path| path.to_string_lossy().to_string())
+        .map_err(|e| e.to_string())
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
@@ -728,55 +1792,57 @@ pub fn run() {
         .setup(|app| {
             // Initialize database
             if let Err(err) = db::initialize(&app.handle()) {
-                eprintln!(
-                    "Database initialization failed, continuing with memory cache only: {err}"
-                );
+                eprintln!("Database initialization failed: {err}");
             }
 
             if let Err(err) = ensure_default_app_settings() {
                 eprintln!("App settings initialization failed: {err}");
             }
-
-            let (toggle_shortcut, open_settings_shortcut) = load_hotkeys_from_settings()?;
-            app.manage(HotkeyState::new(
-                toggle_shortcut.clone(),
-                open_settings_shortcut.clone(),
+            logging::set_verbose_errors(parse_bool_setting(
+                SettingsRepository::get(SETTING_VERBOSE_ERRORS)
+                    .ok()
+                    .flatten(),
+                cfg!(debug_assertions),
             ));
 
-            if let Err(err) = register_hotkey_or_log(
-                &app.handle(),
+            let (toggle_shortcut, open_settings_shortcut) = load_hotkeys_from_settings()?;
+            app.manage(HotkeyState::default());
+            app.manage(provider::RateLimiter::default());
+            app.manage(provider::ConcurrencyLimiter::default());
+            app.manage(provider::StreamRegistry::default());
+            db::spawn_wal_maintenance();
+            tauri::async_runtime::spawn(provider::prewarm_providers());
+
+            let state = app.state::<HotkeyState>();
+            if let Some(fallback) = register_startup_hotkey(
+                &state,
+                HotkeyState::set_toggle_search,
                 &toggle_shortcut,
-                register_toggle_search_shortcut,
+                DEFAULT_HOTKEY_TOGGLE_SEARCH,
+                |shortcut| {
+                    register_hotkey_or_log(shortcut, |shortcut| {
+                        register_toggle_search_shortcut(app.handle(), shortcut)
+                    })
+                },
             ) {
-                eprintln!("Failed to register '{}': {err}", toggle_shortcut);
-                let fallback = DEFAULT_HOTKEY_TOGGLE_SEARCH.to_string();
-                let _ = register_hotkey_or_log(
-                    &app.handle(),
-                    &fallback,
-                    register_toggle_search_shortcut,
-                );
-                if let Some(state) = app.try_state::<HotkeyState>() {
-                    state.set_toggle_search(fallback.clone());
-                }
-                let _ = SettingsRepository::set(SETTING_HOTKEY_TOGGLE_SEARCH, &fallback);
+                logging::log_nonfatal(format!(
+                    "Using '{fallback}' to toggle search for this session."
+                ));
             }
-
-            if let Err(err) = register_hotkey_or_log(
-                &app.handle(),
+            if let Some(fallback) = register_startup_hotkey(
+                &state,
+                HotkeyState::set_open_settings,
                 &open_settings_shortcut,
-                register_open_settings_shortcut,
+                DEFAULT_HOTKEY_OPEN_SETTINGS,
+                |shortcut| {
+                    register_hotkey_or_log(shortcut, |shortcut| {
+                        register_open_settings_shortcut(app.handle(), shortcut)
+                    })
+                },
             ) {
-                eprintln!("Failed to register '{}': {err}", open_settings_shortcut);
-                let fallback = DEFAULT_HOTKEY_OPEN_SETTINGS.to_string();
-                let _ = register_hotkey_or_log(
-                    &app.handle(),
-                    &fallback,
-                    register_open_settings_shortcut,
-                );
-                if let Some(state) = app.try_state::<HotkeyState>() {
-                    state.set_open_settings(fallback.clone());
-                }
-                let _ = SettingsRepository::set(SETTING_HOTKEY_OPEN_SETTINGS, &fallback);
+                logging::log_nonfatal(format!(
+                    "Using '{fallback}' to open settings for this session."
+                ));
             }
 
             // Setup system tray
@@ -820,6 +1886,15 @@ pub fn run() {
             // Position window at middle-top
             position_main_window(&window);
 
+            let always_on_top = parse_always_on_top(
+                SettingsRepository::get(SETTING_ALWAYS_ON_TOP)
+                    .ok()
+                    .flatten(),
+            );
+            if let Err(err) = window.set_always_on_top(always_on_top) {
+                logging::log_nonfatal(format!("Failed to apply always-on-top setting: {err}"));
+            }
+
             // Initialize app cache in background
             tauri::async_runtime::spawn(async {
                 initialize_cache().await;
@@ -831,43 +1906,290 @@ pub fn run() {
             // Legacy single-provider commands
             query_stream,
             query_provider_once,
+            replay_last_failed_request,
+            query_utility_once,
             query_stream_provider,
             set_config,
             get_config,
             get_app_settings,
+            get_registered_hotkeys,
+            settings_schema::list_settings_schema,
             set_app_setting,
+            set_always_on_top,
+            validate_hotkey,
             // Multi-provider CRUD commands
             list_providers,
+            list_providers_by_usage,
+            list_provider_types_in_use,
+            get_effective_provider_config,
+            recommend_provider,
+            get_query_readiness,
+            estimate_tokens,
             create_provider,
             update_provider,
             delete_provider,
             set_active_provider,
+            set_active_by_type,
+            set_provider_concurrency,
             get_provider_api_key,
             set_provider_api_key,
             test_provider_connection,
+            test_provider_streaming,
+            benchmark_provider,
+            validate_all_providers,
+            test_api_key,
+            normalize_base_url,
+            infer_provider_type,
+            cancel_all_queries,
+            abort_column,
+            get_message_request,
+            ping_active_provider,
             // Chat session persistence commands
             list_chat_sessions,
             create_chat_session,
+            persist_session,
             list_chat_session_columns,
+            list_columns_with_latest,
             rename_chat_session,
             save_chat_session_state,
+            replace_provider_in_session,
             set_chat_session_column_provider,
             set_session_system_prompt,
+            set_canonical_answer,
             delete_chat_session,
+            tag_sessions,
+            list_session_tags,
             list_chat_messages,
+            list_provider_messages,
             count_chat_messages,
             create_chat_message,
+            get_message_context,
+            diff_messages,
             update_chat_message,
             delete_chat_message,
+            purge_provider_history,
+            import_text_conversation,
             search_chat_messages,
             export_session_messages,
+            export_session_as_openai,
+            get_chat_stats,
+            get_session_storage,
+            list_heaviest_sessions,
+            get_search_index_stats,
+            repair_session_integrity,
+            compare_with_provider,
+            resubmit_message,
+            flush_database,
+            verify_schema,
+            get_db_lock_diagnostics,
+            relocate_database,
+            get_data_paths,
+            open_data_directory,
+            generate_support_bundle,
+            // Prompt template commands
+            list_prompt_templates,
+            create_prompt_template,
+            update_prompt_template,
+            delete_prompt_template,
+            expand_template,
+            // Column preset commands
+            list_column_presets,
+            create_column_preset,
+            update_column_preset,
+            delete_column_preset,
+            create_session_from_preset,
+            record_query,
+            list_recent_queries,
+            clear_recent_queries,
             // App commands
             search_apps,
+            search_apps_grouped,
+            fuzzy_rank,
             get_suggestions,
             launch_app,
+            get_app_launch_info,
             refresh_app_cache,
-            get_app_icon
+            get_app_icon,
+            get_app_accent_color,
+            list_apps_by_publisher,
+            list_icon_failures,
+            get_launch_timeline,
+            diagnose_missing_apps,
+            import_usage_stats,
+            prune_icon_data
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|_app, event| {
+            // Covers both the tray "quit" item and any other `app.exit` call.
+            if let tauri::RunEvent::Exit = event {
+                db::shutdown();
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_always_on_top_defaults_off() {
+        assert!(!parse_always_on_top(None));
+        assert!(!parse_always_on_top(Some("maybe".to_string())));
+        assert!(parse_always_on_top(Some(" On ".to_string())));
+        assert!(parse_always_on_top(Some("1".to_string())));
+        assert!(!parse_always_on_top(Some("0".to_string())));
+    }
+
+    #[test]
+    fn test_registered_hotkeys_report_startup_fallback() {
+        let state = HotkeyState::default();
+        // The preferred toggle shortcut is taken by another app; the default works.
+        let fallback = register_startup_hotkey(
+            &state,
+            HotkeyState::set_toggle_search,
+            "Ctrl + Space",
+            DEFAULT_HOTKEY_TOGGLE_SEARCH,
+            |shortcut| {
+                register_hotkey_or_log(shortcut, |shortcut| match shortcut {
+                    "Ctrl + Space" => Err("HotKey already registered".to_string()),
+                    _ => Ok(()),
+                })
+            },
+        );
+        assert_eq!(fallback.as_deref(), Some(DEFAULT_HOTKEY_TOGGLE_SEARCH));
+        assert_eq!(
+            register_startup_hotkey(
+                &state,
+                HotkeyState::set_open_settings,
+                "Ctrl + ,",
+                DEFAULT_HOTKEY_OPEN_SETTINGS,
+                |shortcut| register_hotkey_or_log(shortcut, |_| Ok(())),
+            ),
+            None
+        );
+
+        let registered = state.registered();
+        assert_eq!(
+            registered
+                .get(SETTING_HOTKEY_TOGGLE_SEARCH)
+                .map(String::as_str),
+            Some(DEFAULT_HOTKEY_TOGGLE_SEARCH)
+        );
+        assert_eq!(
+            registered
+                .get(SETTING_HOTKEY_OPEN_SETTINGS)
+                .map(String::as_str),
+            Some("Ctrl + ,")
+        );
+
+        // Nothing is recorded when the default is taken as well.
+        let state = HotkeyState::default();
+        let fallback = register_startup_hotkey(
+            &state,
+            HotkeyState::set_toggle_search,
+            "Ctrl + Space",
+            DEFAULT_HOTKEY_TOGGLE_SEARCH,
+            |shortcut| {
+                register_hotkey_or_log(shortcut, |_| Err("HotKey already registered".to_string()))
+            },
+        );
+        assert_eq!(fallback, None);
+        assert!(state.registered().is_empty());
+    }
+
+    fn record(id: &str, role: &str, content: &str) -> ChatMessageRecord {
+        ChatMessageRecord {
+            id: id.to_string(),
+            session_id: "s1".to_string(),
+            column_id: "s1:c0".to_string(),
+            provider_id: "p1".to_string(),
+            role: role.to_string(),
+            content: content.to_string(),
+            status: "done".to_string(),
+            created_at: 0,
+            updated_at: 0,
+            provider_name: None,
+            content_hash: None,
+        }
+    }
+
+    #[test]
+    fn test_compare_history_ends_with_prompt() {
+        // `fork_for_compare` hands back the turns before the column's last
+        // prompt, plus that prompt copied into the new column.
+        let turns = [
+            record("m1", "user", "first question"),
+            record("m2", "assistant", "first answer"),
+        ];
+        let history = history_ending_with(&turns, &record("c1", "user", "follow-up"));
+        let roles: Vec<&str> = history.iter().map(|m| m.role.as_str()).collect();
+        assert_eq!(roles, ["user", "assistant", "user"]);
+        assert_eq!(history.last().unwrap().content, "follow-up");
+    }
+
+    #[test]
+    fn test_resubmit_history_ends_with_prompt() {
+        // `resubmit` re-asks an earlier question after the whole column, so
+        // the history already holds a copy of the prompt's text.
+        let turns = [
+            record("m1", "user", "first question"),
+            record("m2", "assistant", "first answer"),
+            record("m3", "user", "second question"),
+            record("m4", "assistant", "second answer"),
+        ];
+        let history = history_ending_with(&turns, &record("r1", "user", "first question"));
+        assert_eq!(history.len(), 5);
+        assert_eq!(history[3].content, "second answer");
+        let last = history.last().unwrap();
+        assert_eq!(
+            (last.role.as_str(), last.content.as_str()),
+            ("user", "first question")
+        );
+    }
+
+    #[test]
+    fn test_parse_launcher_mode() {
+        assert_eq!(parse_launcher_mode(None), LAUNCHER_MODE_HYBRID);
+        assert_eq!(parse_launcher_mode(Some("")), LAUNCHER_MODE_HYBRID);
+        assert_eq!(parse_launcher_mode(Some("chat-only")), LAUNCHER_MODE_HYBRID);
+        assert_eq!(parse_launcher_mode(Some("hybrid")), LAUNCHER_MODE_HYBRID);
+        assert_eq!(
+            parse_launcher_mode(Some(" Apps_Only ")),
+            LAUNCHER_MODE_APPS_ONLY
+        );
+        assert_eq!(
+            parse_launcher_mode(Some("apps-only")),
+            LAUNCHER_MODE_APPS_ONLY
+        );
+    }
+
+    #[test]
+    fn test_normalize_full_width_hotkeys() {
+        let normalize = |raw: &str| normalize_hotkey_setting(Some(raw.to_string()), "fallback");
+
+        assert_eq!(normalize("Alt＋Space"), "Alt+Space");
+        assert_eq!(normalize("Ctrl ＋ ，"), "Ctrl + ,");
+        assert_eq!(normalize("Ctrl＋Shift＋；"), "Ctrl+Shift+;");
+        assert_eq!(normalize("Alt、"), "Alt,");
+        assert_eq!(normalize("\u{3000}Ａｌｔ＋Ｑ\u{3000}"), "Alt+Q");
+        assert_eq!(normalize("Ctrl＋１"), "Ctrl+1");
+        assert_eq!(normalize("　"), "fallback");
+
+        for raw in [
+            "Alt＋Space",
+            "Ctrl ＋ ，",
+            "Ctrl＋Shift＋；",
+            "\u{3000}Ａｌｔ＋Ｑ\u{3000}",
+            "Ctrl＋１",
+            "Alt + Space",
+        ] {
+            let once = normalize(raw);
+            assert_eq!(normalize(&once), once);
+            assert_eq!(parse_hotkey(raw).unwrap(), once);
+        }
+        // Normalized, but global-hotkey has no key for ':'.
+        assert!(parse_hotkey("Ctrl＋Shift＋：").is_err());
+        assert!(parse_hotkey(" ").is_err());
+    }
 }
@@ -0,0 +1,47 @@
+use std::fmt::Display;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether non-fatal diagnostics reach stderr. On in debug builds, off in
+/// release ones unless the `verbose_errors` setting turns them back on.
+static VERBOSE_ERRORS: AtomicBool = AtomicBool::new(cfg!(debug_assertions));
+
+pub fn set_verbose_errors(enabled: bool) {
+    VERBOSE_ERRORS.store(enabled, Ordering::Relaxed);
+}
+
+/// Log a recoverable problem (a failed cache write, a missing shortcut) that
+/// the user cannot act on. Errors that leave the app degraded should keep
+/// using `eprintln!` so they always show up.
+pub fn log_nonfatal(msg: impl Display) {
+    write_nonfatal(
+        &mut std::io::stderr(),
+        VERBOSE_ERRORS.load(Ordering::Relaxed),
+        msg,
+    );
+}
+
+fn write_nonfatal(out: &mut impl Write, verbose: bool, msg: impl Display) {
+    if verbose {
+        let _ = writeln!(out, "{msg}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nonfatal_logging_respects_flag() {
+        let mut quiet = Vec::new();
+        write_nonfatal(&mut quiet, false, "Failed to persist app icon: disk full");
+        assert!(quiet.is_empty());
+
+        let mut verbose = Vec::new();
+        write_nonfatal(&mut verbose, true, "Failed to persist app icon: disk full");
+        assert_eq!(
+            String::from_utf8(verbose).unwrap(),
+            "Failed to persist app icon: disk full\n"
+        );
+    }
+}
@@ -0,0 +1,195 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::Notify;
+
+/// Error returned by a stream that was stopped by `cancel_all_queries`.
+pub const QUERY_CANCELLED: &str = "Query cancelled";
+
+#[derive(Debug, Default)]
+struct TokenState {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+/// Cheap, clonable cancellation flag a stream can wait on.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    state: Arc<TokenState>,
+}
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.state.cancelled.store(true, Ordering::SeqCst);
+        self.state.notify.notify_waiters();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.state.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Resolve once `cancel` has been called, immediately if it already was.
+    pub async fn cancelled(&self) {
+        let notified = self.state.notify.notified();
+        tokio::pin!(notified);
+        // Register before checking the flag so a concurrent cancel is not missed.
+        notified.as_mut().enable();
+        if self.is_cancelled() {
+            return;
+        }
+        notified.await;
+    }
+}
+
+/// Registered streams by id, with the chat column each one renders into.
+type TokenMap = Arc<Mutex<HashMap<u64, (Option<String>, CancelToken)>>>;
+
+/// App-managed set of the streams currently in flight.
+#[derive(Debug, Default)]
+pub struct StreamRegistry {
+    next_id: AtomicU64,
+    tokens: TokenMap,
+}
+
+impl StreamRegistry {
+    fn register(&self, column_id: Option<&str>) -> StreamRegistration {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let token = CancelToken::default();
+        if let Ok(mut tokens) = self.tokens.lock() {
+            tokens.insert(id, (column_id.map(str::to_string), token.clone()));
+        }
+        StreamRegistration {
+            id,
+            token,
+            tokens: Some(Arc::clone(&self.tokens)),
+        }
+    }
+
+    /// Trip every registered token, returning how many streams were signalled.
+    fn cancel_all(&self) -> usize {
+        let Ok(tokens) = self.tokens.lock() else {
+            return 0;
+        };
+        for (_, token) in tokens.values() {
+            token.cancel();
+        }
+        tokens.len()
+    }
+
+    /// Trip the tokens of streams rendering into `column_id` only.
+    fn cancel_column(&self, column_id: &str) -> usize {
+        let Ok(tokens) = self.tokens.lock() else {
+            return 0;
+        };
+        let mut cancelled = 0;
+        for (_, token) in tokens
+            .values()
+            .filter(|(column, _)| column.as_deref() == Some(column_id))
+        {
+            token.cancel();
+            cancelled += 1;
+        }
+        cancelled
+    }
+}
+
+/// A stream's entry in the registry; unregisters itself when dropped.
+pub struct StreamRegistration {
+    id: u64,
+    token: CancelToken,
+    tokens: Option<TokenMap>,
+}
+
+impl StreamRegistration {
+    pub fn token(&self) -> &CancelToken {
+        &self.token
+    }
+}
+
+impl Drop for StreamRegistration {
+    fn drop(&mut self) {
+        if let Some(Ok(mut tokens)) = self.tokens.as_ref().map(|tokens| tokens.lock()) {
+            tokens.remove(&self.id);
+        }
+    }
+}
+
+/// Register a new stream so `cancel_all_queries` can stop it, and
+/// `cancel_column_streams` too when it belongs to a chat column.
+pub fn register_stream(app: &AppHandle, column_id: Option<&str>) -> StreamRegistration {
+    match app.try_state::<StreamRegistry>() {
+        Some(registry) => registry.register(column_id),
+        None => StreamRegistration {
+            id: 0,
+            token: CancelToken::default(),
+            tokens: None,
+        },
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct AllCancelledPayload {
+    cancelled: usize,
+}
+
+/// Signal every in-flight stream to stop, then emit `query:all-cancelled`
+/// once. Returns how many streams were signalled; zero is not an error.
+pub fn cancel_all_queries(app: &AppHandle) -> usize {
+    let cancelled = app
+        .try_state::<StreamRegistry>()
+        .map_or(0, |registry| registry.cancel_all());
+    let _ = app.emit("query:all-cancelled", AllCancelledPayload { cancelled });
+    cancelled
+}
+
+/// Signal the streams rendering into `column_id` to stop, leaving other
+/// columns streaming. Returns how many streams were signalled.
+pub fn cancel_column_streams(app: &AppHandle, column_id: &str) -> usize {
+    app.try_state::<StreamRegistry>()
+        .map_or(0, |registry| registry.cancel_column(column_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_cancel_all_trips_every_registered_token() {
+        let registry = StreamRegistry::default();
+        assert_eq!(registry.cancel_all(), 0);
+
+        let first = registry.register(None);
+        let second = registry.register(Some("s1:c0"));
+        let waiter = {
+            let token = second.token().clone();
+            tokio::spawn(async move { token.cancelled().await })
+        };
+
+        assert_eq!(registry.cancel_all(), 2);
+        assert!(first.token().is_cancelled());
+        assert!(second.token().is_cancelled());
+        waiter.await.unwrap();
+        // Already-cancelled tokens resolve immediately.
+        first.token().cancelled().await;
+
+        drop(first);
+        drop(second);
+        assert_eq!(registry.cancel_all(), 0);
+    }
+
+    #[test]
+    fn test_cancel_column_only_trips_that_column() {
+        let registry = StreamRegistry::default();
+        let target = registry.register(Some("s1:c0"));
+        let sibling = registry.register(Some("s1:c1"));
+        let unscoped = registry.register(None);
+
+        assert_eq!(registry.cancel_column("s1:c0"), 1);
+        assert!(target.token().is_cancelled());
+        assert!(!sibling.token().is_cancelled());
+        assert!(!unscoped.token().is_cancelled());
+        assert_eq!(registry.cancel_column("missing"), 0);
+    }
+}
@@ -0,0 +1,79 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Manager};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::provider::Provider;
+
+/// App-managed per-provider semaphores bounding requests in flight; providers
+/// without a `max_concurrency` never wait.
+#[derive(Debug, Default)]
+pub struct ConcurrencyLimiter {
+    semaphores: Mutex<HashMap<String, (usize, Arc<Semaphore>)>>,
+}
+
+impl ConcurrencyLimiter {
+    fn semaphore(&self, provider_id: &str, limit: usize) -> Option<Arc<Semaphore>> {
+        let mut semaphores = self.semaphores.lock().ok()?;
+        let entry = semaphores
+            .entry(provider_id.to_string())
+            .or_insert_with(|| (limit, Arc::new(Semaphore::new(limit))));
+        // A changed limit gets a fresh semaphore; requests holding permits
+        // on the old one finish undisturbed.
+        if entry.0 != limit {
+            *entry = (limit, Arc::new(Semaphore::new(limit)));
+        }
+        Some(Arc::clone(&entry.1))
+    }
+
+    async fn acquire(&self, provider_id: &str, limit: usize) -> Option<OwnedSemaphorePermit> {
+        self.semaphore(provider_id, limit)?
+            .acquire_owned()
+            .await
+            .ok()
+    }
+}
+
+fn configured_limit(provider: &Provider) -> Option<usize> {
+    provider
+        .max_concurrency
+        .filter(|v| *v > 0)
+        .map(|v| usize::try_from(v).unwrap_or(usize::MAX))
+}
+
+/// Wait until `provider` has a free request slot. Hold the returned permit
+/// for the whole request; `None` means the provider is unbounded.
+pub async fn acquire_permit(app: &AppHandle, provider: &Provider) -> Option<OwnedSemaphorePermit> {
+    let limit = configured_limit(provider)?;
+    let limiter = app.try_state::<ConcurrencyLimiter>()?;
+    limiter.acquire(&provider.id, limit).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_permits_bound_requests_per_provider() {
+        let limiter = ConcurrencyLimiter::default();
+
+        let first = limiter.acquire("strict", 2).await.unwrap();
+        let _second = limiter.acquire("strict", 2).await.unwrap();
+        let strict = limiter.semaphore("strict", 2).unwrap();
+        assert_eq!(strict.available_permits(), 0);
+        assert!(Arc::clone(&strict).try_acquire_owned().is_err());
+
+        // Other providers are not held back by a saturated one.
+        assert!(limiter.acquire("tolerant", 8).await.is_some());
+
+        drop(first);
+        assert_eq!(strict.available_permits(), 1);
+        assert!(strict.try_acquire_owned().is_ok());
+
+        // Raising the limit takes effect for new requests right away.
+        assert_eq!(
+            limiter.semaphore("strict", 4).unwrap().available_permits(),
+            4
+        );
+    }
+}
@@ -1,9 +1,27 @@
+mod cancel;
+mod concurrency;
 mod openai;
+mod prewarm;
+mod rate_limit;
+mod recording;
+mod sanitize;
+mod tokens;
+mod validate;
 
+pub use cancel::{cancel_all_queries, cancel_column_streams, StreamRegistry};
+pub use concurrency::ConcurrencyLimiter;
 pub use openai::{
-    query_provider_once, query_stream, query_stream_provider, test_provider_connection,
-    ConnectionTestResult, ProviderConfig,
+    benchmark_provider, effective_config, ensure_chat_enabled, parse_max_prompt_chars,
+    parse_max_stream_duration_secs, parse_request_timeout_secs, parse_stream_batch_ms,
+    ping_active_provider, query_provider_once, query_stream, query_stream_provider,
+    query_utility_once, replay_last_failed_request, test_api_key, test_provider_connection,
+    test_provider_streaming, BenchmarkResult, ConnectionTestResult, EffectiveConfig,
+    PromptOverflow, ProviderChatMessage, ProviderConfig, StreamTestResult,
 };
+pub use prewarm::prewarm_providers;
+pub use rate_limit::RateLimiter;
+pub use tokens::estimate_message_tokens;
+pub use validate::{validate_all_providers, ProviderValidation};
 
 use serde::{Deserialize, Serialize};
 use std::fmt;
@@ -76,6 +94,47 @@ impl FromStr for ProviderType {
     }
 }
 
+/// Which kind of work a provider is picked for when no provider is named.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderRole {
+    /// Streaming chat replies.
+    Chat,
+    /// Cheap background work such as auto-titling and inline tasks.
+    Utility,
+    #[default]
+    Both,
+}
+
+impl ProviderRole {
+    pub fn handles(self, wanted: ProviderRole) -> bool {
+        self == ProviderRole::Both || self == wanted
+    }
+}
+
+impl fmt::Display for ProviderRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProviderRole::Chat => write!(f, "chat"),
+            ProviderRole::Utility => write!(f, "utility"),
+            ProviderRole::Both => write!(f, "both"),
+        }
+    }
+}
+
+impl FromStr for ProviderRole {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "chat" => Ok(ProviderRole::Chat),
+            "utility" => Ok(ProviderRole::Utility),
+            "both" => Ok(ProviderRole::Both),
+            other => Err(format!("Unknown provider role: {other}")),
+        }
+    }
+}
+
 /// Provider configuration stored in the database
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Provider {
@@ -86,10 +145,135 @@ pub struct Provider {
     pub model: String,
     pub is_active: bool,
     pub display_order: i32,
+    /// OpenAI only: send requests to `/responses` instead of `/chat/completions`.
+    #[serde(default)]
+    pub use_responses_api: bool,
+    /// Model context window in tokens, when known.
+    #[serde(default)]
+    pub context_window: Option<i64>,
+    /// Local cap on requests per minute; `None` means unlimited.
+    #[serde(default)]
+    pub requests_per_minute: Option<i64>,
+    /// Local cap on requests in flight at once; `None` means unbounded.
+    #[serde(default)]
+    pub max_concurrency: Option<i64>,
+    #[serde(default)]
+    pub role: ProviderRole,
     pub created_at: i64,
     pub updated_at: i64,
 }
 
+impl Provider {
+    /// Base URL requests go to: the stored one or the type default, without a trailing slash.
+    pub fn resolved_base_url(&self) -> Option<String> {
+        resolve_base_url(self.provider_type, self.base_url.as_deref())
+    }
+}
+
+pub fn resolve_base_url(provider_type: ProviderType, base_url: Option<&str>) -> Option<String> {
+    base_url
+        .or_else(|| provider_type.default_base_url())
+        .map(|s| s.trim().trim_end_matches('/').to_string())
+        .filter(|s| !s.is_empty())
+}
+
+/// Hosts that identify a provider type; subdomains match too.
+const KNOWN_PROVIDER_HOSTS: &[(&str, ProviderType)] = &[
+    ("api.openai.com", ProviderType::OpenAI),
+    ("api.anthropic.com", ProviderType::Anthropic),
+    ("generativelanguage.googleapis.com", ProviderType::Google),
+    ("bigmodel.cn", ProviderType::Glm),
+    ("z.ai", ProviderType::Glm),
+    ("volces.com", ProviderType::Volcengine),
+    ("volcengineapi.com", ProviderType::Volcengine),
+];
+
+/// Whether `host` is `domain` or one of its subdomains.
+pub(crate) fn host_matches(host: &str, domain: &str) -> bool {
+    host == domain
+        || host
+            .strip_suffix(domain)
+            .is_some_and(|prefix| prefix.ends_with('.'))
+}
+
+/// Best-guess provider type for a pasted base URL, `Custom` for unknown hosts.
+/// A missing scheme is tolerated so bare hosts still resolve.
+pub fn infer_provider_type(base_url: &str) -> Result<ProviderType, String> {
+    let trimmed = base_url.trim();
+    if trimmed.is_empty() {
+        return Err("Base URL is empty".to_string());
+    }
+    let with_scheme = if trimmed.contains("://") {
+        trimmed.to_string()
+    } else {
+        format!("https://{trimmed}")
+    };
+    let host = reqwest::Url::parse(&with_scheme)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_ascii_lowercase))
+        .ok_or_else(|| format!("Not a valid URL: {trimmed}"))?;
+
+    Ok(KNOWN_PROVIDER_HOSTS
+        .iter()
+        .find(|(domain, _)| host_matches(&host, domain))
+        .map_or(ProviderType::Custom, |(_, provider_type)| *provider_type))
+}
+
+/// Endpoint paths the request builders append to the base URL themselves.
+const ENDPOINT_PATHS: &[&str] = &[
+    "/chat/completions",
+    "/completions",
+    "/responses",
+    "/messages",
+    "/embeddings",
+    "/models",
+];
+
+/// A base URL as it will be stored, plus anything the form should point out.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NormalizedBaseUrl {
+    /// Empty when the input was blank, meaning the type's default is used.
+    pub url: String,
+    pub warning: Option<String>,
+}
+
+/// Apply the same trimming as [`resolve_base_url`] to user input and check it
+/// is an absolute http(s) URL. Blank input keeps the type default unless the
+/// type has none.
+pub fn normalize_base_url(
+    provider_type: ProviderType,
+    input: &str,
+) -> Result<NormalizedBaseUrl, String> {
+    let trimmed = input.trim();
+    if trimmed.is_empty() {
+        if provider_type.default_base_url().is_none() {
+            return Err("Base URL is required for this provider type".to_string());
+        }
+        return Ok(NormalizedBaseUrl {
+            url: String::new(),
+            warning: None,
+        });
+    }
+
+    let url = resolve_base_url(provider_type, Some(trimmed)).unwrap_or_default();
+    let parsed = reqwest::Url::parse(&url)
+        .map_err(|_| format!("Base URL must start with http:// or https://: {url}"))?;
+    if !matches!(parsed.scheme(), "http" | "https") || parsed.host_str().is_none() {
+        return Err(format!(
+            "Base URL must start with http:// or https://: {url}"
+        ));
+    }
+
+    let path = parsed.path().trim_end_matches('/').to_ascii_lowercase();
+    let warning = ENDPOINT_PATHS
+        .iter()
+        .find(|endpoint| path.ends_with(*endpoint))
+        .map(|endpoint| {
+            format!("Base URL already ends with {endpoint}, which is added to every request")
+        });
+    Ok(NormalizedBaseUrl { url, warning })
+}
+
 /// Provider view with API key status (for frontend display)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProviderView {
@@ -100,11 +284,43 @@ pub struct ProviderView {
     pub model: String,
     pub is_active: bool,
     pub display_order: i32,
+    #[serde(default)]
+    pub use_responses_api: bool,
+    #[serde(default)]
+    pub context_window: Option<i64>,
+    #[serde(default)]
+    pub requests_per_minute: Option<i64>,
+    #[serde(default)]
+    pub max_concurrency: Option<i64>,
+    #[serde(default)]
+    pub role: ProviderRole,
     pub has_api_key: bool,
     pub created_at: i64,
     pub updated_at: i64,
 }
 
+impl ProviderView {
+    pub fn from_provider(provider: Provider, has_api_key: bool) -> Self {
+        Self {
+            id: provider.id,
+            name: provider.name,
+            provider_type: provider.provider_type,
+            base_url: provider.base_url,
+            model: provider.model,
+            is_active: provider.is_active,
+            display_order: provider.display_order,
+            use_responses_api: provider.use_responses_api,
+            context_window: provider.context_window,
+            requests_per_minute: provider.requests_per_minute,
+            max_concurrency: provider.max_concurrency,
+            role: provider.role,
+            has_api_key,
+            created_at: provider.created_at,
+            updated_at: provider.updated_at,
+        }
+    }
+}
+
 /// Request to create a new provider
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateProviderRequest {
@@ -113,6 +329,24 @@ pub struct CreateProviderRequest {
     pub base_url: Option<String>,
     pub model: Option<String>,
     pub api_key: Option<String>,
+    pub use_responses_api: Option<bool>,
+    pub context_window: Option<i64>,
+    pub requests_per_minute: Option<i64>,
+    #[serde(default)]
+    pub role: Option<ProviderRole>,
+    /// When false (the default), creating a provider identical in type, base URL
+    /// and model to an existing one returns the existing provider instead
+    /// (see [`CreateProviderResult`]).
+    #[serde(default)]
+    pub allow_duplicate: Option<bool>,
+}
+
+/// Result of `create_provider`. `existing` is set when an identical provider
+/// was found and returned instead; a supplied API key is saved onto it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateProviderResult {
+    pub provider: Provider,
+    pub existing: bool,
 }
 
 /// Request to update an existing provider
@@ -121,12 +355,83 @@ pub struct UpdateProviderRequest {
     pub name: Option<String>,
     pub base_url: Option<String>,
     pub model: Option<String>,
+    pub use_responses_api: Option<bool>,
+    /// Non-positive values clear the stored context window.
+    pub context_window: Option<i64>,
+    /// Non-positive values remove the rate limit.
+    pub requests_per_minute: Option<i64>,
+    pub role: Option<ProviderRole>,
+}
+
+/// Whether the launcher can send a query right now, and why not if it can't.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueryReadiness {
+    pub has_active_provider: bool,
+    pub has_api_key: bool,
+    pub provider_name: Option<String>,
+    /// Set when `launcher_mode` is `apps_only`; the UI should hide chat entirely.
+    #[serde(default)]
+    pub apps_only: bool,
+    /// `None` when ready; otherwise "apps_only", "no_active_provider" or "missing_api_key".
+    pub missing_reason: Option<String>,
+}
+
+/// Rough token estimate for a prompt of `prompt_len` characters (~4 chars per token).
+pub fn estimate_prompt_tokens(prompt_len: usize) -> i64 {
+    (prompt_len as i64).saturating_add(3) / 4
+}
+
+/// Pick the active provider with the largest known context window that can
+/// hold a prompt of `prompt_len` characters. Ties go to the earlier provider.
+pub fn recommend_provider_for_prompt(providers: &[Provider], prompt_len: usize) -> Option<String> {
+    let needed = estimate_prompt_tokens(prompt_len);
+    providers
+        .iter()
+        .filter(|p| p.is_active)
+        .filter_map(|p| p.context_window.map(|window| (p, window)))
+        .filter(|(_, window)| *window > needed)
+        .fold(None::<(&Provider, i64)>, |best, candidate| match best {
+            Some((_, best_window)) if best_window >= candidate.1 => best,
+            _ => Some(candidate),
+        })
+        .map(|(p, _)| p.id.clone())
+}
+
+/// An active OpenAI provider for tests; override fields with `..test_provider()`.
+#[cfg(test)]
+pub(crate) fn test_provider() -> Provider {
+    Provider {
+        id: "p1".to_string(),
+        name: "Test".to_string(),
+        provider_type: ProviderType::OpenAI,
+        base_url: None,
+        model: "gpt-4o-mini".to_string(),
+        is_active: true,
+        display_order: 0,
+        use_responses_api: false,
+        context_window: None,
+        requests_per_minute: None,
+        max_concurrency: None,
+        role: ProviderRole::default(),
+        created_at: 0,
+        updated_at: 0,
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_provider_role_from_str_rejects_unknown() {
+        assert_eq!(
+            ProviderRole::from_str(" Utility ").unwrap(),
+            ProviderRole::Utility
+        );
+        assert_eq!(ProviderRole::from_str("both").unwrap(), ProviderRole::Both);
+        assert!(ProviderRole::from_str("fallback").is_err());
+    }
+
     #[test]
     fn test_provider_type_display() {
         assert_eq!(ProviderType::OpenAI.to_string(), "openai");
@@ -162,6 +467,36 @@ mod tests {
         );
     }
 
+    fn provider(id: &str, is_active: bool, context_window: Option<i64>) -> Provider {
+        Provider {
+            id: id.to_string(),
+            is_active,
+            context_window,
+            ..test_provider()
+        }
+    }
+
+    #[test]
+    fn test_recommend_provider_for_prompt() {
+        let providers = vec![
+            provider("small", true, Some(8_000)),
+            provider("large", true, Some(128_000)),
+            provider("huge-disabled", false, Some(1_000_000)),
+            provider("unknown", true, None),
+            provider("large-later", true, Some(128_000)),
+        ];
+
+        assert_eq!(
+            recommend_provider_for_prompt(&providers, 1_000),
+            Some("large".to_string())
+        );
+        // ~200k tokens: nothing active is big enough.
+        assert_eq!(recommend_provider_for_prompt(&providers, 800_000), None);
+        assert_eq!(recommend_provider_for_prompt(&[], 10), None);
+        assert_eq!(estimate_prompt_tokens(0), 0);
+        assert_eq!(estimate_prompt_tokens(9), 3);
+    }
+
     #[test]
     fn test_provider_type_defaults() {
         assert_eq!(
@@ -184,4 +519,101 @@ mod tests {
             "deepseek-v3-2-251201"
         );
     }
+
+    #[test]
+    fn test_normalize_base_url_rejects_non_http_schemes() {
+        for input in ["api.example.com/v1", "ftp://files.example.com", "https://"] {
+            assert!(
+                normalize_base_url(ProviderType::Custom, input).is_err(),
+                "{input}"
+            );
+        }
+        assert!(normalize_base_url(ProviderType::Custom, "  ").is_err());
+        assert_eq!(
+            normalize_base_url(ProviderType::OpenAI, "  ").unwrap().url,
+            ""
+        );
+    }
+
+    #[test]
+    fn test_normalize_base_url_trims_trailing_slashes() {
+        let normalized =
+            normalize_base_url(ProviderType::Custom, "  http://localhost:11434/v1// ").unwrap();
+        assert_eq!(normalized.url, "http://localhost:11434/v1");
+        assert_eq!(normalized.warning, None);
+    }
+
+    #[test]
+    fn test_normalize_base_url_flags_endpoint_paths() {
+        let normalized = normalize_base_url(
+            ProviderType::Custom,
+            "https://gw.example/v1/Chat/Completions/",
+        )
+        .unwrap();
+        assert_eq!(normalized.url, "https://gw.example/v1/Chat/Completions");
+        assert!(normalized.warning.unwrap().contains("/chat/completions"));
+
+        let anthropic = normalize_base_url(
+            ProviderType::Anthropic,
+            "https://api.anthropic.com/v1/messages",
+        )
+        .unwrap();
+        assert!(anthropic.warning.is_some());
+        assert_eq!(
+            normalize_base_url(ProviderType::OpenAI, "https://api.openai.com/v1")
+                .unwrap()
+                .warning,
+            None
+        );
+    }
+
+    #[test]
+    fn test_infer_provider_type_from_known_hosts() {
+        for (url, expected) in [
+            ("https://api.openai.com/v1", ProviderType::OpenAI),
+            ("https://api.anthropic.com/v1/", ProviderType::Anthropic),
+            (
+                "https://generativelanguage.googleapis.com/v1beta",
+                ProviderType::Google,
+            ),
+            ("https://open.bigmodel.cn/api/paas/v4", ProviderType::Glm),
+            ("https://api.z.ai/api/paas/v4", ProviderType::Glm),
+            (
+                "https://ark.cn-beijing.volces.com/api/v3",
+                ProviderType::Volcengine,
+            ),
+            ("API.OpenAI.com/v1", ProviderType::OpenAI),
+        ] {
+            assert_eq!(infer_provider_type(url), Ok(expected), "{url}");
+        }
+        // Every type's own default URL maps back to it.
+        for provider_type in [
+            ProviderType::OpenAI,
+            ProviderType::Glm,
+            ProviderType::Anthropic,
+            ProviderType::Google,
+            ProviderType::Volcengine,
+        ] {
+            let url = provider_type.default_base_url().unwrap();
+            assert_eq!(infer_provider_type(url), Ok(provider_type));
+        }
+    }
+
+    #[test]
+    fn test_infer_provider_type_falls_back_to_custom() {
+        assert_eq!(
+            infer_provider_type("http://localhost:11434/v1"),
+            Ok(ProviderType::Custom)
+        );
+        // Lookalike hosts are not subdomains.
+        assert_eq!(
+            infer_provider_type("https://notapi.openai.com.evil.example/v1"),
+            Ok(ProviderType::Custom)
+        );
+        assert_eq!(
+            infer_provider_type("https://fakeopenai.com"),
+            Ok(ProviderType::Custom)
+        );
+        assert!(infer_provider_type("   ").is_err());
+    }
 }
@@ -1,7 +1,23 @@
-use crate::db::ProvidersRepository;
-use crate::provider::{Provider, ProviderType};
+use crate::db::{
+    content_hash, ChatMessagesRepository, MessageRequestsRepository, ProvidersRepository,
+    SettingsRepository,
+};
+use crate::provider::cancel::{register_stream, CancelToken, QUERY_CANCELLED};
+use crate::provider::concurrency::acquire_permit;
+use crate::provider::rate_limit::acquire_slot;
+use crate::provider::recording::{
+    debug_recording_enabled, last_failed_request, record_failed_request, StreamRecorder,
+};
+use crate::provider::sanitize::{
+    control_tokens, strip_control_tokens, truncate_excerpt, ControlTokenFilter,
+    EXCERPT_MAX_GRAPHEMES,
+};
+use crate::provider::{host_matches, Provider, ProviderType};
+use once_cell::sync::Lazy;
 use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Mutex;
 use std::time::{Duration, Instant};
 use tauri::{AppHandle, Emitter};
 
@@ -48,6 +64,37 @@ impl ConnectionTestResult {
     }
 }
 
+/// Outcome of `test_provider_streaming`: whether a `stream: true` request
+/// actually produced deltas, and how quickly.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StreamTestResult {
+    pub ok: bool,
+    pub streamed_chars: usize,
+    /// Time from sending the request to the first delta.
+    pub first_chunk_ms: Option<u64>,
+    pub message: String,
+}
+
+impl StreamTestResult {
+    fn failure(message: String) -> Self {
+        Self {
+            ok: false,
+            streamed_chars: 0,
+            first_chunk_ms: None,
+            message,
+        }
+    }
+}
+
+/// Timings from `benchmark_provider`, measured from sending the request.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchmarkResult {
+    pub first_token_ms: u64,
+    pub total_ms: u64,
+    pub total_chars: usize,
+    pub chars_per_sec: f64,
+}
+
 impl Default for ProviderConfig {
     fn default() -> Self {
         Self {
@@ -60,17 +107,72 @@ impl Default for ProviderConfig {
 }
 
 fn resolve_base_url(provider: &Provider) -> Option<String> {
-    provider
-        .base_url
-        .clone()
-        .or_else(|| {
-            provider
-                .provider_type
-                .default_base_url()
-                .map(str::to_string)
-        })
-        .map(|s| s.trim().trim_end_matches('/').to_string())
-        .filter(|s| !s.is_empty())
+    provider.resolved_base_url()
+}
+
+/// Client shared by chat requests so they reuse pooled connections (and
+/// their TLS sessions); timeouts are set per request.
+static HTTP_CLIENT: Lazy<reqwest::Client> = Lazy::new(reqwest::Client::new);
+
+pub(crate) fn shared_client() -> &'static reqwest::Client {
+    &HTTP_CLIENT
+}
+
+/// Default total timeouts, in seconds, for each kind of provider request.
+const STREAM_TIMEOUT_SECS: u64 = 120;
+const REQUEST_TIMEOUT_SECS: u64 = 40;
+const CONNECTION_TEST_TIMEOUT_SECS: u64 = 12;
+/// Deadline for the whole streaming test, first delta included.
+const STREAM_TEST_TIMEOUT_SECS: u64 = 20;
+
+/// Longest `request_timeout_secs` accepted.
+const MAX_REQUEST_TIMEOUT_SECS: u64 = 600;
+
+/// Whole-request timeout in seconds that replaces both the streamed reply
+/// default (`STREAM_TIMEOUT_SECS`) and the one-shot request default
+/// (`REQUEST_TIMEOUT_SECS`); connection and streaming tests keep their own.
+/// `0` (the default, and any invalid value) keeps the built-in defaults.
+pub fn parse_request_timeout_secs(raw: Option<&str>) -> u64 {
+    raw.and_then(|value| value.trim().parse::<u64>().ok())
+        .map_or(0, |secs| secs.min(MAX_REQUEST_TIMEOUT_SECS))
+}
+
+/// Resolve a request timeout, letting the `request_timeout_secs` setting override the default.
+async fn configured_timeout(default_secs: u64) -> Duration {
+    let raw = tauri::async_runtime::spawn_blocking(|| {
+        SettingsRepository::get(crate::SETTING_REQUEST_TIMEOUT_SECS)
+    })
+    .await
+    .ok()
+    .and_then(Result::ok)
+    .flatten();
+    Duration::from_secs(match parse_request_timeout_secs(raw.as_deref()) {
+        0 => default_secs,
+        secs => secs,
+    })
+}
+
+/// Refuse chat queries when the launcher is configured as `apps_only`.
+pub async fn ensure_chat_enabled() -> Result<(), String> {
+    let apps_only = tauri::async_runtime::spawn_blocking(crate::is_apps_only_mode)
+        .await
+        .map_err(|e| e.to_string())??;
+    if apps_only {
+        return Err("Chat is disabled in apps-only launcher mode.".to_string());
+    }
+    Ok(())
+}
+
+/// Turn a transport error into a user-facing message, calling out timeouts explicitly.
+fn describe_request_error(err: &reqwest::Error, timeout: Duration) -> String {
+    if err.is_timeout() {
+        format!(
+            "Request timed out after {}s. Increase the request timeout in settings if the provider is slow.",
+            timeout.as_secs()
+        )
+    } else {
+        format!("Network error: {err}")
+    }
 }
 
 fn elapsed_ms(started_at: Instant) -> u64 {
@@ -81,7 +183,7 @@ async fn response_excerpt(resp: reqwest::Response) -> String {
     match resp.text().await {
         Ok(text) => {
             let compact = text.replace('\n', " ").replace('\r', " ");
-            compact.chars().take(220).collect::<String>()
+            truncate_excerpt(&compact, EXCERPT_MAX_GRAPHEMES)
         }
         Err(_) => String::new(),
     }
@@ -108,6 +210,76 @@ fn classify_http_failure(status: StatusCode, model: &str, details: &str) -> Stri
     }
 }
 
+/// Providers whose streaming endpoint rejected `stream: true` this session;
+/// they go straight to the non-streaming request.
+static STREAMING_UNSUPPORTED: Lazy<Mutex<HashSet<String>>> =
+    Lazy::new(|| Mutex::new(HashSet::new()));
+
+fn supports_streaming(provider_id: &str) -> bool {
+    STREAMING_UNSUPPORTED
+        .lock()
+        .map(|set| !set.contains(provider_id))
+        .unwrap_or(true)
+}
+
+fn mark_streaming_unsupported(provider_id: &str) {
+    if let Ok(mut set) = STREAMING_UNSUPPORTED.lock() {
+        set.insert(provider_id.to_string());
+    }
+}
+
+fn clear_streaming_unsupported(provider_id: &str) {
+    if let Ok(mut set) = STREAMING_UNSUPPORTED.lock() {
+        set.remove(provider_id);
+    }
+}
+
+/// Whether a failed streaming request looks like the endpoint rejecting streaming
+/// itself, rather than a bad key, model or outage.
+fn is_streaming_unsupported_error(status: StatusCode, details: &str) -> bool {
+    matches!(
+        status,
+        StatusCode::BAD_REQUEST
+            | StatusCode::METHOD_NOT_ALLOWED
+            | StatusCode::UNSUPPORTED_MEDIA_TYPE
+            | StatusCode::UNPROCESSABLE_ENTITY
+            | StatusCode::NOT_IMPLEMENTED
+    ) && details.to_ascii_lowercase().contains("stream")
+}
+
+/// Send the streaming request. Returns `Ok(None)` when the provider does not
+/// support streaming, so the caller falls back to `call_provider_and_get_text`.
+async fn open_stream(
+    client: &reqwest::Client,
+    provider: &Provider,
+    base_url: &str,
+    api_key: &str,
+    messages: &[ProviderChatMessage],
+    timeout: Duration,
+) -> Result<Option<reqwest::Response>, String> {
+    if !supports_streaming(&provider.id) {
+        return Ok(None);
+    }
+
+    let response = build_chat_request(provider, base_url, api_key, messages, true)
+        .into_builder(client, timeout)
+        .send()
+        .await
+        .map_err(|e| describe_request_error(&e, timeout))?;
+
+    let status = response.status();
+    if status.is_success() {
+        return Ok(Some(response));
+    }
+
+    let detail = response_excerpt(response).await;
+    if is_streaming_unsupported_error(status, &detail) {
+        mark_streaming_unsupported(&provider.id);
+        return Ok(None);
+    }
+    Err(classify_http_failure(status, &provider.model, &detail))
+}
+
 fn parse_openai_like_text(body: &serde_json::Value) -> Option<String> {
     body.get("choices")
         .and_then(|v| v.as_array())
@@ -169,12 +341,33 @@ fn parse_responses_text(body: &serde_json::Value) -> Option<String> {
         .map(str::to_string)
 }
 
-fn parse_stream_delta(provider_type: ProviderType, body: &serde_json::Value) -> Option<String> {
-    match provider_type {
+/// Wire protocol spoken by a provider's chat endpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ApiFormat {
+    /// OpenAI-compatible `/chat/completions`.
+    ChatCompletions,
+    /// `/responses` (Volcengine ARK, or OpenAI with `use_responses_api`).
+    Responses,
+    Anthropic,
+    Google,
+}
+
+fn api_format(provider: &Provider) -> ApiFormat {
+    match provider.provider_type {
+        ProviderType::OpenAI if provider.use_responses_api => ApiFormat::Responses,
         ProviderType::OpenAI | ProviderType::Glm | ProviderType::Custom => {
-            parse_openai_delta_text(body)
+            ApiFormat::ChatCompletions
         }
-        ProviderType::Volcengine => {
+        ProviderType::Volcengine => ApiFormat::Responses,
+        ProviderType::Anthropic => ApiFormat::Anthropic,
+        ProviderType::Google => ApiFormat::Google,
+    }
+}
+
+fn parse_stream_delta(format: ApiFormat, body: &serde_json::Value) -> Option<String> {
+    match format {
+        ApiFormat::ChatCompletions => parse_openai_delta_text(body),
+        ApiFormat::Responses => {
             if body.get("type").and_then(|v| v.as_str()) == Some("response.output_text.delta") {
                 if let Some(delta) = body.get("delta").and_then(|v| v.as_str()) {
                     if !delta.is_empty() {
@@ -198,13 +391,84 @@ fn parse_stream_delta(provider_type: ProviderType, body: &serde_json::Value) ->
                     .map(str::to_string)
             })
         }
-        ProviderType::Anthropic => body
-            .get("delta")
-            .and_then(|delta| delta.get("text"))
+        ApiFormat::Anthropic => match parse_anthropic_event(body) {
+            Some(StreamEvent::Delta(text)) => Some(text),
+            _ => None,
+        },
+        ApiFormat::Google => parse_google_text(body),
+    }
+}
+
+/// Finish reason and token usage reported while a reply streams.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct StreamMeta {
+    pub stop_reason: Option<String>,
+    pub input_tokens: Option<u64>,
+    pub output_tokens: Option<u64>,
+}
+
+/// A parsed stream payload. Only `Delta` text is emitted on the chunk channel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum StreamEvent {
+    Delta(String),
+    Meta(StreamMeta),
+    /// Provider end-of-stream marker; consumed by the decoder, never sunk.
+    Stop,
+    /// Sent last, with the [`content_hash`] of the full reply. `finish_reason`
+    /// is set when the stream was stopped here rather than by the provider.
+    Done {
+        content_hash: String,
+        finish_reason: Option<&'static str>,
+    },
+}
+
+fn parse_stream_event(format: ApiFormat, body: &serde_json::Value) -> Option<StreamEvent> {
+    match format {
+        ApiFormat::Anthropic => parse_anthropic_event(body),
+        _ => parse_stream_delta(format, body).map(StreamEvent::Delta),
+    }
+}
+
+fn parse_anthropic_event(body: &serde_json::Value) -> Option<StreamEvent> {
+    let non_empty_text = |value: Option<&serde_json::Value>| {
+        value
+            .and_then(|v| v.get("text"))
             .and_then(|v| v.as_str())
             .filter(|s| !s.is_empty())
-            .map(str::to_string),
-        ProviderType::Google => parse_google_text(body),
+            .map(|s| StreamEvent::Delta(s.to_string()))
+    };
+
+    match body.get("type").and_then(|v| v.as_str()) {
+        Some("content_block_start") => non_empty_text(body.get("content_block")),
+        Some("content_block_delta") => non_empty_text(body.get("delta")),
+        // The prompt size arrives up front; `message_delta` carries the output.
+        Some("message_start") => body
+            .pointer("/message/usage/input_tokens")
+            .and_then(|v| v.as_u64())
+            .map(|input_tokens| {
+                StreamEvent::Meta(StreamMeta {
+                    input_tokens: Some(input_tokens),
+                    ..Default::default()
+                })
+            }),
+        Some("message_delta") => {
+            let usage = body.get("usage");
+            let token_count = |key: &str| usage.and_then(|u| u.get(key)).and_then(|v| v.as_u64());
+            let meta = StreamMeta {
+                stop_reason: body
+                    .get("delta")
+                    .and_then(|d| d.get("stop_reason"))
+                    .and_then(|v| v.as_str())
+                    .map(str::to_string),
+                input_tokens: token_count("input_tokens"),
+                output_tokens: token_count("output_tokens"),
+            };
+            (meta != StreamMeta::default()).then_some(StreamEvent::Meta(meta))
+        }
+        Some("message_stop") => Some(StreamEvent::Stop),
+        Some(_) => None,
+        // Untyped payloads from Anthropic-compatible gateways.
+        None => non_empty_text(body.get("delta")),
     }
 }
 
@@ -234,14 +498,12 @@ fn parse_google_text(body: &serde_json::Value) -> Option<String> {
         .map(str::to_string)
 }
 
-fn parse_provider_text(provider_type: ProviderType, body: &serde_json::Value) -> Option<String> {
-    match provider_type {
-        ProviderType::OpenAI | ProviderType::Glm | ProviderType::Custom => {
-            parse_openai_like_text(body)
-        }
-        ProviderType::Anthropic => parse_anthropic_text(body),
-        ProviderType::Google => parse_google_text(body),
-        ProviderType::Volcengine => parse_responses_text(body),
+fn parse_provider_text(format: ApiFormat, body: &serde_json::Value) -> Option<String> {
+    match format {
+        ApiFormat::ChatCompletions => parse_openai_like_text(body),
+        ApiFormat::Anthropic => parse_anthropic_text(body),
+        ApiFormat::Google => parse_google_text(body),
+        ApiFormat::Responses => parse_responses_text(body),
     }
 }
 
@@ -287,6 +549,456 @@ fn normalize_messages(
     Ok(messages)
 }
 
+/// Default `max_prompt_chars`: far above normal typing, well below where
+/// providers start rejecting the request body.
+const DEFAULT_MAX_PROMPT_CHARS: usize = 200_000;
+
+/// What to do with a prompt longer than `max_prompt_chars`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PromptOverflow {
+    #[default]
+    Reject,
+    Truncate,
+}
+
+impl PromptOverflow {
+    pub fn parse(raw: Option<&str>) -> Self {
+        match raw.map(|value| value.trim().to_lowercase()).as_deref() {
+            Some("truncate") => PromptOverflow::Truncate,
+            _ => PromptOverflow::Reject,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PromptOverflow::Reject => "reject",
+            PromptOverflow::Truncate => "truncate",
+        }
+    }
+}
+
+/// Prompt length cap in characters; invalid or non-positive values use the default.
+pub fn parse_max_prompt_chars(raw: Option<&str>) -> usize {
+    raw.and_then(|value| value.trim().parse::<usize>().ok())
+        .filter(|chars| *chars > 0)
+        .unwrap_or(DEFAULT_MAX_PROMPT_CHARS)
+}
+
+/// Longest batching window `stream_batch_ms` accepts.
+const MAX_STREAM_BATCH_MS: u64 = 500;
+/// Buffered delta text that forces a batch out before its window ends.
+const STREAM_BATCH_MAX_BYTES: usize = 4096;
+
+/// Window for coalescing stream deltas in milliseconds; `0` (the default, and
+/// any invalid value) emits every delta as it arrives.
+pub fn parse_stream_batch_ms(raw: Option<&str>) -> u64 {
+    raw.and_then(|value| value.trim().parse::<u64>().ok())
+        .map_or(0, |ms| ms.min(MAX_STREAM_BATCH_MS))
+}
+
+/// Longest hard cap `max_stream_duration_secs` accepts.
+const MAX_STREAM_DURATION_SECS: u64 = 60 * 60;
+/// `Done` finish reason for a stream cut off by its duration cap.
+const FINISH_REASON_TIME_LIMIT: &str = "time_limit";
+
+/// Hard cap on how long one reply may stream, in seconds; `0` (the default,
+/// and any invalid value) leaves streams uncapped.
+pub fn parse_max_stream_duration_secs(raw: Option<&str>) -> u64 {
+    raw.and_then(|value| value.trim().parse::<u64>().ok())
+        .map_or(0, |secs| secs.min(MAX_STREAM_DURATION_SECS))
+}
+
+/// Per-stream settings for `stream_sse_response`. The default neither
+/// batches nor caps.
+#[derive(Debug, Clone, Copy, Default)]
+struct StreamLimits {
+    batch_window: Duration,
+    /// Stop reading and finish with `FINISH_REASON_TIME_LIMIT` after this long.
+    max_duration: Option<Duration>,
+}
+
+async fn configured_stream_limits() -> StreamLimits {
+    let (batch_ms, max_secs) = tauri::async_runtime::spawn_blocking(|| {
+        let get = |key| SettingsRepository::get(key).ok().flatten();
+        (
+            get(crate::SETTING_STREAM_BATCH_MS),
+            get(crate::SETTING_MAX_STREAM_DURATION_SECS),
+        )
+    })
+    .await
+    .unwrap_or_default();
+    let max_secs = parse_max_stream_duration_secs(max_secs.as_deref());
+    StreamLimits {
+        batch_window: Duration::from_millis(parse_stream_batch_ms(batch_ms.as_deref())),
+        max_duration: (max_secs > 0).then(|| Duration::from_secs(max_secs)),
+    }
+}
+
+/// Sleep until `deadline`, or forever without one.
+async fn sleep_until_deadline(deadline: Option<tokio::time::Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Coalesces consecutive deltas for up to `window` (or `STREAM_BATCH_MAX_BYTES`)
+/// into one `Delta` event, so very fast streams do not flood the webview.
+/// Other events flush the batch first, keeping order. A zero window passes
+/// every delta straight through.
+struct DeltaBatch {
+    window: Duration,
+    text: String,
+    started_at: Option<Instant>,
+}
+
+impl DeltaBatch {
+    fn new(window: Duration) -> Self {
+        Self {
+            window,
+            text: String::new(),
+            started_at: None,
+        }
+    }
+
+    /// When the pending batch is due, if one is pending.
+    fn deadline(&self) -> Option<tokio::time::Instant> {
+        self.started_at
+            .map(|started_at| tokio::time::Instant::from_std(started_at + self.window))
+    }
+
+    fn push(
+        &mut self,
+        sink: &mut impl FnMut(StreamEvent) -> Result<(), String>,
+        delta: String,
+    ) -> Result<(), String> {
+        if self.window.is_zero() {
+            return sink(StreamEvent::Delta(delta));
+        }
+        self.text.push_str(&delta);
+        let started_at = *self.started_at.get_or_insert_with(Instant::now);
+        if self.text.len() >= STREAM_BATCH_MAX_BYTES || started_at.elapsed() >= self.window {
+            self.flush(sink)?;
+        }
+        Ok(())
+    }
+
+    fn flush(
+        &mut self,
+        sink: &mut impl FnMut(StreamEvent) -> Result<(), String>,
+    ) -> Result<(), String> {
+        self.started_at = None;
+        if self.text.is_empty() {
+            return Ok(());
+        }
+        sink(StreamEvent::Delta(std::mem::take(&mut self.text)))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct PromptLimit {
+    max_chars: usize,
+    overflow: PromptOverflow,
+}
+
+impl PromptLimit {
+    async fn load() -> Self {
+        let (max_chars, overflow) = tauri::async_runtime::spawn_blocking(|| {
+            (
+                SettingsRepository::get(crate::SETTING_MAX_PROMPT_CHARS)
+                    .ok()
+                    .flatten(),
+                SettingsRepository::get(crate::SETTING_PROMPT_OVERFLOW)
+                    .ok()
+                    .flatten(),
+            )
+        })
+        .await
+        .unwrap_or_default();
+        Self {
+            max_chars: parse_max_prompt_chars(max_chars.as_deref()),
+            overflow: PromptOverflow::parse(overflow.as_deref()),
+        }
+    }
+}
+
+/// Payload of `query:prompt-truncated`, emitted when an oversized prompt was cut.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+struct PromptTruncated {
+    original_chars: usize,
+    kept_chars: usize,
+}
+
+/// Enforce `limit` on the latest user message, which carries the prompt.
+/// Returns `Some` when the prompt was truncated, or an error in reject mode.
+fn apply_prompt_limit(
+    messages: &mut [ProviderChatMessage],
+    limit: PromptLimit,
+) -> Result<Option<PromptTruncated>, String> {
+    let Some(prompt) = messages.iter_mut().rev().find(|m| m.role == "user") else {
+        return Ok(None);
+    };
+    let original_chars = prompt.content.chars().count();
+    if original_chars <= limit.max_chars {
+        return Ok(None);
+    }
+
+    match limit.overflow {
+        PromptOverflow::Reject => Err(format!(
+            "Prompt is too long ({original_chars} characters; the limit is {}). \
+             Shorten it or raise max_prompt_chars in Settings.",
+            limit.max_chars
+        )),
+        PromptOverflow::Truncate => {
+            if let Some((cut, _)) = prompt.content.char_indices().nth(limit.max_chars) {
+                prompt.content.truncate(cut);
+            }
+            Ok(Some(PromptTruncated {
+                original_chars,
+                kept_chars: limit.max_chars,
+            }))
+        }
+    }
+}
+
+/// `normalize_messages` plus the `max_prompt_chars` guard; a truncation is
+/// reported on `query:prompt-truncated` so the UI can warn.
+async fn prepare_messages(
+    app: &AppHandle,
+    history: Option<Vec<ProviderChatMessage>>,
+    prompt: &str,
+) -> Result<Vec<ProviderChatMessage>, String> {
+    let mut messages = normalize_messages(history, prompt)?;
+    if let Some(truncated) = apply_prompt_limit(&mut messages, PromptLimit::load().await)? {
+        app.emit("query:prompt-truncated", truncated)
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(messages)
+}
+
+/// A provider HTTP request resolved to its final URL, headers, query and body.
+#[derive(Debug, Clone)]
+struct ProviderRequest {
+    url: String,
+    headers: Vec<(&'static str, String)>,
+    query: Vec<(&'static str, String)>,
+    body: serde_json::Value,
+}
+
+impl ProviderRequest {
+    fn into_builder(self, client: &reqwest::Client, timeout: Duration) -> reqwest::RequestBuilder {
+        let mut builder = client.post(self.url).timeout(timeout);
+        for (name, value) in self.headers {
+            builder = builder.header(name, value);
+        }
+        if !self.query.is_empty() {
+            builder = builder.query(&self.query);
+        }
+        builder.json(&self.body)
+    }
+
+    /// The request as JSON with every credential header and query value
+    /// replaced, for storing alongside the reply it produced.
+    fn redacted_payload(&self) -> serde_json::Value {
+        let redact = |pairs: &[(&'static str, String)]| {
+            pairs
+                .iter()
+                .map(|(name, value)| {
+                    let value = if CREDENTIAL_FIELDS.contains(&name.to_ascii_lowercase().as_str()) {
+                        REDACTED.to_string()
+                    } else {
+                        value.clone()
+                    };
+                    (name.to_string(), serde_json::Value::String(value))
+                })
+                .collect::<serde_json::Map<_, _>>()
+        };
+        serde_json::json!({
+            "url": self.url,
+            "headers": redact(&self.headers),
+            "query": redact(&self.query),
+            "body": self.body,
+        })
+    }
+}
+
+/// Header and query names, lowercased, that carry the API key.
+const CREDENTIAL_FIELDS: &[&str] = &["authorization", "x-api-key", "key"];
+const REDACTED: &str = "[redacted]";
+
+/// Attribution headers that well-known gateways recommend, keyed by host.
+/// Only hosts whose docs ask for them belong here.
+const GATEWAY_DEFAULT_HEADERS: &[(&str, &[(&str, &str)])] = &[(
+    "openrouter.ai",
+    &[
+        ("HTTP-Referer", "https://github.com/shenzihan666/search"),
+        ("X-Title", "AI Quick Search"),
+    ],
+)];
+
+/// Recommended headers for the gateway at `base_url`, or none for unknown hosts.
+fn gateway_default_headers(base_url: &str) -> Vec<(&'static str, String)> {
+    let Some(host) = reqwest::Url::parse(base_url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_ascii_lowercase))
+    else {
+        return Vec::new();
+    };
+    GATEWAY_DEFAULT_HEADERS
+        .iter()
+        .find(|(gateway, _)| host_matches(&host, gateway))
+        .map(|(_, headers)| {
+            headers
+                .iter()
+                .map(|(name, value)| (*name, value.to_string()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Bearer auth headers for `provider`, plus the gateway defaults when it is a
+/// Custom provider pointed at a known gateway. Every request path uses this.
+fn auth_headers(provider: &Provider, base_url: &str, api_key: &str) -> Vec<(&'static str, String)> {
+    let mut headers = vec![("Authorization", format!("Bearer {}", api_key.trim()))];
+    if provider.provider_type == ProviderType::Custom {
+        headers.extend(gateway_default_headers(base_url));
+    }
+    headers
+}
+
+/// Build the chat request exactly as it is sent, for streaming or one-shot calls.
+fn build_chat_request(
+    provider: &Provider,
+    base_url: &str,
+    api_key: &str,
+    messages: &[ProviderChatMessage],
+    stream: bool,
+) -> ProviderRequest {
+    let api_key = api_key.trim();
+    let bearer = auth_headers(provider, base_url, api_key);
+
+    match api_format(provider) {
+        ApiFormat::ChatCompletions => {
+            let mut body = serde_json::json!({
+                "model": provider.model,
+                "messages": messages,
+                "temperature": 0.7
+            });
+            if stream {
+                body["stream"] = serde_json::json!(true);
+            }
+            ProviderRequest {
+                url: format!("{base_url}/chat/completions"),
+                headers: bearer,
+                query: Vec::new(),
+                body,
+            }
+        }
+        ApiFormat::Responses => {
+            let body = if stream {
+                serde_json::json!({
+                    "model": provider.model,
+                    "input": messages,
+                    "stream": true
+                })
+            } else {
+                serde_json::json!({
+                    "model": provider.model,
+                    "input": messages,
+                    "max_output_tokens": 4096
+                })
+            };
+            ProviderRequest {
+                url: format!("{base_url}/responses"),
+                headers: bearer,
+                query: Vec::new(),
+                body,
+            }
+        }
+        ApiFormat::Anthropic => {
+            let mut body = serde_json::json!({
+                "model": provider.model,
+                "max_tokens": 4096,
+                "messages": messages
+            });
+            if stream {
+                body["stream"] = serde_json::json!(true);
+            }
+            ProviderRequest {
+                url: format!("{base_url}/messages"),
+                headers: vec![
+                    ("x-api-key", api_key.to_string()),
+                    ("anthropic-version", "2023-06-01".to_string()),
+                ],
+                query: Vec::new(),
+                body,
+            }
+        }
+        ApiFormat::Google => {
+            let contents = messages
+                .iter()
+                .map(|msg| {
+                    serde_json::json!({
+                        "role": role_for_google(&msg.role),
+                        "parts": [{ "text": msg.content }]
+                    })
+                })
+                .collect::<Vec<_>>();
+            let (method, query) = if stream {
+                (
+                    "streamGenerateContent",
+                    vec![("key", api_key.to_string()), ("alt", "sse".to_string())],
+                )
+            } else {
+                ("generateContent", vec![("key", api_key.to_string())])
+            };
+            ProviderRequest {
+                url: format!("{base_url}/models/{}:{method}", provider.model),
+                headers: Vec::new(),
+                query,
+                body: serde_json::json!({
+                    "contents": contents,
+                    "generationConfig": { "maxOutputTokens": 4096 }
+                }),
+            }
+        }
+    }
+}
+
+/// How requests to a provider are resolved, for debugging misconfigured
+/// providers. Never includes the key itself.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EffectiveConfig {
+    pub provider_type: ProviderType,
+    pub resolved_base_url: Option<String>,
+    /// Path appended to the base URL for streaming chat requests.
+    pub endpoint_path: String,
+    /// Header carrying the key; `None` when it is sent as the `key` query parameter.
+    pub auth_header_name: Option<String>,
+    pub model: String,
+    pub has_api_key: bool,
+}
+
+/// Resolve `provider` through the same builder used for real chat requests.
+pub fn effective_config(provider: &Provider, has_api_key: bool) -> EffectiveConfig {
+    let request = build_chat_request(provider, "", "", &[], true);
+    let auth_header_name = request
+        .headers
+        .iter()
+        .map(|(name, _)| *name)
+        .find(|name| *name == "Authorization" || *name == "x-api-key")
+        .map(str::to_string);
+
+    EffectiveConfig {
+        provider_type: provider.provider_type,
+        resolved_base_url: resolve_base_url(provider),
+        endpoint_path: request.url,
+        auth_header_name,
+        model: provider.model.clone(),
+        has_api_key,
+    }
+}
+
 fn take_sse_frames(buffer: &mut String) -> Vec<String> {
     let mut frames = Vec::new();
 
@@ -343,85 +1055,460 @@ fn take_ndjson_lines(buffer: &mut String) -> Vec<String> {
     frames
 }
 
-async fn stream_sse_response(
-    app: &AppHandle,
-    event_name: &str,
-    provider_type: ProviderType,
-    mut response: reqwest::Response,
-) -> Result<usize, String> {
-    let mut emitted_chars = 0usize;
-    let mut buffer = String::new();
+/// Mirrors an assistant reply into its `chat_messages` row while it streams,
+/// so a killed app leaves a recoverable partial answer behind.
+struct StreamPersistence {
+    message_id: String,
+    content: String,
+    last_flush: Instant,
+}
 
-    while let Some(chunk) = response
-        .chunk()
+impl StreamPersistence {
+    const FLUSH_INTERVAL: Duration = Duration::from_millis(750);
+
+    async fn begin(
+        message_id: String,
+        session_id: String,
+        column_id: String,
+        provider_id: String,
+    ) -> Result<Self, String> {
+        let id = message_id.clone();
+        tauri::async_runtime::spawn_blocking(move || {
+            ChatMessagesRepository::begin_streaming(&id, &session_id, &column_id, &provider_id)
+        })
         .await
-        .map_err(|e| format!("Failed reading SSE stream: {e}"))?
-    {
-        let chunk_text = String::from_utf8_lossy(&chunk);
-        let normalized = chunk_text.replace("\r\n", "\n").replace('\r', "\n");
-        buffer.push_str(&normalized);
-
-        for payload in take_sse_frames(&mut buffer) {
-            if payload.trim() == "[DONE]" {
-                return Ok(emitted_chars);
-            }
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?;
 
-            let parsed: serde_json::Value = match serde_json::from_str(payload.trim()) {
-                Ok(v) => v,
-                Err(_) => continue,
-            };
+        Ok(Self {
+            message_id,
+            content: String::new(),
+            last_flush: Instant::now(),
+        })
+    }
 
-            if let Some(delta) = parse_stream_delta(provider_type, &parsed) {
-                emitted_chars += delta.chars().count();
-                app.emit(event_name, delta)
-                    .map_err(|e| format!("Failed to emit stream chunk: {e}"))?;
-            }
+    async fn push(&mut self, delta: &str) {
+        self.content.push_str(delta);
+        if self.last_flush.elapsed() >= Self::FLUSH_INTERVAL {
+            self.flush("streaming").await;
         }
+    }
 
-        // Some providers stream as line-delimited JSON (no `data:` prefix).
-        if !buffer.contains("data:") {
-            for payload in take_ndjson_lines(&mut buffer) {
-                if payload.trim() == "[DONE]" {
-                    return Ok(emitted_chars);
-                }
-
-                let parsed: serde_json::Value = match serde_json::from_str(payload.trim()) {
-                    Ok(v) => v,
-                    Err(_) => continue,
-                };
+    async fn finish(mut self, status: &str) {
+        self.flush(status).await;
+    }
 
-                if let Some(delta) = parse_stream_delta(provider_type, &parsed) {
-                    emitted_chars += delta.chars().count();
-                    app.emit(event_name, delta)
-                        .map_err(|e| format!("Failed to emit stream chunk: {e}"))?;
-                }
-            }
+    /// Delete the row instead of finishing it, for a stream cancelled before
+    /// any of the reply arrived.
+    async fn discard(self) {
+        let id = self.message_id;
+        // The row may already be gone, e.g. deleted by `abort_column`.
+        if let Err(e) =
+            tauri::async_runtime::spawn_blocking(move || ChatMessagesRepository::delete(&id)).await
+        {
+            eprintln!("Failed to join stream persistence task: {e}");
         }
     }
 
-    // If nothing was streamed, try parsing the full buffered payload once.
-    if emitted_chars == 0 {
-        let tail = buffer.trim();
-        if !tail.is_empty() && tail != "[DONE]" {
-            if let Ok(body) = serde_json::from_str::<serde_json::Value>(tail) {
-                if let Some(text) = parse_provider_text(provider_type, &body) {
-                    emitted_chars = text.chars().count();
-                    app.emit(event_name, text)
-                        .map_err(|e| format!("Failed to emit stream chunk: {e}"))?;
-                }
+    async fn flush(&mut self, status: &str) {
+        self.last_flush = Instant::now();
+        let id = self.message_id.clone();
+        let content = self.content.clone();
+        let status = status.to_string();
+        match tauri::async_runtime::spawn_blocking(move || {
+            ChatMessagesRepository::update_content(&id, &content, &status)
+        })
+        .await
+        {
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => eprintln!("Failed to persist streamed message: {e}"),
+            Err(e) => eprintln!("Failed to join stream persistence task: {e}"),
+        }
+    }
+}
+
+/// Store the redacted streaming request behind `message_id` when the
+/// `store_message_requests` setting is on. Failures only get logged.
+async fn store_message_request(
+    message_id: &str,
+    provider: &Provider,
+    api_key: &str,
+    messages: &[ProviderChatMessage],
+) {
+    let Some(base_url) = resolve_base_url(provider) else {
+        return;
+    };
+    let payload =
+        build_chat_request(provider, &base_url, api_key, messages, true).redacted_payload();
+    let message_id = message_id.to_string();
+    let provider_id = provider.id.clone();
+    match tauri::async_runtime::spawn_blocking(move || {
+        let enabled = crate::parse_bool_setting(
+            SettingsRepository::get(crate::SETTING_STORE_MESSAGE_REQUESTS)
+                .ok()
+                .flatten(),
+            false,
+        );
+        if !enabled {
+            return Ok(());
+        }
+        MessageRequestsRepository::save(&message_id, &provider_id, &payload)
+    })
+    .await
+    {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => crate::logging::log_nonfatal(format!("Failed to store message request: {e}")),
+        Err(e) => crate::logging::log_nonfatal(format!("Failed to join message request task: {e}")),
+    }
+}
+
+/// Adapts a stream sink to Tauri: deltas go out on `event_name`, finish
+/// reason and usage on the sibling `{event_name}:meta` channel so chunk
+/// listeners only see text, and the reply hash on `{event_name}:done`.
+fn tauri_stream_sink<'a>(
+    app: &'a AppHandle,
+    event_name: &'a str,
+) -> impl FnMut(StreamEvent) -> Result<(), String> + 'a {
+    let meta_event_name = format!("{event_name}:meta");
+    let done_event_name = format!("{event_name}:done");
+    move |event| match event {
+        StreamEvent::Delta(text) => app
+            .emit(event_name, text)
+            .map_err(|e| format!("Failed to emit stream chunk: {e}")),
+        StreamEvent::Meta(meta) => app
+            .emit(&meta_event_name, meta)
+            .map_err(|e| format!("Failed to emit stream metadata: {e}")),
+        StreamEvent::Stop => Ok(()),
+        StreamEvent::Done {
+            content_hash,
+            finish_reason,
+        } => app
+            .emit(
+                &done_event_name,
+                serde_json::json!({
+                    "content_hash": content_hash,
+                    "finish_reason": finish_reason
+                }),
+            )
+            .map_err(|e| format!("Failed to emit stream completion: {e}")),
+    }
+}
+
+/// Send a one-shot reply through `tauri_stream_sink` as a single delta, so
+/// `{event_name}:done` listeners see it finish like a stream.
+fn emit_full_reply(app: &AppHandle, event_name: &str, text: String) -> Result<(), String> {
+    let mut sink = tauri_stream_sink(app, event_name);
+    let content_hash = content_hash(&text);
+    sink(StreamEvent::Delta(text))?;
+    sink(StreamEvent::Done {
+        content_hash,
+        finish_reason: None,
+    })
+}
+
+/// Incremental SSE / NDJSON decoder, fed one network chunk at a time.
+struct StreamDecoder {
+    format: ApiFormat,
+    buffer: String,
+    finished: bool,
+}
+
+impl StreamDecoder {
+    fn new(format: ApiFormat) -> Self {
+        Self {
+            format,
+            buffer: String::new(),
+            finished: false,
+        }
+    }
+
+    /// Set once `[DONE]` or a provider stop event has been seen.
+    fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Decode the complete frames now in the buffer. Stop markers end the
+    /// stream and are not returned.
+    fn feed(&mut self, chunk: &[u8]) -> Vec<StreamEvent> {
+        let mut events = Vec::new();
+        if self.finished {
+            return events;
+        }
+
+        let chunk_text = String::from_utf8_lossy(chunk);
+        let normalized = chunk_text.replace("\r\n", "\n").replace('\r', "\n");
+        self.buffer.push_str(&normalized);
+
+        let mut payloads = take_sse_frames(&mut self.buffer);
+        // Some providers stream as line-delimited JSON (no `data:` prefix).
+        if !self.buffer.contains("data:") {
+            payloads.extend(take_ndjson_lines(&mut self.buffer));
+        }
+
+        for payload in payloads {
+            if payload.trim() == "[DONE]" {
+                self.finished = true;
+                break;
+            }
+
+            let parsed: serde_json::Value = match serde_json::from_str(payload.trim()) {
+                Ok(v) => v,
+                Err(_) => continue,
+            };
+
+            match parse_stream_event(self.format, &parsed) {
+                Some(StreamEvent::Stop) => {
+                    self.finished = true;
+                    break;
+                }
+                Some(event) => events.push(event),
+                None => {}
+            }
+        }
+        events
+    }
+
+    /// Text from a provider that ignored `stream: true` and sent one JSON body.
+    fn buffered_text(&self) -> Option<String> {
+        let tail = self.buffer.trim();
+        if tail.is_empty() || tail == "[DONE]" {
+            return None;
+        }
+        let body = serde_json::from_str::<serde_json::Value>(tail).ok()?;
+        parse_provider_text(self.format, &body)
+    }
+}
+
+/// Decode a streaming response into `sink`, ending with `StreamEvent::Done`
+/// when the stream completes. Returns the number of characters streamed.
+#[allow(clippy::too_many_arguments)]
+async fn stream_sse_response(
+    sink: &mut impl FnMut(StreamEvent) -> Result<(), String>,
+    format: ApiFormat,
+    mut response: reqwest::Response,
+    timeout: Duration,
+    limits: StreamLimits,
+    mut persistence: Option<&mut StreamPersistence>,
+    mut recorder: Option<StreamRecorder>,
+    control_tokens: &'static [&'static str],
+) -> Result<usize, String> {
+    let mut emitted_chars = 0usize;
+    let mut content = String::new();
+    let mut decoder = StreamDecoder::new(format);
+    let mut filter = ControlTokenFilter::new(control_tokens);
+    let mut batch = DeltaBatch::new(limits.batch_window);
+    let time_limit = limits
+        .max_duration
+        .map(|max| tokio::time::Instant::now() + max);
+    let mut finish_reason = None;
+
+    loop {
+        // While a batch is pending, stop waiting for the network when it is due.
+        let chunk = tokio::select! {
+            chunk = response.chunk() => chunk,
+            _ = sleep_until_deadline(batch.deadline()) => {
+                batch.flush(sink)?;
+                continue;
+            }
+            _ = sleep_until_deadline(time_limit) => {
+                // Dropping the response closes the connection.
+                finish_reason = Some(FINISH_REASON_TIME_LIMIT);
+                break;
+            }
+        };
+        let Some(chunk) = chunk.map_err(|e| {
+            if e.is_timeout() {
+                describe_request_error(&e, timeout)
+            } else {
+                format!("Failed reading SSE stream: {e}")
+            }
+        })?
+        else {
+            break;
+        };
+        if let Some(recorder) = recorder.as_mut() {
+            recorder.write(&chunk);
+        }
+
+        for event in decoder.feed(&chunk) {
+            let event = match event {
+                StreamEvent::Delta(delta) => {
+                    let delta = filter.push(&delta);
+                    if delta.is_empty() {
+                        continue;
+                    }
+                    emitted_chars += delta.chars().count();
+                    content.push_str(&delta);
+                    if let Some(persistence) = persistence.as_deref_mut() {
+                        persistence.push(&delta).await;
+                    }
+                    batch.push(sink, delta)?;
+                    continue;
+                }
+                event => event,
+            };
+            batch.flush(sink)?;
+            sink(event)?;
+        }
+        if decoder.is_finished() {
+            break;
+        }
+    }
+    batch.flush(sink)?;
+
+    let tail = filter.finish();
+    if !tail.is_empty() {
+        emitted_chars += tail.chars().count();
+        content.push_str(&tail);
+        if let Some(persistence) = persistence.as_deref_mut() {
+            persistence.push(&tail).await;
+        }
+        sink(StreamEvent::Delta(tail))?;
+    }
+    if decoder.is_finished() || finish_reason.is_some() {
+        sink(StreamEvent::Done {
+            content_hash: content_hash(&content),
+            finish_reason,
+        })?;
+        return Ok(emitted_chars);
+    }
+
+    // If nothing was streamed, try parsing the full buffered payload once.
+    if emitted_chars == 0 {
+        if let Some(text) = decoder
+            .buffered_text()
+            .map(|text| strip_control_tokens(&text, control_tokens))
+            .filter(|text| !text.is_empty())
+        {
+            emitted_chars = text.chars().count();
+            if let Some(persistence) = persistence {
+                persistence.push(&text).await;
             }
+            content = text.clone();
+            sink(StreamEvent::Delta(text))?;
         }
     }
 
+    sink(StreamEvent::Done {
+        content_hash: content_hash(&content),
+        finish_reason: None,
+    })?;
     Ok(emitted_chars)
 }
 
+/// Whether a streamed reply reached the user, for callers that fall back to
+/// a one-shot request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StreamOutcome {
+    /// Nothing was emitted or persisted, so a one-shot retry is safe.
+    NotStreamed,
+    Streamed,
+    /// `max_stream_duration_secs` ended the stream, possibly before any
+    /// text; an uncapped one-shot retry would defeat the limit.
+    TimeLimited,
+}
+
 async fn stream_provider_and_emit(
     app: &AppHandle,
     event_name: &str,
     provider: &Provider,
     api_key: &str,
     messages: &[ProviderChatMessage],
+    persistence: Option<&mut StreamPersistence>,
+    cancel: &CancelToken,
+) -> Result<StreamOutcome, String> {
+    let recorder = StreamRecorder::start(app, provider.provider_type).await;
+    let limits = configured_stream_limits().await;
+    let mut sink = tauri_stream_sink(app, event_name);
+    tokio::select! {
+        result = stream_reply(
+            &mut sink,
+            provider,
+            api_key,
+            messages,
+            persistence,
+            recorder,
+            limits,
+        ) => result,
+        _ = cancel.cancelled() => Err(QUERY_CANCELLED.to_string()),
+    }
+}
+
+/// `stream_provider` for callers with a one-shot fallback. A failure before
+/// any text reached `sink` or `persistence` is logged and reported as
+/// `NotStreamed`; one after that is returned, since retrying would repeat
+/// the partial reply. `Done` is held back until the stream is known to be
+/// the reply, so an empty stream that gets replaced does not announce one.
+async fn stream_reply(
+    sink: &mut impl FnMut(StreamEvent) -> Result<(), String>,
+    provider: &Provider,
+    api_key: &str,
+    messages: &[ProviderChatMessage],
+    mut persistence: Option<&mut StreamPersistence>,
+    recorder: Option<StreamRecorder>,
+    limits: StreamLimits,
+) -> Result<StreamOutcome, String> {
+    let mut emitted = false;
+    let mut done = None;
+    let mut tracking_sink = |event: StreamEvent| match event {
+        StreamEvent::Done { .. } => {
+            done = Some(event);
+            Ok(())
+        }
+        event => {
+            emitted |= matches!(&event, StreamEvent::Delta(delta) if !delta.is_empty());
+            sink(event)
+        }
+    };
+    let result = stream_provider(
+        &mut tracking_sink,
+        provider,
+        api_key,
+        messages,
+        persistence.as_deref_mut(),
+        recorder,
+        limits,
+    )
+    .await;
+    let emitted = emitted || persistence.is_some_and(|p| !p.content.is_empty());
+    let time_limited = matches!(
+        done,
+        Some(StreamEvent::Done {
+            finish_reason: Some(FINISH_REASON_TIME_LIMIT),
+            ..
+        })
+    );
+
+    let outcome = match result {
+        Ok(_) if time_limited => StreamOutcome::TimeLimited,
+        Ok(_) if emitted => StreamOutcome::Streamed,
+        Ok(_) => StreamOutcome::NotStreamed,
+        Err(err) if emitted => return Err(err),
+        Err(err) => {
+            crate::logging::log_nonfatal(format!(
+                "Streaming from {} failed, retrying without streaming: {err}",
+                provider.name
+            ));
+            StreamOutcome::NotStreamed
+        }
+    };
+    if let Some(done) = done.filter(|_| outcome != StreamOutcome::NotStreamed) {
+        sink(done)?;
+    }
+    Ok(outcome)
+}
+
+/// Stream a chat reply from `provider` into `sink`, independent of Tauri.
+/// Returns 0 without emitting anything when the provider rejects streaming,
+/// so callers can retry with a one-shot request.
+async fn stream_provider(
+    sink: &mut impl FnMut(StreamEvent) -> Result<(), String>,
+    provider: &Provider,
+    api_key: &str,
+    messages: &[ProviderChatMessage],
+    persistence: Option<&mut StreamPersistence>,
+    recorder: Option<StreamRecorder>,
+    limits: StreamLimits,
 ) -> Result<usize, String> {
     if api_key.trim().is_empty() {
         return Err("API key is empty.".to_string());
@@ -433,85 +1520,32 @@ async fn stream_provider_and_emit(
     let base_url = resolve_base_url(provider)
         .ok_or_else(|| "Base URL is empty. Configure provider base URL.".to_string())?;
 
-    let client = reqwest::Client::builder()
-        .timeout(Duration::from_secs(120))
-        .build()
-        .map_err(|e| format!("Failed to create HTTP client: {e}"))?;
-
-    let response = match provider.provider_type {
-        ProviderType::OpenAI | ProviderType::Glm | ProviderType::Custom => {
-            let url = format!("{base_url}/chat/completions");
-            client
-                .post(url)
-                .header("Authorization", format!("Bearer {}", api_key.trim()))
-                .json(&serde_json::json!({
-                    "model": provider.model,
-                    "messages": messages,
-                    "temperature": 0.7,
-                    "stream": true
-                }))
-                .send()
-                .await
-        }
-        ProviderType::Volcengine => {
-            let url = format!("{base_url}/responses");
-            client
-                .post(url)
-                .header("Authorization", format!("Bearer {}", api_key.trim()))
-                .json(&serde_json::json!({
-                    "model": provider.model,
-                    "input": messages,
-                    "stream": true
-                }))
-                .send()
-                .await
-        }
-        ProviderType::Anthropic => {
-            let url = format!("{base_url}/messages");
-            client
-                .post(url)
-                .header("x-api-key", api_key.trim())
-                .header("anthropic-version", "2023-06-01")
-                .json(&serde_json::json!({
-                    "model": provider.model,
-                    "max_tokens": 4096,
-                    "messages": messages,
-                    "stream": true
-                }))
-                .send()
-                .await
-        }
-        ProviderType::Google => {
-            let url = format!("{base_url}/models/{}:streamGenerateContent", provider.model);
-            let contents = messages
-                .iter()
-                .map(|msg| {
-                    serde_json::json!({
-                        "role": role_for_google(&msg.role),
-                        "parts": [{ "text": msg.content }]
-                    })
-                })
-                .collect::<Vec<_>>();
-            client
-                .post(url)
-                .query(&[("key", api_key.trim()), ("alt", "sse")])
-                .json(&serde_json::json!({
-                    "contents": contents,
-                    "generationConfig": { "maxOutputTokens": 4096 }
-                }))
-                .send()
-                .await
-        }
-    }
-    .map_err(|e| format!("Network error: {e}"))?;
-
-    let status = response.status();
-    if !status.is_success() {
-        let detail = response_excerpt(response).await;
-        return Err(classify_http_failure(status, &provider.model, &detail));
-    }
+    let timeout = configured_timeout(STREAM_TIMEOUT_SECS).await;
+    let Some(response) = open_stream(
+        shared_client(),
+        provider,
+        &base_url,
+        api_key,
+        messages,
+        timeout,
+    )
+    .await?
+    else {
+        // Nothing emitted; callers retry without streaming.
+        return Ok(0);
+    };
 
-    stream_sse_response(app, event_name, provider.provider_type, response).await
+    stream_sse_response(
+        sink,
+        api_format(provider),
+        response,
+        timeout,
+        limits,
+        persistence,
+        recorder,
+        control_tokens(provider.provider_type),
+    )
+    .await
 }
 
 async fn call_provider_and_get_text(
@@ -529,85 +1563,27 @@ async fn call_provider_and_get_text(
     let base_url = resolve_base_url(provider)
         .ok_or_else(|| "Base URL is empty. Configure provider base URL.".to_string())?;
 
-    let client = reqwest::Client::builder()
-        .timeout(Duration::from_secs(40))
-        .build()
-        .map_err(|e| format!("Failed to create HTTP client: {e}"))?;
-
-    let response = match provider.provider_type {
-        ProviderType::OpenAI | ProviderType::Glm | ProviderType::Custom => {
-            let url = format!("{base_url}/chat/completions");
-            client
-                .post(url)
-                .header("Authorization", format!("Bearer {}", api_key.trim()))
-                .json(&serde_json::json!({
-                    "model": provider.model,
-                    "messages": messages,
-                    "temperature": 0.7
-                }))
-                .send()
-                .await
-        }
-        ProviderType::Volcengine => {
-            let url = format!("{base_url}/responses");
-            client
-                .post(url)
-                .header("Authorization", format!("Bearer {}", api_key.trim()))
-                .json(&serde_json::json!({
-                    "model": provider.model,
-                    "input": messages,
-                    "max_output_tokens": 4096
-                }))
-                .send()
-                .await
-        }
-        ProviderType::Anthropic => {
-            let url = format!("{base_url}/messages");
-            client
-                .post(url)
-                .header("x-api-key", api_key.trim())
-                .header("anthropic-version", "2023-06-01")
-                .json(&serde_json::json!({
-                    "model": provider.model,
-                    "max_tokens": 4096,
-                    "messages": messages
-                }))
-                .send()
-                .await
-        }
-        ProviderType::Google => {
-            let url = format!("{base_url}/models/{}:generateContent", provider.model);
-            let contents = messages
-                .iter()
-                .map(|msg| {
-                    serde_json::json!({
-                        "role": role_for_google(&msg.role),
-                        "parts": [{ "text": msg.content }]
-                    })
-                })
-                .collect::<Vec<_>>();
-            client
-                .post(url)
-                .query(&[("key", api_key.trim())])
-                .json(&serde_json::json!({
-                    "contents": contents,
-                    "generationConfig": { "maxOutputTokens": 4096 }
-                }))
-                .send()
-                .await
-        }
-    }
-    .map_err(|e| format!("Network error: {e}"))?;
+    let timeout = configured_timeout(REQUEST_TIMEOUT_SECS).await;
+    let response = build_chat_request(provider, &base_url, api_key, messages, false)
+        .into_builder(shared_client(), timeout)
+        .send()
+        .await
+        .map_err(|e| describe_request_error(&e, timeout))?;
 
     let status = response.status();
-    let body: serde_json::Value = response
-        .json()
-        .await
-        .map_err(|e| format!("Failed to parse provider response: {e}"))?;
+    let body: serde_json::Value = response.json().await.map_err(|e| {
+        if e.is_timeout() {
+            describe_request_error(&e, timeout)
+        } else if !status.is_success() {
+            // Gateways often answer errors with HTML; keep the status visible.
+            classify_http_failure(status, &provider.model, "")
+        } else {
+            format!("Failed to parse provider response: {e}")
+        }
+    })?;
 
     if !status.is_success() {
-        let detail = body.to_string();
-        let detail_excerpt: String = detail.chars().take(220).collect();
+        let detail_excerpt = truncate_excerpt(&body.to_string(), EXCERPT_MAX_GRAPHEMES);
         return Err(classify_http_failure(
             status,
             &provider.model,
@@ -615,35 +1591,450 @@ async fn call_provider_and_get_text(
         ));
     }
 
-    let parsed = parse_provider_text(provider.provider_type, &body);
+    let parsed = parse_provider_text(api_format(provider), &body)
+        .map(|text| strip_control_tokens(&text, control_tokens(provider.provider_type)));
 
     parsed.ok_or_else(|| {
-        let excerpt: String = body.to_string().chars().take(220).collect();
+        let excerpt = truncate_excerpt(&body.to_string(), EXCERPT_MAX_GRAPHEMES);
         format!("Provider returned no readable text. Response excerpt: {excerpt}")
     })
 }
 
-fn placeholder_response(provider: &Provider, prompt: &str, api_key: &str) -> String {
-    format!(
-        "You asked: '{}'\n\nUsing provider: {} (model: {})\nAPI key configured: {}\n\nThis is a placeholder response. Configure your API key in settings to get real AI responses.",
-        prompt,
-        provider.name,
-        provider.model,
-        if api_key.is_empty() { "No" } else { "Yes" }
-    )
+/// Providers tried by one `query_stream` call when failover is on, primary included.
+const MAX_FAILOVER_PROVIDERS: usize = 3;
+
+#[derive(Debug, Clone, Serialize)]
+struct FailoverPayload {
+    from_provider_id: String,
+    from_provider_name: String,
+    to_provider_id: String,
+    to_provider_name: String,
+    error: String,
 }
 
-pub async fn test_provider_connection(id: String) -> Result<ConnectionTestResult, String> {
-    let (provider, api_key) = tauri::async_runtime::spawn_blocking(move || {
-        let provider = ProvidersRepository::get(&id)
-            .map_err(|e| e.to_string())?
-            .ok_or_else(|| "Provider not found".to_string())?;
-        let api_key = ProvidersRepository::get_api_key(&provider.id).map_err(|e| e.to_string())?;
+/// Whether a failed request is worth retrying on another provider: the
+/// provider was unreachable or erroring, rather than rejecting this request.
+/// Matches the messages built by `describe_request_error` and
+/// `classify_http_failure`.
+fn is_failover_error(err: &str) -> bool {
+    err.starts_with("Network error:")
+        || err.starts_with("Request timed out")
+        || err.starts_with("Provider server error.")
+}
+
+/// Run `attempt` against each candidate in order until one succeeds. Only
+/// network and 5xx errors move on to the next provider (after calling
+/// `on_failover`); any other error, or the last candidate's, is returned.
+async fn with_failover<T, F, Fut>(
+    candidates: &[(Provider, String)],
+    mut attempt: F,
+    mut on_failover: impl FnMut(&Provider, &Provider, &str),
+) -> Result<T, String>
+where
+    F: FnMut(Provider, String) -> Fut,
+    Fut: std::future::Future<Output = Result<T, String>>,
+{
+    let mut last_err = "No active provider configured.".to_string();
+    for (index, (provider, api_key)) in candidates.iter().enumerate() {
+        match attempt(provider.clone(), api_key.clone()).await {
+            Ok(value) => return Ok(value),
+            Err(err) => {
+                let next = candidates.get(index + 1);
+                match next {
+                    Some((next, _)) if is_failover_error(&err) => {
+                        on_failover(provider, next, &err);
+                        last_err = err;
+                    }
+                    _ => return Err(err),
+                }
+            }
+        }
+    }
+    Err(last_err)
+}
+
+/// One provider's try at a `query_stream` answer: stream it, or fall back to
+/// a one-shot request. `Ok(None)` means the reply was already streamed.
+async fn query_stream_attempt(
+    app: &AppHandle,
+    provider: Provider,
+    api_key: String,
+    messages: &[ProviderChatMessage],
+) -> Result<Option<String>, String> {
+    let _permit = acquire_permit(app, &provider).await;
+    acquire_slot(app, &provider, &provider.id).await;
+    let registration = register_stream(app, None);
+    let outcome = stream_provider_and_emit(
+        app,
+        "query:chunk",
+        &provider,
+        &api_key,
+        messages,
+        None,
+        registration.token(),
+    )
+    .await;
+
+    match outcome {
+        Ok(StreamOutcome::Streamed | StreamOutcome::TimeLimited) => return Ok(None),
+        Ok(StreamOutcome::NotStreamed) => {}
+        Err(err) if err == QUERY_CANCELLED => return Err(err),
+        // Part of the reply is already on screen; failing over or the
+        // placeholder would be appended to it.
+        Err(err) => {
+            crate::logging::log_nonfatal(format!(
+                "query_stream stream from {} broke off: {err}",
+                provider.name
+            ));
+            return Ok(None);
+        }
+    }
+    call_provider_logged(&provider, &api_key, messages)
+        .await
+        .map(Some)
+}
+
+/// `call_provider_and_get_text`, remembering failures for
+/// `replay_last_failed_request` while debug recording is on.
+async fn call_provider_logged(
+    provider: &Provider,
+    api_key: &str,
+    messages: &[ProviderChatMessage],
+) -> Result<String, String> {
+    let result = call_provider_and_get_text(provider, api_key, messages).await;
+    if result.is_err() {
+        record_failed_request(&provider.id, messages).await;
+    }
+    result
+}
+
+fn placeholder_response(provider: &Provider, prompt: &str, api_key: &str) -> String {
+    format!(
+        "You asked: '{}'\n\nUsing provider: {} (model: {})\nAPI key configured: {}\n\nThis is a placeholder response. Configure your API key in settings to get real AI responses.",
+        prompt,
+        provider.name,
+        provider.model,
+        if api_key.is_empty() { "No" } else { "Yes" }
+    )
+}
+
+pub async fn test_provider_connection(id: String) -> Result<ConnectionTestResult, String> {
+    let (provider, api_key) = tauri::async_runtime::spawn_blocking(move || {
+        let provider = ProvidersRepository::get(&id)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| "Provider not found".to_string())?;
+        let api_key = ProvidersRepository::get_api_key(&provider.id).map_err(|e| e.to_string())?;
+        Ok::<(Provider, String), String>((provider, api_key))
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    check_provider_connection(&provider, &api_key).await
+}
+
+/// Send a tiny `stream: true` request to provider `id` and check that deltas
+/// arrive. Unlike `test_provider_connection`, this catches endpoints that
+/// answer one-shot requests but break on streaming.
+pub async fn test_provider_streaming(id: String) -> Result<StreamTestResult, String> {
+    let (provider, api_key) = tauri::async_runtime::spawn_blocking(move || {
+        let provider = ProvidersRepository::get(&id)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| "Provider not found".to_string())?;
+        let api_key = ProvidersRepository::get_api_key(&provider.id).map_err(|e| e.to_string())?;
         Ok::<(Provider, String), String>((provider, api_key))
     })
     .await
     .map_err(|e| e.to_string())??;
 
+    check_provider_streaming(
+        &provider,
+        &api_key,
+        Duration::from_secs(STREAM_TEST_TIMEOUT_SECS),
+    )
+    .await
+}
+
+async fn check_provider_streaming(
+    provider: &Provider,
+    api_key: &str,
+    timeout: Duration,
+) -> Result<StreamTestResult, String> {
+    if api_key.trim().is_empty() {
+        return Ok(StreamTestResult::failure(
+            "API key is empty. Save API key before testing.".to_string(),
+        ));
+    }
+    let Some(base_url) = resolve_base_url(provider) else {
+        return Ok(StreamTestResult::failure(
+            "Base URL is empty. Set a valid base URL before testing.".to_string(),
+        ));
+    };
+
+    let client = reqwest::Client::builder()
+        .timeout(timeout)
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {e}"))?;
+    let messages = [ProviderChatMessage {
+        role: "user".to_string(),
+        content: "Reply with OK.".to_string(),
+    }];
+
+    let started_at = Instant::now();
+    let mut response = match build_chat_request(provider, &base_url, api_key, &messages, true)
+        .into_builder(&client, timeout)
+        .send()
+        .await
+    {
+        Ok(response) => response,
+        Err(e) => {
+            return Ok(StreamTestResult::failure(describe_request_error(
+                &e, timeout,
+            )))
+        }
+    };
+
+    let status = response.status();
+    if !status.is_success() {
+        let detail = response_excerpt(response).await;
+        if is_streaming_unsupported_error(status, &detail) {
+            mark_streaming_unsupported(&provider.id);
+            return Ok(StreamTestResult::failure(format!(
+                "Endpoint rejected streaming (HTTP {}). Replies will fall back to one-shot requests.",
+                status.as_u16()
+            )));
+        }
+        return Ok(StreamTestResult::failure(classify_http_failure(
+            status,
+            &provider.model,
+            &detail,
+        )));
+    }
+
+    let mut decoder = StreamDecoder::new(api_format(provider));
+    let mut streamed_chars = 0usize;
+    let mut first_chunk_ms = None;
+    let read = tokio::time::timeout(timeout, async {
+        while let Some(chunk) = response.chunk().await? {
+            for event in decoder.feed(&chunk) {
+                if let StreamEvent::Delta(delta) = event {
+                    first_chunk_ms.get_or_insert(started_at.elapsed().as_millis() as u64);
+                    streamed_chars += delta.chars().count();
+                }
+            }
+            if decoder.is_finished() {
+                break;
+            }
+        }
+        Ok::<(), reqwest::Error>(())
+    })
+    .await;
+
+    if streamed_chars == 0 {
+        let message = match read {
+            Err(_) => format!("No stream data within {}s.", timeout.as_secs()),
+            Ok(Err(e)) => format!("Failed reading SSE stream: {e}"),
+            Ok(Ok(())) if decoder.buffered_text().is_some() => {
+                "Endpoint ignored stream: true and sent a single JSON body.".to_string()
+            }
+            Ok(Ok(())) => "Stream ended without any text.".to_string(),
+        };
+        return Ok(StreamTestResult::failure(message));
+    }
+
+    clear_streaming_unsupported(&provider.id);
+    let mut message = format!(
+        "Streamed {streamed_chars} characters; first chunk after {} ms.",
+        first_chunk_ms.unwrap_or(0)
+    );
+    if !matches!(read, Ok(Ok(()))) {
+        message.push_str(" The stream did not finish cleanly.");
+    }
+    Ok(StreamTestResult {
+        ok: true,
+        streamed_chars,
+        first_chunk_ms,
+        message,
+    })
+}
+
+/// Stream `prompt` from provider `id` without a UI and time the reply, so
+/// providers can be compared on responsiveness.
+pub async fn benchmark_provider(id: String, prompt: String) -> Result<BenchmarkResult, String> {
+    let (provider, api_key) = tauri::async_runtime::spawn_blocking(move || {
+        let provider = ProvidersRepository::get(&id)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| "Provider not found".to_string())?;
+        let api_key = ProvidersRepository::get_api_key(&provider.id).map_err(|e| e.to_string())?;
+        Ok::<(Provider, String), String>((provider, api_key))
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    measure_stream(&provider, &api_key, &prompt).await
+}
+
+async fn measure_stream(
+    provider: &Provider,
+    api_key: &str,
+    prompt: &str,
+) -> Result<BenchmarkResult, String> {
+    if api_key.trim().is_empty() {
+        return Err("API key is empty. Save API key before benchmarking.".to_string());
+    }
+    if prompt.trim().is_empty() {
+        return Err("Benchmark prompt is empty.".to_string());
+    }
+    let messages = [ProviderChatMessage {
+        role: "user".to_string(),
+        content: prompt.to_string(),
+    }];
+
+    let started_at = Instant::now();
+    let mut first_token_ms = None;
+    let total_chars = stream_provider(
+        &mut |event| {
+            if matches!(&event, StreamEvent::Delta(delta) if !delta.is_empty()) {
+                first_token_ms.get_or_insert_with(|| elapsed_ms(started_at));
+            }
+            Ok(())
+        },
+        provider,
+        api_key,
+        &messages,
+        None,
+        None,
+        // Batching would delay the first token being measured.
+        StreamLimits::default(),
+    )
+    .await?;
+    let total_ms = elapsed_ms(started_at);
+
+    let first_token_ms = match first_token_ms {
+        Some(ms) if total_chars > 0 => ms,
+        _ => return Err("Provider did not stream any text.".to_string()),
+    };
+    Ok(BenchmarkResult {
+        first_token_ms,
+        total_ms,
+        total_chars,
+        chars_per_sec: total_chars as f64 / (total_ms.max(1) as f64 / 1000.0),
+    })
+}
+
+/// Unsaved provider settings, for testing a key before it is stored.
+fn transient_provider(
+    provider_type: ProviderType,
+    base_url: Option<String>,
+    model: Option<String>,
+) -> Provider {
+    let model = model
+        .map(|m| m.trim().to_string())
+        .filter(|m| !m.is_empty())
+        .unwrap_or_else(|| provider_type.default_model().to_string());
+    Provider {
+        id: String::new(),
+        name: String::new(),
+        provider_type,
+        base_url: base_url
+            .map(|url| url.trim().to_string())
+            .filter(|url| !url.is_empty()),
+        model,
+        is_active: false,
+        display_order: 0,
+        use_responses_api: false,
+        context_window: None,
+        requests_per_minute: None,
+        max_concurrency: None,
+        role: Default::default(),
+        created_at: 0,
+        updated_at: 0,
+    }
+}
+
+/// Run the connection test with a key that has not been saved; nothing is
+/// read from or written to the database.
+pub async fn test_api_key(
+    provider_type: ProviderType,
+    base_url: Option<String>,
+    model: Option<String>,
+    api_key: String,
+) -> Result<ConnectionTestResult, String> {
+    let provider = transient_provider(provider_type, base_url, model);
+    check_provider_connection(&provider, &api_key).await
+}
+
+/// Test whichever provider `get_chat_provider_with_key` picks, for the status indicator.
+pub async fn ping_active_provider() -> Result<ConnectionTestResult, String> {
+    let active =
+        tauri::async_runtime::spawn_blocking(ProvidersRepository::get_chat_provider_with_key)
+            .await
+            .map_err(|e| e.to_string())?
+            .map_err(|e| e.to_string())?;
+    ping_provider(active).await
+}
+
+async fn ping_provider(active: Option<(Provider, String)>) -> Result<ConnectionTestResult, String> {
+    match active {
+        Some((provider, api_key)) => check_provider_connection(&provider, &api_key).await,
+        None => Ok(ConnectionTestResult::failure(
+            None,
+            0,
+            "No active provider configured. Please configure a provider in Settings.".to_string(),
+        )),
+    }
+}
+
+/// How an OpenAI-compatible connection test reaches the provider.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ConnectionProbe {
+    /// `GET /models/{model}`: cheap, but only for plain model ids.
+    ModelLookup,
+    /// Minimal `POST /chat/completions`, which every compatible endpoint accepts.
+    ChatPing,
+}
+
+/// Gateways such as OpenRouter use ids like `anthropic/claude-3.5-sonnet`,
+/// which can't be looked up as a single `/models/{model}` path segment.
+fn openai_connection_probe(model: &str) -> ConnectionProbe {
+    if model.contains('/') {
+        ConnectionProbe::ChatPing
+    } else {
+        ConnectionProbe::ModelLookup
+    }
+}
+
+/// A failed model lookup is retried as a chat ping unless the key itself was rejected.
+fn should_retry_with_chat_ping(status: reqwest::StatusCode) -> bool {
+    !status.is_success()
+        && status != reqwest::StatusCode::UNAUTHORIZED
+        && status != reqwest::StatusCode::FORBIDDEN
+}
+
+async fn chat_completions_ping(
+    client: &reqwest::Client,
+    provider: &Provider,
+    base_url: &str,
+    api_key: &str,
+) -> Result<reqwest::Response, reqwest::Error> {
+    let mut builder = client.post(format!("{base_url}/chat/completions"));
+    for (name, value) in auth_headers(provider, base_url, api_key) {
+        builder = builder.header(name, value);
+    }
+    builder
+        .json(&serde_json::json!({
+            "model": provider.model,
+            "messages": [{ "role": "user", "content": "ping" }],
+            "max_tokens": 8
+        }))
+        .send()
+        .await
+}
+
+/// Send a minimal request to `provider` and classify the outcome.
+pub(super) async fn check_provider_connection(
+    provider: &Provider,
+    api_key: &str,
+) -> Result<ConnectionTestResult, String> {
     if api_key.trim().is_empty() {
         return Ok(ConnectionTestResult::failure(
             None,
@@ -652,7 +2043,7 @@ pub async fn test_provider_connection(id: String) -> Result<ConnectionTestResult
         ));
     }
 
-    let Some(base_url) = resolve_base_url(&provider) else {
+    let Some(base_url) = resolve_base_url(provider) else {
         return Ok(ConnectionTestResult::failure(
             None,
             0,
@@ -660,34 +2051,36 @@ pub async fn test_provider_connection(id: String) -> Result<ConnectionTestResult
         ));
     };
 
+    let timeout = Duration::from_secs(CONNECTION_TEST_TIMEOUT_SECS);
     let client = reqwest::Client::builder()
-        .timeout(Duration::from_secs(12))
+        .timeout(timeout)
         .build()
         .map_err(|e| format!("Failed to create HTTP client: {e}"))?;
 
     let started_at = Instant::now();
     let request_result = match provider.provider_type {
         ProviderType::OpenAI | ProviderType::Custom => {
-            let url = format!("{base_url}/models/{}", provider.model);
-            client
-                .get(url)
-                .header("Authorization", format!("Bearer {}", api_key.trim()))
-                .send()
-                .await
-        }
-        ProviderType::Glm => {
-            let url = format!("{base_url}/chat/completions");
-            client
-                .post(url)
-                .header("Authorization", format!("Bearer {}", api_key.trim()))
-                .json(&serde_json::json!({
-                    "model": provider.model,
-                    "messages": [{ "role": "user", "content": "ping" }],
-                    "max_tokens": 8
-                }))
-                .send()
-                .await
+            match openai_connection_probe(&provider.model) {
+                ConnectionProbe::ChatPing => {
+                    chat_completions_ping(&client, provider, &base_url, api_key).await
+                }
+                ConnectionProbe::ModelLookup => {
+                    let url = format!("{base_url}/models/{}", provider.model);
+                    let lookup = client
+                        .get(url)
+                        .header("Authorization", format!("Bearer {}", api_key.trim()))
+                        .send()
+                        .await;
+                    match lookup {
+                        Ok(resp) if should_retry_with_chat_ping(resp.status()) => {
+                            chat_completions_ping(&client, provider, &base_url, api_key).await
+                        }
+                        other => other,
+                    }
+                }
+            }
         }
+        ProviderType::Glm => chat_completions_ping(&client, provider, &base_url, api_key).await,
         ProviderType::Volcengine => {
             let url = format!("{base_url}/responses");
             client
@@ -751,57 +2144,78 @@ pub async fn test_provider_connection(id: String) -> Result<ConnectionTestResult
         Err(err) => Ok(ConnectionTestResult::failure(
             None,
             elapsed_ms(started_at),
-            format!("Network error: {err}"),
+            describe_request_error(&err, timeout),
         )),
     }
 }
 
 #[tauri::command]
 pub async fn query_stream(prompt: String, app: AppHandle) -> Result<(), String> {
-    let messages = normalize_messages(None, &prompt)?;
-
-    // Get the active provider with its API key
-    let active_provider =
-        tauri::async_runtime::spawn_blocking(ProvidersRepository::get_active_with_key)
-            .await
-            .map_err(|e| e.to_string())?
-            .map_err(|e| e.to_string())?;
+    ensure_chat_enabled().await?;
+    let messages = prepare_messages(&app, None, &prompt).await?;
+
+    // The active provider with its API key, followed by the providers to
+    // fail over to when the `provider_failover` setting is on.
+    let candidates = tauri::async_runtime::spawn_blocking(|| {
+        let failover = crate::parse_bool_setting(
+            SettingsRepository::get(crate::SETTING_PROVIDER_FAILOVER)
+                .ok()
+                .flatten(),
+            false,
+        );
+        let mut chain =
+            ProvidersRepository::get_chat_failover_chain().map_err(|e| e.to_string())?;
+        chain.truncate(if failover { MAX_FAILOVER_PROVIDERS } else { 1 });
+        Ok::<_, String>(chain)
+    })
+    .await
+    .map_err(|e| e.to_string())??;
 
-    match active_provider {
-        Some((provider, api_key)) => {
-            let streamed =
-                stream_provider_and_emit(&app, "query:chunk", &provider, &api_key, &messages)
-                    .await
-                    .unwrap_or(0);
+    let Some((primary, primary_key)) = candidates.first().cloned() else {
+        // No active provider or no API key
+        let response = "No active provider configured. Please configure a provider in Settings.";
 
-            if streamed > 0 {
-                return Ok(());
-            }
-
-            let response = match call_provider_and_get_text(&provider, &api_key, &messages).await {
-                Ok(text) => text,
-                Err(err) => {
-                    eprintln!("query_stream provider call failed: {err}");
-                    placeholder_response(&provider, &prompt, &api_key)
-                }
-            };
+        app.emit("query:chunk", response.to_string())
+            .map_err(|e| e.to_string())?;
 
-            app.emit("query:chunk", response)
-                .map_err(|e| e.to_string())?;
+        return Ok(());
+    };
 
-            Ok(())
+    let app_ref = &app;
+    let messages_ref = &messages;
+    let result = with_failover(
+        &candidates,
+        move |provider, api_key| query_stream_attempt(app_ref, provider, api_key, messages_ref),
+        |from, to, err| {
+            crate::logging::log_nonfatal(format!(
+                "query_stream failing over from {} to {}: {err}",
+                from.name, to.name
+            ));
+            let _ = app.emit(
+                "query:failover",
+                FailoverPayload {
+                    from_provider_id: from.id.clone(),
+                    from_provider_name: from.name.clone(),
+                    to_provider_id: to.id.clone(),
+                    to_provider_name: to.name.clone(),
+                    error: err.to_string(),
+                },
+            );
+        },
+    )
+    .await;
+
+    let response = match result {
+        Ok(None) => return Ok(()),
+        Ok(Some(text)) => text,
+        Err(err) if err == QUERY_CANCELLED => return Err(err),
+        Err(err) => {
+            eprintln!("query_stream provider call failed: {err}");
+            placeholder_response(&primary, &prompt, &primary_key)
         }
-        None => {
-            // No active provider or no API key
-            let response =
-                "No active provider configured. Please configure a provider in Settings.";
-
-            app.emit("query:chunk", response.to_string())
-                .map_err(|e| e.to_string())?;
+    };
 
-            Ok(())
-        }
-    }
+    emit_full_reply(&app, "query:chunk", response)
 }
 
 #[tauri::command]
@@ -809,7 +2223,9 @@ pub async fn query_provider_once(
     provider_id: String,
     prompt: String,
     history: Option<Vec<ProviderChatMessage>>,
+    app: AppHandle,
 ) -> Result<String, String> {
+    ensure_chat_enabled().await?;
     let provider_data = tauri::async_runtime::spawn_blocking(move || {
         let provider = ProvidersRepository::get(&provider_id)
             .map_err(|e| e.to_string())?
@@ -822,18 +2238,46 @@ pub async fn query_provider_once(
     .map_err(|e| e.to_string())?;
 
     let (provider, api_key) = provider_data;
-    let messages = normalize_messages(history, &prompt)?;
-    call_provider_and_get_text(&provider, &api_key, &messages).await
+    let messages = prepare_messages(&app, history, &prompt).await?;
+    let _permit = acquire_permit(&app, &provider).await;
+    acquire_slot(&app, &provider, &provider.id).await;
+    call_provider_logged(&provider, &api_key, &messages).await
 }
 
+/// One-shot request to the utility provider, for auto-titling and inline tasks.
 #[tauri::command]
+pub async fn query_utility_once(
+    prompt: String,
+    history: Option<Vec<ProviderChatMessage>>,
+    app: AppHandle,
+) -> Result<String, String> {
+    ensure_chat_enabled().await?;
+    let (provider, api_key) =
+        tauri::async_runtime::spawn_blocking(ProvidersRepository::get_utility_provider_with_key)
+            .await
+            .map_err(|e| e.to_string())?
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| "No active provider with an API key".to_string())?;
+
+    let messages = prepare_messages(&app, history, &prompt).await?;
+    let _permit = acquire_permit(&app, &provider).await;
+    acquire_slot(&app, &provider, &provider.id).await;
+    call_provider_logged(&provider, &api_key, &messages).await
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn query_stream_provider(
     provider_id: String,
     prompt: String,
     history: Option<Vec<ProviderChatMessage>>,
     stream_key: Option<String>,
+    session_id: Option<String>,
+    column_id: Option<String>,
+    message_id: Option<String>,
     app: AppHandle,
 ) -> Result<(), String> {
+    ensure_chat_enabled().await?;
     // Get the specific provider with its API key
     let provider_data = tauri::async_runtime::spawn_blocking(move || {
         let provider = ProvidersRepository::get(&provider_id)
@@ -850,19 +2294,1257 @@ pub async fn query_stream_provider(
 
     // Emit chunks with a caller-provided stream key so duplicate providers
     // in multiple columns do not conflict on the same event channel.
-    let event_name = stream_key
-        .map(|v| format!("query:chunk:{v}"))
-        .unwrap_or_else(|| format!("query:chunk:{}", provider.id));
-    let messages = normalize_messages(history, &prompt)?;
-    let streamed = stream_provider_and_emit(&app, &event_name, &provider, &api_key, &messages)
+    let stream_key = stream_key.unwrap_or_else(|| provider.id.clone());
+    let event_name = format!("query:chunk:{stream_key}");
+    // Register before queueing so a stream still waiting for a permit or a
+    // rate-limit slot can be cancelled too.
+    let registration = register_stream(&app, column_id.as_deref());
+    let messages = prepare_messages(&app, history, &prompt).await?;
+    let _permit = tokio::select! {
+        permit = acquire_permit(&app, &provider) => permit,
+        _ = registration.token().cancelled() => return Err(QUERY_CANCELLED.to_string()),
+    };
+    tokio::select! {
+        _ = acquire_slot(&app, &provider, &stream_key) => {}
+        _ = registration.token().cancelled() => return Err(QUERY_CANCELLED.to_string()),
+    }
+
+    // When the caller names the assistant message, persist it from here so the
+    // reply survives even if the frontend never gets to save it.
+    let mut persistence = match (message_id, session_id, column_id) {
+        (Some(message_id), Some(session_id), Some(column_id)) => Some(
+            StreamPersistence::begin(message_id, session_id, column_id, provider.id.clone())
+                .await?,
+        ),
+        _ => None,
+    };
+    // A cancel that landed while the row was being created (e.g. `abort_column`
+    // deleting it first) must not leave the row behind.
+    if registration.token().is_cancelled() {
+        if let Some(persistence) = persistence {
+            persistence.discard().await;
+        }
+        return Err(QUERY_CANCELLED.to_string());
+    }
+    if let Some(persistence) = &persistence {
+        store_message_request(&persistence.message_id, &provider, &api_key, &messages).await;
+    }
+
+    let outcome = stream_provider_and_emit(
+        &app,
+        &event_name,
+        &provider,
+        &api_key,
+        &messages,
+        persistence.as_mut(),
+        registration.token(),
+    )
+    .await;
+    match outcome {
+        Ok(StreamOutcome::Streamed | StreamOutcome::TimeLimited) => {
+            if let Some(persistence) = persistence {
+                persistence.finish("done").await;
+            }
+            return Ok(());
+        }
+        Ok(StreamOutcome::NotStreamed) => {}
+        // Cancelled, or failed after part of the reply went out: keep what
+        // arrived rather than retrying on top of it.
+        Err(err) => {
+            if let Some(mut persistence) = persistence {
+                if persistence.content.trim().is_empty() {
+                    persistence.content = format!("Error: {err}");
+                }
+                persistence.finish("error").await;
+            }
+            return Err(err);
+        }
+    }
+
+    let response = match call_provider_logged(&provider, &api_key, &messages).await {
+        Ok(text) => text,
+        Err(err) => {
+            if let Some(mut persistence) = persistence {
+                if persistence.content.trim().is_empty() {
+                    persistence.content = format!("Error: {err}");
+                }
+                persistence.finish("error").await;
+            }
+            return Err(err);
+        }
+    };
+    if let Some(mut persistence) = persistence {
+        persistence.push(&response).await;
+        persistence.finish("done").await;
+    }
+    emit_full_reply(&app, &event_name, response)
+}
+
+/// Re-send the most recent failed request logged for `provider_id`, to check
+/// whether a transient error has cleared. Requests are only logged while the
+/// `provider_debug_record` setting is on.
+#[tauri::command]
+pub async fn replay_last_failed_request(
+    provider_id: String,
+    app: AppHandle,
+) -> Result<String, String> {
+    ensure_chat_enabled().await?;
+    if !debug_recording_enabled().await {
+        return Err(
+            "Turn on provider debug recording to log and replay failed requests.".to_string(),
+        );
+    }
+    let messages = last_failed_request(&provider_id)
+        .ok_or_else(|| "No failed request logged for this provider".to_string())?;
+    let (provider, api_key) = tauri::async_runtime::spawn_blocking(move || {
+        let provider = ProvidersRepository::get(&provider_id)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| "Provider not found".to_string())?;
+        let api_key = ProvidersRepository::get_api_key(&provider.id).map_err(|e| e.to_string())?;
+        Ok::<(Provider, String), String>((provider, api_key))
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    let _permit = acquire_permit(&app, &provider).await;
+    acquire_slot(&app, &provider, &provider.id).await;
+    call_provider_logged(&provider, &api_key, &messages).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provider(provider_type: ProviderType, use_responses_api: bool) -> Provider {
+        Provider {
+            provider_type,
+            use_responses_api,
+            ..crate::provider::test_provider()
+        }
+    }
+
+    fn messages() -> Vec<ProviderChatMessage> {
+        vec![ProviderChatMessage {
+            role: "user".to_string(),
+            content: "hello".to_string(),
+        }]
+    }
+
+    #[test]
+    fn test_openai_defaults_to_chat_completions() {
+        let provider = provider(ProviderType::OpenAI, false);
+        let request = build_chat_request(
+            &provider,
+            "https://api.openai.com/v1",
+            " sk-test ",
+            &messages(),
+            true,
+        );
+
+        assert_eq!(request.url, "https://api.openai.com/v1/chat/completions");
+        assert_eq!(
+            request.headers,
+            vec![("Authorization", "Bearer sk-test".to_string())]
+        );
+        assert_eq!(request.body["messages"][0]["content"], "hello");
+        assert_eq!(request.body["temperature"], 0.7);
+        assert_eq!(request.body["stream"], true);
+    }
+
+    #[test]
+    fn test_openai_responses_flag_builds_responses_request() {
+        let provider = provider(ProviderType::OpenAI, true);
+        let streaming = build_chat_request(
+            &provider,
+            "https://api.openai.com/v1",
+            "sk-test",
+            &messages(),
+            true,
+        );
+        assert_eq!(streaming.url, "https://api.openai.com/v1/responses");
+        assert_eq!(streaming.body["input"][0]["content"], "hello");
+        assert_eq!(streaming.body["stream"], true);
+        assert!(streaming.body.get("messages").is_none());
+
+        let once = build_chat_request(
+            &provider,
+            "https://api.openai.com/v1",
+            "sk-test",
+            &messages(),
+            false,
+        );
+        assert_eq!(once.url, "https://api.openai.com/v1/responses");
+        assert_eq!(once.body["max_output_tokens"], 4096);
+        assert!(once.body.get("stream").is_none());
+    }
+
+    #[test]
+    fn test_redacted_payload_matches_request_without_key() {
+        for (provider_type, base_url) in [
+            (ProviderType::OpenAI, "https://api.openai.com/v1"),
+            (ProviderType::Anthropic, "https://api.anthropic.com/v1"),
+            (
+                ProviderType::Google,
+                "https://generativelanguage.googleapis.com/v1beta",
+            ),
+        ] {
+            let request = build_chat_request(
+                &provider(provider_type, false),
+                base_url,
+                "sk-secret",
+                &messages(),
+                true,
+            );
+            let payload = request.redacted_payload();
+
+            assert_eq!(payload["url"], request.url);
+            assert_eq!(payload["body"], request.body);
+            assert!(
+                !payload.to_string().contains("sk-secret"),
+                "{provider_type}: {payload}"
+            );
+            for (section, pairs) in [("headers", &request.headers), ("query", &request.query)] {
+                for (name, value) in pairs {
+                    let expected = if value.contains("sk-secret") {
+                        REDACTED
+                    } else {
+                        value.as_str()
+                    };
+                    assert_eq!(payload[section][*name], expected, "{provider_type} {name}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_effective_config_per_provider_type() {
+        let cases = [
+            (
+                ProviderType::OpenAI,
+                Some("https://api.openai.com/v1"),
+                "/chat/completions",
+                Some("Authorization"),
+            ),
+            (
+                ProviderType::Glm,
+                Some("https://open.bigmodel.cn/api/paas/v4"),
+                "/chat/completions",
+                Some("Authorization"),
+            ),
+            (
+                ProviderType::Anthropic,
+                Some("https://api.anthropic.com/v1"),
+                "/messages",
+                Some("x-api-key"),
+            ),
+            (
+                ProviderType::Google,
+                Some("https://generativelanguage.googleapis.com/v1beta"),
+                "/models/gpt-4o-mini:streamGenerateContent",
+                None,
+            ),
+            (
+                ProviderType::Volcengine,
+                Some("https://ark.cn-beijing.volces.com/api/v3"),
+                "/responses",
+                Some("Authorization"),
+            ),
+            (
+                ProviderType::Custom,
+                None,
+                "/chat/completions",
+                Some("Authorization"),
+            ),
+        ];
+
+        for (provider_type, base_url, endpoint_path, auth_header_name) in cases {
+            let config = effective_config(&provider(provider_type, false), true);
+            assert_eq!(config.provider_type, provider_type);
+            assert_eq!(config.resolved_base_url.as_deref(), base_url);
+            assert_eq!(config.endpoint_path, endpoint_path, "{provider_type}");
+            assert_eq!(config.auth_header_name.as_deref(), auth_header_name);
+            assert_eq!(config.model, "gpt-4o-mini");
+            assert!(config.has_api_key);
+        }
+
+        let custom = Provider {
+            base_url: Some(" https://gateway.example.com/api/v1/ ".to_string()),
+            ..provider(ProviderType::Custom, false)
+        };
+        let config = effective_config(&custom, false);
+        assert_eq!(
+            config.resolved_base_url.as_deref(),
+            Some("https://gateway.example.com/api/v1")
+        );
+        assert!(!config.has_api_key);
+        assert_eq!(
+            effective_config(&provider(ProviderType::OpenAI, true), true).endpoint_path,
+            "/responses"
+        );
+    }
+
+    #[test]
+    fn test_gateway_headers_only_for_known_hosts() {
+        let mut custom = provider(ProviderType::Custom, false);
+        let header_names = |request: &ProviderRequest| {
+            request
+                .headers
+                .iter()
+                .map(|(name, _)| *name)
+                .collect::<Vec<_>>()
+        };
+
+        let request = build_chat_request(&custom, "https://openrouter.ai/api/v1", "sk", &[], true);
+        assert_eq!(
+            header_names(&request),
+            vec!["Authorization", "HTTP-Referer", "X-Title"]
+        );
+        assert!(request
+            .headers
+            .contains(&("X-Title", "AI Quick Search".to_string())));
+
+        let request =
+            build_chat_request(&custom, "https://gateway.example.com/v1", "sk", &[], true);
+        assert_eq!(header_names(&request), vec!["Authorization"]);
+        // Look-alike hosts do not match.
+        assert!(gateway_default_headers("https://notopenrouter.ai/v1").is_empty());
+        // The connection test ping sends the same headers as chat requests.
+        assert_eq!(
+            auth_headers(&custom, "https://openrouter.ai/api/v1", " sk "),
+            build_chat_request(&custom, "https://openrouter.ai/api/v1", "sk", &[], true).headers
+        );
+
+        custom.provider_type = ProviderType::OpenAI;
+        let request = build_chat_request(&custom, "https://openrouter.ai/api/v1", "sk", &[], true);
+        assert_eq!(header_names(&request), vec!["Authorization"]);
+    }
+
+    #[test]
+    fn test_responses_flag_only_applies_to_openai() {
+        assert_eq!(
+            api_format(&provider(ProviderType::Glm, true)),
+            ApiFormat::ChatCompletions
+        );
+        assert_eq!(
+            api_format(&provider(ProviderType::Anthropic, true)),
+            ApiFormat::Anthropic
+        );
+        assert_eq!(
+            api_format(&provider(ProviderType::Volcengine, false)),
+            ApiFormat::Responses
+        );
+    }
+
+    #[test]
+    fn test_parse_request_timeout_secs() {
+        assert_eq!(parse_request_timeout_secs(Some(" 90 ")), 90);
+        assert_eq!(parse_request_timeout_secs(Some("0")), 0);
+        assert_eq!(parse_request_timeout_secs(Some("slow")), 0);
+        assert_eq!(parse_request_timeout_secs(None), 0);
+        assert_eq!(
+            parse_request_timeout_secs(Some("100000")),
+            MAX_REQUEST_TIMEOUT_SECS
+        );
+    }
+
+    fn prompt_messages(prompt: &str) -> Vec<ProviderChatMessage> {
+        normalize_messages(
+            Some(vec![
+                ProviderChatMessage {
+                    role: "user".to_string(),
+                    content: "earlier question that is long".to_string(),
+                },
+                ProviderChatMessage {
+                    role: "assistant".to_string(),
+                    content: "earlier answer".to_string(),
+                },
+                ProviderChatMessage {
+                    role: "user".to_string(),
+                    content: prompt.to_string(),
+                },
+            ]),
+            prompt,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_prompt_limit_rejects_oversized_prompt() {
+        let limit = PromptLimit {
+            max_chars: 10,
+            overflow: PromptOverflow::parse(None),
+        };
+        let mut messages = prompt_messages("0123456789abc");
+        let err = apply_prompt_limit(&mut messages, limit).unwrap_err();
+        assert!(err.contains("13 characters"), "{err}");
+        assert!(err.contains("limit is 10"), "{err}");
+
+        // At the limit nothing changes, and only the latest prompt is measured.
+        let mut messages = prompt_messages("0123456789");
+        assert_eq!(apply_prompt_limit(&mut messages, limit).unwrap(), None);
+        assert_eq!(messages[2].content, "0123456789");
+        assert_eq!(parse_max_prompt_chars(Some("0")), DEFAULT_MAX_PROMPT_CHARS);
+    }
+
+    #[test]
+    fn test_prompt_limit_truncates_with_warning() {
+        let limit = PromptLimit {
+            max_chars: 4,
+            overflow: PromptOverflow::parse(Some(" Truncate ")),
+        };
+        let mut messages = prompt_messages("你好世界和平");
+        let truncated = apply_prompt_limit(&mut messages, limit).unwrap();
+        assert_eq!(
+            truncated,
+            Some(PromptTruncated {
+                original_chars: 6,
+                kept_chars: 4,
+            })
+        );
+        assert_eq!(messages[2].content, "你好世界");
+        assert_eq!(messages[0].content, "earlier question that is long");
+    }
+
+    #[tokio::test]
+    async fn test_timeout_error_message_includes_timeout() {
+        // Accept the connection but never answer, so the client times out.
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (_socket, _) = listener.accept().await.unwrap();
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        });
+
+        let timeout = Duration::from_millis(200);
+        let client = reqwest::Client::builder().timeout(timeout).build().unwrap();
+        let err = client
+            .get(format!("http://{addr}/"))
+            .send()
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            describe_request_error(&err, Duration::from_secs(40)),
+            "Request timed out after 40s. Increase the request timeout in settings if the provider is slow."
+        );
+        server.abort();
+    }
+
+    #[test]
+    fn test_parse_responses_output_text_delta() {
+        let format = api_format(&provider(ProviderType::OpenAI, true));
+        let event = serde_json::json!({
+            "type": "response.output_text.delta",
+            "delta": "Hel"
+        });
+        assert_eq!(parse_stream_delta(format, &event), Some("Hel".to_string()));
+
+        let done = serde_json::json!({
+            "type": "response.completed",
+            "response": { "status": "completed" }
+        });
+        assert_eq!(parse_stream_delta(format, &done), None);
+
+        let body = serde_json::json!({
+            "output": [{
+                "type": "message",
+                "content": [{ "type": "output_text", "text": "Hello" }]
+            }]
+        });
+        assert_eq!(
+            parse_provider_text(format, &body),
+            Some("Hello".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_anthropic_stream_sequence() {
+        let events = [
+            r#"{"type":"message_start","message":{"id":"msg_1","usage":{"input_tokens":12,"output_tokens":1}}}"#,
+            r#"{"type":"content_block_start","index":0,"content_block":{"type":"text","text":""}}"#,
+            r#"{"type":"ping"}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hello"}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":" world"}}"#,
+            r#"{"type":"content_block_stop","index":0}"#,
+            r#"{"type":"message_delta","delta":{"stop_reason":"end_turn","stop_sequence":null},"usage":{"output_tokens":15}}"#,
+            r#"{"type":"message_stop"}"#,
+        ];
+        let parsed = events
+            .iter()
+            .filter_map(|raw| {
+                let body: serde_json::Value = serde_json::from_str(raw).unwrap();
+                parse_stream_event(ApiFormat::Anthropic, &body)
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            parsed,
+            vec![
+                StreamEvent::Meta(StreamMeta {
+                    input_tokens: Some(12),
+                    ..Default::default()
+                }),
+                StreamEvent::Delta("Hello".to_string()),
+                StreamEvent::Delta(" world".to_string()),
+                StreamEvent::Meta(StreamMeta {
+                    stop_reason: Some("end_turn".to_string()),
+                    input_tokens: None,
+                    output_tokens: Some(15),
+                }),
+                StreamEvent::Stop,
+            ]
+        );
+
+        // Only text is surfaced through the plain delta parser.
+        let stop: serde_json::Value = serde_json::from_str(events[6]).unwrap();
+        assert_eq!(parse_stream_delta(ApiFormat::Anthropic, &stop), None);
+    }
+
+    #[tokio::test]
+    async fn test_ping_provider_uses_active_provider() {
+        let (addr, server) = spawn_mock_server(vec![
+            "HTTP/1.1 200 OK\r\ncontent-length: 2\r\nconnection: close\r\n\r\n{}",
+        ])
+        .await;
+
+        let active = Provider {
+            base_url: Some(format!("http://{addr}/v1")),
+            model: "gpt-test".to_string(),
+            ..provider(ProviderType::OpenAI, false)
+        };
+        let result = ping_provider(Some((active, "sk-test".to_string())))
+            .await
+            .unwrap();
+        assert!(result.success);
+        assert_eq!(result.status_code, Some(200));
+        assert!(result.message.contains("gpt-test"));
+
+        let requests = server.await.unwrap();
+        assert!(requests[0].starts_with("GET /v1/models/gpt-test "));
+
+        let none = ping_provider(None).await.unwrap();
+        assert!(!none.success);
+        assert!(none.message.contains("No active provider"));
+    }
+
+    /// Serve one canned HTTP response per connection, returning the raw requests.
+    async fn spawn_mock_server(
+        responses: Vec<impl AsRef<[u8]> + Send + 'static>,
+    ) -> (std::net::SocketAddr, tokio::task::JoinHandle<Vec<String>>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let mut requests = Vec::new();
+            for response in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buf = vec![0u8; 8192];
+                let read = socket.read(&mut buf).await.unwrap();
+                requests.push(String::from_utf8_lossy(&buf[..read]).to_string());
+                socket.write_all(response.as_ref()).await.unwrap();
+            }
+            requests
+        });
+        (addr, server)
+    }
+
+    #[test]
+    fn test_connection_probe_for_gateway_model_ids() {
+        assert_eq!(
+            openai_connection_probe("gpt-4o-mini"),
+            ConnectionProbe::ModelLookup
+        );
+        assert_eq!(
+            openai_connection_probe("anthropic/claude-3.5-sonnet"),
+            ConnectionProbe::ChatPing
+        );
+        assert!(should_retry_with_chat_ping(reqwest::StatusCode::NOT_FOUND));
+        assert!(!should_retry_with_chat_ping(
+            reqwest::StatusCode::UNAUTHORIZED
+        ));
+        assert!(!should_retry_with_chat_ping(reqwest::StatusCode::OK));
+    }
+
+    #[tokio::test]
+    async fn test_gateway_connection_test_uses_chat_ping() {
+        let (addr, server) = spawn_mock_server(vec![
+            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: 44\r\nconnection: close\r\n\r\n{\"choices\":[{\"message\":{\"content\":\"pong\"}}]}",
+        ])
+        .await;
+        let gateway = Provider {
+            base_url: Some(format!("http://{addr}/api/v1")),
+            model: "anthropic/claude-3.5-sonnet".to_string(),
+            ..provider(ProviderType::Custom, false)
+        };
+
+        let result = check_provider_connection(&gateway, "sk-test")
+            .await
+            .unwrap();
+        assert!(result.success, "{}", result.message);
+        let requests = server.await.unwrap();
+        assert!(requests[0].starts_with("POST /api/v1/chat/completions "));
+    }
+
+    #[tokio::test]
+    async fn test_api_key_success_and_unauthorized() {
+        let (addr, server) = spawn_mock_server(vec![
+            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: 20\r\nconnection: close\r\n\r\n{\"id\":\"gpt-4o-mini\"}",
+            "HTTP/1.1 401 Unauthorized\r\ncontent-type: application/json\r\ncontent-length: 35\r\nconnection: close\r\n\r\n{\"error\":{\"message\":\"bad key\"}}",
+        ])
+        .await;
+        let base_url = Some(format!("http://{addr}/v1"));
+        let model = Some("gpt-4o-mini".to_string());
+
+        let ok = test_api_key(
+            ProviderType::Custom,
+            base_url.clone(),
+            model.clone(),
+            "sk-good".into(),
+        )
         .await
-        .unwrap_or(0);
-    if streamed > 0 {
-        return Ok(());
+        .unwrap();
+        assert!(ok.success, "{}", ok.message);
+        assert_eq!(ok.status_code, Some(200));
+
+        let denied = test_api_key(ProviderType::Custom, base_url, model, "sk-bad".into())
+            .await
+            .unwrap();
+        assert!(!denied.success);
+        assert_eq!(denied.status_code, Some(401));
+
+        let requests = server.await.unwrap();
+        assert!(requests[0].starts_with("GET /v1/models/gpt-4o-mini "));
+        assert!(requests[0].contains("Bearer sk-good"));
+        assert!(requests[1].contains("Bearer sk-bad"));
     }
 
-    let response = call_provider_and_get_text(&provider, &api_key, &messages).await?;
-    app.emit(&event_name, response).map_err(|e| e.to_string())?;
+    #[tokio::test]
+    async fn test_failed_model_lookup_falls_back_to_chat_ping() {
+        let (addr, server) = spawn_mock_server(vec![
+            "HTTP/1.1 404 Not Found\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: 44\r\nconnection: close\r\n\r\n{\"choices\":[{\"message\":{\"content\":\"pong\"}}]}",
+        ])
+        .await;
+        let custom = Provider {
+            base_url: Some(format!("http://{addr}/v1")),
+            ..provider(ProviderType::Custom, false)
+        };
 
-    Ok(())
+        let result = check_provider_connection(&custom, "sk-test").await.unwrap();
+        assert!(result.success, "{}", result.message);
+        let requests = server.await.unwrap();
+        assert!(requests[0].starts_with("GET /v1/models/"));
+        assert!(requests[1].starts_with("POST /v1/chat/completions "));
+    }
+
+    /// Serve a recorded stream from `provider/fixtures` over loopback HTTP and
+    /// run it through `stream_sse_response`, split mid-frame so the decoder
+    /// has to buffer across chunks.
+    async fn replay_stream(file: &str, format: ApiFormat) -> (Vec<StreamEvent>, usize) {
+        replay_stream_batched(file, format, Duration::ZERO).await
+    }
+
+    async fn replay_stream_batched(
+        file: &str,
+        format: ApiFormat,
+        batch_window: Duration,
+    ) -> (Vec<StreamEvent>, usize) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("src/provider/fixtures")
+            .join(file);
+        let body = std::fs::read(&path).unwrap();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0u8; 8192];
+            let _ = socket.read(&mut buf).await.unwrap();
+            let headers = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+                body.len()
+            );
+            socket.write_all(headers.as_bytes()).await.unwrap();
+            let (head, tail) = body.split_at(body.len() / 2);
+            socket.write_all(head).await.unwrap();
+            socket.flush().await.unwrap();
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            socket.write_all(tail).await.unwrap();
+        });
+
+        let response = reqwest::get(format!("http://{addr}/")).await.unwrap();
+        let mut events = Vec::new();
+        let emitted = stream_sse_response(
+            &mut |event| {
+                events.push(event);
+                Ok(())
+            },
+            format,
+            response,
+            Duration::from_secs(5),
+            StreamLimits {
+                batch_window,
+                ..Default::default()
+            },
+            None,
+            None,
+            &[],
+        )
+        .await
+        .unwrap();
+        server.await.unwrap();
+        (events, emitted)
+    }
+
+    #[tokio::test]
+    async fn test_replay_recorded_openai_stream() {
+        let (events, emitted) =
+            replay_stream("openai_chat_completions.sse", ApiFormat::ChatCompletions).await;
+
+        assert_eq!(
+            events,
+            vec![
+                StreamEvent::Delta("Hello".to_string()),
+                StreamEvent::Delta(", world".to_string()),
+                StreamEvent::Delta("!".to_string()),
+                StreamEvent::Done {
+                    content_hash: content_hash("Hello, world!"),
+                    finish_reason: None,
+                },
+            ]
+        );
+        assert_eq!(emitted, "Hello, world!".chars().count());
+    }
+
+    #[tokio::test]
+    async fn test_batched_stream_coalesces_deltas_in_order() {
+        let deltas = |events: &[StreamEvent]| {
+            events
+                .iter()
+                .filter_map(|event| match event {
+                    StreamEvent::Delta(text) => Some(text.clone()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        let (unbatched, _) =
+            replay_stream("openai_chat_completions.sse", ApiFormat::ChatCompletions).await;
+        let (batched, emitted) = replay_stream_batched(
+            "openai_chat_completions.sse",
+            ApiFormat::ChatCompletions,
+            Duration::from_millis(250),
+        )
+        .await;
+
+        assert_eq!(deltas(&unbatched).len(), 3);
+        assert_eq!(deltas(&batched), vec!["Hello, world!"]);
+        assert_eq!(emitted, "Hello, world!".chars().count());
+        assert_eq!(batched.last(), unbatched.last());
+        assert!(batched.len() < unbatched.len());
+    }
+
+    #[tokio::test]
+    async fn test_stream_stops_at_max_duration() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        const FRAMES: usize = 100;
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        // A runaway generation: a delta every 20ms for far longer than the cap.
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0u8; 8192];
+            let _ = socket.read(&mut buf).await.unwrap();
+            let headers =
+                "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\nconnection: close\r\n\r\n";
+            socket.write_all(headers.as_bytes()).await.unwrap();
+            for _ in 0..FRAMES {
+                let frame = "data: {\"choices\":[{\"delta\":{\"content\":\"word \"}}]}\n\n";
+                if socket.write_all(frame.as_bytes()).await.is_err() {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        });
+
+        let response = reqwest::get(format!("http://{addr}/")).await.unwrap();
+        let started_at = Instant::now();
+        let mut events = Vec::new();
+        let emitted = stream_sse_response(
+            &mut |event| {
+                events.push(event);
+                Ok(())
+            },
+            ApiFormat::ChatCompletions,
+            response,
+            Duration::from_secs(5),
+            StreamLimits {
+                max_duration: Some(Duration::from_millis(150)),
+                ..Default::default()
+            },
+            None,
+            None,
+            &[],
+        )
+        .await
+        .unwrap();
+        assert!(started_at.elapsed() < Duration::from_secs(1));
+        server.abort();
+
+        let text: String = events
+            .iter()
+            .filter_map(|event| match event {
+                StreamEvent::Delta(text) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert!(!text.is_empty());
+        assert!(text.len() < FRAMES * "word ".len());
+        assert_eq!(emitted, text.chars().count());
+        assert_eq!(
+            events.last(),
+            Some(&StreamEvent::Done {
+                content_hash: content_hash(&text),
+                finish_reason: Some(FINISH_REASON_TIME_LIMIT),
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_max_stream_duration_secs() {
+        assert_eq!(parse_max_stream_duration_secs(None), 0);
+        assert_eq!(parse_max_stream_duration_secs(Some(" 90 ")), 90);
+        assert_eq!(parse_max_stream_duration_secs(Some("-1")), 0);
+        assert_eq!(
+            parse_max_stream_duration_secs(Some("999999")),
+            MAX_STREAM_DURATION_SECS
+        );
+    }
+
+    #[test]
+    fn test_parse_stream_batch_ms() {
+        assert_eq!(parse_stream_batch_ms(None), 0);
+        assert_eq!(parse_stream_batch_ms(Some(" 30 ")), 30);
+        assert_eq!(parse_stream_batch_ms(Some("-5")), 0);
+        assert_eq!(parse_stream_batch_ms(Some("9000")), MAX_STREAM_BATCH_MS);
+    }
+
+    #[tokio::test]
+    async fn test_stream_provider_feeds_sink_without_tauri() {
+        const BODY: &str = "event: message_start\ndata: {\"type\":\"message_start\",\"message\":{\"usage\":{\"input_tokens\":9}}}\n\nevent: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"Hi\"}}\n\nevent: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\" there\"}}\n\nevent: message_delta\ndata: {\"type\":\"message_delta\",\"delta\":{\"stop_reason\":\"end_turn\"},\"usage\":{\"output_tokens\":2}}\n\nevent: message_stop\ndata: {\"type\":\"message_stop\"}\n\n";
+        let response = format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{BODY}",
+            BODY.len()
+        );
+        let (addr, server) = spawn_mock_server(vec![response]).await;
+        let anthropic = Provider {
+            base_url: Some(format!("http://{addr}/v1")),
+            ..provider(ProviderType::Anthropic, false)
+        };
+
+        let mut events = Vec::new();
+        let streamed = stream_provider(
+            &mut |event| {
+                events.push(event);
+                Ok(())
+            },
+            &anthropic,
+            "sk-test",
+            &messages(),
+            None,
+            None,
+            StreamLimits::default(),
+        )
+        .await
+        .unwrap();
+        server.await.unwrap();
+
+        assert_eq!(streamed, "Hi there".len());
+        assert_eq!(
+            events,
+            vec![
+                StreamEvent::Meta(StreamMeta {
+                    input_tokens: Some(9),
+                    ..Default::default()
+                }),
+                StreamEvent::Delta("Hi".to_string()),
+                StreamEvent::Delta(" there".to_string()),
+                StreamEvent::Meta(StreamMeta {
+                    stop_reason: Some("end_turn".to_string()),
+                    input_tokens: None,
+                    output_tokens: Some(2),
+                }),
+                StreamEvent::Done {
+                    content_hash: content_hash("Hi there"),
+                    finish_reason: None,
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stream_reply_does_not_retry_after_partial_reply() {
+        const DELTA: &str = "data: {\"choices\":[{\"delta\":{\"content\":\"Hel\"}}]}\n\n";
+        // Both responses promise more body than they send, then hang up.
+        let partial = format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\ncontent-length: 1000\r\nconnection: close\r\n\r\n{DELTA}"
+        );
+        let empty = "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\ncontent-length: 1000\r\nconnection: close\r\n\r\n".to_string();
+        let (addr, server) = spawn_mock_server(vec![partial, empty]).await;
+        let openai = Provider {
+            base_url: Some(format!("http://{addr}/v1")),
+            ..provider(ProviderType::OpenAI, false)
+        };
+
+        let mut events = Vec::new();
+        let mut sink = |event| {
+            events.push(event);
+            Ok(())
+        };
+        let broken = stream_reply(
+            &mut sink,
+            &openai,
+            "sk-test",
+            &messages(),
+            None,
+            None,
+            StreamLimits::default(),
+        )
+        .await;
+        assert!(broken.is_err(), "{broken:?}");
+
+        // Nothing reached the sink, so the one-shot retry is still allowed.
+        let silent = stream_reply(
+            &mut sink,
+            &openai,
+            "sk-test",
+            &messages(),
+            None,
+            None,
+            StreamLimits::default(),
+        )
+        .await;
+        server.await.unwrap();
+        assert_eq!(silent, Ok(StreamOutcome::NotStreamed));
+        assert_eq!(events, vec![StreamEvent::Delta("Hel".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn test_empty_stream_does_not_announce_done() {
+        const BODY: &str = "data: [DONE]\n\n";
+        let response = format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{BODY}",
+            BODY.len()
+        );
+        let (addr, server) = spawn_mock_server(vec![response]).await;
+        let openai = Provider {
+            base_url: Some(format!("http://{addr}/v1")),
+            ..provider(ProviderType::OpenAI, false)
+        };
+
+        let mut events = Vec::new();
+        let outcome = stream_reply(
+            &mut |event| {
+                events.push(event);
+                Ok(())
+            },
+            &openai,
+            "sk-test",
+            &messages(),
+            None,
+            None,
+            StreamLimits::default(),
+        )
+        .await;
+        server.await.unwrap();
+
+        // The one-shot retry sends its own `Done` with the real reply's hash.
+        assert_eq!(outcome, Ok(StreamOutcome::NotStreamed));
+        assert!(events.is_empty(), "{events:?}");
+    }
+
+    #[tokio::test]
+    async fn test_time_limit_before_first_delta_is_not_retried() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        // A provider that accepts the request but never starts answering.
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0u8; 8192];
+            let _ = socket.read(&mut buf).await.unwrap();
+            let headers =
+                "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\nconnection: close\r\n\r\n";
+            socket.write_all(headers.as_bytes()).await.unwrap();
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        });
+        let openai = Provider {
+            base_url: Some(format!("http://{addr}/v1")),
+            ..provider(ProviderType::OpenAI, false)
+        };
+
+        let mut events = Vec::new();
+        let outcome = stream_reply(
+            &mut |event| {
+                events.push(event);
+                Ok(())
+            },
+            &openai,
+            "sk-test",
+            &messages(),
+            None,
+            None,
+            StreamLimits {
+                max_duration: Some(Duration::from_millis(100)),
+                ..Default::default()
+            },
+        )
+        .await;
+        server.abort();
+
+        assert_eq!(outcome, Ok(StreamOutcome::TimeLimited));
+        assert_eq!(
+            events,
+            vec![StreamEvent::Done {
+                content_hash: content_hash(""),
+                finish_reason: Some(FINISH_REASON_TIME_LIMIT),
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_benchmark_measures_first_token_latency() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        const BODY: &str = "data: {\"choices\":[{\"delta\":{\"content\":\"Hello\"}}]}\n\ndata: {\"choices\":[{\"delta\":{\"content\":\" there\"}}]}\n\ndata: [DONE]\n\n";
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0u8; 8192];
+            let _ = socket.read(&mut buf).await.unwrap();
+            let headers = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+                BODY.len()
+            );
+            socket.write_all(headers.as_bytes()).await.unwrap();
+            socket.flush().await.unwrap();
+            // Hold the first token back so the latency is measurable.
+            tokio::time::sleep(Duration::from_millis(80)).await;
+            socket.write_all(BODY.as_bytes()).await.unwrap();
+        });
+        let custom = Provider {
+            base_url: Some(format!("http://{addr}/v1")),
+            ..provider(ProviderType::Custom, false)
+        };
+
+        let result = measure_stream(&custom, "sk-test", "Say hello.")
+            .await
+            .unwrap();
+        server.await.unwrap();
+
+        assert!(result.first_token_ms >= 80, "{result:?}");
+        assert!(result.total_ms >= result.first_token_ms);
+        assert_eq!(result.total_chars, "Hello there".len());
+        assert!(result.chars_per_sec > 0.0);
+
+        assert!(measure_stream(&custom, " ", "Say hello.").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_streaming_check_reports_first_delta() {
+        const BODY: &str = "data: {\"choices\":[{\"delta\":{\"content\":\"O\"}}]}\n\ndata: {\"choices\":[{\"delta\":{\"content\":\"K.\"}}]}\n\ndata: [DONE]\n\n";
+        let (addr, server) = spawn_mock_server(vec![
+            "HTTP/1.1 400 Bad Request\r\ncontent-type: application/json\r\ncontent-length: 47\r\nconnection: close\r\n\r\n{\"error\":{\"message\":\"stream is not supported\"}}".to_string(),
+            format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{BODY}",
+                BODY.len()
+            ),
+        ])
+        .await;
+        let custom = Provider {
+            id: "stream-test-provider".to_string(),
+            base_url: Some(format!("http://{addr}/v1")),
+            ..provider(ProviderType::Custom, false)
+        };
+        let timeout = Duration::from_secs(5);
+
+        let rejected = check_provider_streaming(&custom, "sk-test", timeout)
+            .await
+            .unwrap();
+        assert!(!rejected.ok);
+        assert_eq!(rejected.streamed_chars, 0);
+        assert!(!supports_streaming(&custom.id));
+
+        let result = check_provider_streaming(&custom, "sk-test", timeout)
+            .await
+            .unwrap();
+        assert!(result.ok, "{}", result.message);
+        assert_eq!(result.streamed_chars, "OK.".len());
+        assert!(result.first_chunk_ms.is_some());
+        // A passing test re-enables streaming for the provider.
+        assert!(supports_streaming(&custom.id));
+
+        let requests = server.await.unwrap();
+        assert!(requests[1].contains("\"stream\":true"));
+    }
+
+    #[tokio::test]
+    async fn test_stream_rejection_falls_back_and_is_remembered() {
+        let (addr, server) = spawn_mock_server(vec![
+            "HTTP/1.1 400 Bad Request\r\ncontent-type: application/json\r\ncontent-length: 47\r\nconnection: close\r\n\r\n{\"error\":{\"message\":\"stream is not supported\"}}",
+            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: 44\r\nconnection: close\r\n\r\n{\"choices\":[{\"message\":{\"content\":\"pong\"}}]}",
+        ])
+        .await;
+
+        let custom = Provider {
+            id: "no-stream-provider".to_string(),
+            base_url: Some(format!("http://{addr}/v1")),
+            ..provider(ProviderType::Custom, false)
+        };
+        let base_url = resolve_base_url(&custom).unwrap();
+        let client = reqwest::Client::new();
+        let timeout = Duration::from_secs(5);
+
+        assert!(supports_streaming(&custom.id));
+        let opened = open_stream(&client, &custom, &base_url, "sk-test", &messages(), timeout)
+            .await
+            .unwrap();
+        assert!(opened.is_none());
+        assert!(!supports_streaming(&custom.id));
+
+        let text = call_provider_and_get_text(&custom, "sk-test", &messages())
+            .await
+            .unwrap();
+        assert_eq!(text, "pong");
+
+        // The flag skips the streaming attempt entirely next time.
+        let again = open_stream(&client, &custom, &base_url, "sk-test", &messages(), timeout)
+            .await
+            .unwrap();
+        assert!(again.is_none());
+
+        let requests = server.await.unwrap();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[0].contains("\"stream\":true"));
+        assert!(!requests[1].contains("\"stream\":true"));
+    }
+
+    #[tokio::test]
+    async fn test_failing_primary_fails_over_to_secondary() {
+        let (primary_addr, primary_server) = spawn_mock_server(vec![
+            "HTTP/1.1 503 Service Unavailable\r\ncontent-type: text/html\r\ncontent-length: 9\r\nconnection: close\r\n\r\nupstream!",
+        ])
+        .await;
+        let (secondary_addr, secondary_server) = spawn_mock_server(vec![
+            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: 44\r\nconnection: close\r\n\r\n{\"choices\":[{\"message\":{\"content\":\"pong\"}}]}",
+        ])
+        .await;
+        let candidate = |id: &str, addr: std::net::SocketAddr| {
+            (
+                Provider {
+                    id: id.to_string(),
+                    name: id.to_string(),
+                    base_url: Some(format!("http://{addr}/v1")),
+                    ..provider(ProviderType::Custom, false)
+                },
+                "sk-test".to_string(),
+            )
+        };
+        let candidates = vec![
+            candidate("primary", primary_addr),
+            candidate("secondary", secondary_addr),
+        ];
+
+        let mut switches = Vec::new();
+        let text = with_failover(
+            &candidates,
+            |provider, api_key| async move {
+                call_provider_and_get_text(&provider, &api_key, &messages()).await
+            },
+            |from, to, err| switches.push((from.id.clone(), to.id.clone(), err.to_string())),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(text, "pong");
+        assert_eq!(switches.len(), 1);
+        assert_eq!(switches[0].0, "primary");
+        assert_eq!(switches[0].1, "secondary");
+        assert!(switches[0].2.starts_with("Provider server error."));
+        primary_server.await.unwrap();
+        secondary_server.await.unwrap();
+
+        // Request errors such as a bad key are not retried elsewhere.
+        let mut attempts = 0;
+        let err = with_failover(
+            &candidates,
+            |_, _| {
+                attempts += 1;
+                async { Err::<String, _>("Authentication failed. Check API key.".to_string()) }
+            },
+            |_, _, _| panic!("should not fail over"),
+        )
+        .await
+        .unwrap_err();
+        assert!(err.starts_with("Authentication failed"));
+        assert_eq!(attempts, 1);
+    }
+
+    #[tokio::test]
+    async fn test_replay_reconstructs_logged_request() {
+        let history = vec![
+            ProviderChatMessage {
+                role: "system".to_string(),
+                content: "Be brief.".to_string(),
+            },
+            ProviderChatMessage {
+                role: "user".to_string(),
+                content: "What failed earlier?".to_string(),
+            },
+        ];
+        crate::provider::recording::log_failed_request("replay-provider", &history);
+        let logged = last_failed_request("replay-provider").expect("logged entry");
+        assert!(last_failed_request("other-provider").is_none());
+
+        let (addr, server) = spawn_mock_server(vec![
+            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: 44\r\nconnection: close\r\n\r\n{\"choices\":[{\"message\":{\"content\":\"pong\"}}]}",
+        ])
+        .await;
+        let custom = Provider {
+            id: "replay-provider".to_string(),
+            base_url: Some(format!("http://{addr}/v1")),
+            ..provider(ProviderType::Custom, false)
+        };
+        let text = call_provider_and_get_text(&custom, "sk-test", &logged)
+            .await
+            .unwrap();
+        assert_eq!(text, "pong");
+
+        let requests = server.await.unwrap();
+        let body = requests[0].split("\r\n\r\n").nth(1).unwrap();
+        let body: serde_json::Value = serde_json::from_str(body).unwrap();
+        assert_eq!(
+            body["messages"],
+            serde_json::json!([
+                {"role": "system", "content": "Be brief."},
+                {"role": "user", "content": "What failed earlier?"},
+            ])
+        );
+    }
+
+    #[test]
+    fn test_is_streaming_unsupported_error() {
+        assert!(is_streaming_unsupported_error(
+            StatusCode::BAD_REQUEST,
+            "Unsupported parameter: 'stream'"
+        ));
+        assert!(!is_streaming_unsupported_error(
+            StatusCode::BAD_REQUEST,
+            "Invalid model"
+        ));
+        assert!(!is_streaming_unsupported_error(
+            StatusCode::UNAUTHORIZED,
+            "stream requires auth"
+        ));
+    }
 }
@@ -0,0 +1,118 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+
+use crate::db::{ProvidersRepository, SettingsRepository};
+use crate::provider::openai::shared_client;
+use crate::provider::Provider;
+
+/// Warm-up requests in flight at once, so startup never floods the network.
+const PREWARM_CONCURRENCY: usize = 3;
+const PREWARM_TIMEOUT_SECS: u64 = 5;
+
+/// Whether to warm providers: `prewarm_providers` is on and the launcher
+/// has a chat surface to use them.
+fn prewarm_enabled(setting: Option<String>, apps_only: bool) -> bool {
+    !apps_only && crate::parse_bool_setting(setting, false)
+}
+
+/// Distinct base URLs worth warming, or none when `prewarm_providers` is off.
+fn prewarm_targets(enabled: bool, providers: &[Provider]) -> Vec<String> {
+    if !enabled {
+        return Vec::new();
+    }
+    let mut seen = HashSet::new();
+    providers
+        .iter()
+        .filter(|provider| provider.is_active)
+        .filter_map(Provider::resolved_base_url)
+        .filter(|base_url| seen.insert(base_url.clone()))
+        .collect()
+}
+
+/// Open a connection to each active provider in the background so the first
+/// query skips the TLS handshake. Results are discarded; any HTTP status,
+/// even an error, leaves a pooled connection behind. Skipped in apps-only mode.
+pub async fn prewarm_providers() {
+    let loaded = tauri::async_runtime::spawn_blocking(|| {
+        let enabled = prewarm_enabled(
+            SettingsRepository::get(crate::SETTING_PREWARM_PROVIDERS)
+                .ok()
+                .flatten(),
+            crate::is_apps_only_mode().unwrap_or(false),
+        );
+        let providers = if enabled {
+            ProvidersRepository::list_active().unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+        (enabled, providers)
+    })
+    .await;
+    let Ok((enabled, providers)) = loaded else {
+        return;
+    };
+
+    let semaphore = Arc::new(Semaphore::new(PREWARM_CONCURRENCY));
+    let mut tasks = tokio::task::JoinSet::new();
+    for base_url in prewarm_targets(enabled, &providers) {
+        let semaphore = Arc::clone(&semaphore);
+        tasks.spawn(async move {
+            let Ok(_permit) = semaphore.acquire_owned().await else {
+                return;
+            };
+            let result = shared_client()
+                .head(&base_url)
+                .timeout(Duration::from_secs(PREWARM_TIMEOUT_SECS))
+                .send()
+                .await;
+            if let Err(e) = result {
+                crate::logging::log_nonfatal(format!(
+                    "Provider prewarm for {base_url} failed: {e}"
+                ));
+            }
+        });
+    }
+    while tasks.join_next().await.is_some() {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::{test_provider, ProviderType};
+
+    fn provider(id: &str, base_url: &str, is_active: bool) -> Provider {
+        Provider {
+            id: id.to_string(),
+            provider_type: ProviderType::Custom,
+            base_url: Some(base_url.to_string()),
+            is_active,
+            ..test_provider()
+        }
+    }
+
+    #[test]
+    fn test_prewarm_skipped_in_apps_only_mode() {
+        assert!(prewarm_enabled(Some("true".to_string()), false));
+        assert!(!prewarm_enabled(Some("true".to_string()), true));
+        assert!(!prewarm_enabled(Some("false".to_string()), false));
+        assert!(!prewarm_enabled(None, false));
+    }
+
+    #[test]
+    fn test_prewarm_skipped_when_setting_off() {
+        let providers = vec![
+            provider("a", "https://gateway.example/v1", true),
+            provider("b", "https://gateway.example/v1", true),
+            provider("c", "https://other.example/v1", true),
+            provider("d", "https://inactive.example/v1", false),
+        ];
+
+        assert!(prewarm_targets(false, &providers).is_empty());
+        assert_eq!(
+            prewarm_targets(true, &providers),
+            vec!["https://gateway.example/v1", "https://other.example/v1"]
+        );
+    }
+}
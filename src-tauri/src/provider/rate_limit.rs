@@ -0,0 +1,163 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::provider::Provider;
+
+/// Token bucket holding up to `capacity` requests, refilled evenly over a minute.
+#[derive(Debug, Clone)]
+struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(requests_per_minute: u32, now: Instant) -> Self {
+        let capacity = f64::from(requests_per_minute.max(1));
+        Self {
+            capacity,
+            tokens: capacity,
+            last_refill: now,
+        }
+    }
+
+    fn refill_per_sec(&self) -> f64 {
+        self.capacity / 60.0
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now
+            .saturating_duration_since(self.last_refill)
+            .as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_per_sec()).min(self.capacity);
+        self.last_refill = now;
+    }
+
+    /// Take one token, or return how long until one becomes available.
+    fn try_consume(&mut self, now: Instant) -> Result<(), Duration> {
+        self.refill(now);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            let missing = 1.0 - self.tokens;
+            Err(Duration::from_secs_f64(missing / self.refill_per_sec()))
+        }
+    }
+}
+
+/// App-managed per-provider rate limiter; providers without a limit pass straight through.
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    buckets: Mutex<HashMap<String, TokenBucket>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct QueuedPayload {
+    provider_id: String,
+    wait_ms: u64,
+}
+
+impl RateLimiter {
+    fn try_acquire(
+        &self,
+        provider_id: &str,
+        requests_per_minute: u32,
+        now: Instant,
+    ) -> Result<(), Duration> {
+        let Ok(mut buckets) = self.buckets.lock() else {
+            return Ok(());
+        };
+        let bucket = buckets
+            .entry(provider_id.to_string())
+            .or_insert_with(|| TokenBucket::new(requests_per_minute, now));
+        // Start over when the configured limit changes.
+        if bucket.capacity != f64::from(requests_per_minute.max(1)) {
+            *bucket = TokenBucket::new(requests_per_minute, now);
+        }
+        bucket.try_consume(now)
+    }
+}
+
+fn configured_rpm(provider: &Provider) -> Option<u32> {
+    provider
+        .requests_per_minute
+        .filter(|v| *v > 0)
+        .map(|v| u32::try_from(v).unwrap_or(u32::MAX))
+}
+
+/// Wait for a request slot for `provider`, emitting `query:queued:{key}` while
+/// locally rate-limited so the UI can show the column as waiting.
+pub async fn acquire_slot(app: &AppHandle, provider: &Provider, event_key: &str) {
+    let Some(rpm) = configured_rpm(provider) else {
+        return;
+    };
+
+    let mut announced = false;
+    loop {
+        let wait = match app.try_state::<RateLimiter>() {
+            Some(limiter) => limiter.try_acquire(&provider.id, rpm, Instant::now()),
+            None => Ok(()),
+        };
+        let Err(wait) = wait else {
+            return;
+        };
+
+        if !announced {
+            announced = true;
+            let _ = app.emit(
+                &format!("query:queued:{event_key}"),
+                QueuedPayload {
+                    provider_id: provider.id.clone(),
+                    wait_ms: wait.as_millis() as u64,
+                },
+            );
+        }
+        tokio::time::sleep(wait).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket_consume_and_refill() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(2, start);
+
+        assert!(bucket.try_consume(start).is_ok());
+        assert!(bucket.try_consume(start).is_ok());
+        // 2 rpm refills one token every 30s.
+        let wait = bucket.try_consume(start).unwrap_err();
+        assert!((wait.as_secs_f64() - 30.0).abs() < 0.01);
+
+        let later = start + Duration::from_secs(15);
+        let wait = bucket.try_consume(later).unwrap_err();
+        assert!((wait.as_secs_f64() - 15.0).abs() < 0.01);
+
+        let refilled = start + Duration::from_secs(31);
+        assert!(bucket.try_consume(refilled).is_ok());
+
+        // Idle time never banks more than the burst capacity.
+        let idle = refilled + Duration::from_secs(3600);
+        assert!(bucket.try_consume(idle).is_ok());
+        assert!(bucket.try_consume(idle).is_ok());
+        assert!(bucket.try_consume(idle).is_err());
+    }
+
+    #[test]
+    fn test_rate_limiter_resets_bucket_when_limit_changes() {
+        let limiter = RateLimiter::default();
+        let now = Instant::now();
+
+        assert!(limiter.try_acquire("p1", 1, now).is_ok());
+        assert!(limiter.try_acquire("p1", 1, now).is_err());
+        // Other providers have their own bucket.
+        assert!(limiter.try_acquire("p2", 1, now).is_ok());
+        assert!(limiter.try_acquire("p1", 5, now).is_ok());
+    }
+}
@@ -0,0 +1,133 @@
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+use crate::db::SettingsRepository;
+use crate::provider::{ProviderChatMessage, ProviderType};
+
+/// Directory under the app data dir that holds raw stream recordings.
+const RECORDINGS_DIR: &str = "stream-recordings";
+
+/// Whether the `provider_debug_record` setting is on.
+pub async fn debug_recording_enabled() -> bool {
+    tauri::async_runtime::spawn_blocking(|| {
+        crate::parse_bool_setting(
+            SettingsRepository::get(crate::SETTING_PROVIDER_DEBUG_RECORD)
+                .ok()
+                .flatten(),
+            false,
+        )
+    })
+    .await
+    .unwrap_or(false)
+}
+
+/// Messages of the most recent one-shot request that failed per provider id
+/// while debug recording was on, kept for this session only so they can be
+/// replayed once a transient error may have cleared.
+static FAILED_REQUESTS: Lazy<Mutex<HashMap<String, Vec<ProviderChatMessage>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+pub(super) fn log_failed_request(provider_id: &str, messages: &[ProviderChatMessage]) {
+    if let Ok(mut requests) = FAILED_REQUESTS.lock() {
+        requests.insert(provider_id.to_string(), messages.to_vec());
+    }
+}
+
+/// Remember a failed request for `replay_last_failed_request`, if debug
+/// recording is on.
+pub async fn record_failed_request(provider_id: &str, messages: &[ProviderChatMessage]) {
+    if debug_recording_enabled().await {
+        log_failed_request(provider_id, messages);
+    }
+}
+
+pub fn last_failed_request(provider_id: &str) -> Option<Vec<ProviderChatMessage>> {
+    FAILED_REQUESTS
+        .lock()
+        .ok()
+        .and_then(|requests| requests.get(provider_id).cloned())
+}
+
+fn recording_file_name(provider_type: ProviderType, started_at_ms: u128) -> String {
+    format!("{provider_type}-{started_at_ms}.sse")
+}
+
+/// Copies the raw bytes of a provider stream to disk while the
+/// `provider_debug_record` setting is on, so real traffic can be turned into
+/// replayable test fixtures.
+pub struct StreamRecorder {
+    path: PathBuf,
+    /// Created on the first chunk, so streams rejected up front leave no file.
+    file: Option<File>,
+    failed: bool,
+}
+
+impl StreamRecorder {
+    /// Prepare a recording for `provider_type`, or `None` when recording is off.
+    pub async fn start(app: &AppHandle, provider_type: ProviderType) -> Option<Self> {
+        if !debug_recording_enabled().await {
+            return None;
+        }
+
+        let dir = app.path().app_local_data_dir().ok()?.join(RECORDINGS_DIR);
+        let started_at_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or(0);
+        let path = dir.join(recording_file_name(provider_type, started_at_ms));
+        Some(Self {
+            path,
+            file: None,
+            failed: false,
+        })
+    }
+
+    fn open(&mut self) -> std::io::Result<&mut File> {
+        match self.file {
+            Some(ref mut file) => Ok(file),
+            None => {
+                if let Some(dir) = self.path.parent() {
+                    std::fs::create_dir_all(dir)?;
+                }
+                Ok(self.file.insert(File::create(&self.path)?))
+            }
+        }
+    }
+
+    /// Append one network chunk exactly as received.
+    pub fn write(&mut self, chunk: &[u8]) {
+        if self.failed {
+            return;
+        }
+        if let Err(e) = self.open().and_then(|file| file.write_all(chunk)) {
+            self.failed = true;
+            eprintln!(
+                "Failed to write stream recording {}: {e}",
+                self.path.display()
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recording_file_name_is_keyed_by_provider_type() {
+        assert_eq!(
+            recording_file_name(ProviderType::OpenAI, 1_700_000_000_000),
+            "openai-1700000000000.sse"
+        );
+        assert_eq!(
+            recording_file_name(ProviderType::Anthropic, 1),
+            "anthropic-1.sse"
+        );
+    }
+}
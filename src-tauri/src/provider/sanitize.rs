@@ -0,0 +1,235 @@
+use crate::provider::ProviderType;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Chat-template markers self-hosted models tend to leak into their output.
+const TEMPLATE_TOKENS: &[&str] = &[
+    "<|im_end|>",
+    "<|im_start|>",
+    "<|endoftext|>",
+    "<|eot_id|>",
+    "<|end|>",
+    "</s>",
+    "<s>",
+];
+const OPENAI_TOKENS: &[&str] = &["<|im_end|>", "<|endoftext|>"];
+const GLM_TOKENS: &[&str] = &["<|endoftext|>", "<|user|>", "<|observation|>"];
+
+/// Control/stop tokens stripped from `provider_type`'s replies; empty when
+/// the provider never leaks them.
+pub(crate) fn control_tokens(provider_type: ProviderType) -> &'static [&'static str] {
+    match provider_type {
+        ProviderType::Custom => TEMPLATE_TOKENS,
+        ProviderType::OpenAI => OPENAI_TOKENS,
+        ProviderType::Glm => GLM_TOKENS,
+        ProviderType::Volcengine | ProviderType::Anthropic | ProviderType::Google => &[],
+    }
+}
+
+/// Whether `text` is made only of known tokens (whitespace between them is
+/// allowed), optionally ending in the start of one still being streamed.
+fn is_token_tail(mut text: &str, tokens: &[&str]) -> bool {
+    loop {
+        if text.is_empty() {
+            return true;
+        }
+        match tokens.iter().find(|token| text.starts_with(**token)) {
+            Some(token) => text = text[token.len()..].trim_start(),
+            None => return tokens.iter().any(|token| token.starts_with(text)),
+        }
+    }
+}
+
+/// Strips known control tokens from the start and end of a streamed reply.
+/// Tokens in the middle of the text are left alone, since there they are
+/// more likely to be content (e.g. a reply explaining chat templates), so
+/// text that could still turn into a trailing token is held back until the
+/// next delta shows otherwise.
+pub(crate) struct ControlTokenFilter {
+    tokens: &'static [&'static str],
+    pending: String,
+    started: bool,
+}
+
+impl ControlTokenFilter {
+    pub(crate) fn new(tokens: &'static [&'static str]) -> Self {
+        Self {
+            tokens,
+            pending: String::new(),
+            started: false,
+        }
+    }
+
+    /// Feed one delta; returns the text that is now safe to emit.
+    pub(crate) fn push(&mut self, delta: &str) -> String {
+        if self.tokens.is_empty() {
+            return delta.to_string();
+        }
+        self.pending.push_str(delta);
+
+        if !self.started {
+            while let Some(token) = self.tokens.iter().find(|t| self.pending.starts_with(**t)) {
+                self.pending.drain(..token.len());
+            }
+            let undecided =
+                self.pending.is_empty() || self.tokens.iter().any(|t| t.starts_with(&self.pending));
+            if undecided {
+                return String::new();
+            }
+            self.started = true;
+        }
+
+        let tokens = self.tokens;
+        let hold_from = self
+            .pending
+            .char_indices()
+            .map(|(index, _)| index)
+            .find(|&index| is_token_tail(&self.pending[index..], tokens))
+            .unwrap_or(self.pending.len());
+        let held = self.pending.split_off(hold_from);
+        std::mem::replace(&mut self.pending, held)
+    }
+
+    /// End of stream: drop the complete tokens still held back and return
+    /// whatever text remains.
+    pub(crate) fn finish(&mut self) -> String {
+        let mut rest = std::mem::take(&mut self.pending);
+        let mut out = String::new();
+        while !rest.is_empty() {
+            match self.tokens.iter().find(|token| rest.starts_with(**token)) {
+                Some(token) => {
+                    rest.drain(..token.len());
+                }
+                None => {
+                    let ch = rest.remove(0);
+                    out.push(ch);
+                }
+            }
+        }
+        out
+    }
+}
+
+/// Strip control tokens from a complete, non-streamed reply.
+pub(crate) fn strip_control_tokens(text: &str, tokens: &'static [&'static str]) -> String {
+    let mut filter = ControlTokenFilter::new(tokens);
+    let mut out = filter.push(text);
+    out.push_str(&filter.finish());
+    out
+}
+
+/// Length, in grapheme clusters, of response bodies quoted in error messages.
+pub(crate) const EXCERPT_MAX_GRAPHEMES: usize = 220;
+
+/// Where a JSON escape (`\n`, `\u00e9`, ...) cut off by the end of `text`
+/// starts, if any. Backslashes are ASCII, so byte offsets are char boundaries.
+fn incomplete_escape_start(text: &str) -> Option<usize> {
+    let bytes = text.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] != b'\\' {
+            i += 1;
+            continue;
+        }
+        let len = if bytes.get(i + 1) == Some(&b'u') {
+            6
+        } else {
+            2
+        };
+        if i + len > bytes.len() {
+            return Some(i);
+        }
+        i += len;
+    }
+    None
+}
+
+/// `text` cut to at most `max` grapheme clusters, with "…" appended when
+/// anything was dropped. A trailing escape sequence split by the cut is
+/// dropped whole so excerpts of JSON bodies stay readable.
+pub(crate) fn truncate_excerpt(text: &str, max: usize) -> String {
+    let Some((cut, _)) = text.grapheme_indices(true).nth(max) else {
+        return text.to_string();
+    };
+    let mut excerpt = &text[..cut];
+    if let Some(start) = incomplete_escape_start(excerpt) {
+        excerpt = &excerpt[..start];
+    }
+    format!("{}…", excerpt.trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stream(deltas: &[&str]) -> String {
+        let mut filter = ControlTokenFilter::new(TEMPLATE_TOKENS);
+        let mut out: String = deltas.iter().map(|delta| filter.push(delta)).collect();
+        out.push_str(&filter.finish());
+        out
+    }
+
+    #[test]
+    fn test_known_tokens_removed_at_stream_boundaries() {
+        assert_eq!(stream(&["Hello", " world", "<|im_end|>"]), "Hello world");
+        // Split across deltas, and several stacked at the end.
+        assert_eq!(stream(&["Done.", "<|im", "_end|>", "\n</s>"]), "Done.\n");
+        assert_eq!(stream(&["<s>", "Hi"]), "Hi");
+        assert_eq!(
+            strip_control_tokens("Answer<|eot_id|>", control_tokens(ProviderType::Custom)),
+            "Answer"
+        );
+    }
+
+    #[test]
+    fn test_similar_content_preserved() {
+        // Tokens in the middle of a reply are content, not leaks.
+        assert_eq!(
+            stream(&["Qwen ends turns with <|im_end|>", " in its template."]),
+            "Qwen ends turns with <|im_end|> in its template."
+        );
+        // Near-misses and partial prefixes at the end are kept.
+        assert_eq!(stream(&["Use </", "span> tags"]), "Use </span> tags");
+        assert_eq!(stream(&["a <", "|im_end"]), "a <|im_end");
+        assert_eq!(stream(&["x < y", " and y <", "s"]), "x < y and y <s");
+        assert_eq!(stream(&["<"]), "<");
+        // Providers without a token set pass text through untouched.
+        assert_eq!(
+            strip_control_tokens("Answer</s>", control_tokens(ProviderType::Anthropic)),
+            "Answer</s>"
+        );
+    }
+
+    #[test]
+    fn test_truncate_excerpt_multibyte() {
+        assert_eq!(truncate_excerpt("short", 10), "short");
+        assert_eq!(truncate_excerpt("", 0), "");
+        let cjk = "错误".repeat(200);
+        let excerpt = truncate_excerpt(&cjk, EXCERPT_MAX_GRAPHEMES);
+        assert_eq!(excerpt.chars().count(), EXCERPT_MAX_GRAPHEMES + 1);
+        assert!(excerpt.ends_with("错误…"));
+        assert_eq!(truncate_excerpt("ab cd", 3), "ab…");
+    }
+
+    #[test]
+    fn test_truncate_excerpt_keeps_grapheme_clusters_whole() {
+        // "e" + combining acute: two chars, one grapheme.
+        let accented = "e\u{301}".repeat(5);
+        assert_eq!(truncate_excerpt(&accented, 2), "e\u{301}e\u{301}…");
+        // A ZWJ family emoji is seven chars but a single cluster.
+        let family = "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}\u{200D}\u{1F466}";
+        let text = format!("a{family}{family}");
+        assert_eq!(truncate_excerpt(&text, 2), format!("a{family}…"));
+    }
+
+    #[test]
+    fn test_truncate_excerpt_drops_split_json_escapes() {
+        assert_eq!(
+            truncate_excerpt(r#"{"e":"caf\u00e9"}"#, 11),
+            r#"{"e":"caf…"#
+        );
+        assert_eq!(truncate_excerpt(r#"{"e":"a\nb"}"#, 8), r#"{"e":"a…"#);
+        // Complete escapes and escaped backslashes are left alone.
+        assert_eq!(truncate_excerpt(r#""a\nb""#, 4), r#""a\n…"#);
+        assert_eq!(truncate_excerpt(r#""\\x""#, 3), r#""\\…"#);
+    }
+}
@@ -0,0 +1,127 @@
+use crate::provider::{estimate_prompt_tokens, ProviderChatMessage, ProviderType};
+
+/// Tokens OpenAI-style chat formats add around each message, plus reply priming.
+const PER_MESSAGE_OVERHEAD: usize = 4;
+const REPLY_PRIMING: usize = 3;
+
+/// How a provider's text is split into tokens for estimation purposes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TokenizerFamily {
+    /// cl100k/o200k-style BPE, approximated without shipping the vocabulary.
+    OpenAiBpe,
+    /// Roughly four characters per token.
+    CharHeuristic,
+}
+
+impl TokenizerFamily {
+    fn for_provider(provider_type: ProviderType) -> Self {
+        match provider_type {
+            ProviderType::OpenAI | ProviderType::Custom => TokenizerFamily::OpenAiBpe,
+            ProviderType::Glm
+            | ProviderType::Anthropic
+            | ProviderType::Google
+            | ProviderType::Volcengine => TokenizerFamily::CharHeuristic,
+        }
+    }
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x3040..=0x30FF | 0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xAC00..=0xD7AF | 0xF900..=0xFAFF
+    )
+}
+
+/// Word-piece approximation of BPE: words cost about one token per six
+/// letters, punctuation one each, CJK one per character, other symbols two.
+fn estimate_bpe_tokens(text: &str) -> usize {
+    let mut tokens = 0usize;
+    let mut word_len = 0usize;
+    let flush = |word_len: &mut usize, tokens: &mut usize| {
+        if *word_len > 0 {
+            *tokens += word_len.div_ceil(6);
+            *word_len = 0;
+        }
+    };
+
+    for c in text.chars() {
+        if is_cjk(c) {
+            flush(&mut word_len, &mut tokens);
+            tokens += 1;
+        } else if c.is_alphanumeric() {
+            word_len += 1;
+        } else {
+            flush(&mut word_len, &mut tokens);
+            if c.is_whitespace() {
+                continue;
+            }
+            tokens += if c.is_ascii() { 1 } else { 2 };
+        }
+    }
+    flush(&mut word_len, &mut tokens);
+    tokens
+}
+
+fn estimate_text_tokens(family: TokenizerFamily, text: &str) -> usize {
+    match family {
+        TokenizerFamily::OpenAiBpe => estimate_bpe_tokens(text),
+        TokenizerFamily::CharHeuristic => {
+            usize::try_from(estimate_prompt_tokens(text.chars().count())).unwrap_or(0)
+        }
+    }
+}
+
+/// Advisory token count for sending `messages` to a provider of `provider_type`.
+pub fn estimate_message_tokens(
+    provider_type: ProviderType,
+    messages: &[ProviderChatMessage],
+) -> usize {
+    let family = TokenizerFamily::for_provider(provider_type);
+    messages
+        .iter()
+        .map(|m| PER_MESSAGE_OVERHEAD + estimate_text_tokens(family, &m.content))
+        .sum::<usize>()
+        + REPLY_PRIMING
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(content: &str) -> ProviderChatMessage {
+        ProviderChatMessage {
+            role: "user".to_string(),
+            content: content.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_bpe_estimate_order_of_magnitude() {
+        // cl100k encodes each of these as 2 and 4 tokens respectively.
+        assert_eq!(estimate_bpe_tokens("hello world"), 2);
+        assert_eq!(estimate_bpe_tokens("你好世界"), 4);
+
+        let paragraph = "The quick brown fox jumps over the lazy dog. ".repeat(20);
+        // Real tokenizers give ~200 here; stay within the same order of magnitude.
+        let estimate = estimate_bpe_tokens(&paragraph);
+        assert!((100..=400).contains(&estimate), "estimate was {estimate}");
+
+        let heuristic = estimate_text_tokens(TokenizerFamily::CharHeuristic, &"a".repeat(400));
+        assert_eq!(heuristic, 100);
+    }
+
+    #[test]
+    fn test_estimate_handles_empty_input() {
+        assert_eq!(estimate_bpe_tokens(""), 0);
+        assert_eq!(
+            estimate_message_tokens(ProviderType::OpenAI, &[]),
+            REPLY_PRIMING
+        );
+        assert_eq!(
+            estimate_message_tokens(ProviderType::Anthropic, &[user("")]),
+            PER_MESSAGE_OVERHEAD + REPLY_PRIMING
+        );
+        assert_eq!(estimate_bpe_tokens("  \n\t "), 0);
+        assert_eq!(estimate_bpe_tokens("🙂"), 2);
+    }
+}
@@ -0,0 +1,131 @@
+use serde::{Deserialize, Serialize};
+
+use crate::db::ProvidersRepository;
+use crate::provider::openai::{check_provider_connection, ConnectionTestResult};
+use crate::provider::{normalize_base_url, Provider};
+
+/// Problems found with one active provider by `validate_all_providers`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderValidation {
+    pub provider_id: String,
+    pub provider_name: String,
+    /// Empty when the provider looks ready to chat.
+    pub issues: Vec<String>,
+    /// Only set when a connection test was requested and the static checks passed.
+    pub connection: Option<ConnectionTestResult>,
+}
+
+/// Checks that need no network: key present, base URL usable, model set.
+fn static_issues(provider: &Provider, api_key: &str) -> Vec<String> {
+    let mut issues = Vec::new();
+    if api_key.trim().is_empty() {
+        issues.push("API key is missing.".to_string());
+    }
+    match provider.resolved_base_url() {
+        None => issues.push("Base URL is required for this provider type.".to_string()),
+        Some(url) => match normalize_base_url(provider.provider_type, &url) {
+            Ok(normalized) => issues.extend(normalized.warning),
+            Err(e) => issues.push(e),
+        },
+    }
+    if provider.model.trim().is_empty() {
+        issues.push("Model is empty.".to_string());
+    }
+    issues
+}
+
+/// Check every active provider at once and report all problems, so setup
+/// can be fixed in one pass. With `test_connection`, providers that pass the
+/// static checks are also probed, concurrently.
+pub async fn validate_all_providers(
+    test_connection: bool,
+) -> Result<Vec<ProviderValidation>, String> {
+    let providers = tauri::async_runtime::spawn_blocking(|| {
+        ProvidersRepository::list_active()
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .map(|provider| {
+                let api_key =
+                    ProvidersRepository::get_api_key(&provider.id).map_err(|e| e.to_string())?;
+                Ok((provider, api_key))
+            })
+            .collect::<Result<Vec<_>, String>>()
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    let checks = providers.into_iter().map(|(provider, api_key)| {
+        tauri::async_runtime::spawn(async move {
+            let mut validation = ProviderValidation {
+                issues: static_issues(&provider, &api_key),
+                provider_id: provider.id.clone(),
+                provider_name: provider.name.clone(),
+                connection: None,
+            };
+            if test_connection && validation.issues.is_empty() {
+                let result = check_provider_connection(&provider, &api_key)
+                    .await
+                    .unwrap_or_else(|message| ConnectionTestResult {
+                        success: false,
+                        message,
+                        status_code: None,
+                        latency_ms: 0,
+                    });
+                if !result.success {
+                    validation
+                        .issues
+                        .push(format!("Connection test failed: {}", result.message));
+                }
+                validation.connection = Some(result);
+            }
+            validation
+        })
+    });
+
+    let mut validations = Vec::new();
+    for check in checks.collect::<Vec<_>>() {
+        validations.push(check.await.map_err(|e| e.to_string())?);
+    }
+    Ok(validations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::{test_provider, ProviderType};
+
+    fn provider(base_url: Option<&str>, model: &str) -> Provider {
+        Provider {
+            provider_type: ProviderType::Custom,
+            base_url: base_url.map(str::to_string),
+            model: model.to_string(),
+            ..test_provider()
+        }
+    }
+
+    #[test]
+    fn test_static_issues_name_each_problem() {
+        let ok = provider(Some("https://llm.example.com/v1"), "m");
+        assert!(static_issues(&ok, "sk-test").is_empty());
+
+        assert_eq!(static_issues(&ok, "  "), vec!["API key is missing."]);
+
+        let bad_url = provider(Some("ftp://llm.example.com"), "m");
+        let issues = static_issues(&bad_url, "sk-test");
+        assert_eq!(issues.len(), 1);
+        assert!(
+            issues[0].starts_with("Base URL must start with http"),
+            "{issues:?}"
+        );
+
+        // Custom providers have no default base URL to fall back on.
+        let missing = provider(None, " ");
+        assert_eq!(
+            static_issues(&missing, "sk-test"),
+            vec![
+                "Base URL is required for this provider type.",
+                "Model is empty."
+            ]
+        );
+    }
+}
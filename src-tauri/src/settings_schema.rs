@@ -0,0 +1,186 @@
+use crate::{
+    DEFAULT_HOTKEY_OPEN_SETTINGS, DEFAULT_HOTKEY_TOGGLE_SEARCH, DEFAULT_THEME,
+    LAUNCHER_MODE_APPS_ONLY, LAUNCHER_MODE_HYBRID, SETTING_ALWAYS_ON_TOP,
+    SETTING_DB_BUSY_TIMEOUT_MS, SETTING_DEFAULT_SYSTEM_PROMPT, SETTING_HIDE_ON_BLUR,
+    SETTING_HOTKEY_OPEN_SETTINGS, SETTING_HOTKEY_TOGGLE_SEARCH, SETTING_LAUNCHER_MODE,
+    SETTING_LAUNCH_ON_STARTUP, SETTING_MAX_MESSAGES_PER_SESSION, SETTING_MAX_PROMPT_CHARS,
+    SETTING_MAX_STREAM_DURATION_SECS, SETTING_NAME_CLEANUP, SETTING_PREWARM_PROVIDERS,
+    SETTING_PROMPT_OVERFLOW, SETTING_PROVIDER_DEBUG_RECORD, SETTING_PROVIDER_FAILOVER,
+    SETTING_REQUEST_TIMEOUT_SECS, SETTING_SCAN_REGISTRY, SETTING_SCAN_START_MENU,
+    SETTING_SHOW_CONSOLE_WINDOW, SETTING_STORE_MESSAGE_REQUESTS, SETTING_STREAM_BATCH_MS,
+    SETTING_THEME, SETTING_VERBOSE_ERRORS, SETTING_WAL_CHECKPOINT_INTERVAL_MINUTES,
+};
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SettingKind {
+    /// Stored as "1"/"0".
+    Bool,
+    String,
+    Integer,
+    /// A global shortcut such as "Alt + Space".
+    Hotkey,
+    /// One of `allowed_values`.
+    Enum,
+}
+
+/// One user-facing setting. `SETTINGS_SCHEMA` is the single list of them:
+/// the settings UI renders from it and startup seeds defaults from it.
+#[derive(Debug, Clone, Serialize)]
+pub struct SettingSchema {
+    pub key: &'static str,
+    pub kind: SettingKind,
+    /// Empty for integer settings whose fallback depends on the caller.
+    pub default: &'static str,
+    pub allowed_values: &'static [&'static str],
+    /// Whether startup writes `default` when the key is absent; the others
+    /// are only read with a fallback.
+    #[serde(skip)]
+    pub seeded: bool,
+}
+
+const fn setting(key: &'static str, kind: SettingKind, default: &'static str) -> SettingSchema {
+    SettingSchema {
+        key,
+        kind,
+        default,
+        allowed_values: &[],
+        seeded: false,
+    }
+}
+
+const fn seeded(key: &'static str, kind: SettingKind, default: &'static str) -> SettingSchema {
+    SettingSchema {
+        seeded: true,
+        ..setting(key, kind, default)
+    }
+}
+
+pub const SETTINGS_SCHEMA: &[SettingSchema] = &[
+    // Synced with the OS autostart entry at startup rather than seeded.
+    setting(SETTING_LAUNCH_ON_STARTUP, SettingKind::Bool, "0"),
+    seeded(SETTING_HIDE_ON_BLUR, SettingKind::Bool, "1"),
+    seeded(
+        SETTING_HOTKEY_TOGGLE_SEARCH,
+        SettingKind::Hotkey,
+        DEFAULT_HOTKEY_TOGGLE_SEARCH,
+    ),
+    seeded(
+        SETTING_HOTKEY_OPEN_SETTINGS,
+        SettingKind::Hotkey,
+        DEFAULT_HOTKEY_OPEN_SETTINGS,
+    ),
+    SettingSchema {
+        allowed_values: &["light", "dark", "system"],
+        ..seeded(SETTING_THEME, SettingKind::Enum, DEFAULT_THEME)
+    },
+    seeded(SETTING_DEFAULT_SYSTEM_PROMPT, SettingKind::String, ""),
+    seeded(SETTING_SHOW_CONSOLE_WINDOW, SettingKind::Bool, "1"),
+    SettingSchema {
+        allowed_values: &[LAUNCHER_MODE_APPS_ONLY, LAUNCHER_MODE_HYBRID],
+        ..seeded(
+            SETTING_LAUNCHER_MODE,
+            SettingKind::Enum,
+            LAUNCHER_MODE_HYBRID,
+        )
+    },
+    seeded(SETTING_SCAN_REGISTRY, SettingKind::Bool, "1"),
+    seeded(SETTING_SCAN_START_MENU, SettingKind::Bool, "1"),
+    seeded(SETTING_NAME_CLEANUP, SettingKind::Bool, "1"),
+    seeded(SETTING_ALWAYS_ON_TOP, SettingKind::Bool, "0"),
+    seeded(SETTING_PROVIDER_DEBUG_RECORD, SettingKind::Bool, "0"),
+    seeded(SETTING_PREWARM_PROVIDERS, SettingKind::Bool, "0"),
+    seeded(SETTING_PROVIDER_FAILOVER, SettingKind::Bool, "0"),
+    seeded(SETTING_STORE_MESSAGE_REQUESTS, SettingKind::Bool, "0"),
+    setting(
+        SETTING_VERBOSE_ERRORS,
+        SettingKind::Bool,
+        if cfg!(debug_assertions) { "1" } else { "0" },
+    ),
+    setting(SETTING_REQUEST_TIMEOUT_SECS, SettingKind::Integer, "0"),
+    setting(SETTING_DB_BUSY_TIMEOUT_MS, SettingKind::Integer, "5000"),
+    setting(
+        SETTING_WAL_CHECKPOINT_INTERVAL_MINUTES,
+        SettingKind::Integer,
+        "10",
+    ),
+    setting(SETTING_MAX_MESSAGES_PER_SESSION, SettingKind::Integer, "0"),
+    setting(SETTING_MAX_PROMPT_CHARS, SettingKind::Integer, "200000"),
+    setting(SETTING_STREAM_BATCH_MS, SettingKind::Integer, "0"),
+    setting(SETTING_MAX_STREAM_DURATION_SECS, SettingKind::Integer, "0"),
+    SettingSchema {
+        allowed_values: &["reject", "truncate"],
+        ..setting(SETTING_PROMPT_OVERFLOW, SettingKind::Enum, "reject")
+    },
+];
+
+/// Every known setting with its kind, default and allowed values.
+#[tauri::command]
+pub fn list_settings_schema() -> Result<Vec<SettingSchema>, String> {
+    Ok(SETTINGS_SCHEMA.to_vec())
+}
+
+/// Write each seeded setting's default through `set_if_absent`.
+pub fn seed_default_settings(
+    mut set_if_absent: impl FnMut(&str, &str) -> Result<(), String>,
+) -> Result<(), String> {
+    for setting in SETTINGS_SCHEMA.iter().filter(|setting| setting.seeded) {
+        set_if_absent(setting.key, setting.default)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_startup_settings_are_all_in_schema() {
+        let known: HashSet<_> = SETTINGS_SCHEMA.iter().map(|s| s.key).collect();
+        assert_eq!(known.len(), SETTINGS_SCHEMA.len(), "duplicate schema key");
+
+        for saved in [None, Some("1".to_string())] {
+            let written = RefCell::new(Vec::new());
+            let record = |key: &str, _: &str| {
+                written.borrow_mut().push(key.to_string());
+                Ok(())
+            };
+            crate::seed_app_settings(
+                record,
+                |_| Ok(saved.clone()),
+                record,
+                |saved| Ok(crate::parse_bool_setting(saved, false)),
+            )
+            .unwrap();
+
+            let written = written.into_inner();
+            assert!(written.iter().any(|key| key == SETTING_LAUNCH_ON_STARTUP));
+            assert!(written.len() > 1);
+            for key in &written {
+                assert!(known.contains(key.as_str()), "{key} missing from schema");
+            }
+        }
+    }
+
+    #[test]
+    fn test_defaults_match_their_kind() {
+        for setting in SETTINGS_SCHEMA {
+            match setting.kind {
+                SettingKind::Bool => assert!(["0", "1"].contains(&setting.default)),
+                SettingKind::Integer => assert!(
+                    setting.default.is_empty() || setting.default.parse::<u64>().is_ok(),
+                    "{}",
+                    setting.key
+                ),
+                SettingKind::Hotkey => assert!(crate::parse_hotkey(setting.default).is_ok()),
+                SettingKind::Enum => {
+                    assert!(setting.allowed_values.contains(&setting.default))
+                }
+                SettingKind::String => {}
+            }
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use serde_json::{json, Map, Value};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::db::{self, DataPaths, ProvidersRepository, SettingsRepository};
+use crate::provider::{self, EffectiveConfig, ProviderView};
+
+/// Directory under the app data dir that receives generated bundles.
+const SUPPORT_DIR: &str = "support";
+
+/// Field names whose values are credentials. Flags such as `has_api_key`
+/// only say whether one is set and are kept.
+fn is_secret_key(key: &str) -> bool {
+    let key = key.to_ascii_lowercase();
+    if key.starts_with("has_") {
+        return false;
+    }
+    matches!(
+        key.as_str(),
+        "api_key" | "apikey" | "key" | "token" | "secret" | "password" | "authorization"
+    ) || ["_key", "_token", "_secret", "_password"]
+        .iter()
+        .any(|suffix| key.ends_with(suffix))
+}
+
+/// Drop every secret field, at any depth.
+fn redact(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            map.retain(|key, _| !is_secret_key(key));
+            map.values_mut().for_each(redact);
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+/// Settings as a JSON object, minus secrets. Values holding JSON (such as
+/// the legacy `provider_config`) are parsed so their fields get redacted too.
+fn redacted_settings(settings: Vec<(String, String)>) -> Value {
+    let mut out = Map::new();
+    for (key, raw) in settings {
+        if is_secret_key(&key) {
+            continue;
+        }
+        let value = match serde_json::from_str::<Value>(&raw) {
+            Ok(parsed @ (Value::Object(_) | Value::Array(_))) => parsed,
+            _ => Value::String(raw),
+        };
+        out.insert(key, value);
+    }
+    let mut settings = Value::Object(out);
+    redact(&mut settings);
+    settings
+}
+
+fn build_bundle(
+    app_version: &str,
+    generated_at: i64,
+    schema_version: Option<u32>,
+    data_paths: Option<&DataPaths>,
+    providers: Vec<(ProviderView, Option<EffectiveConfig>)>,
+    settings: Vec<(String, String)>,
+) -> Value {
+    let providers = providers
+        .into_iter()
+        .map(|(view, effective)| json!({ "provider": view, "effective_config": effective }))
+        .collect::<Vec<_>>();
+    let mut bundle = json!({
+        "app_version": app_version,
+        "generated_at": generated_at,
+        "schema_version": schema_version,
+        "data_paths": data_paths,
+        "providers": providers,
+        "settings": redacted_settings(settings),
+    });
+    redact(&mut bundle);
+    bundle
+}
+
+/// Write a redacted JSON snapshot of the app's configuration for bug
+/// reports and return its path. API keys and other secrets are never
+/// included.
+#[tauri::command]
+pub async fn generate_support_bundle(app: tauri::AppHandle) -> Result<String, String> {
+    let data_paths = db::data_paths(&app).map_err(|e| e.to_string())?;
+    let (schema_version, providers, settings) = tauri::async_runtime::spawn_blocking(|| {
+        let providers = ProvidersRepository::list()
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .map(|view| {
+                let effective = ProvidersRepository::get(&view.id)
+                    .ok()
+                    .flatten()
+                    .map(|provider| provider::effective_config(&provider, view.has_api_key));
+                (view, effective)
+            })
+            .collect::<Vec<_>>();
+        let settings = SettingsRepository::list_all().map_err(|e| e.to_string())?;
+        Ok::<_, String>((db::schema_version().ok(), providers, settings))
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    let generated_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0);
+    let bundle = build_bundle(
+        env!("CARGO_PKG_VERSION"),
+        generated_at,
+        schema_version,
+        Some(&data_paths),
+        providers,
+        settings,
+    );
+
+    let dir = data_paths.data_dir.join(SUPPORT_DIR);
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create {}: {e}", dir.display()))?;
+    let path = dir.join(format!("support-bundle-{generated_at}.json"));
+    let contents = serde_json::to_string_pretty(&bundle).map_err(|e| e.to_string())?;
+    std::fs::write(&path, contents)
+        .map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::Provider;
+
+    fn has_key(value: &Value, name: &str) -> bool {
+        match value {
+            Value::Object(map) => map
+                .iter()
+                .any(|(key, value)| key == name || has_key(value, name)),
+            Value::Array(items) => items.iter().any(|item| has_key(item, name)),
+            _ => false,
+        }
+    }
+
+    #[test]
+    fn test_support_bundle_excludes_api_keys() {
+        let provider = Provider {
+            name: "Work".to_string(),
+            ..provider::test_provider()
+        };
+        let effective = provider::effective_config(&provider, true);
+        let settings = vec![
+            ("theme".to_string(), "dark".to_string()),
+            (
+                "provider_config".to_string(),
+                r#"{"api_key":"sk-legacy-secret","model":"gpt-4o","provider_type":"openai","base_url":null}"#
+                    .to_string(),
+            ),
+            ("gateway_api_key".to_string(), "sk-gateway-secret".to_string()),
+        ];
+
+        let bundle = build_bundle(
+            "1.0.0",
+            0,
+            Some(21),
+            None,
+            vec![(ProviderView::from_provider(provider, true), Some(effective))],
+            settings,
+        );
+
+        assert!(!has_key(&bundle, "api_key"));
+        let text = bundle.to_string();
+        assert!(!text.contains("sk-legacy-secret"));
+        assert!(!text.contains("sk-gateway-secret"));
+        // Non-secret context survives redaction.
+        assert_eq!(bundle["settings"]["theme"], "dark");
+        assert_eq!(bundle["settings"]["provider_config"]["model"], "gpt-4o");
+        assert_eq!(bundle["providers"][0]["provider"]["has_api_key"], true);
+        assert_eq!(bundle["schema_version"], 21);
+    }
+}
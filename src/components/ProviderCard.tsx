@@ -9,6 +9,7 @@ import {
 } from "@/components/ui/dialog";
 import type {
   ConnectionTestResult,
+  NormalizedBaseUrl,
   ProviderType,
   ProviderView,
 } from "@/types/provider";
@@ -25,6 +26,10 @@ interface ProviderCardProps {
   onSetApiKey: (id: string, apiKey: string) => Promise<void>;
   onGetApiKey: (id: string) => Promise<string>;
   onTestConnection: (id: string) => Promise<ConnectionTestResult>;
+  onNormalizeBaseUrl: (
+    providerType: ProviderType,
+    input: string,
+  ) => Promise<NormalizedBaseUrl>;
 }
 
 const PROVIDER_ICONS: Record<
@@ -63,6 +68,7 @@ export function ProviderCard({
   onSetApiKey,
   onGetApiKey,
   onTestConnection,
+  onNormalizeBaseUrl,
 }: ProviderCardProps) {
   const withUiTimeout = async <T,>(
     promise: Promise<T>,
@@ -84,6 +90,10 @@ export function ProviderCard({
   const [editedName, setEditedName] = useState(provider.name);
   const [editedModel, setEditedModel] = useState(provider.model);
   const [editedBaseUrl, setEditedBaseUrl] = useState(provider.base_url ?? "");
+  const [baseUrlHint, setBaseUrlHint] = useState<{
+    message: string;
+    isError: boolean;
+  } | null>(null);
   const [apiKey, setApiKey] = useState("");
   const [showApiKey, setShowApiKey] = useState(false);
   const [apiKeyLoadError, setApiKeyLoadError] = useState<string | null>(null);
@@ -117,6 +127,24 @@ export function ProviderCard({
     })();
   }, [provider.id, onGetApiKey]);
 
+  const handleBaseUrlBlur = async () => {
+    try {
+      const normalized = await onNormalizeBaseUrl(
+        provider.provider_type,
+        editedBaseUrl,
+      );
+      setEditedBaseUrl(normalized.url);
+      setBaseUrlHint(
+        normalized.warning
+          ? { message: normalized.warning, isError: false }
+          : null,
+      );
+    } catch (error) {
+      const message = error instanceof Error ? error.message : String(error);
+      setBaseUrlHint({ message, isError: true });
+    }
+  };
+
   const handleSave = async () => {
     setIsSavingSettings(true);
     setSettingsSaveError(null);
@@ -369,6 +397,7 @@ export function ProviderCard({
                   className="w-full h-9 px-3 border border-input rounded-md text-xs focus:ring-1 focus:ring-primary focus:border-primary outline-none transition-all bg-background text-foreground"
                   value={editedBaseUrl}
                   onChange={(e) => setEditedBaseUrl(e.target.value)}
+                  onBlur={() => void handleBaseUrlBlur()}
                   placeholder={
                     typeInfo.defaultBaseUrl ?? "https://api.example.com/v1"
                   }
@@ -378,6 +407,13 @@ export function ProviderCard({
                   {provider.base_url || "(default)"}
                 </div>
               )}
+              {isEditing && baseUrlHint && (
+                <p
+                  className={`text-[11px] mt-1 ${baseUrlHint.isError ? "text-destructive" : "text-amber-600"}`}
+                >
+                  {baseUrlHint.message}
+                </p>
+              )}
             </div>
           </div>
           <div className="flex justify-end gap-2 mt-4">
@@ -392,6 +428,7 @@ export function ProviderCard({
                     setEditedName(provider.name);
                     setEditedModel(provider.model);
                     setEditedBaseUrl(provider.base_url ?? "");
+                    setBaseUrlHint(null);
                   }}
                 >
                   Cancel
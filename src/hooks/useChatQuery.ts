@@ -104,18 +104,12 @@ export function useChatQuery(deps: ChatQueryDeps): UseChatQueryReturn {
   );
 
   const generateTitle = useCallback(
-    async (
-      sessionId: string,
-      providerId: string,
-      userMsg: string,
-      assistantMsg: string,
-    ) => {
+    async (sessionId: string, userMsg: string, assistantMsg: string) => {
       if (!userMsg.trim()) return;
       const prompt = `Summarize this conversation in 5 words or fewer. Reply with ONLY the title, no punctuation.\n\nUser: ${userMsg.slice(0, 300)}\nAssistant: ${assistantMsg.slice(0, 300)}`;
       try {
         const title = await withTimeout(
-          invoke<string>("query_provider_once", {
-            providerId,
+          invoke<string>("query_utility_once", {
             prompt,
             history: null,
           }),
@@ -278,6 +272,9 @@ export function useChatQuery(deps: ChatQueryDeps): UseChatQueryReturn {
             prompt: normalizedPrompt,
             history,
             streamKey: columnId,
+            sessionId,
+            columnId,
+            messageId: assistantMsgId,
           }),
           180_000,
           "query_stream_provider",
@@ -295,12 +292,7 @@ export function useChatQuery(deps: ChatQueryDeps): UseChatQueryReturn {
         void ChatDb.updateMessage(assistantMsgId, accumulated, "done");
 
         if (nextTurns === 1) {
-          void generateTitle(
-            sessionId,
-            provider.id,
-            normalizedPrompt,
-            accumulated,
-          );
+          void generateTitle(sessionId, normalizedPrompt, accumulated);
         }
       } catch (err) {
         if (columnRequestIdsRef.current[columnId] !== reqId) return;
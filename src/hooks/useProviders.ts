@@ -1,10 +1,15 @@
 import { invoke } from "@tauri-apps/api/core";
 import { useCallback, useEffect, useState } from "react";
 import type {
+  BenchmarkResult,
   ConnectionTestResult,
   CreateProviderRequest,
-  Provider,
+  CreateProviderResult,
+  NormalizedBaseUrl,
+  ProviderType,
+  ProviderValidation,
   ProviderView,
+  StreamTestResult,
   UpdateProviderRequest,
 } from "../types/provider";
 
@@ -57,17 +62,39 @@ export function useProviders() {
     void loadProviders();
   }, [loadProviders]);
 
+  // Most-used first; does not replace the display-ordered `providers` list.
+  const listByUsage = useCallback(async (): Promise<ProviderView[]> => {
+    try {
+      return await withTimeout(
+        invoke<ProviderView[]>("list_providers_by_usage"),
+        10000,
+        "list_providers_by_usage",
+      );
+    } catch (err) {
+      console.error("Failed to list providers by usage:", err);
+      throw err;
+    }
+  }, []);
+
   const createProvider = useCallback(
-    async (req: CreateProviderRequest): Promise<ProviderView | null> => {
+    async (
+      req: CreateProviderRequest,
+    ): Promise<{ provider: ProviderView | null; existing: boolean }> => {
       try {
-        const created = await invoke<Provider>("create_provider", { req });
+        const created = await invoke<CreateProviderResult>("create_provider", {
+          req,
+        });
         const result = await withTimeout(
           invoke<ProviderView[]>("list_providers"),
           10000,
           "list_providers",
         );
         setProviders(result);
-        return result.find((item) => item.id === created.id) ?? null;
+        return {
+          provider:
+            result.find((item) => item.id === created.provider.id) ?? null,
+          existing: created.existing,
+        };
       } catch (err) {
         console.error("Failed to create provider:", err);
         throw err;
@@ -119,6 +146,48 @@ export function useProviders() {
     [loadProviders],
   );
 
+  const normalizeBaseUrl = useCallback(
+    (providerType: string, input: string): Promise<NormalizedBaseUrl> =>
+      invoke<NormalizedBaseUrl>("normalize_base_url", { providerType, input }),
+    [],
+  );
+
+  const setActiveByType = useCallback(
+    async (providerType: string, isActive: boolean): Promise<number> => {
+      try {
+        const changed = await invoke<number>("set_active_by_type", {
+          providerType,
+          isActive,
+        });
+        await loadProviders();
+        return changed;
+      } catch (err) {
+        console.error("Failed to set providers active by type:", err);
+        throw err;
+      }
+    },
+    [loadProviders],
+  );
+
+  const inferProviderType = useCallback(
+    (baseUrl: string): Promise<ProviderType> =>
+      invoke<ProviderType>("infer_provider_type", { baseUrl }),
+    [],
+  );
+
+  const setProviderConcurrency = useCallback(
+    async (id: string, n: number | null): Promise<void> => {
+      try {
+        await invoke("set_provider_concurrency", { id, n });
+        await loadProviders();
+      } catch (err) {
+        console.error("Failed to set provider concurrency:", err);
+        throw err;
+      }
+    },
+    [loadProviders],
+  );
+
   const getApiKey = useCallback(async (id: string): Promise<string> => {
     try {
       return await invoke<string>("get_provider_api_key", { id });
@@ -163,6 +232,102 @@ export function useProviders() {
     [],
   );
 
+  // Every problem across the active providers, for a "check setup" button.
+  const validateAll = useCallback(
+    async (testConnection = false): Promise<ProviderValidation[]> => {
+      try {
+        return await withTimeout(
+          invoke<ProviderValidation[]>("validate_all_providers", {
+            testConnection,
+          }),
+          testConnection ? 30000 : 10000,
+          "validate_all_providers",
+        );
+      } catch (err) {
+        console.error("Failed to validate providers:", err);
+        throw err;
+      }
+    },
+    [],
+  );
+
+  // Sends a short streamed request and checks that deltas actually arrive.
+  const testStreaming = useCallback(
+    async (id: string): Promise<StreamTestResult> => {
+      try {
+        return await withTimeout(
+          invoke<StreamTestResult>("test_provider_streaming", { id }),
+          25000,
+          "test_provider_streaming",
+        );
+      } catch (err) {
+        console.error("Failed to test provider streaming:", err);
+        throw err;
+      }
+    },
+    [],
+  );
+
+  const benchmark = useCallback(
+    async (id: string, prompt: string): Promise<BenchmarkResult> => {
+      try {
+        return await withTimeout(
+          invoke<BenchmarkResult>("benchmark_provider", { id, prompt }),
+          130000,
+          "benchmark_provider",
+        );
+      } catch (err) {
+        console.error("Failed to benchmark provider:", err);
+        throw err;
+      }
+    },
+    [],
+  );
+
+  // Re-sends the last failed request (logged while debug recording is on).
+  const replayLastFailedRequest = useCallback(
+    async (providerId: string): Promise<string> => {
+      try {
+        return await withTimeout(
+          invoke<string>("replay_last_failed_request", { providerId }),
+          60000,
+          "replay_last_failed_request",
+        );
+      } catch (err) {
+        console.error("Failed to replay request:", err);
+        throw err;
+      }
+    },
+    [],
+  );
+
+  // Checks a key typed into the provider form without saving it.
+  const testApiKey = useCallback(
+    async (
+      providerType: string,
+      baseUrl: string | null,
+      model: string | null,
+      apiKey: string,
+    ): Promise<ConnectionTestResult> => {
+      try {
+        return await withTimeout(
+          invoke<ConnectionTestResult>("test_api_key", {
+            providerType,
+            baseUrl,
+            model,
+            apiKey,
+          }),
+          15000,
+          "test_api_key",
+        );
+      } catch (err) {
+        console.error("Failed to test API key:", err);
+        throw err;
+      }
+    },
+    [],
+  );
+
   return {
     providers,
     activeProvider,
@@ -172,9 +337,19 @@ export function useProviders() {
     updateProvider,
     deleteProvider,
     setActiveProvider,
+    setActiveByType,
+    setProviderConcurrency,
     getApiKey,
     setApiKey,
     testConnection,
+    testStreaming,
+    benchmark,
+    listByUsage,
+    validateAll,
+    replayLastFailedRequest,
+    testApiKey,
+    normalizeBaseUrl,
+    inferProviderType,
     reload: loadProviders,
   };
 }
@@ -8,15 +8,50 @@ export type AppSettings = {
   hotkeyOpenSettings: string;
   theme: string;
   defaultSystemPrompt: string;
+  showConsoleWindow: boolean;
+  launcherMode: LauncherMode;
+  scanRegistry: boolean;
+  scanStartMenu: boolean;
+  alwaysOnTop: boolean;
 };
 
+export type LauncherMode = "apps_only" | "hybrid";
+
 export type SettingKey =
   | "launch_on_startup"
   | "hide_on_blur"
   | "hotkey_toggle_search"
   | "hotkey_open_settings"
   | "theme"
-  | "default_system_prompt";
+  | "default_system_prompt"
+  | "show_console_window"
+  | "request_timeout_secs"
+  | "launcher_mode"
+  | "db_busy_timeout_ms"
+  | "wal_checkpoint_interval_minutes"
+  | "scan_registry"
+  | "scan_start_menu"
+  | "always_on_top"
+  | "provider_debug_record"
+  | "max_messages_per_session"
+  | "max_prompt_chars"
+  | "prompt_overflow"
+  | "prewarm_providers"
+  | "verbose_errors"
+  | "provider_failover"
+  | "name_cleanup"
+  | "stream_batch_ms"
+  | "store_message_requests"
+  | "max_stream_duration_secs";
+
+export type SettingKind = "bool" | "string" | "integer" | "hotkey" | "enum";
+
+export type SettingSchema = {
+  key: SettingKey;
+  kind: SettingKind;
+  default: string;
+  allowed_values: string[];
+};
 
 export const DEFAULT_APP_SETTINGS: AppSettings = {
   launchOnStartup: false,
@@ -25,6 +60,11 @@ export const DEFAULT_APP_SETTINGS: AppSettings = {
   hotkeyOpenSettings: "Ctrl + ,",
   theme: "system",
   defaultSystemPrompt: "",
+  showConsoleWindow: true,
+  launcherMode: "hybrid",
+  scanRegistry: true,
+  scanStartMenu: true,
+  alwaysOnTop: false,
 };
 
 export const AppSettingsApi = {
@@ -43,4 +83,38 @@ export const AppSettingsApi = {
       "set_app_setting",
     );
   },
+
+  async setAlwaysOnTop(enabled: boolean): Promise<boolean> {
+    return withTimeout(
+      invoke<boolean>("set_always_on_top", { enabled }),
+      10_000,
+      "set_always_on_top",
+    );
+  },
+
+  /** Shortcuts actually registered with the OS, keyed by setting name. */
+  async getRegisteredHotkeys(): Promise<Partial<Record<SettingKey, string>>> {
+    return withTimeout(
+      invoke<Partial<Record<SettingKey, string>>>("get_registered_hotkeys"),
+      10_000,
+      "get_registered_hotkeys",
+    );
+  },
+
+  /** Every setting the backend knows about, for rendering the settings UI. */
+  async listSchema(): Promise<SettingSchema[]> {
+    return withTimeout(
+      invoke<SettingSchema[]>("list_settings_schema"),
+      10_000,
+      "list_settings_schema",
+    );
+  },
+
+  async validateHotkey(hotkey: string): Promise<string> {
+    return withTimeout(
+      invoke<string>("validate_hotkey", { hotkey }),
+      10_000,
+      "validate_hotkey",
+    );
+  },
 };
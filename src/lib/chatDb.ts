@@ -2,10 +2,22 @@ import { invoke } from "@tauri-apps/api/core";
 import type {
   ChatMessage,
   ChatMessageStatus,
+  ChatMessageWithSession,
+  ChatStats,
+  ColumnPreset,
+  ColumnWithLatest,
+  CompareWithProviderResult,
   DbChatMessageRecord,
   DbChatSessionColumnRecord,
   DbChatSessionRecord,
+  DbLockDiagnostics,
+  DiffSegment,
+  FtsStats,
+  MessageRequest,
   MessageSearchResult,
+  ResubmitMessageResult,
+  SchemaVerification,
+  SessionStorage,
 } from "../types/chat";
 import { withTimeout } from "./utils";
 
@@ -18,18 +30,78 @@ export const ChatDb = {
     );
   },
 
+  /** With `scratch`, the session stays in memory until its first message. */
   createSession(
     id: string,
     title: string,
     providerIds: string[],
+    scratch?: boolean,
   ): Promise<DbChatSessionRecord> {
     return withTimeout(
-      invoke("create_chat_session", { id, title, providerIds }),
+      invoke("create_chat_session", { id, title, providerIds, scratch }),
       10_000,
       "create_chat_session",
     );
   },
 
+  /** Providers deleted since the preset was saved are skipped. */
+  createSessionFromPreset(
+    presetName: string,
+    title: string,
+  ): Promise<DbChatSessionRecord> {
+    return withTimeout(
+      invoke("create_session_from_preset", { presetName, title }),
+      10_000,
+      "create_session_from_preset",
+    );
+  },
+
+  listColumnPresets(): Promise<ColumnPreset[]> {
+    return withTimeout(
+      invoke("list_column_presets"),
+      10_000,
+      "list_column_presets",
+    );
+  },
+
+  createColumnPreset(
+    name: string,
+    providerIds: string[],
+  ): Promise<ColumnPreset> {
+    return withTimeout(
+      invoke("create_column_preset", { name, providerIds }),
+      10_000,
+      "create_column_preset",
+    );
+  },
+
+  updateColumnPreset(
+    id: string,
+    patch: { name?: string; providerIds?: string[] },
+  ): Promise<ColumnPreset> {
+    return withTimeout(
+      invoke("update_column_preset", { id, ...patch }),
+      10_000,
+      "update_column_preset",
+    );
+  },
+
+  deleteColumnPreset(id: string): Promise<void> {
+    return withTimeout(
+      invoke("delete_column_preset", { id }),
+      10_000,
+      "delete_column_preset",
+    );
+  },
+
+  persistSession(id: string): Promise<DbChatSessionRecord> {
+    return withTimeout(
+      invoke("persist_session", { id }),
+      10_000,
+      "persist_session",
+    );
+  },
+
   renameSession(id: string, title: string): Promise<DbChatSessionRecord> {
     return withTimeout(
       invoke("rename_chat_session", { id, title }),
@@ -50,6 +122,22 @@ export const ChatDb = {
     );
   },
 
+  replaceProviderInSession(
+    sessionId: string,
+    oldProviderId: string,
+    newProviderId: string,
+  ): Promise<number> {
+    return withTimeout(
+      invoke("replace_provider_in_session", {
+        sessionId,
+        oldProviderId,
+        newProviderId,
+      }),
+      10_000,
+      "replace_provider_in_session",
+    );
+  },
+
   setSystemPrompt(
     id: string,
     systemPrompt: string,
@@ -61,6 +149,17 @@ export const ChatDb = {
     );
   },
 
+  setCanonicalAnswer(
+    sessionId: string,
+    messageId: string | null,
+  ): Promise<DbChatSessionRecord> {
+    return withTimeout(
+      invoke("set_canonical_answer", { sessionId, messageId }),
+      10_000,
+      "set_canonical_answer",
+    );
+  },
+
   deleteSession(id: string): Promise<void> {
     return withTimeout(
       invoke("delete_chat_session", { id }),
@@ -69,6 +168,26 @@ export const ChatDb = {
     );
   },
 
+  tagSessions(
+    sessionIds: string[],
+    tag: string,
+    add: boolean,
+  ): Promise<number> {
+    return withTimeout(
+      invoke("tag_sessions", { sessionIds, tag, add }),
+      10_000,
+      "tag_sessions",
+    );
+  },
+
+  listSessionTags(): Promise<Record<string, string[]>> {
+    return withTimeout(
+      invoke("list_session_tags"),
+      10_000,
+      "list_session_tags",
+    );
+  },
+
   listSessionColumns(sessionId: string): Promise<DbChatSessionColumnRecord[]> {
     return withTimeout(
       invoke("list_chat_session_columns", { sessionId }),
@@ -77,6 +196,14 @@ export const ChatDb = {
     );
   },
 
+  listColumnsWithLatest(sessionId: string): Promise<ColumnWithLatest[]> {
+    return withTimeout(
+      invoke("list_columns_with_latest", { sessionId }),
+      10_000,
+      "list_columns_with_latest",
+    );
+  },
+
   setSessionColumnProvider(
     columnId: string,
     providerId: string,
@@ -100,6 +227,19 @@ export const ChatDb = {
     );
   },
 
+  /** One provider's messages from every session, newest first. */
+  listProviderMessages(
+    providerId: string,
+    limit = 0,
+    offset = 0,
+  ): Promise<ChatMessageWithSession[]> {
+    return withTimeout(
+      invoke("list_provider_messages", { providerId, limit, offset }),
+      10_000,
+      "list_provider_messages",
+    );
+  },
+
   countMessages(sessionId: string): Promise<number> {
     return withTimeout(
       invoke("count_chat_messages", { sessionId }),
@@ -146,9 +286,54 @@ export const ChatDb = {
     );
   },
 
-  searchMessages(query: string, limit = 20): Promise<MessageSearchResult[]> {
+  /** Stop a column's stream and drop its partial reply; others keep going. */
+  abortColumn(columnId: string): Promise<void> {
+    return withTimeout(
+      invoke("abort_column", { columnId }),
+      10_000,
+      "abort_column",
+    );
+  },
+
+  /** `null` unless `store_message_requests` was on when it streamed. */
+  getMessageRequest(messageId: string): Promise<MessageRequest | null> {
+    return withTimeout(
+      invoke("get_message_request", { messageId }),
+      10_000,
+      "get_message_request",
+    );
+  },
+
+  importTextConversation(
+    sessionId: string,
+    text: string,
+    providerId: string,
+  ): Promise<number> {
+    return withTimeout(
+      invoke("import_text_conversation", { sessionId, text, providerId }),
+      10_000,
+      "import_text_conversation",
+    );
+  },
+
+  purgeProviderHistory(
+    providerId: string,
+    removeEmpty = false,
+  ): Promise<number> {
     return withTimeout(
-      invoke("search_chat_messages", { query, limit }),
+      invoke("purge_provider_history", { providerId, removeEmpty }),
+      30_000,
+      "purge_provider_history",
+    );
+  },
+
+  searchMessages(
+    query: string,
+    limit = 20,
+    dedup = false,
+  ): Promise<MessageSearchResult[]> {
+    return withTimeout(
+      invoke("search_chat_messages", { query, limit, dedup }),
       10_000,
       "search_chat_messages",
     );
@@ -161,4 +346,84 @@ export const ChatDb = {
       "export_session_messages",
     );
   },
+
+  exportSessionAsOpenAI(
+    sessionId: string,
+    columnId?: string,
+  ): Promise<{ messages: { role: string; content: string }[] }> {
+    return withTimeout(
+      invoke("export_session_as_openai", { sessionId, columnId }),
+      15_000,
+      "export_session_as_openai",
+    );
+  },
+
+  getStats(): Promise<ChatStats> {
+    return withTimeout(invoke("get_chat_stats"), 10_000, "get_chat_stats");
+  },
+
+  getSessionStorage(sessionId: string): Promise<SessionStorage> {
+    return withTimeout(
+      invoke("get_session_storage", { sessionId }),
+      10_000,
+      "get_session_storage",
+    );
+  },
+
+  listHeaviestSessions(limit = 10): Promise<SessionStorage[]> {
+    return withTimeout(
+      invoke("list_heaviest_sessions", { limit }),
+      10_000,
+      "list_heaviest_sessions",
+    );
+  },
+
+  getSearchIndexStats(): Promise<FtsStats> {
+    return withTimeout(
+      invoke("get_search_index_stats"),
+      10_000,
+      "get_search_index_stats",
+    );
+  },
+
+  verifySchema(): Promise<SchemaVerification> {
+    return withTimeout(invoke("verify_schema"), 10_000, "verify_schema");
+  },
+
+  getDbLockDiagnostics(): Promise<DbLockDiagnostics> {
+    return invoke("get_db_lock_diagnostics");
+  },
+
+  // Resolves once the other provider's reply has finished streaming.
+  compareWithProvider(
+    sessionId: string,
+    columnId: string,
+    otherProviderId: string,
+  ): Promise<CompareWithProviderResult> {
+    return invoke("compare_with_provider", {
+      sessionId,
+      columnId,
+      otherProviderId,
+    });
+  },
+
+  resubmitMessage(messageId: string): Promise<ResubmitMessageResult> {
+    return invoke("resubmit_message", { messageId });
+  },
+
+  getMessageContext(messageId: string): Promise<DbChatMessageRecord[]> {
+    return withTimeout(
+      invoke("get_message_context", { messageId }),
+      10_000,
+      "get_message_context",
+    );
+  },
+
+  diffMessages(messageIdA: string, messageIdB: string): Promise<DiffSegment[]> {
+    return withTimeout(
+      invoke("diff_messages", { messageIdA, messageIdB }),
+      10_000,
+      "diff_messages",
+    );
+  },
 };
@@ -0,0 +1,60 @@
+import { invoke } from "@tauri-apps/api/core";
+import { withTimeout } from "./utils";
+
+export type PromptTemplate = {
+  id: string;
+  name: string;
+  body: string;
+  shortcut: string | null;
+  created_at: number;
+  updated_at: number;
+};
+
+export const PromptTemplatesApi = {
+  list(): Promise<PromptTemplate[]> {
+    return withTimeout(
+      invoke("list_prompt_templates"),
+      10_000,
+      "list_prompt_templates",
+    );
+  },
+
+  create(
+    name: string,
+    body: string,
+    shortcut?: string,
+  ): Promise<PromptTemplate> {
+    return withTimeout(
+      invoke("create_prompt_template", { name, body, shortcut }),
+      10_000,
+      "create_prompt_template",
+    );
+  },
+
+  update(
+    id: string,
+    changes: { name?: string; body?: string; shortcut?: string },
+  ): Promise<PromptTemplate> {
+    return withTimeout(
+      invoke("update_prompt_template", { id, ...changes }),
+      10_000,
+      "update_prompt_template",
+    );
+  },
+
+  delete(id: string): Promise<void> {
+    return withTimeout(
+      invoke("delete_prompt_template", { id }),
+      10_000,
+      "delete_prompt_template",
+    );
+  },
+
+  expand(shortcut: string, input: string): Promise<string> {
+    return withTimeout(
+      invoke("expand_template", { shortcut, input }),
+      10_000,
+      "expand_template",
+    );
+  },
+};
@@ -0,0 +1,30 @@
+import { invoke } from "@tauri-apps/api/core";
+import { withTimeout } from "./utils";
+
+export type RecentQuery = {
+  id: number;
+  text: string;
+  created_at: number;
+};
+
+export const RecentQueriesApi = {
+  record(text: string): Promise<void> {
+    return withTimeout(invoke("record_query", { text }), 10_000, "record_query");
+  },
+
+  list(limit?: number): Promise<RecentQuery[]> {
+    return withTimeout(
+      invoke("list_recent_queries", { limit }),
+      10_000,
+      "list_recent_queries",
+    );
+  },
+
+  clear(): Promise<void> {
+    return withTimeout(
+      invoke("clear_recent_queries"),
+      10_000,
+      "clear_recent_queries",
+    );
+  },
+};
@@ -24,10 +24,13 @@ interface AppInfo {
   name: string;
   path: string;
   publisher: string | null;
+  /** Scanned name before cleanup, present only when cleanup changed it. */
+  raw_name?: string;
 }
 interface SearchResult {
   app: AppInfo;
   score: number;
+  match_indices?: [number, number][];
 }
 interface AppSettingUpdateEvent {
   key: string;
@@ -44,6 +44,9 @@ export default function Settings() {
     useState<ProviderType>("openai");
   const [newProviderName, setNewProviderName] = useState("");
   const [newProviderBaseUrl, setNewProviderBaseUrl] = useState("");
+  const [newProviderBaseUrlHint, setNewProviderBaseUrlHint] = useState<
+    string | null
+  >(null);
   const [newProviderApiKey, setNewProviderApiKey] = useState("");
   const [newProviderShowApiKey, setNewProviderShowApiKey] = useState(false);
   const [newProviderModel, setNewProviderModel] = useState("");
@@ -75,6 +78,8 @@ export default function Settings() {
     setApiKey,
     getApiKey,
     testConnection,
+    normalizeBaseUrl,
+    inferProviderType,
   } = useProviders();
 
   useEffect(() => {
@@ -203,6 +208,14 @@ export default function Settings() {
     );
   };
 
+  const handleShowConsoleWindowChange = (checked: boolean) => {
+    const previous = appSettings.showConsoleWindow;
+    setAppSettings((prev) => ({ ...prev, showConsoleWindow: checked }));
+    setSettingWithRollback("show_console_window", checked ? "1" : "0", () =>
+      setAppSettings((prev) => ({ ...prev, showConsoleWindow: previous })),
+    );
+  };
+
   const handleThemeChange = (newTheme: "light" | "dark" | "system") => {
     const previous = normalizeTheme(appSettings.theme);
     setTheme(newTheme);
@@ -440,10 +453,36 @@ export default function Settings() {
     formatRecordedKey,
   ]);
 
+  const handleNewProviderBaseUrlBlur = async () => {
+    // Blank keeps the type default; only check what the user typed.
+    if (!newProviderBaseUrl.trim()) {
+      setNewProviderBaseUrlHint(null);
+      return;
+    }
+    try {
+      const inferred = await inferProviderType(newProviderBaseUrl);
+      const providerType = inferred === "custom" ? newProviderType : inferred;
+      const normalized = await normalizeBaseUrl(
+        providerType,
+        newProviderBaseUrl,
+      );
+      if (providerType !== newProviderType) {
+        // Preselect the type the URL belongs to, keeping the pasted URL.
+        handleNewProviderTypeChange(providerType);
+      }
+      setNewProviderBaseUrl(normalized.url);
+      setNewProviderBaseUrlHint(normalized.warning);
+    } catch (error) {
+      setNewProviderBaseUrlHint(
+        error instanceof Error ? error.message : String(error),
+      );
+    }
+  };
+
   const handleCreateProvider = async () => {
     setIsCreatingProvider(true);
     try {
-      await createProvider({
+      const { provider, existing } = await createProvider({
         name:
           newProviderName.trim() || PROVIDER_TYPE_INFO[newProviderType].label,
         provider_type: newProviderType,
@@ -451,11 +490,19 @@ export default function Settings() {
         model: newProviderModel.trim() || undefined,
         api_key: newProviderApiKey.trim() || undefined,
       });
+      if (existing) {
+        // Nothing new was added; point the user at the provider they already have.
+        pushToast(
+          "Provider already exists",
+          `Using "${provider?.name ?? "the existing provider"}" with the same type, base URL and model.`,
+        );
+      }
       // Reset form
       setIsNewProviderOpen(false);
       setNewProviderType("openai");
       setNewProviderName("");
       setNewProviderBaseUrl("");
+      setNewProviderBaseUrlHint(null);
       setNewProviderApiKey("");
       setNewProviderShowApiKey(false);
       setNewProviderModel("");
@@ -538,6 +585,27 @@ export default function Settings() {
                     </label>
                   </div>
                   <div className="h-px bg-border w-full"></div>
+                  <div className="flex items-center justify-between">
+                    <div>
+                      <h3 className="text-sm font-bold text-foreground">Show Console Window</h3>
+                      <p className="text-xs text-muted-foreground mt-1">
+                        Launch command-line tools with a visible console window.
+                      </p>
+                    </div>
+                    <label className="relative inline-flex items-center cursor-pointer">
+                      <input
+                        type="checkbox"
+                        className="sr-only peer"
+                        checked={appSettings.showConsoleWindow}
+                        disabled={isLoadingAppSettings}
+                        onChange={(e) =>
+                          handleShowConsoleWindowChange(e.target.checked)
+                        }
+                      />
+                      <div className="w-9 h-5 bg-muted peer-focus:outline-none rounded-full peer peer-checked:after:translate-x-full peer-checked:after:border-background after:content-[''] after:absolute after:top-[2px] after:left-[2px] after:bg-background after:border-border after:border after:rounded-full after:h-4 after:w-4 after:transition-all peer-checked:bg-primary"></div>
+                    </label>
+                  </div>
+                  <div className="h-px bg-border w-full"></div>
                   <div>
                     <h3 className="text-sm font-bold text-foreground">Default System Prompt</h3>
                     <p className="text-xs text-muted-foreground mt-1 mb-3">
@@ -925,8 +993,14 @@ export default function Settings() {
                         placeholder="https://api.example.com/v1"
                         value={newProviderBaseUrl}
                         onChange={(e) => setNewProviderBaseUrl(e.target.value)}
+                        onBlur={() => void handleNewProviderBaseUrlBlur()}
                         className="flex h-9 w-full rounded-md border border-input bg-background px-3 py-1 text-sm shadow-sm transition-colors placeholder:text-muted-foreground focus-visible:outline-none focus-visible:ring-1 focus-visible:ring-ring text-foreground"
                       />
+                      {newProviderBaseUrlHint && (
+                        <p className="text-[11px] text-amber-600">
+                          {newProviderBaseUrlHint}
+                        </p>
+                      )}
                     </div>
                     <div className="grid gap-2">
                       <label
@@ -1017,6 +1091,7 @@ export default function Settings() {
                     onSetApiKey={setApiKey}
                     onGetApiKey={getApiKey}
                     onTestConnection={testConnection}
+                    onNormalizeBaseUrl={normalizeBaseUrl}
                   />
                 ))
               )}
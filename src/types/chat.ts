@@ -45,6 +45,7 @@ export interface DbChatSessionRecord {
   prompt: string;
   system_prompt: string;
   turns: number;
+  canonical_message_id: string | null;
   created_at: number;
   updated_at: number;
 }
@@ -68,6 +69,36 @@ export interface DbChatMessageRecord {
   status: string;
   created_at: number;
   updated_at: number;
+  provider_name?: string | null;
+  content_hash?: string | null;
+}
+
+/** A message with its session's title, for views across sessions. */
+export interface ChatMessageWithSession extends DbChatMessageRecord {
+  session_title: string;
+}
+
+export interface CompareWithProviderResult {
+  column: DbChatSessionColumnRecord;
+  user_message: DbChatMessageRecord;
+  history: DbChatMessageRecord[];
+  assistant_message_id: string;
+}
+
+export interface ResubmitMessageResult {
+  user_message: DbChatMessageRecord;
+  history: DbChatMessageRecord[];
+  assistant_message_id: string;
+}
+
+export interface ColumnWithLatest {
+  column: DbChatSessionColumnRecord;
+  latest_message: DbChatMessageRecord | null;
+}
+
+export interface DiffSegment {
+  kind: "equal" | "added" | "removed";
+  text: string;
 }
 
 export interface MessageSearchResult {
@@ -77,3 +108,63 @@ export interface MessageSearchResult {
   snippet: string;
   created_at: number;
 }
+
+export interface ChatStats {
+  session_count: number;
+  message_count: number;
+  total_user_messages: number;
+  total_assistant_messages: number;
+  first_session_at: number | null;
+  last_activity_at: number | null;
+  most_active_provider: string | null;
+}
+
+export interface ColumnPreset {
+  id: string;
+  name: string;
+  provider_ids: string[];
+  created_at: number;
+  updated_at: number;
+}
+
+export interface SessionStorage {
+  session_id: string;
+  title: string;
+  message_count: number;
+  total_content_bytes: number;
+  icon_bytes: number;
+  fts_estimate: number;
+}
+
+/** The request behind an assistant message, with credentials redacted. */
+export interface MessageRequest {
+  message_id: string;
+  provider_id: string;
+  /** `url`, `headers`, `query` and `body` as sent. */
+  payload: Record<string, unknown>;
+  created_at: number;
+}
+
+export interface SchemaVerification {
+  ok: boolean;
+  schema_version: number;
+  missing_tables: string[];
+  /** As `table.column`. */
+  missing_columns: string[];
+  missing_indexes: string[];
+  missing_triggers: string[];
+}
+
+export interface DbLockDiagnostics {
+  /** Source location of the call holding the database lock, if any. */
+  held_by: string | null;
+  held_for_ms: number | null;
+  longest_hold_ms: number;
+  longest_hold_by: string | null;
+}
+
+export interface FtsStats {
+  indexed_message_count: number;
+  total_message_count: number;
+  orphaned_fts_rows: number;
+}
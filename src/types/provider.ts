@@ -6,6 +6,8 @@ export type ProviderType =
   | "volcengine"
   | "custom";
 
+export type ProviderRole = "chat" | "utility" | "both";
+
 export interface Provider {
   id: string;
   name: string;
@@ -14,6 +16,11 @@ export interface Provider {
   model: string;
   is_active: boolean;
   display_order: number;
+  use_responses_api: boolean;
+  context_window: number | null;
+  requests_per_minute: number | null;
+  max_concurrency: number | null;
+  role: ProviderRole;
   created_at: number;
   updated_at: number;
 }
@@ -26,6 +33,11 @@ export interface ProviderView {
   model: string;
   is_active: boolean;
   display_order: number;
+  use_responses_api: boolean;
+  context_window: number | null;
+  requests_per_minute: number | null;
+  max_concurrency: number | null;
+  role: ProviderRole;
   has_api_key: boolean;
   created_at: number;
   updated_at: number;
@@ -37,12 +49,32 @@ export interface CreateProviderRequest {
   base_url?: string;
   model?: string;
   api_key?: string;
+  use_responses_api?: boolean;
+  context_window?: number;
+  requests_per_minute?: number;
+  role?: ProviderRole;
+  allow_duplicate?: boolean;
+}
+
+/** `existing` is set when an identical provider was returned instead. */
+export interface CreateProviderResult {
+  provider: Provider;
+  existing: boolean;
 }
 
 export interface UpdateProviderRequest {
   name?: string;
   base_url?: string;
   model?: string;
+  use_responses_api?: boolean;
+  context_window?: number;
+  requests_per_minute?: number;
+  role?: ProviderRole;
+}
+
+export interface NormalizedBaseUrl {
+  url: string;
+  warning: string | null;
 }
 
 export interface ConnectionTestResult {
@@ -52,6 +84,37 @@ export interface ConnectionTestResult {
   latency_ms: number;
 }
 
+export interface StreamTestResult {
+  ok: boolean;
+  streamed_chars: number;
+  first_chunk_ms: number | null;
+  message: string;
+}
+
+export interface ProviderValidation {
+  provider_id: string;
+  provider_name: string;
+  /** Empty when the provider looks ready to chat. */
+  issues: string[];
+  connection: ConnectionTestResult | null;
+}
+
+export interface BenchmarkResult {
+  first_token_ms: number;
+  total_ms: number;
+  total_chars: number;
+  chars_per_sec: number;
+}
+
+export interface EffectiveConfig {
+  provider_type: ProviderType;
+  resolved_base_url: string | null;
+  endpoint_path: string;
+  auth_header_name: string | null;
+  model: string;
+  has_api_key: boolean;
+}
+
 export const PROVIDER_TYPE_INFO: Record<
   ProviderType,
   {
@@ -98,3 +161,16 @@ export const PROVIDER_TYPE_INFO: Record<
     keyUrl: "",
   },
 };
+
+export type QueryMissingReason =
+  | "apps_only"
+  | "no_active_provider"
+  | "missing_api_key";
+
+export interface QueryReadiness {
+  has_active_provider: boolean;
+  has_api_key: boolean;
+  provider_name: string | null;
+  apps_only: boolean;
+  missing_reason: QueryMissingReason | null;
+}